@@ -0,0 +1,144 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use crate::backend::MemoryBackend;
+use crate::error::{Error, Result};
+use crate::memory::format_address;
+
+/// Address in a process that survive relocation of modules across restarts.
+///
+/// module relative address is resolved lazily against the modules loaded at the time, so it
+/// stays valid when ASLR load the module somewhere else.
+///
+/// taken as `impl Into<Address>` by [Session](crate::session::Session),
+/// [AddressTable](crate::table::AddressTable), [Binding](crate::binding::Binding) and
+/// [Freezer](crate::freeze::Freezer). reading and writing through [Handle](crate::handle::Handle)
+/// take an absolute address, look at [Address::resolve].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Address {
+    /// address as is, valid only while the process run
+    Absolute(usize),
+    /// offset from the start of a module
+    ModuleOffset {
+        /// name of the module, like `game.exe`
+        module: String,
+        /// offset from the start of the module
+        offset: usize,
+    },
+}
+
+impl Address {
+    /// create address relative to the start of the module
+    pub fn module_offset(module: &str, offset: usize) -> Self {
+        Self::ModuleOffset {
+            module: module.to_string(),
+            offset,
+        }
+    }
+
+    /// absolute address in the process, looking up the module when relative to one
+    pub fn resolve<B: MemoryBackend + ?Sized>(&self, backend: &B) -> Result<usize> {
+        match self {
+            Self::Absolute(address) => Ok(*address),
+            Self::ModuleOffset { module, offset } => backend
+                .get_module_range(module)?
+                .start
+                .checked_add(*offset)
+                .ok_or(Error::InvalidInput),
+        }
+    }
+
+    /// same address moved by `offset`, like a field inside a struct
+    pub fn add(&self, offset: usize) -> Self {
+        match self {
+            Self::Absolute(address) => Self::Absolute(address.wrapping_add(offset)),
+            Self::ModuleOffset {
+                module,
+                offset: base,
+            } => Self::ModuleOffset {
+                module: module.clone(),
+                offset: base.wrapping_add(offset),
+            },
+        }
+    }
+}
+
+impl From<usize> for Address {
+    fn from(value: usize) -> Self {
+        Self::Absolute(value)
+    }
+}
+
+impl From<&Address> for Address {
+    fn from(value: &Address) -> Self {
+        value.clone()
+    }
+}
+
+/// format as `0x7ff6_1000_1234` or `game.exe+0x1234`
+impl Display for Address {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Absolute(address) => write!(f, "{}", format_address(*address)),
+            Self::ModuleOffset { module, offset } => write!(f, "{}+{:#x}", module, offset),
+        }
+    }
+}
+
+/// parse `0x7ff6_1000_1234` or `game.exe+0x1234`, numbers are hex with or without `0x`, module
+/// may be quoted like in Cheat Engine
+impl FromStr for Address {
+    type Err = Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let parse_hex = |text: &str| {
+            let text = text.trim();
+            let text = text
+                .strip_prefix("0x")
+                .or_else(|| text.strip_prefix("0X"))
+                .unwrap_or(text);
+
+            usize::from_str_radix(&text.replace('_', ""), 16).map_err(|_| Error::InvalidInput)
+        };
+
+        match s.rsplit_once('+') {
+            Some((module, offset)) => {
+                let module = module.trim().trim_matches('"');
+                if module.is_empty() {
+                    return Err(Error::InvalidInput);
+                }
+
+                Ok(Self::module_offset(module, parse_hex(offset)?))
+            }
+            None => Ok(Self::Absolute(parse_hex(s)?)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Address;
+
+    #[test]
+    fn parsing_addresses() {
+        assert_eq!(
+            "0x7ff6_1000".parse::<Address>().unwrap(),
+            Address::Absolute(0x7ff6_1000)
+        );
+        assert_eq!(
+            "1A2B".parse::<Address>().unwrap(),
+            Address::Absolute(0x1A2B)
+        );
+        assert_eq!(
+            "\"game.exe\"+0x10".parse::<Address>().unwrap(),
+            Address::module_offset("game.exe", 0x10)
+        );
+        assert!("game.exe+zz".parse::<Address>().is_err());
+        assert!("+10".parse::<Address>().is_err());
+
+        let address = Address::module_offset("game.exe", 0x10).add(8);
+        assert_eq!(address.to_string(), "game.exe+0x18");
+        assert_eq!(address.to_string().parse::<Address>().unwrap(), address);
+    }
+}
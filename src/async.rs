@@ -0,0 +1,220 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+use crate::error::{Error, Result};
+use crate::handle::Handle;
+use crate::memory::MemoryBasicInformation;
+
+/// Runner of blocking jobs off the async executor, like `tokio::task::spawn_blocking`.
+pub trait Spawn {
+    /// run `job` on a thread where blocking is fine
+    fn spawn_blocking(&self, job: Box<dyn FnOnce() + Send + 'static>);
+}
+
+/// [Spawn] running every job on a new std thread, works with any executor
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ThreadSpawn;
+
+impl Spawn for ThreadSpawn {
+    fn spawn_blocking(&self, job: Box<dyn FnOnce() + Send + 'static>) {
+        std::thread::spawn(job);
+    }
+}
+
+struct TaskState<T> {
+    output: Option<Result<T>>,
+    waker: Option<Waker>,
+}
+
+/// end of a job holding where its output goes, resolving the task with an error when dropped
+/// without output, like when the job panicked or the spawner never ran it
+struct Completion<T> {
+    state: Arc<Mutex<TaskState<T>>>,
+    output: Option<Result<T>>,
+}
+
+impl<T> Completion<T> {
+    fn complete(mut self, output: Result<T>) {
+        self.output = Some(output);
+    }
+}
+
+impl<T> Drop for Completion<T> {
+    fn drop(&mut self) {
+        let output = self.output.take().unwrap_or_else(|| {
+            Err(Error::Io(std::io::Error::other(
+                "blocking job did not finish",
+            )))
+        });
+
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.output = Some(output);
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// Future resolving to the output of a job run by [Spawn].
+///
+/// resolve with an error when the job panicked or was dropped without running.
+pub struct BlockingTask<T> {
+    state: Arc<Mutex<TaskState<T>>>,
+}
+
+impl<T: Send + 'static> BlockingTask<T> {
+    /// run `job` through `spawner`
+    pub fn spawn<S, F>(spawner: &S, job: F) -> Self
+    where
+        S: Spawn + ?Sized,
+        F: FnOnce() -> Result<T> + Send + 'static,
+    {
+        let state = Arc::new(Mutex::new(TaskState {
+            output: None,
+            waker: None,
+        }));
+
+        let completion = Completion {
+            state: state.clone(),
+            output: None,
+        };
+        // NOTE: unwinding out of `job` drop `completion` without output
+        spawner.spawn_blocking(Box::new(move || completion.complete(job())));
+
+        Self { state }
+    }
+}
+
+impl<T> Future for BlockingTask<T> {
+    type Output = Result<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+
+        match state.output.take() {
+            Some(output) => Poll::Ready(output),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl Handle {
+    /// same as [Handle::read_memory], run through `spawner` so the executor is not blocked
+    pub fn read_memory_async<S: Spawn + ?Sized>(
+        self: &Arc<Self>,
+        spawner: &S,
+        address: usize,
+        len: usize,
+    ) -> BlockingTask<Vec<u8>> {
+        let handle = self.clone();
+        BlockingTask::spawn(spawner, move || handle.read_memory(address, len))
+    }
+
+    /// same as [Handle::write_memory], run through `spawner` so the executor is not blocked
+    pub fn write_memory_async<S: Spawn + ?Sized>(
+        self: &Arc<Self>,
+        spawner: &S,
+        address: usize,
+        bytes: Vec<u8>,
+    ) -> BlockingTask<()> {
+        let handle = self.clone();
+        BlockingTask::spawn(spawner, move || handle.write_memory(address, &bytes))
+    }
+
+    /// same as [Handle::get_memory_basic_informations], querying every region through `spawner`
+    pub fn get_memory_basic_informations_async<S: Spawn>(
+        self: &Arc<Self>,
+        spawner: S,
+    ) -> RegionStream<S> {
+        RegionStream {
+            handle: self.clone(),
+            spawner,
+            current_address: Some(0),
+        }
+    }
+}
+
+/// Async iterator over memory regions of a process, made by
+/// [Handle::get_memory_basic_informations_async]
+pub struct RegionStream<S: Spawn> {
+    handle: Arc<Handle>,
+    spawner: S,
+    current_address: Option<usize>,
+}
+
+impl<S: Spawn> RegionStream<S> {
+    /// next region, `None` once every region is queried
+    pub async fn next(&mut self) -> Option<MemoryBasicInformation> {
+        let address = self.current_address?;
+
+        let handle = self.handle.clone();
+        let mbi = BlockingTask::spawn(&self.spawner, move || {
+            Ok(handle.query_memory_basic_information(address))
+        })
+        .await
+        .ok()
+        .flatten();
+
+        self.current_address = mbi
+            .as_ref()
+            .and_then(|e| address.checked_add(e.get_region_size()));
+
+        mbi
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::pin::pin;
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake};
+    use std::thread::Thread;
+
+    use super::{BlockingTask, Spawn, ThreadSpawn};
+
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = pin!(future);
+        let waker = Arc::new(ThreadWaker(std::thread::current())).into();
+        let mut context = Context::from_waker(&waker);
+
+        loop {
+            match future.as_mut().poll(&mut context) {
+                Poll::Ready(output) => return output,
+                Poll::Pending => std::thread::park(),
+            }
+        }
+    }
+
+    #[test]
+    fn resolving_blocking_task() {
+        let output = block_on(BlockingTask::spawn(&ThreadSpawn, || Ok(40 + 2)));
+
+        assert_eq!(output.unwrap(), 42);
+    }
+
+    #[test]
+    fn resolving_failed_blocking_task() {
+        let panicked = BlockingTask::<u32>::spawn(&ThreadSpawn, || panic!("job failed"));
+        assert!(block_on(panicked).is_err());
+
+        struct DropSpawn;
+        impl Spawn for DropSpawn {
+            fn spawn_blocking(&self, _: Box<dyn FnOnce() + Send + 'static>) {}
+        }
+        assert!(block_on(BlockingTask::<u32>::spawn(&DropSpawn, || Ok(1))).is_err());
+    }
+}
@@ -0,0 +1,300 @@
+use std::io::Write;
+use std::mem::size_of;
+use std::ops::Range;
+
+use windows::Win32::System::Memory::{VirtualAllocEx, VirtualFreeEx, MEM_RELEASE};
+
+use crate::dump;
+use crate::error::{Error, Result};
+use crate::handle::{Handle, HandleSnapshotFlag};
+use crate::memory::{
+    self, MemoryBasicInformation, PageProtectionFlags, Pod, VirtualAllocationType,
+};
+use crate::thread::StackRange;
+
+/// Access to memory of a process, what scanners, pointer chains and dumpers are built on.
+///
+/// [Handle] implements it with Win32 api, implement it over another transport, like IOCTL of
+/// a driver, to reuse the rest of the crate with it.
+pub trait MemoryBackend {
+    /// read memory starting from `address` into `buf`, failing unless all of it read
+    fn read_memory_into(&self, address: usize, buf: &mut [u8]) -> Result<()>;
+
+    /// write `bytes` to memory starting from `address`
+    fn write_memory(&self, address: usize, bytes: &[u8]) -> Result<()>;
+
+    /// information of the region containing `address`, `None` past the last region
+    fn query_memory(&self, address: usize) -> Option<MemoryBasicInformation>;
+
+    /// allocate committed memory, returning its address
+    fn alloc_memory(&self, size: usize, protection: PageProtectionFlags) -> Result<usize>;
+
+    /// release memory allocated by [MemoryBackend::alloc_memory]
+    fn free_memory(&self, address: usize) -> Result<()>;
+
+    /// size of pointer in the process, size for the current process by default
+    fn get_pointer_size(&self) -> Result<usize> {
+        Ok(size_of::<usize>())
+    }
+
+    /// address range of the module with the given name, unsupported by default
+    fn get_module_range(&self, name: &str) -> Result<Range<usize>> {
+        let _ = name;
+        Err(Error::Unsupported)
+    }
+
+    /// address ranges of the stack of every thread, unsupported by default
+    fn get_stack_ranges(&self) -> Result<Vec<Range<usize>>> {
+        Err(Error::Unsupported)
+    }
+
+    /// make code written to memory visible to the cpu, nothing to do by default
+    fn flush_instruction_cache(&self, address: usize, size: usize) -> Result<()> {
+        let _ = (address, size);
+        Ok(())
+    }
+
+    /// read `len` bytes of memory starting from `address`
+    fn read_memory(&self, address: usize, len: usize) -> Result<Vec<u8>> {
+        address.checked_add(len).ok_or(Error::InvalidInput)?;
+
+        let mut buf = vec![0u8; len];
+        self.read_memory_into(address, &mut buf)?;
+
+        Ok(buf)
+    }
+
+    /// read value of type `T` at `address`
+    fn read<T: Pod>(&self, address: usize) -> Result<T>
+    where
+        Self: Sized,
+    {
+        let buf = self.read_memory(address, size_of::<T>())?;
+
+        memory::from_bytes(&buf).ok_or(Error::InvalidInput)
+    }
+
+    /// write `value` of type `T` at `address`
+    fn write<T: Pod>(&self, address: usize, value: T) -> Result<()>
+    where
+        Self: Sized,
+    {
+        self.write_memory(address, memory::bytes_of(&value))
+    }
+
+    /// read pointer at `address` sized for the process
+    fn read_pointer(&self, address: usize) -> Result<usize> {
+        read_pointer_sized(self, address, self.get_pointer_size()?)
+    }
+
+    /// follow pointer chain starting at `base`, each level dereference the address then add
+    /// its offset
+    fn resolve_pointer_chain(&self, base: usize, offsets: &[usize]) -> Result<usize> {
+        let pointer_size = self.get_pointer_size()?;
+
+        offsets.iter().try_fold(base, |address, offset| {
+            Ok(read_pointer_sized(self, address, pointer_size)?.wrapping_add(*offset))
+        })
+    }
+
+    /// iterator over every region of memory, from the lowest address
+    fn memory_regions(&self) -> MemoryRegions<'_, Self> {
+        MemoryRegions {
+            backend: self,
+            current_address: Some(0),
+        }
+    }
+
+    /// stream `size` bytes of memory starting from `address` into `writer`
+    fn dump_region<W: Write>(&self, address: usize, size: usize, writer: &mut W) -> Result<()>
+    where
+        Self: Sized,
+    {
+        dump::dump_region(self, address, size, writer)
+    }
+
+    /// stream every committed readable region into `writer` after an index header, returning
+    /// the number of regions
+    fn dump_all<W: Write>(&self, writer: &mut W) -> Result<usize>
+    where
+        Self: Sized,
+    {
+        dump::dump_all(self, writer)
+    }
+}
+
+/// Iterator over regions of memory of a [MemoryBackend]
+pub struct MemoryRegions<'a, B: MemoryBackend + ?Sized> {
+    backend: &'a B,
+    current_address: Option<usize>,
+}
+
+impl<B: MemoryBackend + ?Sized> Iterator for MemoryRegions<'_, B> {
+    type Item = MemoryBasicInformation;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mbi = self.backend.query_memory(self.current_address?)?;
+        // NOTE: stop instead of looping forever on a backend reporting empty region
+        self.current_address = (mbi.get_region_size() != 0)
+            .then(|| mbi.get_base_address().checked_add(mbi.get_region_size()))
+            .flatten();
+
+        Some(mbi)
+    }
+}
+
+fn read_pointer_sized<B: MemoryBackend + ?Sized>(
+    backend: &B,
+    address: usize,
+    pointer_size: usize,
+) -> Result<usize> {
+    let mut buf = [0u8; size_of::<usize>()];
+    let len = pointer_size.min(buf.len());
+    backend.read_memory_into(address, &mut buf[..len])?;
+
+    Ok(usize::from_le_bytes(buf))
+}
+
+impl MemoryBackend for Handle {
+    fn read_memory_into(&self, address: usize, buf: &mut [u8]) -> Result<()> {
+        Handle::read_memory_into(self, address, buf)
+    }
+
+    fn write_memory(&self, address: usize, bytes: &[u8]) -> Result<()> {
+        Handle::write_memory(self, address, bytes)
+    }
+
+    fn query_memory(&self, address: usize) -> Option<MemoryBasicInformation> {
+        self.query_memory_basic_information(address)
+    }
+
+    fn alloc_memory(&self, size: usize, protection: PageProtectionFlags) -> Result<usize> {
+        let address = unsafe {
+            VirtualAllocEx(
+                **self,
+                None,
+                size,
+                (VirtualAllocationType::Commit | VirtualAllocationType::Reserve).into(),
+                protection.into(),
+            )
+        };
+        if address.is_null() {
+            return Err(Error::last_win32("VirtualAllocEx"));
+        }
+
+        Ok(address as usize)
+    }
+
+    fn free_memory(&self, address: usize) -> Result<()> {
+        unsafe { VirtualFreeEx(**self, address as *mut _, 0, MEM_RELEASE) }
+            .map_err(|e| Error::win32("VirtualFreeEx", e))
+    }
+
+    fn get_pointer_size(&self) -> Result<usize> {
+        Handle::get_pointer_size(self)
+    }
+
+    fn get_module_range(&self, name: &str) -> Result<Range<usize>> {
+        let module = self
+            .create_snapshot(HandleSnapshotFlag::SnapModule | HandleSnapshotFlag::SnapModule32)?
+            .find_module(name)
+            .ok_or(Error::NotFound)?;
+
+        Ok(module.get_address()..module.get_address() + module.get_size() as usize)
+    }
+
+    fn get_stack_ranges(&self) -> Result<Vec<Range<usize>>> {
+        Ok(self.stacks()?.iter().map(StackRange::get_range).collect())
+    }
+
+    fn flush_instruction_cache(&self, address: usize, size: usize) -> Result<()> {
+        Handle::flush_instruction_cache(self, address, size)
+    }
+}
+
+/// backend over a buffer for tests of code generic over [MemoryBackend]
+#[cfg(test)]
+pub(crate) mod testing {
+    use std::cell::RefCell;
+
+    use windows::Win32::System::Memory::{
+        MEMORY_BASIC_INFORMATION, MEM_COMMIT, PAGE_READWRITE, PAGE_TYPE,
+    };
+
+    use super::MemoryBackend;
+    use crate::error::{Error, Result};
+    use crate::memory::{MemoryBasicInformation, PageProtectionFlags};
+
+    /// backend over a buffer starting at address 0x1000
+    pub(crate) struct BufferBackend(pub(crate) RefCell<Vec<u8>>);
+
+    impl MemoryBackend for BufferBackend {
+        fn read_memory_into(&self, address: usize, buf: &mut [u8]) -> Result<()> {
+            let data = self.0.borrow();
+            let start = address.checked_sub(0x1000).ok_or(Error::InvalidInput)?;
+            let bytes = data
+                .get(start..start + buf.len())
+                .ok_or(Error::InvalidInput)?;
+            buf.copy_from_slice(bytes);
+            Ok(())
+        }
+
+        fn write_memory(&self, address: usize, bytes: &[u8]) -> Result<()> {
+            let mut data = self.0.borrow_mut();
+            let start = address.checked_sub(0x1000).ok_or(Error::InvalidInput)?;
+            data.get_mut(start..start + bytes.len())
+                .ok_or(Error::InvalidInput)?
+                .copy_from_slice(bytes);
+            Ok(())
+        }
+
+        fn query_memory(&self, address: usize) -> Option<MemoryBasicInformation> {
+            (address < 0x1000 + self.0.borrow().len()).then(|| {
+                MemoryBasicInformation::from(MEMORY_BASIC_INFORMATION {
+                    BaseAddress: 0x1000 as *mut _,
+                    AllocationBase: 0x1000 as *mut _,
+                    AllocationProtect: PAGE_READWRITE,
+                    RegionSize: self.0.borrow().len(),
+                    State: MEM_COMMIT,
+                    Protect: PAGE_READWRITE,
+                    Type: PAGE_TYPE(0x20000),
+                    ..Default::default()
+                })
+            })
+        }
+
+        fn alloc_memory(&self, _: usize, _: PageProtectionFlags) -> Result<usize> {
+            Err(Error::Unsupported)
+        }
+
+        fn free_memory(&self, _: usize) -> Result<()> {
+            Err(Error::Unsupported)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::testing::BufferBackend;
+    use super::MemoryBackend;
+
+    #[test]
+    fn reusing_helpers_over_custom_backend() {
+        let backend = BufferBackend(RefCell::new(vec![0u8; 0x40]));
+        backend.write(0x1000, 0x1010usize).unwrap();
+        backend.write(0x1018, 0x1020usize).unwrap();
+        backend.write(0x1020, 1234u32).unwrap();
+
+        assert_eq!(
+            backend.resolve_pointer_chain(0x1000, &[8, 0]).unwrap(),
+            0x1020
+        );
+        assert_eq!(backend.read::<u32>(0x1020).unwrap(), 1234);
+        assert_eq!(backend.memory_regions().count(), 1);
+
+        let mut dump = Vec::new();
+        assert_eq!(backend.dump_all(&mut dump).unwrap(), 1);
+    }
+}
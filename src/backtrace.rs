@@ -0,0 +1,231 @@
+use std::ffi::c_void;
+use std::fmt::{Display, Formatter};
+
+use windows::Win32::Foundation::HANDLE;
+use windows::Win32::System::Diagnostics::Debug::{
+    AddrModeFlat, StackWalk64, SymFunctionTableAccess64, SymGetModuleBase64, Wow64GetThreadContext,
+    ADDRESS64, STACKFRAME64, WOW64_CONTEXT, WOW64_CONTEXT_ALL,
+};
+use windows::Win32::System::SystemInformation::{IMAGE_FILE_MACHINE, IMAGE_FILE_MACHINE_I386};
+
+#[cfg(target_arch = "x86_64")]
+use windows::Win32::System::SystemInformation::IMAGE_FILE_MACHINE_AMD64 as NATIVE_MACHINE;
+#[cfg(target_arch = "aarch64")]
+use windows::Win32::System::SystemInformation::IMAGE_FILE_MACHINE_ARM64 as NATIVE_MACHINE;
+#[cfg(target_arch = "x86")]
+use windows::Win32::System::SystemInformation::IMAGE_FILE_MACHINE_I386 as NATIVE_MACHINE;
+
+use crate::dbghelp::{self, DbgHelpSession};
+use crate::error::{Error, Result};
+use crate::handle::{Handle, HandleSnapshotFlag};
+use crate::memory::format_address;
+use crate::module::Module;
+use crate::thread::Thread;
+
+/// frames walked before giving up, corrupted stack may never end
+const MAX_FRAMES: usize = 256;
+
+/// frame of a call stack
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frame {
+    address: usize,
+    return_address: usize,
+    frame_pointer: usize,
+    stack_pointer: usize,
+    module_name: Option<String>,
+    module_offset: usize,
+    symbol: Option<String>,
+}
+
+impl Frame {
+    /// address of the instruction the frame is executing
+    pub fn get_address(&self) -> usize {
+        self.address
+    }
+
+    /// address the frame return to
+    pub fn get_return_address(&self) -> usize {
+        self.return_address
+    }
+
+    /// frame pointer of the frame
+    pub fn get_frame_pointer(&self) -> usize {
+        self.frame_pointer
+    }
+
+    /// stack pointer of the frame
+    pub fn get_stack_pointer(&self) -> usize {
+        self.stack_pointer
+    }
+
+    /// name of the module containing the address, `None` for code outside of any module
+    pub fn get_module_name(&self) -> Option<&str> {
+        self.module_name.as_deref()
+    }
+
+    /// offset of the address from the start of its module
+    pub fn get_module_offset(&self) -> usize {
+        self.module_offset
+    }
+
+    /// symbol of the address like `kernel32!Sleep+0x10`, `None` when not symbolized
+    pub fn get_symbol(&self) -> Option<&str> {
+        self.symbol.as_deref()
+    }
+}
+
+/// format as `0x7ff6_1000_1234 kernel32.dll+0x1234 kernel32!Sleep+0x14`
+impl Display for Frame {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", format_address(self.address))?;
+        if let Some(module_name) = &self.module_name {
+            write!(f, " {}+{:#x}", module_name, self.module_offset)?;
+        }
+        if let Some(symbol) = &self.symbol {
+            write!(f, " {}", symbol)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Thread {
+    /// walk the call stack of the thread, which should be suspended and belong to the
+    /// process of `handle`.
+    ///
+    /// frames are annotated with their module but not symbolized, look at
+    /// `SymbolResolver::backtrace` of `symbols` feature for that.
+    pub fn backtrace(&self, handle: &Handle) -> Result<Vec<Frame>> {
+        // NOTE: DbgHelp is needed to find function tables while walking
+        let _session = DbgHelpSession::try_new(handle, None)?;
+        walk_stack(handle, self, |_| None)
+    }
+}
+
+unsafe extern "system" fn function_table_access(process: HANDLE, address: u64) -> *mut c_void {
+    SymFunctionTableAccess64(process, address)
+}
+
+unsafe extern "system" fn get_module_base(process: HANDLE, address: u64) -> u64 {
+    SymGetModuleBase64(process, address)
+}
+
+/// walk the call stack of `thread`, DbgHelp should be initialized for the process
+pub(crate) fn walk_stack<F>(handle: &Handle, thread: &Thread, symbolize: F) -> Result<Vec<Frame>>
+where
+    F: Fn(usize) -> Option<String>,
+{
+    let modules: Vec<Module> = handle
+        .create_snapshot(HandleSnapshotFlag::SnapModule | HandleSnapshotFlag::SnapModule32)?
+        .get_modules()
+        .collect();
+
+    // NOTE: StackWalk64 update the context as it unwind, keep it alive for the whole walk
+    let mut wow64_context;
+    let mut native_context;
+    let (machine, context, mut stack_frame): (IMAGE_FILE_MACHINE, *mut c_void, _) =
+        match handle.is_wow64()? {
+            true => {
+                wow64_context = WOW64_CONTEXT {
+                    ContextFlags: WOW64_CONTEXT_ALL,
+                    ..Default::default()
+                };
+                unsafe { Wow64GetThreadContext(**thread, &mut wow64_context) }
+                    .map_err(|e| Error::win32("Wow64GetThreadContext", e))?;
+
+                let stack_frame = new_stack_frame(
+                    wow64_context.Eip as usize,
+                    wow64_context.Esp as usize,
+                    wow64_context.Ebp as usize,
+                );
+                let context = &mut wow64_context as *mut WOW64_CONTEXT as *mut c_void;
+                (IMAGE_FILE_MACHINE_I386, context, stack_frame)
+            }
+            false => {
+                native_context = thread.get_context()?;
+
+                let stack_frame = new_stack_frame(
+                    native_context.get_instruction_pointer(),
+                    native_context.get_stack_pointer(),
+                    native_context.get_frame_pointer(),
+                );
+                let context = &mut *native_context as *mut _ as *mut c_void;
+                (NATIVE_MACHINE, context, stack_frame)
+            }
+        };
+
+    let mut frames = Vec::new();
+    while frames.len() < MAX_FRAMES {
+        let is_walked = unsafe {
+            let _lock = dbghelp::lock();
+            StackWalk64(
+                machine.0 as u32,
+                **handle,
+                **thread,
+                &mut stack_frame,
+                context,
+                None,
+                Some(function_table_access),
+                Some(get_module_base),
+                None,
+            )
+        };
+        let address = stack_frame.AddrPC.Offset as usize;
+        if !is_walked.as_bool() || address == 0 {
+            break;
+        }
+
+        let module = modules.iter().find(|module| module.contains(address));
+        frames.push(Frame {
+            address,
+            return_address: stack_frame.AddrReturn.Offset as usize,
+            frame_pointer: stack_frame.AddrFrame.Offset as usize,
+            stack_pointer: stack_frame.AddrStack.Offset as usize,
+            module_name: module.map(|e| e.get_name()),
+            module_offset: module.map_or(0, |e| address - e.get_address()),
+            symbol: symbolize(address),
+        });
+    }
+
+    Ok(frames)
+}
+
+fn new_stack_frame(address: usize, stack_pointer: usize, frame_pointer: usize) -> STACKFRAME64 {
+    let flat = |offset: usize| ADDRESS64 {
+        Offset: offset as u64,
+        Segment: 0,
+        Mode: AddrModeFlat,
+    };
+
+    STACKFRAME64 {
+        AddrPC: flat(address),
+        AddrFrame: flat(frame_pointer),
+        AddrStack: flat(stack_pointer),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Frame;
+
+    #[test]
+    fn formatting_frame() {
+        let mut frame = Frame {
+            address: 0x7FF6_1000_1234,
+            return_address: 0,
+            frame_pointer: 0,
+            stack_pointer: 0,
+            module_name: Some("kernel32.dll".to_string()),
+            module_offset: 0x1234,
+            symbol: None,
+        };
+        assert_eq!(frame.to_string(), "0x7ff6_1000_1234 kernel32.dll+0x1234");
+
+        frame.symbol = Some("kernel32!Sleep+0x14".to_string());
+        assert_eq!(
+            frame.to_string(),
+            "0x7ff6_1000_1234 kernel32.dll+0x1234 kernel32!Sleep+0x14"
+        );
+    }
+}
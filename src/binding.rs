@@ -0,0 +1,147 @@
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use crate::address::Address;
+use crate::error::Result;
+use crate::handle::Handle;
+use crate::memory::Pod;
+use crate::worker::StoppableThread;
+
+/// Value of a process polled from a background thread, like health shown by an overlay.
+///
+/// the latest value is kept behind a shared lock and changes are delivered to subscribers,
+/// polling stop when the binding dropped.
+pub struct Binding<T> {
+    value: Arc<RwLock<T>>,
+    subscribers: Arc<Mutex<Vec<Sender<T>>>>,
+    _thread: StoppableThread,
+}
+
+impl<T: Pod + PartialEq + Send + Sync> Binding<T> {
+    /// start reading value of type `T` at `address` on `interval`, `address` is resolved once
+    pub fn spawn<A: Into<Address>>(
+        handle: Arc<Handle>,
+        address: A,
+        interval: Duration,
+    ) -> Result<Self> {
+        let address = address.into().resolve(handle.as_ref())?;
+        Self::spawn_with(handle, interval, move |handle| handle.read(address))
+    }
+
+    /// start reading value of type `T` at the end of pointer chain on `interval`.
+    ///
+    /// the chain is followed again on every poll, look at [Handle::resolve_pointer_chain].
+    pub fn spawn_chain<A: Into<Address>>(
+        handle: Arc<Handle>,
+        base: A,
+        offsets: &[usize],
+        interval: Duration,
+    ) -> Result<Self> {
+        let base = base.into().resolve(handle.as_ref())?;
+        let offsets = offsets.to_vec();
+
+        Self::spawn_with(handle, interval, move |handle| {
+            handle.read(handle.resolve_pointer_chain(base, &offsets)?)
+        })
+    }
+}
+
+impl<T: Clone + PartialEq + Send + Sync + 'static> Binding<T> {
+    /// start calling `read` on `interval`, like reading a [RemoteStruct](crate::memory::RemoteStruct).
+    ///
+    /// the first read happen before returning and must succeed, later failed reads keep the
+    /// previous value.
+    pub fn spawn_with<F>(handle: Arc<Handle>, interval: Duration, mut read: F) -> Result<Self>
+    where
+        F: FnMut(&Handle) -> Result<T> + Send + 'static,
+    {
+        let value = Arc::new(RwLock::new(read(&handle)?));
+        let subscribers = Arc::new(Mutex::new(Vec::<Sender<T>>::new()));
+
+        let thread = StoppableThread::spawn_polling(interval, {
+            let value = value.clone();
+            let subscribers = subscribers.clone();
+            move || {
+                // NOTE: failed read keeps the last value, it is tried again next interval
+                if let Ok(current) = read(&handle) {
+                    publish(&value, &subscribers, current);
+                }
+
+                true
+            }
+        });
+
+        Ok(Self {
+            value,
+            subscribers,
+            _thread: thread,
+        })
+    }
+
+    /// latest value read
+    pub fn get_value(&self) -> T {
+        self.value.read().unwrap().clone()
+    }
+
+    /// lock the latest value is kept behind, to be shared with a renderer
+    pub fn get_shared(&self) -> Arc<RwLock<T>> {
+        self.value.clone()
+    }
+
+    /// channel receiving every value different from the previous one
+    pub fn subscribe(&self) -> Receiver<T> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribers.lock().unwrap().push(sender);
+
+        receiver
+    }
+
+    /// stop polling and wait for the background thread to exit
+    pub fn stop(self) {}
+}
+
+/// store `current` and send it to subscribers when it differ from the stored value
+fn publish<T: Clone + PartialEq>(
+    value: &RwLock<T>,
+    subscribers: &Mutex<Vec<Sender<T>>>,
+    current: T,
+) {
+    {
+        let mut value = value.write().unwrap();
+        if *value == current {
+            return;
+        }
+        *value = current.clone();
+    }
+
+    subscribers
+        .lock()
+        .unwrap()
+        .retain(|e| e.send(current.clone()).is_ok());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+    use std::sync::{Mutex, RwLock};
+
+    use super::publish;
+
+    #[test]
+    fn publishing_changes() {
+        let value = RwLock::new(1u32);
+        let (sender, receiver) = mpsc::channel();
+        let (dropped, _) = mpsc::channel();
+        let subscribers = Mutex::new(vec![sender, dropped]);
+
+        publish(&value, &subscribers, 1);
+        publish(&value, &subscribers, 2);
+        publish(&value, &subscribers, 2);
+        publish(&value, &subscribers, 3);
+
+        assert_eq!(*value.read().unwrap(), 3);
+        assert_eq!(receiver.try_iter().collect::<Vec<_>>(), vec![2, 3]);
+        assert_eq!(subscribers.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,266 @@
+use std::io::{Read, Write};
+use std::time::{Duration, Instant};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use windows::core::HSTRING;
+use windows::Win32::Foundation::{
+    CloseHandle, ERROR_BROKEN_PIPE, ERROR_FILE_NOT_FOUND, ERROR_PIPE_BUSY, ERROR_PIPE_CONNECTED,
+    GENERIC_READ, GENERIC_WRITE, HANDLE,
+};
+use windows::Win32::Storage::FileSystem::{
+    CreateFileW, ReadFile, WriteFile, FILE_FLAGS_AND_ATTRIBUTES, FILE_SHARE_MODE, OPEN_EXISTING,
+    PIPE_ACCESS_DUPLEX,
+};
+use windows::Win32::System::Pipes::{
+    ConnectNamedPipe, CreateNamedPipeW, DisconnectNamedPipe, PIPE_READMODE_BYTE,
+    PIPE_REJECT_REMOTE_CLIENTS, PIPE_TYPE_BYTE, PIPE_UNLIMITED_INSTANCES, PIPE_WAIT,
+};
+use windows::Win32::System::Threading::GetCurrentProcessId;
+
+use crate::error::{Error, Result};
+
+/// interval connecting to the pipe is retried at while the payload has not created it
+const CONNECT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// size of the buffers of the pipe in either direction
+const PIPE_BUFFER_SIZE: u32 = 0x10000;
+/// longest frame accepted, longer length is taken as garbage
+const MAX_FRAME_LEN: usize = 0x100_0000;
+
+/// Controlling side of a request and response channel with a payload injected into a process.
+///
+/// every request and response is a frame of json prefixed with its length as little endian
+/// `u32`, over a named pipe the payload serve with [BridgeServer]. the pipe is named after the
+/// id of the process, so the payload need not be told anything.
+pub struct Bridge {
+    process_id: u32,
+    pipe: Pipe,
+}
+
+impl Bridge {
+    /// connect to the payload of the process with the id, waiting for it to serve.
+    ///
+    /// wait forever when `timeout` is `None`.
+    pub fn connect(process_id: u32, timeout: Option<Duration>) -> Result<Self> {
+        let name = HSTRING::from(pipe_name(process_id));
+        let deadline = timeout.map(|e| Instant::now() + e);
+
+        loop {
+            match unsafe {
+                CreateFileW(
+                    &name,
+                    (GENERIC_READ | GENERIC_WRITE).0,
+                    FILE_SHARE_MODE(0),
+                    None,
+                    OPEN_EXISTING,
+                    FILE_FLAGS_AND_ATTRIBUTES(0),
+                    HANDLE::default(),
+                )
+            } {
+                Ok(raw) => {
+                    return Ok(Self {
+                        process_id,
+                        pipe: Pipe(raw),
+                    })
+                }
+                // NOTE: not served yet, or the only instance is taken by another client
+                Err(e)
+                    if e.code() == ERROR_FILE_NOT_FOUND.to_hresult()
+                        || e.code() == ERROR_PIPE_BUSY.to_hresult() => {}
+                Err(e) => return Err(Error::win32("CreateFileW", e)),
+            }
+
+            if deadline.is_some_and(|e| Instant::now() >= e) {
+                return Err(Error::Timeout);
+            }
+            std::thread::sleep(CONNECT_POLL_INTERVAL);
+        }
+    }
+
+    /// id of the process connected to
+    pub fn get_process_id(&self) -> u32 {
+        self.process_id
+    }
+
+    /// send `request` and wait for its response, as long as the payload take to handle it
+    pub fn call<Req, Resp>(&mut self, request: &Req) -> Result<Resp>
+    where
+        Req: Serialize,
+        Resp: DeserializeOwned,
+    {
+        write_frame(&mut self.pipe, request)?;
+        read_frame(&mut self.pipe)
+    }
+}
+
+/// Payload side of [Bridge], serving requests inside the process it is injected into.
+pub struct BridgeServer {
+    name: HSTRING,
+}
+
+impl BridgeServer {
+    /// server of the pipe named after the current process
+    pub fn new() -> Self {
+        Self {
+            name: HSTRING::from(pipe_name(unsafe { GetCurrentProcessId() })),
+        }
+    }
+
+    /// answer every request with `handler`, serving one client after another on the current
+    /// thread.
+    ///
+    /// client sending frame which does not decode into `Req` is disconnected. return only
+    /// when creating or connecting the pipe fail.
+    pub fn serve<Req, Resp, F>(&self, mut handler: F) -> Result<()>
+    where
+        Req: DeserializeOwned,
+        Resp: Serialize,
+        F: FnMut(Req) -> Resp,
+    {
+        loop {
+            let mut pipe = self.accept()?;
+
+            while let Ok(request) = read_frame(&mut pipe) {
+                if write_frame(&mut pipe, &handler(request)).is_err() {
+                    break;
+                }
+            }
+
+            let _ = unsafe { DisconnectNamedPipe(pipe.0) };
+        }
+    }
+
+    /// create an instance of the pipe and wait for a client to connect to it
+    fn accept(&self) -> Result<Pipe> {
+        let raw = unsafe {
+            CreateNamedPipeW(
+                &self.name,
+                PIPE_ACCESS_DUPLEX,
+                PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+                PIPE_UNLIMITED_INSTANCES,
+                PIPE_BUFFER_SIZE,
+                PIPE_BUFFER_SIZE,
+                0,
+                None,
+            )
+        };
+        if raw.is_invalid() {
+            return Err(Error::last_win32("CreateNamedPipeW"));
+        }
+        let pipe = Pipe(raw);
+
+        match unsafe { ConnectNamedPipe(pipe.0, None) } {
+            // NOTE: client connected between creating and waiting
+            Err(e) if e.code() != ERROR_PIPE_CONNECTED.to_hresult() => {
+                Err(Error::win32("ConnectNamedPipe", e))
+            }
+            _ => Ok(pipe),
+        }
+    }
+}
+
+impl Default for BridgeServer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// either end of a named pipe, closed when dropped
+struct Pipe(HANDLE);
+
+impl Read for Pipe {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let mut n = 0u32;
+        match unsafe { ReadFile(self.0, Some(buf), Some(&mut n), None) } {
+            Ok(()) => Ok(n as usize),
+            // NOTE: other end closed, end of file to readers
+            Err(e) if e.code() == ERROR_BROKEN_PIPE.to_hresult() => Ok(0),
+            Err(e) => Err(Error::win32("ReadFile", e).into()),
+        }
+    }
+}
+
+impl Write for Pipe {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let mut n = 0u32;
+        unsafe { WriteFile(self.0, Some(buf), Some(&mut n), None) }
+            .map_err(|e| Error::win32("WriteFile", e))?;
+
+        Ok(n as usize)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for Pipe {
+    fn drop(&mut self) {
+        let _ = unsafe { CloseHandle(self.0) };
+    }
+}
+
+fn pipe_name(process_id: u32) -> String {
+    format!("\\\\.\\pipe\\winmem-bridge-{}", process_id)
+}
+
+/// write `value` as json prefixed with its length
+fn write_frame<W: Write, T: Serialize>(writer: &mut W, value: &T) -> Result<()> {
+    let json = serde_json::to_vec(value).map_err(|e| Error::Io(e.into()))?;
+    let len = u32::try_from(json.len())
+        .ok()
+        .filter(|e| *e as usize <= MAX_FRAME_LEN)
+        .ok_or(Error::InvalidInput)?;
+
+    writer.write_all(&[&len.to_le_bytes()[..], &json].concat())?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// read json prefixed with its length into `T`
+fn read_frame<R: Read, T: DeserializeOwned>(reader: &mut R) -> Result<T> {
+    let mut len = [0u8; 4];
+    reader.read_exact(&mut len)?;
+    let len = u32::from_le_bytes(len) as usize;
+    if len > MAX_FRAME_LEN {
+        return Err(Error::InvalidInput);
+    }
+
+    let mut json = vec![0u8; len];
+    reader.read_exact(&mut json)?;
+
+    serde_json::from_slice(&json).map_err(|e| Error::Io(e.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::{read_frame, write_frame, MAX_FRAME_LEN};
+
+    #[test]
+    fn framing_values() {
+        let mut buffer = Vec::new();
+        write_frame(&mut buffer, &(1u32, "health".to_string())).unwrap();
+        write_frame(&mut buffer, &Some(2.5f64)).unwrap();
+        assert_eq!(&buffer[..4], &12u32.to_le_bytes());
+
+        let mut reader = Cursor::new(buffer.clone());
+        assert_eq!(
+            read_frame::<_, (u32, String)>(&mut reader).unwrap(),
+            (1, "health".to_string())
+        );
+        assert_eq!(
+            read_frame::<_, Option<f64>>(&mut reader).unwrap(),
+            Some(2.5)
+        );
+        assert!(read_frame::<_, u32>(&mut reader).is_err());
+
+        assert!(read_frame::<_, u32>(&mut Cursor::new(&buffer[..10])).is_err());
+        assert!(read_frame::<_, String>(&mut Cursor::new(&buffer[..16])).is_err());
+
+        let garbage = ((MAX_FRAME_LEN + 1) as u32).to_le_bytes();
+        assert!(read_frame::<_, u32>(&mut Cursor::new(garbage)).is_err());
+    }
+}
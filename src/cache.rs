@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+
+use crate::backend::MemoryBackend;
+use crate::error::{Error, Result};
+use crate::handle::Handle;
+use crate::memory::{self, Pod, PAGE_SIZE};
+use crate::pe::ReadFn;
+
+/// Reader of a process keeping every page it touched, so repeated small reads skip the syscall,
+/// through any [MemoryBackend].
+///
+/// cached pages go stale when the process write them, invalidate them to read again.
+pub struct CachedReader<'a, B: MemoryBackend + ?Sized = Handle> {
+    handle: &'a B,
+    pages: HashMap<usize, Vec<u8>>,
+}
+
+impl<'a, B: MemoryBackend + ?Sized> CachedReader<'a, B> {
+    /// empty cache reading through `handle`
+    pub fn new(handle: &'a B) -> Self {
+        Self {
+            handle,
+            pages: HashMap::new(),
+        }
+    }
+
+    /// read `len` bytes starting from `address`, reading whole pages not cached yet
+    pub fn read_memory(&mut self, address: usize, len: usize) -> Result<Vec<u8>> {
+        let handle = self.handle;
+        read_pages(
+            &mut self.pages,
+            &|address, len| handle.read_memory(address, len),
+            address,
+            len,
+        )
+    }
+
+    /// read value of `T` at `address` through the cache
+    pub fn read<T: Pod>(&mut self, address: usize) -> Result<T> {
+        let buf = self.read_memory(address, size_of::<T>())?;
+
+        memory::from_bytes(&buf).ok_or(Error::InvalidInput)
+    }
+
+    /// drop cached pages overlapping `address..address + len`
+    pub fn invalidate(&mut self, address: usize, len: usize) {
+        let first_page = address / PAGE_SIZE * PAGE_SIZE;
+        let end = address.saturating_add(len);
+        self.pages
+            .retain(|page, _| *page + PAGE_SIZE <= first_page || *page >= end);
+    }
+
+    /// drop every cached page
+    pub fn invalidate_all(&mut self) {
+        self.pages.clear();
+    }
+
+    /// number of pages in the cache
+    pub fn get_page_count(&self) -> usize {
+        self.pages.len()
+    }
+}
+
+fn read_pages(
+    pages: &mut HashMap<usize, Vec<u8>>,
+    read: &ReadFn,
+    address: usize,
+    len: usize,
+) -> Result<Vec<u8>> {
+    let end = address.checked_add(len).ok_or(Error::InvalidInput)?;
+    let mut bytes = Vec::with_capacity(len);
+
+    let mut current_address = address;
+    while current_address < end {
+        let page_address = current_address / PAGE_SIZE * PAGE_SIZE;
+        let page = match pages.get(&page_address) {
+            Some(page) => page,
+            None => pages
+                .entry(page_address)
+                .or_insert(read(page_address, PAGE_SIZE)?),
+        };
+
+        let offset = current_address - page_address;
+        let take = (PAGE_SIZE - offset).min(end - current_address);
+        bytes.extend_from_slice(&page[offset..offset + take]);
+        current_address += take;
+    }
+
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::{Cell, RefCell};
+    use std::collections::HashMap;
+
+    use super::{read_pages, CachedReader};
+    use crate::backend::testing::BufferBackend;
+    use crate::backend::MemoryBackend;
+    use crate::memory::PAGE_SIZE;
+
+    #[test]
+    fn reading_across_cached_pages() {
+        let calls = Cell::new(0);
+        let read = |address: usize, len: usize| {
+            calls.set(calls.get() + 1);
+            Ok((address..address + len)
+                .map(|e| (e / PAGE_SIZE) as u8)
+                .collect())
+        };
+        let mut pages = HashMap::new();
+
+        let bytes = read_pages(&mut pages, &read, PAGE_SIZE * 2 - 2, 4).unwrap();
+        assert_eq!(bytes, vec![1, 1, 2, 2]);
+        assert_eq!(calls.get(), 2);
+
+        read_pages(&mut pages, &read, PAGE_SIZE + 8, 8).unwrap();
+        assert_eq!(calls.get(), 2);
+        assert_eq!(pages.len(), 2);
+    }
+
+    #[test]
+    fn caching_backend_pages() {
+        let backend = BufferBackend(RefCell::new(vec![0u8; PAGE_SIZE * 2]));
+        backend.write(0x1010, 7u32).unwrap();
+        let mut reader = CachedReader::new(&backend);
+
+        assert_eq!(reader.read::<u32>(0x1010).unwrap(), 7);
+        backend.write(0x1010, 8u32).unwrap();
+        assert_eq!(reader.read::<u32>(0x1010).unwrap(), 7);
+        assert_eq!(reader.get_page_count(), 1);
+
+        reader.invalidate(0x1010, 4);
+        assert_eq!(reader.read::<u32>(0x1010).unwrap(), 8);
+    }
+}
@@ -0,0 +1,202 @@
+use crate::error::{Error, Result};
+use crate::handle::Handle;
+use crate::memory::{PageProtectionFlags, RemoteAllocation, VirtualAllocationType};
+
+/// argument of a function called by [Handle::call_remote]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RemoteArg {
+    /// integer or pointer passed as is
+    Value(u64),
+    /// bytes copied into memory of the process, its address is passed
+    Bytes(Vec<u8>),
+}
+
+impl RemoteArg {
+    /// nul terminated narrow string copied into memory of the process
+    pub fn str(s: &str) -> Self {
+        Self::Bytes(s.bytes().chain([0]).collect())
+    }
+
+    /// nul terminated wide string copied into memory of the process
+    pub fn wide_str(s: &str) -> Self {
+        Self::Bytes(
+            s.encode_utf16()
+                .chain([0])
+                .flat_map(|e| e.to_le_bytes())
+                .collect(),
+        )
+    }
+}
+
+impl From<u64> for RemoteArg {
+    fn from(value: u64) -> Self {
+        Self::Value(value)
+    }
+}
+
+impl From<usize> for RemoteArg {
+    fn from(value: usize) -> Self {
+        Self::Value(value as u64)
+    }
+}
+
+pub(crate) fn call_remote(handle: &Handle, address: usize, args: &[RemoteArg]) -> Result<u64> {
+    let mut allocations = Vec::new();
+    let values = args
+        .iter()
+        .map(|arg| match arg {
+            RemoteArg::Value(value) => Ok(*value),
+            RemoteArg::Bytes(bytes) => {
+                let allocation = alloc(handle, bytes.len(), PageProtectionFlags::ReadWrite)?;
+                handle.write_memory(allocation.get_address(), bytes)?;
+                let address = allocation.get_address() as u64;
+                allocations.push(allocation);
+                Ok(address)
+            }
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let pointer_size = handle.get_pointer_size()?;
+
+    // NOTE: stdcall thread routine already take one argument and return it in exit code
+    if pointer_size == 4 && values.len() <= 1 {
+        let parameter = values.first().copied().unwrap_or(0);
+        let parameter = u32::try_from(parameter).map_err(|_| Error::InvalidInput)?;
+        let exit_code = handle
+            .create_remote_thread(address, parameter as usize)?
+            .join(None)?;
+
+        return Ok(exit_code as u64);
+    }
+
+    // NOTE: result is stored at the start of the allocation, followed by the stub
+    let allocation = alloc(handle, 0x1000, PageProtectionFlags::ExecuteReadWrite)?;
+    let result_address = allocation.get_address() as u64;
+    let stub = match pointer_size {
+        4 => build_stub_x86(address as u64, &values, result_address)?,
+        _ => build_stub_x64(address as u64, &values, result_address),
+    };
+    if stub.len() + 8 > allocation.get_size() {
+        return Err(Error::InvalidInput);
+    }
+
+    handle.write_memory(allocation.get_address() + 8, &stub)?;
+    handle
+        .create_remote_thread(allocation.get_address() + 8, 0)?
+        .join(None)?;
+
+    handle.read::<u64>(allocation.get_address())
+}
+
+fn alloc(
+    handle: &Handle,
+    size: usize,
+    protection: PageProtectionFlags,
+) -> Result<RemoteAllocation<'_>> {
+    handle.alloc(
+        size.max(1),
+        protection,
+        VirtualAllocationType::Commit | VirtualAllocationType::Reserve,
+    )
+}
+
+/// thread routine calling `function` with microsoft x64 calling convention
+fn build_stub_x64(function: u64, args: &[u64], result_address: u64) -> Vec<u8> {
+    let stack_args = args.len().saturating_sub(4);
+    let stack_size = ((32 + stack_args * 8 + 15) & !15) as u32;
+
+    let mut stub = Vec::new();
+    // push rbx; mov rbx, rsp; sub rsp, stack_size
+    stub.extend([0x53, 0x48, 0x89, 0xE3, 0x48, 0x81, 0xEC]);
+    stub.extend(stack_size.to_le_bytes());
+
+    for (index, arg) in args.iter().enumerate().skip(4) {
+        // mov rax, arg; mov [rsp + 32 + 8 * n], rax
+        stub.extend([0x48, 0xB8]);
+        stub.extend(arg.to_le_bytes());
+        stub.extend([0x48, 0x89, 0x84, 0x24]);
+        stub.extend((32 + (index as u32 - 4) * 8).to_le_bytes());
+    }
+
+    // mov rcx, mov rdx, mov r8, mov r9
+    let registers: [[u8; 2]; 4] = [[0x48, 0xB9], [0x48, 0xBA], [0x49, 0xB8], [0x49, 0xB9]];
+    for (register, arg) in registers.iter().zip(args) {
+        stub.extend(register);
+        stub.extend(arg.to_le_bytes());
+    }
+
+    // mov rax, function; call rax; mov [result_address], rax
+    stub.extend([0x48, 0xB8]);
+    stub.extend(function.to_le_bytes());
+    stub.extend([0xFF, 0xD0, 0x48, 0xA3]);
+    stub.extend(result_address.to_le_bytes());
+
+    // mov rsp, rbx; pop rbx; xor eax, eax; ret
+    stub.extend([0x48, 0x89, 0xDC, 0x5B, 0x31, 0xC0, 0xC3]);
+
+    stub
+}
+
+/// thread routine calling `function` with arguments on stack, both cdecl and stdcall
+fn build_stub_x86(function: u64, args: &[u64], result_address: u64) -> Result<Vec<u8>> {
+    let to_u32 = |value: u64| u32::try_from(value).map_err(|_| Error::InvalidInput);
+
+    let mut stub = Vec::new();
+    // push ebp; mov ebp, esp
+    stub.extend([0x55, 0x89, 0xE5]);
+
+    for arg in args.iter().rev() {
+        // push arg
+        stub.push(0x68);
+        stub.extend(to_u32(*arg)?.to_le_bytes());
+    }
+
+    // mov eax, function; call eax; mov [result_address], eax; mov [result_address + 4], edx
+    stub.push(0xB8);
+    stub.extend(to_u32(function)?.to_le_bytes());
+    stub.extend([0xFF, 0xD0, 0xA3]);
+    stub.extend(to_u32(result_address)?.to_le_bytes());
+    stub.extend([0x89, 0x15]);
+    stub.extend(to_u32(result_address + 4)?.to_le_bytes());
+
+    // mov esp, ebp; pop ebp; xor eax, eax; ret 4
+    stub.extend([0x89, 0xEC, 0x5D, 0x31, 0xC0, 0xC2, 0x04, 0x00]);
+
+    Ok(stub)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build_stub_x64, build_stub_x86};
+
+    #[test]
+    fn building_x64_stub() {
+        let stub = build_stub_x64(0x7FF0_0000_1000, &[1, 2, 3, 4, 5], 0x2000);
+
+        // shadow space and one stack argument, kept 16 bytes aligned
+        assert_eq!(
+            &stub[..11],
+            &[0x53, 0x48, 0x89, 0xE3, 0x48, 0x81, 0xEC, 48, 0, 0, 0]
+        );
+        // fifth argument stored at [rsp + 32]
+        assert_eq!(&stub[11..13], &[0x48, 0xB8]);
+        assert_eq!(&stub[13..21], &5u64.to_le_bytes());
+        assert_eq!(&stub[21..29], &[0x48, 0x89, 0x84, 0x24, 32, 0, 0, 0]);
+        // first argument in rcx
+        assert_eq!(&stub[29..31], &[0x48, 0xB9]);
+        assert_eq!(&stub[31..39], &1u64.to_le_bytes());
+        assert!(stub.ends_with(&[0x48, 0x89, 0xDC, 0x5B, 0x31, 0xC0, 0xC3]));
+    }
+
+    #[test]
+    fn building_x86_stub() {
+        let stub = build_stub_x86(0x1000, &[1, 2], 0x2000).unwrap();
+
+        assert_eq!(
+            &stub[..13],
+            &[0x55, 0x89, 0xE5, 0x68, 2, 0, 0, 0, 0x68, 1, 0, 0, 0]
+        );
+        assert!(stub.ends_with(&[0x89, 0xEC, 0x5D, 0x31, 0xC0, 0xC2, 0x04, 0x00]));
+        assert!(build_stub_x86(0x1000, &[u64::MAX], 0x2000).is_err());
+    }
+}
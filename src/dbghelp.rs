@@ -0,0 +1,109 @@
+use std::sync::{Mutex, MutexGuard};
+
+use windows::core::{HSTRING, PCWSTR};
+use windows::Win32::Foundation::{BOOL, HANDLE};
+use windows::Win32::System::Diagnostics::Debug::{
+    SymCleanup, SymInitializeW, SymSetOptions, SYMOPT_DEFERRED_LOADS, SYMOPT_UNDNAME,
+};
+
+use crate::error::{Error, Result};
+use crate::handle::Handle;
+
+/// raw process handles DbgHelp is initialized for, with how many sessions of each are alive
+type Sessions = Vec<(isize, usize)>;
+
+static SESSIONS: Mutex<Sessions> = Mutex::new(Vec::new());
+
+/// lock every DbgHelp call has to be made holding, DbgHelp is single threaded
+pub(crate) fn lock() -> MutexGuard<'static, Sessions> {
+    // NOTE: sessions are still counted right after a panic while holding it
+    SESSIONS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// DbgHelp initialized for a process, cleaned up when the last session of the process dropped
+pub(crate) struct DbgHelpSession(HANDLE);
+
+impl DbgHelpSession {
+    /// initialize DbgHelp for the process of `handle`, loading symbols of its modules.
+    ///
+    /// `search_path` is only used by the first session of the process, later ones share it.
+    pub(crate) fn try_new(handle: &Handle, search_path: Option<&str>) -> Result<Self> {
+        let raw = **handle;
+        let mut sessions = lock();
+        if !acquire(&mut sessions, raw.0) {
+            return Ok(Self(raw));
+        }
+
+        let search_path = search_path.map(HSTRING::from);
+        let search_path = match &search_path {
+            Some(search_path) => PCWSTR(search_path.as_ptr()),
+            None => PCWSTR::null(),
+        };
+        let initialized = unsafe {
+            SymSetOptions(SYMOPT_UNDNAME | SYMOPT_DEFERRED_LOADS);
+            SymInitializeW(raw, search_path, BOOL(1))
+        };
+        if let Err(e) = initialized {
+            release(&mut sessions, raw.0);
+            return Err(Error::win32("SymInitializeW", e));
+        }
+
+        Ok(Self(raw))
+    }
+}
+
+impl Drop for DbgHelpSession {
+    fn drop(&mut self) {
+        let mut sessions = lock();
+        if release(&mut sessions, self.0 .0) {
+            let _ = unsafe { SymCleanup(self.0) };
+        }
+    }
+}
+
+/// count a session of `process`, `true` when it is the first one
+fn acquire(sessions: &mut Sessions, process: isize) -> bool {
+    match sessions.iter_mut().find(|(e, _)| *e == process) {
+        Some((_, count)) => {
+            *count += 1;
+            false
+        }
+        None => {
+            sessions.push((process, 1));
+            true
+        }
+    }
+}
+
+/// forget a session of `process`, `true` when it was the last one
+fn release(sessions: &mut Sessions, process: isize) -> bool {
+    let Some(index) = sessions.iter().position(|(e, _)| *e == process) else {
+        return false;
+    };
+
+    sessions[index].1 -= 1;
+    if sessions[index].1 > 0 {
+        return false;
+    }
+    sessions.swap_remove(index);
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{acquire, release};
+
+    #[test]
+    fn counting_sessions() {
+        let mut sessions = Vec::new();
+        assert!(acquire(&mut sessions, 4));
+        assert!(!acquire(&mut sessions, 4));
+        assert!(acquire(&mut sessions, 8));
+
+        assert!(!release(&mut sessions, 4));
+        assert!(release(&mut sessions, 4));
+        assert!(!release(&mut sessions, 4));
+        assert_eq!(sessions, vec![(8, 1)]);
+    }
+}
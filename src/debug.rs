@@ -0,0 +1,733 @@
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::time::Duration;
+
+use windows::Win32::Foundation::{CloseHandle, BOOL, ERROR_SEM_TIMEOUT, HANDLE, NTSTATUS};
+use windows::Win32::System::Diagnostics::Debug::{
+    ContinueDebugEvent, DebugActiveProcess, DebugActiveProcessStop, DebugSetProcessKillOnExit,
+    WaitForDebugEventEx, CREATE_PROCESS_DEBUG_EVENT, CREATE_THREAD_DEBUG_EVENT, DEBUG_EVENT,
+    EXCEPTION_DEBUG_EVENT, EXIT_PROCESS_DEBUG_EVENT, EXIT_THREAD_DEBUG_EVENT, LOAD_DLL_DEBUG_EVENT,
+    OUTPUT_DEBUG_STRING_EVENT, RIP_EVENT, UNLOAD_DLL_DEBUG_EVENT,
+};
+use windows::Win32::System::Threading::INFINITE;
+
+use crate::error::{Error, Result};
+use crate::handle::Handle;
+use crate::thread::{Thread, ThreadContext};
+
+/// `DBG_CONTINUE`
+const DBG_CONTINUE: NTSTATUS = NTSTATUS(0x00010002);
+/// `DBG_EXCEPTION_NOT_HANDLED`
+const DBG_EXCEPTION_NOT_HANDLED: NTSTATUS = NTSTATUS(0x80010001_u32 as i32);
+
+/// `EXCEPTION_BREAKPOINT` and `STATUS_WX86_BREAKPOINT` of WOW64 process
+const BREAKPOINT_CODES: [u32; 2] = [0x80000003, 0x4000001F];
+/// `EXCEPTION_SINGLE_STEP` and `STATUS_WX86_SINGLE_STEP` of WOW64 process
+const SINGLE_STEP_CODES: [u32; 2] = [0x80000004, 0x4000001E];
+
+/// `int3` instruction
+const INT3: u8 = 0xCC;
+
+/// count of hardware breakpoint slots, `DR0` to `DR3`
+const HARDWARE_BREAKPOINT_SLOTS: usize = 4;
+
+/// access that trigger a hardware breakpoint
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BreakpointKind {
+    /// instruction at the address is executed
+    Execute,
+    /// the address is written
+    Write,
+    /// the address is read or written
+    Access,
+}
+
+impl BreakpointKind {
+    fn get_bits(&self) -> usize {
+        match self {
+            Self::Execute => 0b00,
+            Self::Write => 0b01,
+            Self::Access => 0b11,
+        }
+    }
+
+    fn from_bits(bits: usize) -> Option<Self> {
+        match bits {
+            0b00 => Some(Self::Execute),
+            0b01 => Some(Self::Write),
+            0b11 => Some(Self::Access),
+            _ => None,
+        }
+    }
+}
+
+/// breakpoint set on debug registers of a thread
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct HardwareBreakpoint {
+    slot: usize,
+    address: usize,
+    kind: BreakpointKind,
+    size: usize,
+}
+
+impl HardwareBreakpoint {
+    /// index of the debug register holding the address, `0` for `DR0`
+    pub fn get_slot(&self) -> usize {
+        self.slot
+    }
+
+    /// address watched by the breakpoint
+    pub fn get_address(&self) -> usize {
+        self.address
+    }
+
+    /// access that trigger the breakpoint
+    pub fn get_kind(&self) -> BreakpointKind {
+        self.kind
+    }
+
+    /// count of bytes watched by the breakpoint
+    pub fn get_size(&self) -> usize {
+        self.size
+    }
+}
+
+/// `DR0` to `DR3` and `DR7` of a thread
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct DebugRegisters {
+    addresses: [usize; HARDWARE_BREAKPOINT_SLOTS],
+    control: usize,
+}
+
+impl DebugRegisters {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    fn from_context(context: &ThreadContext) -> Self {
+        Self {
+            addresses: [
+                context.Dr0 as usize,
+                context.Dr1 as usize,
+                context.Dr2 as usize,
+                context.Dr3 as usize,
+            ],
+            control: context.Dr7 as usize,
+        }
+    }
+
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    fn apply(&self, context: &mut ThreadContext) {
+        context.Dr0 = self.addresses[0] as _;
+        context.Dr1 = self.addresses[1] as _;
+        context.Dr2 = self.addresses[2] as _;
+        context.Dr3 = self.addresses[3] as _;
+        context.Dr7 = self.control as _;
+    }
+
+    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+    fn from_context(_context: &ThreadContext) -> Self {
+        Self::default()
+    }
+
+    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+    fn apply(&self, _context: &mut ThreadContext) {}
+
+    fn is_enabled(&self, slot: usize) -> bool {
+        self.control & (1 << (slot * 2)) != 0
+    }
+
+    fn set(&mut self, address: usize, kind: BreakpointKind, size: usize) -> Result<usize> {
+        let size_bits = match (kind, size) {
+            (BreakpointKind::Execute, 1) => 0b00,
+            (BreakpointKind::Execute, _) => return Err(Error::InvalidInput),
+            (_, 1) => 0b00,
+            (_, 2) => 0b01,
+            (_, 4) => 0b11,
+            (_, 8) if cfg!(target_pointer_width = "64") => 0b10,
+            _ => return Err(Error::InvalidInput),
+        };
+        if !address.is_multiple_of(size) {
+            return Err(Error::InvalidInput);
+        }
+
+        let slot = (0..HARDWARE_BREAKPOINT_SLOTS)
+            .find(|slot| !self.is_enabled(*slot))
+            .ok_or(Error::NotFound)?;
+
+        self.addresses[slot] = address;
+        self.control &= !(0b1111 << (16 + slot * 4));
+        self.control |= (kind.get_bits() | size_bits << 2) << (16 + slot * 4);
+        self.control |= 1 << (slot * 2);
+
+        Ok(slot)
+    }
+
+    fn clear(&mut self, slot: usize) {
+        self.addresses[slot] = 0;
+        self.control &= !(0b11 << (slot * 2));
+        self.control &= !(0b1111 << (16 + slot * 4));
+    }
+
+    fn get_breakpoints(&self) -> Vec<HardwareBreakpoint> {
+        (0..HARDWARE_BREAKPOINT_SLOTS)
+            .filter(|slot| self.is_enabled(*slot))
+            .filter_map(|slot| {
+                let bits = self.control >> (16 + slot * 4);
+                let size = match (bits >> 2) & 0b11 {
+                    0b00 => 1,
+                    0b01 => 2,
+                    0b11 => 4,
+                    _ => 8,
+                };
+
+                Some(HardwareBreakpoint {
+                    slot,
+                    address: self.addresses[slot],
+                    kind: BreakpointKind::from_bits(bits & 0b11)?,
+                    size,
+                })
+            })
+            .collect()
+    }
+}
+
+/// `int3` written over an instruction, remembering the byte it replaced
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Breakpoint {
+    address: usize,
+    original_byte: u8,
+    enabled: bool,
+}
+
+impl Breakpoint {
+    /// write `int3` at `address` in memory of `handle`
+    pub fn set(handle: &Handle, address: usize) -> Result<Self> {
+        let mut breakpoint = Self {
+            address,
+            original_byte: handle.read::<u8>(address)?,
+            enabled: false,
+        };
+        breakpoint.enable(handle)?;
+
+        Ok(breakpoint)
+    }
+
+    /// address of the breakpoint
+    pub fn get_address(&self) -> usize {
+        self.address
+    }
+
+    /// byte replaced by `int3`
+    pub fn get_original_byte(&self) -> u8 {
+        self.original_byte
+    }
+
+    /// whether `int3` is currently written
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// write `int3` back
+    pub fn enable(&mut self, handle: &Handle) -> Result<()> {
+        if !self.enabled {
+            self.write_byte(handle, INT3)?;
+            self.enabled = true;
+        }
+
+        Ok(())
+    }
+
+    /// restore the original byte
+    pub fn disable(&mut self, handle: &Handle) -> Result<()> {
+        if self.enabled {
+            self.write_byte(handle, self.original_byte)?;
+            self.enabled = false;
+        }
+
+        Ok(())
+    }
+
+    fn write_byte(&self, handle: &Handle, byte: u8) -> Result<()> {
+        handle.write(self.address, byte)?;
+        handle.flush_instruction_cache(self.address, 1)
+    }
+}
+
+/// called when software breakpoint hit, the context is applied to the thread afterward
+type BreakpointCallback = dyn FnMut(&DebugEvent, &mut ThreadContext);
+
+/// what happened in the debugged process
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DebugEventKind {
+    /// `int3` executed at the address
+    Breakpoint {
+        /// `EXCEPTION_BREAKPOINT`, or `STATUS_WX86_BREAKPOINT` of WOW64 process
+        code: u32,
+        /// address of the breakpoint instruction
+        address: usize,
+        /// whether the debugger see the exception before handler of the process
+        first_chance: bool,
+    },
+    /// single step or hardware breakpoint triggered at the address
+    SingleStep {
+        /// `EXCEPTION_SINGLE_STEP`, or `STATUS_WX86_SINGLE_STEP` of WOW64 process
+        code: u32,
+        /// address of the instruction
+        address: usize,
+        /// whether the debugger see the exception before handler of the process
+        first_chance: bool,
+    },
+    /// any other exception
+    Exception {
+        /// exception code, like `0xC0000005` for access violation
+        code: u32,
+        /// address of the instruction raising the exception
+        address: usize,
+        /// whether the debugger see the exception before handler of the process
+        first_chance: bool,
+    },
+    /// process is created or attached
+    CreateProcess {
+        /// base address of the executable image
+        base_address: usize,
+    },
+    /// process exited
+    ExitProcess {
+        /// exit code of the process
+        exit_code: u32,
+    },
+    /// thread is created
+    CreateThread {
+        /// start address of the thread
+        start_address: usize,
+    },
+    /// thread exited
+    ExitThread {
+        /// exit code of the thread
+        exit_code: u32,
+    },
+    /// dll is loaded
+    LoadDll {
+        /// base address of the dll
+        base_address: usize,
+    },
+    /// dll is unloaded
+    UnloadDll {
+        /// base address of the dll
+        base_address: usize,
+    },
+    /// process called `OutputDebugString`
+    OutputString {
+        /// the string, empty when it can not be read
+        message: String,
+    },
+    /// debugging error
+    Rip {
+        /// win32 error code
+        error: u32,
+    },
+    /// event with a code not known to the crate
+    Unknown(u32),
+}
+
+impl DebugEventKind {
+    fn from_exception(code: u32, address: usize, first_chance: bool) -> Self {
+        match code {
+            _ if BREAKPOINT_CODES.contains(&code) => Self::Breakpoint {
+                code,
+                address,
+                first_chance,
+            },
+            _ if SINGLE_STEP_CODES.contains(&code) => Self::SingleStep {
+                code,
+                address,
+                first_chance,
+            },
+            _ => Self::Exception {
+                code,
+                address,
+                first_chance,
+            },
+        }
+    }
+}
+
+/// event reported by [Debugger::wait_event]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DebugEvent {
+    process_id: u32,
+    thread_id: u32,
+    kind: DebugEventKind,
+}
+
+impl DebugEvent {
+    /// process id of the event
+    pub fn get_process_id(&self) -> u32 {
+        self.process_id
+    }
+
+    /// thread id of the event
+    pub fn get_thread_id(&self) -> u32 {
+        self.thread_id
+    }
+
+    /// what happened
+    pub fn get_kind(&self) -> &DebugEventKind {
+        &self.kind
+    }
+}
+
+/// Debugger attached to a process, detached when dropped.
+///
+/// events must be waited and continued from the thread that attached, so it is not `Send`.
+pub struct Debugger {
+    process_id: u32,
+    handle: Handle,
+    breakpoints: HashMap<usize, (Breakpoint, Box<BreakpointCallback>)>,
+    /// thread stepping over a breakpoint, to be enabled again afterward
+    stepping: HashMap<u32, usize>,
+    // NOTE: windows deliver events of the process only to the thread that attached
+    _thread_bound: PhantomData<*const ()>,
+}
+
+impl Debugger {
+    /// attach to the process as debugger, the process keep running after detached
+    pub fn attach(process_id: u32) -> Result<Self> {
+        let handle = Handle::try_from(process_id)?;
+
+        unsafe { DebugActiveProcess(process_id) }
+            .map_err(|e| Error::win32("DebugActiveProcess", e))?;
+        let _ = unsafe { DebugSetProcessKillOnExit(BOOL(0)) };
+
+        Ok(Self {
+            process_id,
+            handle,
+            breakpoints: HashMap::new(),
+            stepping: HashMap::new(),
+            _thread_bound: PhantomData,
+        })
+    }
+
+    /// process id of the debugged process
+    pub fn get_process_id(&self) -> u32 {
+        self.process_id
+    }
+
+    /// handle of the debugged process
+    pub fn get_handle(&self) -> &Handle {
+        &self.handle
+    }
+
+    /// wait for the next event, wait forever when `timeout` is `None`.
+    ///
+    /// the process stay suspended until [Debugger::continue_event] called.
+    pub fn wait_event(&self, timeout: Option<Duration>) -> Result<DebugEvent> {
+        let milliseconds =
+            timeout.map_or(INFINITE, |e| e.as_millis().min(INFINITE as u128 - 1) as u32);
+
+        let mut raw = DEBUG_EVENT::default();
+        unsafe { WaitForDebugEventEx(&mut raw, milliseconds) }.map_err(|e| {
+            let error = Error::win32("WaitForDebugEventEx", e);
+            match error.get_win32_code() {
+                Some(code) if code == ERROR_SEM_TIMEOUT.0 => Error::Timeout,
+                _ => error,
+            }
+        })?;
+
+        Ok(DebugEvent {
+            process_id: raw.dwProcessId,
+            thread_id: raw.dwThreadId,
+            kind: self.to_event_kind(&raw),
+        })
+    }
+
+    /// let the process run after the event.
+    ///
+    /// `handled` tell whether exception of the event is handled by the debugger,
+    /// otherwise it is passed to handler of the process.
+    pub fn continue_event(&self, event: &DebugEvent, handled: bool) -> Result<()> {
+        let status = match handled {
+            true => DBG_CONTINUE,
+            false => DBG_EXCEPTION_NOT_HANDLED,
+        };
+
+        unsafe { ContinueDebugEvent(event.process_id, event.thread_id, status) }
+            .map_err(|e| Error::win32("ContinueDebugEvent", e))
+    }
+
+    /// set software breakpoint calling `callback` on every hit.
+    ///
+    /// the breakpoint is handled by [Debugger::handle_event]. only for x86 process which
+    /// bitness match the current process.
+    pub fn set_breakpoint<F>(&mut self, address: usize, callback: F) -> Result<()>
+    where
+        F: FnMut(&DebugEvent, &mut ThreadContext) + 'static,
+    {
+        if self.breakpoints.contains_key(&address) {
+            return Err(Error::InvalidInput);
+        }
+
+        let breakpoint = Breakpoint::set(&self.handle, address)?;
+        self.breakpoints
+            .insert(address, (breakpoint, Box::new(callback)));
+
+        Ok(())
+    }
+
+    /// remove software breakpoint, restoring the original byte
+    pub fn remove_breakpoint(&mut self, address: usize) -> Result<()> {
+        let (mut breakpoint, _) = self.breakpoints.remove(&address).ok_or(Error::NotFound)?;
+        self.stepping.retain(|_, e| *e != address);
+
+        breakpoint.disable(&self.handle)
+    }
+
+    /// software breakpoints currently set
+    pub fn get_breakpoints(&self) -> impl Iterator<Item = &Breakpoint> + '_ {
+        self.breakpoints.values().map(|(breakpoint, _)| breakpoint)
+    }
+
+    /// handle the event if it belong to a software breakpoint, returning whether it is handled.
+    ///
+    /// on hit, the callback is called, then the original instruction is single stepped
+    /// and the breakpoint is written back. handled event should be continued as handled.
+    pub fn handle_event(&mut self, event: &DebugEvent) -> Result<bool> {
+        match event.get_kind() {
+            DebugEventKind::Breakpoint { address, .. } => {
+                let (breakpoint, callback) = match self.breakpoints.get_mut(address) {
+                    Some(entry) => entry,
+                    None => return Ok(false),
+                };
+
+                let thread = Thread::try_from(event.get_thread_id())?;
+                let mut context = thread.get_context()?;
+                // NOTE: instruction pointer is past `int3`, rewind to execute the original
+                context.set_instruction_pointer(*address);
+                callback(event, &mut context);
+
+                breakpoint.disable(&self.handle)?;
+                context.set_single_step(true);
+                thread.set_context(&context)?;
+                self.stepping.insert(event.get_thread_id(), *address);
+
+                Ok(true)
+            }
+            DebugEventKind::SingleStep { .. } => {
+                let address = match self.stepping.remove(&event.get_thread_id()) {
+                    Some(address) => address,
+                    None => return Ok(false),
+                };
+
+                if let Some((breakpoint, _)) = self.breakpoints.get_mut(&address) {
+                    breakpoint.enable(&self.handle)?;
+                }
+
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    fn to_event_kind(&self, raw: &DEBUG_EVENT) -> DebugEventKind {
+        let close_file = |file: HANDLE| {
+            if !file.is_invalid() && file.0 != 0 {
+                let _ = unsafe { CloseHandle(file) };
+            }
+        };
+
+        unsafe {
+            match raw.dwDebugEventCode {
+                EXCEPTION_DEBUG_EVENT => {
+                    let info = &raw.u.Exception;
+                    DebugEventKind::from_exception(
+                        info.ExceptionRecord.ExceptionCode.0 as u32,
+                        info.ExceptionRecord.ExceptionAddress as usize,
+                        info.dwFirstChance != 0,
+                    )
+                }
+                CREATE_PROCESS_DEBUG_EVENT => {
+                    let info = &raw.u.CreateProcessInfo;
+                    close_file(info.hFile);
+                    DebugEventKind::CreateProcess {
+                        base_address: info.lpBaseOfImage as usize,
+                    }
+                }
+                EXIT_PROCESS_DEBUG_EVENT => DebugEventKind::ExitProcess {
+                    exit_code: raw.u.ExitProcess.dwExitCode,
+                },
+                CREATE_THREAD_DEBUG_EVENT => DebugEventKind::CreateThread {
+                    start_address: raw.u.CreateThread.lpStartAddress.map_or(0, |e| e as usize),
+                },
+                EXIT_THREAD_DEBUG_EVENT => DebugEventKind::ExitThread {
+                    exit_code: raw.u.ExitThread.dwExitCode,
+                },
+                LOAD_DLL_DEBUG_EVENT => {
+                    let info = &raw.u.LoadDll;
+                    close_file(info.hFile);
+                    DebugEventKind::LoadDll {
+                        base_address: info.lpBaseOfDll as usize,
+                    }
+                }
+                UNLOAD_DLL_DEBUG_EVENT => DebugEventKind::UnloadDll {
+                    base_address: raw.u.UnloadDll.lpBaseOfDll as usize,
+                },
+                OUTPUT_DEBUG_STRING_EVENT => {
+                    let info = &raw.u.DebugString;
+                    let address = info.lpDebugStringData.0 as usize;
+                    let len = info.nDebugStringLength as usize;
+                    let message = match info.fUnicode {
+                        0 => self.handle.read_cstring(address, len),
+                        _ => self.handle.read_wstring(address, len),
+                    };
+
+                    DebugEventKind::OutputString {
+                        message: message.unwrap_or_default(),
+                    }
+                }
+                RIP_EVENT => DebugEventKind::Rip {
+                    error: raw.u.RipInfo.dwError,
+                },
+                code => DebugEventKind::Unknown(code.0),
+            }
+        }
+    }
+
+    /// set breakpoint on a free debug register of the thread, returning the slot used.
+    ///
+    /// `size` is 1 for execute, and 1, 2, 4, or 8 aligned to `address` otherwise.
+    /// the thread is suspended while its context changed, so it must not be the current thread.
+    pub fn set_hardware_breakpoint(
+        thread: &Thread,
+        address: usize,
+        kind: BreakpointKind,
+        size: usize,
+    ) -> Result<usize> {
+        Self::update_debug_registers(thread, |registers| registers.set(address, kind, size))
+    }
+
+    /// hardware breakpoints currently set on the thread
+    pub fn get_hardware_breakpoints(thread: &Thread) -> Result<Vec<HardwareBreakpoint>> {
+        Self::update_debug_registers(thread, |registers| Ok(registers.get_breakpoints()))
+    }
+
+    /// clear hardware breakpoint on the slot of the thread
+    pub fn clear_hardware_breakpoint(thread: &Thread, slot: usize) -> Result<()> {
+        if slot >= HARDWARE_BREAKPOINT_SLOTS {
+            return Err(Error::InvalidInput);
+        }
+
+        Self::update_debug_registers(thread, |registers| {
+            registers.clear(slot);
+            Ok(())
+        })
+    }
+
+    /// clear every hardware breakpoint of the thread
+    pub fn clear_hardware_breakpoints(thread: &Thread) -> Result<()> {
+        Self::update_debug_registers(thread, |registers| {
+            (0..HARDWARE_BREAKPOINT_SLOTS).for_each(|slot| registers.clear(slot));
+            Ok(())
+        })
+    }
+
+    fn update_debug_registers<T>(
+        thread: &Thread,
+        f: impl FnOnce(&mut DebugRegisters) -> Result<T>,
+    ) -> Result<T> {
+        thread.suspend()?;
+
+        let result = thread.get_context().and_then(|mut context| {
+            let mut registers = DebugRegisters::from_context(&context);
+            let before = registers;
+            let value = f(&mut registers)?;
+
+            if registers != before {
+                registers.apply(&mut context);
+                thread.set_context(&context)?;
+            }
+
+            Ok(value)
+        });
+
+        thread.resume()?;
+        result
+    }
+}
+
+impl Drop for Debugger {
+    fn drop(&mut self) {
+        for (breakpoint, _) in self.breakpoints.values_mut() {
+            let _ = breakpoint.disable(&self.handle);
+        }
+        let _ = unsafe { DebugActiveProcessStop(self.process_id) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BreakpointKind, DebugEventKind, DebugRegisters};
+
+    #[test]
+    fn setting_debug_registers() {
+        let mut registers = DebugRegisters::default();
+
+        assert_eq!(
+            registers.set(0x1000, BreakpointKind::Execute, 1).ok(),
+            Some(0)
+        );
+        assert_eq!(
+            registers.set(0x2004, BreakpointKind::Write, 4).ok(),
+            Some(1)
+        );
+        assert!(registers.set(0x2001, BreakpointKind::Write, 4).is_err());
+        assert!(registers.set(0x2000, BreakpointKind::Execute, 4).is_err());
+        assert_eq!(registers.control, 0b1101_0000 << 16 | 0b0101);
+
+        let breakpoints = registers.get_breakpoints();
+        assert_eq!(breakpoints.len(), 2);
+        assert_eq!(breakpoints[1].get_address(), 0x2004);
+        assert_eq!(breakpoints[1].get_kind(), BreakpointKind::Write);
+        assert_eq!(breakpoints[1].get_size(), 4);
+
+        registers.clear(0);
+        assert_eq!(
+            registers.set(0x3000, BreakpointKind::Access, 2).ok(),
+            Some(0)
+        );
+        assert_eq!(
+            registers.set(0x3000, BreakpointKind::Access, 2).ok(),
+            Some(2)
+        );
+        assert_eq!(
+            registers.set(0x3000, BreakpointKind::Access, 2).ok(),
+            Some(3)
+        );
+        assert!(registers.set(0x3000, BreakpointKind::Access, 2).is_err());
+    }
+
+    #[test]
+    fn classifying_exceptions() {
+        assert_eq!(
+            DebugEventKind::from_exception(0x80000003, 0x1000, true),
+            DebugEventKind::Breakpoint {
+                code: 0x80000003,
+                address: 0x1000,
+                first_chance: true
+            }
+        );
+        assert_eq!(
+            DebugEventKind::from_exception(0x4000001E, 0x1000, false),
+            DebugEventKind::SingleStep {
+                code: 0x4000001E,
+                address: 0x1000,
+                first_chance: false
+            }
+        );
+        assert_eq!(
+            DebugEventKind::from_exception(0xC0000005, 0x1000, false),
+            DebugEventKind::Exception {
+                code: 0xC0000005,
+                address: 0x1000,
+                first_chance: false
+            }
+        );
+    }
+}
@@ -0,0 +1,84 @@
+use iced_x86::{BlockEncoder, BlockEncoderOptions, Decoder, DecoderOptions, InstructionBlock};
+
+use crate::error::{Error, Result};
+
+pub use iced_x86::Instruction;
+
+/// longest x86 instruction in bytes
+pub(crate) const MAX_INSTRUCTION_LEN: usize = 15;
+
+/// decode up to `count` instructions of `bytes` located at `address`, stop at invalid one
+pub(crate) fn decode(bytes: &[u8], address: usize, bitness: u32, count: usize) -> Vec<Instruction> {
+    Decoder::with_ip(bitness, bytes, address as u64, DecoderOptions::NONE)
+        .into_iter()
+        .take_while(|e| !e.is_invalid())
+        .take(count)
+        .collect()
+}
+
+/// whole instructions at the start of `bytes` covering at least `min_len` bytes
+pub(crate) fn steal_instructions(
+    bytes: &[u8],
+    address: usize,
+    bitness: u32,
+    min_len: usize,
+) -> Result<Vec<Instruction>> {
+    let mut instructions = Vec::new();
+    let mut len = 0usize;
+
+    for instruction in Decoder::with_ip(bitness, bytes, address as u64, DecoderOptions::NONE) {
+        if len >= min_len {
+            break;
+        }
+        if instruction.is_invalid() {
+            return Err(Error::Unsupported);
+        }
+
+        len += instruction.len();
+        instructions.push(instruction);
+    }
+
+    if len < min_len {
+        return Err(Error::InvalidInput);
+    }
+
+    Ok(instructions)
+}
+
+/// encode `instructions` to run from `address`, fixing up relative branches and operands
+pub(crate) fn relocate(
+    instructions: &[Instruction],
+    bitness: u32,
+    address: usize,
+) -> Result<Vec<u8>> {
+    BlockEncoder::encode(
+        bitness,
+        InstructionBlock::new(instructions, address as u64),
+        BlockEncoderOptions::NONE,
+    )
+    .map(|e| e.code_buffer)
+    .map_err(|_| Error::Unsupported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, relocate, steal_instructions};
+
+    #[test]
+    fn stealing_whole_instructions() {
+        // push rbp; mov rbp, rsp; sub rsp, 0x20; call rel32
+        let bytes = [
+            0x55, 0x48, 0x89, 0xE5, 0x48, 0x83, 0xEC, 0x20, 0xE8, 0x00, 0x00, 0x00, 0x00,
+        ];
+
+        assert_eq!(decode(&bytes, 0x1000, 64, 10).len(), 4);
+
+        let stolen = steal_instructions(&bytes, 0x1000, 64, 5).unwrap();
+        assert_eq!(stolen.iter().map(|e| e.len()).sum::<usize>(), 8);
+
+        let stolen = steal_instructions(&bytes, 0x1000, 64, 9).unwrap();
+        let relocated = relocate(&stolen, 64, 0x2000).unwrap();
+        // call target 0x100D is now 0x100D - 0x200D away
+        assert_eq!(&relocated[8..], &[0xE8, 0x00, 0xF0, 0xFF, 0xFF]);
+    }
+}
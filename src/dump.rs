@@ -0,0 +1,170 @@
+use std::io::{Read, Write};
+
+use crate::backend::MemoryBackend;
+use crate::error::{Error, Result};
+use crate::memory::MemoryBasicInformationFilter;
+
+/// first bytes of a dump made by [Handle::dump_all]
+pub const DUMP_MAGIC: &[u8; 8] = b"WINMEMDP";
+
+const CHUNK_SIZE: usize = 0x10000;
+
+/// region entry in the index header of a dump
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DumpRegion {
+    address: u64,
+    size: u64,
+    protect: u32,
+}
+
+impl DumpRegion {
+    /// base address of the region in the process
+    pub fn get_address(&self) -> u64 {
+        self.address
+    }
+
+    /// size of the region, also the number of bytes it take in the dump
+    pub fn get_size(&self) -> u64 {
+        self.size
+    }
+
+    /// raw page protection of the region when dumped
+    pub fn get_protect(&self) -> u32 {
+        self.protect
+    }
+}
+
+/// read the index header of a dump, leaving `reader` at the start of the first region bytes
+pub fn read_dump_index<R: Read>(reader: &mut R) -> Result<Vec<DumpRegion>> {
+    let mut magic = [0u8; 8];
+    reader.read_exact(&mut magic)?;
+    if &magic != DUMP_MAGIC {
+        return Err(Error::Unsupported);
+    }
+
+    let mut count = [0u8; 4];
+    reader.read_exact(&mut count)?;
+
+    (0..u32::from_le_bytes(count))
+        .map(|_| {
+            let mut entry = [0u8; 20];
+            reader.read_exact(&mut entry)?;
+
+            Ok(DumpRegion {
+                address: u64::from_le_bytes(entry[0..8].try_into().unwrap()),
+                size: u64::from_le_bytes(entry[8..16].try_into().unwrap()),
+                protect: u32::from_le_bytes(entry[16..20].try_into().unwrap()),
+            })
+        })
+        .collect()
+}
+
+fn write_dump_index<W: Write>(writer: &mut W, regions: &[DumpRegion]) -> Result<()> {
+    writer.write_all(DUMP_MAGIC)?;
+    writer.write_all(&(regions.len() as u32).to_le_bytes())?;
+
+    for region in regions {
+        writer.write_all(&region.address.to_le_bytes())?;
+        writer.write_all(&region.size.to_le_bytes())?;
+        writer.write_all(&region.protect.to_le_bytes())?;
+    }
+
+    Ok(())
+}
+
+pub(crate) fn dump_region<B: MemoryBackend + ?Sized, W: Write>(
+    handle: &B,
+    address: usize,
+    size: usize,
+    writer: &mut W,
+) -> Result<()> {
+    let mut buf = vec![0u8; CHUNK_SIZE.min(size)];
+
+    let mut offset = 0;
+    while offset < size {
+        let len = CHUNK_SIZE.min(size - offset);
+        handle.read_memory_into(address + offset, &mut buf[..len])?;
+        writer.write_all(&buf[..len])?;
+        offset += len;
+    }
+
+    Ok(())
+}
+
+pub(crate) fn dump_all<B: MemoryBackend + ?Sized, W: Write>(
+    handle: &B,
+    writer: &mut W,
+) -> Result<usize> {
+    let regions: Vec<DumpRegion> = handle
+        .memory_regions()
+        .committed()
+        .readable()
+        .map(|mbi| DumpRegion {
+            address: mbi.get_base_address() as u64,
+            size: mbi.get_region_size() as u64,
+            protect: mbi.get_protect().bits(),
+        })
+        .collect();
+
+    write_dump_index(writer, &regions)?;
+
+    let mut buf = vec![0u8; CHUNK_SIZE];
+    for region in &regions {
+        let address = region.address as usize;
+        let size = region.size as usize;
+
+        let mut offset = 0;
+        while offset < size {
+            let len = CHUNK_SIZE.min(size - offset);
+
+            // NOTE: region may be gone or shrunk since queried, keep the layout by filling zero
+            if handle
+                .read_memory_into(address + offset, &mut buf[..len])
+                .is_err()
+            {
+                buf[..len].fill(0);
+            }
+
+            writer.write_all(&buf[..len])?;
+            offset += len;
+        }
+    }
+
+    Ok(regions.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_dump_index, write_dump_index, DumpRegion};
+
+    #[test]
+    fn index_round_trip() {
+        let regions = vec![
+            DumpRegion {
+                address: 0x10000,
+                size: 0x1000,
+                protect: 0x04,
+            },
+            DumpRegion {
+                address: 0x7FF6_0000_0000,
+                size: 0x2000,
+                protect: 0x20,
+            },
+        ];
+
+        let mut data = Vec::new();
+        write_dump_index(&mut data, &regions).unwrap();
+        data.extend_from_slice(&[0xAA; 4]);
+
+        let mut reader = data.as_slice();
+        assert_eq!(read_dump_index(&mut reader).unwrap(), regions);
+        assert_eq!(reader, &[0xAA; 4]);
+    }
+
+    #[test]
+    fn rejecting_unknown_magic() {
+        let mut reader = &b"NOTADUMP\0\0\0\0"[..];
+        assert!(read_dump_index(&mut reader).is_err());
+    }
+}
@@ -0,0 +1,212 @@
+use std::fmt::{Debug, Display, Formatter};
+use std::io::ErrorKind;
+
+/// win32 error code of access denied
+const ERROR_ACCESS_DENIED: u32 = 5;
+
+/// Result with [Error] as default error
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// error of the crate
+pub enum Error {
+    /// win32 call failed
+    Win32 {
+        /// name of the win32 function that failed
+        operation: &'static str,
+        /// error reported by the win32 function
+        source: windows::core::Error,
+    },
+    /// io operation failed
+    Io(std::io::Error),
+    /// searched item does not exist
+    NotFound,
+    /// given argument is not valid
+    InvalidInput,
+    /// value is not supported by the crate
+    Unsupported,
+    /// waiting did not finish in time
+    Timeout,
+    /// function called inside the target process reported failure
+    RemoteFailed(&'static str),
+    /// operation stopped by cancellation request
+    Cancelled,
+    /// pointer of a pointer chain is not valid
+    BrokenChain {
+        /// index of the offset whose pointer is not valid
+        level: usize,
+        /// address of the pointer which is not valid
+        address: usize,
+    },
+}
+
+impl Error {
+    /// create error of failed win32 call
+    pub fn win32(operation: &'static str, source: windows::core::Error) -> Self {
+        Self::Win32 { operation, source }
+    }
+
+    /// create error of failed win32 call from `GetLastError`
+    pub fn last_win32(operation: &'static str) -> Self {
+        Self::win32(operation, windows::core::Error::from_win32())
+    }
+
+    /// name of the win32 function that failed
+    pub fn get_operation(&self) -> Option<&'static str> {
+        match self {
+            Self::Win32 { operation, .. } => Some(operation),
+            _ => None,
+        }
+    }
+
+    /// win32 error code (`GetLastError`) of the failure
+    pub fn get_win32_code(&self) -> Option<u32> {
+        match self {
+            Self::Win32 { source, .. } => {
+                let hresult = source.code().0 as u32;
+                // HRESULT_FROM_WIN32 put the code in the lower word with facility 7
+                if hresult & 0xFFFF0000 == 0x80070000 {
+                    Some(hresult & 0xFFFF)
+                } else {
+                    Some(hresult)
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// closest [ErrorKind] of the error
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Win32 { .. } => match self.get_win32_code() {
+                Some(ERROR_ACCESS_DENIED) => ErrorKind::PermissionDenied,
+                _ => ErrorKind::Other,
+            },
+            Self::Io(e) => e.kind(),
+            Self::NotFound => ErrorKind::NotFound,
+            Self::InvalidInput => ErrorKind::InvalidInput,
+            Self::Unsupported => ErrorKind::Unsupported,
+            Self::Timeout => ErrorKind::TimedOut,
+            Self::RemoteFailed(_) => ErrorKind::Other,
+            Self::Cancelled => ErrorKind::Interrupted,
+            Self::BrokenChain { .. } => ErrorKind::InvalidData,
+        }
+    }
+}
+
+// NOTE: formatting `windows::core::Error` looks up its message through win32 call,
+// so only the code is shown to keep `Debug` usable off windows (e.g. unwrap in tests)
+impl Debug for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Win32 { operation, .. } => f
+                .debug_struct("Win32")
+                .field("operation", operation)
+                .field("code", &self.get_win32_code().unwrap_or(0))
+                .finish(),
+            Self::Io(e) => f.debug_tuple("Io").field(e).finish(),
+            Self::NotFound => write!(f, "NotFound"),
+            Self::InvalidInput => write!(f, "InvalidInput"),
+            Self::Unsupported => write!(f, "Unsupported"),
+            Self::Timeout => write!(f, "Timeout"),
+            Self::RemoteFailed(operation) => {
+                f.debug_tuple("RemoteFailed").field(operation).finish()
+            }
+            Self::Cancelled => write!(f, "Cancelled"),
+            Self::BrokenChain { level, address } => f
+                .debug_struct("BrokenChain")
+                .field("level", level)
+                .field("address", &format_args!("{:#x}", address))
+                .finish(),
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Win32 { operation, source } => write!(
+                f,
+                "{} failed with code {:#x}: {}",
+                operation,
+                self.get_win32_code().unwrap_or(0),
+                source
+            ),
+            Self::Io(e) => write!(f, "{}", e),
+            Self::NotFound => write!(f, "not found"),
+            Self::InvalidInput => write!(f, "invalid input"),
+            Self::Unsupported => write!(f, "unsupported"),
+            Self::Timeout => write!(f, "timed out"),
+            Self::RemoteFailed(operation) => {
+                write!(f, "{} failed inside the target process", operation)
+            }
+            Self::Cancelled => write!(f, "cancelled"),
+            Self::BrokenChain { level, address } => write!(
+                f,
+                "pointer chain broken at level {}, {:#x} is not a valid pointer",
+                level, address
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Win32 { source, .. } => Some(source),
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(value: std::io::Error) -> Self {
+        if value.get_ref().is_some_and(|e| e.is::<Error>()) {
+            return *value.into_inner().unwrap().downcast::<Error>().unwrap();
+        }
+
+        Self::Io(value)
+    }
+}
+
+impl From<Error> for std::io::Error {
+    fn from(value: Error) -> Self {
+        match value {
+            Error::Io(e) => e,
+            e => std::io::Error::new(e.kind(), e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Error;
+    use std::io::ErrorKind;
+    use windows::core::{Error as WindowsError, HRESULT};
+
+    #[test]
+    fn win32_code_from_hresult() {
+        let error = Error::win32(
+            "OpenProcess",
+            WindowsError::from_hresult(HRESULT::from_win32(5)),
+        );
+
+        assert_eq!(error.get_win32_code(), Some(5));
+        assert_eq!(error.get_operation(), Some("OpenProcess"));
+        assert_eq!(error.kind(), ErrorKind::PermissionDenied);
+    }
+
+    #[test]
+    fn broken_chain_level() {
+        let error = Error::BrokenChain {
+            level: 2,
+            address: 0x1000,
+        };
+
+        assert_eq!(
+            format!("{:?}", error),
+            "BrokenChain { level: 2, address: 0x1000 }"
+        );
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+
+use crate::address::Address;
+use crate::backend::MemoryBackend;
+use crate::error::Result;
+use crate::handle::Handle;
+use crate::worker::StoppableThread;
+
+/// bytes kept at the end of a pointer chain, by id
+type FrozenEntries = Mutex<HashMap<u64, (usize, Vec<usize>, Vec<u8>)>>;
+
+/// value kept by [Freezer], dropping it does not unfreeze the value
+pub struct FrozenValue {
+    id: u64,
+    address: usize,
+    entries: Weak<FrozenEntries>,
+}
+
+impl FrozenValue {
+    /// address of the frozen value when it was frozen
+    pub fn get_address(&self) -> usize {
+        self.address
+    }
+
+    /// stop rewriting the value
+    pub fn unfreeze(self) {
+        if let Some(entries) = self.entries.upgrade() {
+            entries.lock().unwrap().remove(&self.id);
+        }
+    }
+}
+
+/// Rewriting values in memory of a process at a fixed rate from a background thread.
+///
+/// every value is unfrozen when the freezer dropped.
+pub struct Freezer {
+    handle: Arc<Handle>,
+    entries: Arc<FrozenEntries>,
+    next_id: u64,
+    _thread: StoppableThread,
+}
+
+impl Freezer {
+    /// start rewriting frozen values every `interval`
+    pub fn new(handle: Arc<Handle>, interval: Duration) -> Self {
+        let entries = Arc::new(FrozenEntries::default());
+
+        let thread = StoppableThread::spawn_polling(interval, {
+            let handle = handle.clone();
+            let entries = entries.clone();
+            move || {
+                rewrite(handle.as_ref(), &entries);
+                true
+            }
+        });
+
+        Self {
+            handle,
+            entries,
+            next_id: 0,
+            _thread: thread,
+        }
+    }
+
+    /// write `bytes` at `address` now and keep rewriting it until unfrozen
+    pub fn freeze<A: Into<Address>>(&mut self, address: A, bytes: &[u8]) -> Result<FrozenValue> {
+        self.freeze_chain(address, &[], bytes)
+    }
+
+    /// write `bytes` at the end of pointer chain from `base` now and keep rewriting it until
+    /// unfrozen.
+    ///
+    /// `base` is resolved once, the chain is followed again on every rewrite, look at
+    /// [Handle::resolve_pointer_chain].
+    pub fn freeze_chain<A: Into<Address>>(
+        &mut self,
+        base: A,
+        offsets: &[usize],
+        bytes: &[u8],
+    ) -> Result<FrozenValue> {
+        let base = base.into().resolve(self.handle.as_ref())?;
+        let address = self.handle.resolve_pointer_chain(base, offsets)?;
+        self.handle.write_memory(address, bytes)?;
+
+        let id = self.next_id;
+        self.next_id += 1;
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(id, (base, offsets.to_vec(), bytes.to_vec()));
+
+        Ok(FrozenValue {
+            id,
+            address,
+            entries: Arc::downgrade(&self.entries),
+        })
+    }
+
+    /// number of values currently frozen
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    /// whether no value is frozen
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// stop rewriting every value
+    pub fn unfreeze_all(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
+
+/// write every entry at the end of its pointer chain once
+fn rewrite<B: MemoryBackend + ?Sized>(backend: &B, entries: &FrozenEntries) {
+    for (base, offsets, bytes) in entries.lock().unwrap().values() {
+        // NOTE: pointers in the chain are often null while the game is loading
+        if let Ok(address) = backend.resolve_pointer_chain(*base, offsets) {
+            let _ = backend.write_memory(address, bytes);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::sync::Arc;
+
+    use super::{rewrite, FrozenEntries, FrozenValue};
+    use crate::backend::testing::BufferBackend;
+    use crate::backend::MemoryBackend;
+
+    #[test]
+    fn rewriting_entries() {
+        let backend = BufferBackend(RefCell::new(vec![0u8; 0x40]));
+        let entries = FrozenEntries::default();
+        entries
+            .lock()
+            .unwrap()
+            .insert(0, (0x1000, vec![], vec![1, 2]));
+        entries
+            .lock()
+            .unwrap()
+            .insert(1, (0x1008, vec![4], vec![3]));
+        // chain through a null pointer is skipped
+        entries
+            .lock()
+            .unwrap()
+            .insert(2, (0x1030, vec![0], vec![9]));
+
+        backend.write(0x1008, 0x1010usize).unwrap();
+        rewrite(&backend, &entries);
+        assert_eq!(backend.read_memory(0x1000, 2).unwrap(), vec![1, 2]);
+        assert_eq!(backend.read::<u8>(0x1014).unwrap(), 3);
+
+        // chain is followed again once the game moved the value
+        backend.write(0x1008, 0x1020usize).unwrap();
+        rewrite(&backend, &entries);
+        assert_eq!(backend.read::<u8>(0x1024).unwrap(), 3);
+        assert_eq!(backend.read::<u8>(0x1000).unwrap(), 1);
+    }
+
+    #[test]
+    fn unfreezing_values() {
+        let entries = Arc::new(FrozenEntries::default());
+        entries.lock().unwrap().insert(0, (0x1000, vec![], vec![1]));
+        entries.lock().unwrap().insert(1, (0x1008, vec![], vec![2]));
+        let frozen = |id| FrozenValue {
+            id,
+            address: 0x1000,
+            entries: Arc::downgrade(&entries),
+        };
+
+        frozen(0).unfreeze();
+        assert_eq!(
+            entries.lock().unwrap().keys().copied().collect::<Vec<_>>(),
+            vec![1]
+        );
+
+        // value outliving its freezer unfreeze nothing
+        let value = frozen(1);
+        let weak = value.entries.clone();
+        drop(entries);
+        value.unfreeze();
+        assert!(weak.upgrade().is_none());
+    }
+}
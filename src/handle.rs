@@ -1,22 +1,60 @@
-use std::io::ErrorKind;
+use std::cell::Cell;
+use std::io::{ErrorKind, Read, Write};
+use std::marker::PhantomData;
 use std::mem::size_of;
 use std::ops::Deref;
+use std::time::Duration;
 
 use bitflags::bitflags;
-use windows::Win32::Foundation::{CloseHandle, BOOL, HANDLE, HMODULE};
+use windows::Win32::Foundation::{
+    CloseHandle, DuplicateHandle, BOOL, DUPLICATE_SAME_ACCESS, HANDLE, HMODULE, WAIT_OBJECT_0,
+    WAIT_TIMEOUT,
+};
+use windows::Win32::System::Diagnostics::Debug::{FlushInstructionCache, ReadProcessMemory};
 use windows::Win32::System::Diagnostics::ToolHelp::{
-    CreateToolhelp32Snapshot, Module32FirstW, Module32NextW, CREATE_TOOLHELP_SNAPSHOT_FLAGS,
-    MODULEENTRY32W,
+    CreateToolhelp32Snapshot, Heap32ListFirst, Heap32ListNext, Module32FirstW, Module32NextW,
+    Thread32First, Thread32Next, CREATE_TOOLHELP_SNAPSHOT_FLAGS, HEAPLIST32, MODULEENTRY32W,
+    THREADENTRY32,
 };
 use windows::Win32::System::Memory::{
     VirtualQueryEx, MEMORY_BASIC_INFORMATION, PAGE_PROTECTION_FLAGS, PAGE_TYPE,
     VIRTUAL_ALLOCATION_TYPE,
 };
-use windows::Win32::System::Threading::GetCurrentProcessId;
-use windows::Win32::System::Threading::{OpenProcess, PROCESS_ACCESS_RIGHTS};
+use windows::Win32::System::ProcessStatus::{
+    K32GetMappedFileNameW, K32GetProcessMemoryInfo, K32QueryWorkingSetEx, PROCESS_MEMORY_COUNTERS,
+    PROCESS_MEMORY_COUNTERS_EX, PSAPI_WORKING_SET_EX_INFORMATION,
+};
+use windows::Win32::System::Threading::{
+    CreateRemoteThread, GetCurrentProcess, GetCurrentProcessId, GetExitCodeProcess, IsWow64Process,
+    OpenProcess, WaitForSingleObject, INFINITE, LPTHREAD_START_ROUTINE, PROCESS_ACCESS_RIGHTS,
+};
 
-use crate::memory::MemoryBasicInformation;
+use crate::call::{self, RemoteArg};
+#[cfg(feature = "iced-x86")]
+use crate::disasm::{self, Instruction};
+use crate::dump;
+use crate::error::{Error, Result};
+use crate::heap::HeapList;
+use crate::memory::{
+    self, Memory, MemoryBasicInformation, MemoryCounters, MemoryImageInformation,
+    MemoryRegionInformation, PageProtectionFlags, Pod, ProtectionGuard, RawMemoryImageInformation,
+    RawMemoryRegionInformation, RemoteAllocation, VirtualAllocationType, WorkingSetInfo,
+};
 use crate::module::Module;
+use crate::ntdll;
+use crate::peb;
+use crate::privileges;
+use crate::process::{next_process_entry, Process, ProcessEntry, ProtectionLevel};
+use crate::thread::{Thread, ThreadEntry, STILL_ACTIVE};
+
+/// code units of the longest string `UNICODE_STRING` can hold
+const MAX_UNICODE_STRING_LEN: usize = 0x7FFF;
+/// `ProcessProtectionInformation` of `PROCESSINFOCLASS`
+const PROCESS_PROTECTION_INFORMATION: u32 = 61;
+/// `MemoryRegionInformation` of `MEMORY_INFORMATION_CLASS`
+const MEMORY_REGION_INFORMATION_CLASS: u32 = 3;
+/// `MemoryImageInformation` of `MEMORY_INFORMATION_CLASS`
+const MEMORY_IMAGE_INFORMATION_CLASS: u32 = 6;
 
 // TODO: bitflags bad at doc generation
 bitflags! {
@@ -38,26 +76,252 @@ impl Into<CREATE_TOOLHELP_SNAPSHOT_FLAGS> for HandleSnapshotFlag {
     }
 }
 
-/// process handle
+bitflags! {
+    /// Look at [Process Security and Access Rights - Win32 API](https://learn.microsoft.com/en-us/windows/win32/procthread/process-security-and-access-rights)
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct HandleAccess: u32 {
+        /// `PROCESS_TERMINATE`
+        const Terminate = 0x1;
+        /// `PROCESS_CREATE_THREAD`
+        const CreateThread = 0x2;
+        /// `PROCESS_VM_OPERATION`
+        const VmOperation = 0x8;
+        /// `PROCESS_VM_READ`
+        const VmRead = 0x10;
+        /// `PROCESS_VM_WRITE`
+        const VmWrite = 0x20;
+        /// `PROCESS_DUP_HANDLE`
+        const DupHandle = 0x40;
+        /// `PROCESS_CREATE_PROCESS`
+        const CreateProcess = 0x80;
+        /// `PROCESS_SET_QUOTA`
+        const SetQuota = 0x100;
+        /// `PROCESS_SET_INFORMATION`
+        const SetInformation = 0x200;
+        /// `PROCESS_QUERY_INFORMATION`
+        const QueryInformation = 0x400;
+        /// `PROCESS_SUSPEND_RESUME`
+        const SuspendResume = 0x800;
+        /// `PROCESS_QUERY_LIMITED_INFORMATION`
+        const QueryLimitedInformation = 0x1000;
+        /// `SYNCHRONIZE`
+        const Synchronize = 0x100000;
+        /// `PROCESS_ALL_ACCESS`
+        const All = 0x1FFFFF;
+        /// rights needed to read memory, query its regions and tell whether the process is
+        /// still running
+        const Read = Self::VmRead.bits() | Self::QueryInformation.bits() | Self::Synchronize.bits();
+        /// rights needed to read, write and change protection of memory
+        const ReadWrite = Self::Read.bits() | Self::VmWrite.bits() | Self::VmOperation.bits();
+    }
+}
+
+impl From<HandleAccess> for PROCESS_ACCESS_RIGHTS {
+    fn from(value: HandleAccess) -> Self {
+        PROCESS_ACCESS_RIGHTS(value.bits())
+    }
+}
+
+/// how memory of a process is read
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ReadBackend {
+    /// `ReadProcessMemory`
+    Win32,
+    /// `NtReadVirtualMemory`, skipping the checks of `ReadProcessMemory`
+    #[default]
+    Nt,
+}
+
+/// Process handle.
+///
+/// process handle is usable from any thread, so it is both `Send` and `Sync`.
 pub struct Handle {
     raw: HANDLE,
     process_id: u32,
+    access: HandleAccess,
+    read_backend: ReadBackend,
+    is_current_process: bool,
 }
 
 impl Handle {
+    /// open process with only the given access rights
+    pub fn open(process_id: u32, access: HandleAccess) -> Result<Handle> {
+        let h = unsafe { OpenProcess(access.into(), BOOL(0), process_id) }
+            .map_err(|e| Error::win32("OpenProcess", e))?;
+
+        if h.is_invalid() {
+            return Err(Error::last_win32("OpenProcess"));
+        }
+
+        Ok(Self::from_raw(h, process_id, access))
+    }
+
+    pub(crate) fn from_raw(raw: HANDLE, process_id: u32, access: HandleAccess) -> Self {
+        Self {
+            raw,
+            process_id,
+            access,
+            read_backend: ReadBackend::default(),
+            is_current_process: process_id == unsafe { GetCurrentProcessId() },
+        }
+    }
+
+    /// open process with only the given access rights, enabling `SeDebugPrivilege` first when
+    /// opening failed.
+    ///
+    /// the privilege is only granted to elevated process, letting it open system processes.
+    pub fn open_with_debug_privilege(process_id: u32, access: HandleAccess) -> Result<Handle> {
+        Handle::open(process_id, access).or_else(|e| {
+            privileges::enable_debug_privilege().map_err(|_| e)?;
+            Handle::open(process_id, access)
+        })
+    }
+
     /// process id of the handle
     pub fn get_process_id(&self) -> u32 {
         self.process_id
     }
 
+    /// whether the process is still running, handle need `Synchronize` access
+    pub fn is_alive(&self) -> Result<bool> {
+        match self.wait_for_exit(Some(Duration::ZERO)) {
+            Ok(_) => Ok(false),
+            Err(Error::Timeout) => Ok(true),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// exit code of the process, `None` when the process is still running
+    pub fn get_exit_code(&self) -> Result<Option<u32>> {
+        let mut exit_code = 0u32;
+        unsafe { GetExitCodeProcess(self.raw, &mut exit_code) }
+            .map_err(|e| Error::win32("GetExitCodeProcess", e))?;
+
+        // NOTE: process may exit with STILL_ACTIVE itself, ask the process object to be sure
+        // when the handle can wait on it
+        if exit_code == STILL_ACTIVE && self.is_alive().unwrap_or(true) {
+            return Ok(None);
+        }
+
+        Ok(Some(exit_code))
+    }
+
+    /// wait for the process to exit, returning its exit code.
+    ///
+    /// wait forever when `timeout` is `None`, handle need `Synchronize` access.
+    pub fn wait_for_exit(&self, timeout: Option<Duration>) -> Result<u32> {
+        let milliseconds =
+            timeout.map_or(INFINITE, |e| e.as_millis().min(INFINITE as u128 - 1) as u32);
+
+        match unsafe { WaitForSingleObject(self.raw, milliseconds) } {
+            WAIT_OBJECT_0 => (),
+            WAIT_TIMEOUT => return Err(Error::Timeout),
+            _ => return Err(Error::last_win32("WaitForSingleObject")),
+        }
+
+        let mut exit_code = 0u32;
+        unsafe { GetExitCodeProcess(self.raw, &mut exit_code) }
+            .map_err(|e| Error::win32("GetExitCodeProcess", e))?;
+
+        Ok(exit_code)
+    }
+
+    /// duplicate the handle with the same access rights, closed independently of this one
+    pub fn try_clone(&self) -> Result<Handle> {
+        let mut handle = Self::from_raw(duplicate_handle(self.raw)?, self.process_id, self.access);
+        handle.read_backend = self.read_backend;
+
+        Ok(handle)
+    }
+
+    /// access rights the handle opened with
+    pub fn get_access(&self) -> HandleAccess {
+        self.access
+    }
+
+    /// whether the handle is for the process running this code
+    pub fn is_current_process(&self) -> bool {
+        self.is_current_process
+    }
+
+    /// how memory of the process is read
+    pub fn get_read_backend(&self) -> ReadBackend {
+        self.read_backend
+    }
+
+    /// change how memory of the process is read
+    pub fn set_read_backend(&mut self, read_backend: ReadBackend) {
+        self.read_backend = read_backend;
+    }
+
+    /// open the first process whose executable name matches `name`, ignoring case
+    pub fn try_from_name(name: &str) -> Result<Handle> {
+        let name = name.to_lowercase();
+
+        let mut error = Error::NotFound;
+        for process in Process::enumerate()? {
+            if process.get_name().to_lowercase() != name {
+                continue;
+            }
+
+            match Handle::try_from(process.get_process_id()) {
+                Ok(handle) => return Ok(handle),
+                Err(e) => error = e,
+            }
+        }
+
+        Err(error)
+    }
+
+    /// open every process whose executable name matches `name`, ignoring case.
+    ///
+    /// process that fail to be opened are skipped.
+    pub fn try_from_name_all(name: &str) -> Result<Vec<Handle>> {
+        let name = name.to_lowercase();
+
+        let handles = Process::enumerate()?
+            .filter(|process| process.get_name().to_lowercase() == name)
+            .filter_map(|process| Handle::try_from(process.get_process_id()).ok())
+            .collect();
+
+        Ok(handles)
+    }
+
     /// createting handle snapshot
-    pub fn create_snapshot(&self, flag: HandleSnapshotFlag) -> Result<HandleSnapshot, ErrorKind> {
-        let new_handle = HandleSnapshot {
-            raw: unsafe { CreateToolhelp32Snapshot(flag.into(), self.process_id) }
-                .map_err(|_| ErrorKind::Other)?,
-            process_id: self.process_id,
+    pub fn create_snapshot(&self, flag: HandleSnapshotFlag) -> Result<HandleSnapshot> {
+        let mut snapshot = HandleSnapshot::try_new(flag, self.process_id)?;
+        // NOTE: handle without `QueryLimitedInformation` can still snapshot, keep every module
+        snapshot.is_wow64 = self.is_wow64().unwrap_or(false);
+        Ok(snapshot)
+    }
+
+    /// memory information of the region containing `address`
+    pub(crate) fn query_memory_basic_information(
+        &self,
+        address: usize,
+    ) -> Option<MemoryBasicInformation> {
+        let mut mbi = MEMORY_BASIC_INFORMATION {
+            BaseAddress: std::ptr::null_mut(),
+            AllocationBase: std::ptr::null_mut(),
+            AllocationProtect: PAGE_PROTECTION_FLAGS(0),
+            #[cfg(target_arch = "x86_64")]
+            PartitionId: 0,
+            RegionSize: 0,
+            State: VIRTUAL_ALLOCATION_TYPE(0),
+            Protect: PAGE_PROTECTION_FLAGS(0),
+            Type: PAGE_TYPE(0),
         };
-        return Ok(new_handle);
+
+        let n = unsafe {
+            VirtualQueryEx(
+                self.raw,
+                Some(address as *const _),
+                &mut mbi as *mut _,
+                size_of::<MEMORY_BASIC_INFORMATION>(),
+            )
+        };
+
+        (n != 0).then(|| MemoryBasicInformation::from(mbi))
     }
 
     /// iterator for memory information related to handle
@@ -67,6 +331,500 @@ impl Handle {
             current_address: None,
         }
     }
+
+    /// read `len` bytes of memory starting from `address`
+    pub fn read_memory(&self, address: usize, len: usize) -> Result<Vec<u8>> {
+        let end_address = address.checked_add(len).ok_or(Error::InvalidInput)?;
+
+        let mut buf = vec![0u8; len];
+        Memory::new(self, address, end_address).read_exact(&mut buf)?;
+
+        Ok(buf)
+    }
+
+    /// read `len` bytes of memory starting from `address`, stopping at the first page that
+    /// cannot be read instead of failing the whole read.
+    ///
+    /// return the bytes along with how many of them read from the start, the rest are zero.
+    pub fn read_memory_partial(&self, address: usize, len: usize) -> Result<(Vec<u8>, usize)> {
+        address.checked_add(len).ok_or(Error::InvalidInput)?;
+
+        let mut buf = vec![0u8; len];
+        if self.read_memory_into(address, &mut buf).is_ok() {
+            return Ok((buf, len));
+        }
+
+        let mut bytes_read = 0;
+        for range in memory::page_ranges(address, len) {
+            if self
+                .read_memory_into(address + range.start, &mut buf[range.clone()])
+                .is_err()
+            {
+                break;
+            }
+            bytes_read = range.end;
+        }
+        // NOTE: failed read may have copied part of a page
+        buf[bytes_read..].fill(0);
+
+        Ok((buf, bytes_read))
+    }
+
+    /// read memory starting from `address` into `buf` without allocating, using the
+    /// [ReadBackend] of the handle
+    pub fn read_memory_into(&self, address: usize, buf: &mut [u8]) -> Result<()> {
+        // NOTE: memory of the current process is read through the syscall too, copying it
+        // directly would fault on a page freed meanwhile instead of failing
+        let n = match self.read_backend {
+            ReadBackend::Win32 => self.read_process_memory(address, buf)?,
+            ReadBackend::Nt => match ntdll::NtReadVirtualMemory() {
+                Some(nt_read_virtual_memory) => {
+                    let mut n = 0usize;
+                    unsafe {
+                        nt_read_virtual_memory(
+                            self.raw,
+                            address as *const _,
+                            buf.as_mut_ptr() as *mut _,
+                            buf.len(),
+                            &mut n,
+                        )
+                    }
+                    .ok()
+                    .map_err(|e| Error::win32("NtReadVirtualMemory", e))?;
+                    n
+                }
+                None => self.read_process_memory(address, buf)?,
+            },
+        };
+
+        if n < buf.len() {
+            return Err(Error::Io(ErrorKind::UnexpectedEof.into()));
+        }
+
+        Ok(())
+    }
+
+    /// read every `(address, buf)` request with `NtReadVirtualMemory` directly, returning how
+    /// many bytes of each request read, zero for request that failed.
+    ///
+    /// every request is still its own syscall, only the [ReadBackend] and error handling of
+    /// [Handle::read_memory_into] are skipped.
+    pub fn nt_read_many(&self, requests: &mut [(usize, &mut [u8])]) -> Result<Vec<usize>> {
+        let nt_read_virtual_memory = ntdll::NtReadVirtualMemory().ok_or(Error::Unsupported)?;
+
+        Ok(requests
+            .iter_mut()
+            .map(|(address, buf)| {
+                let mut n = 0usize;
+                // NOTE: partial copy still report the bytes read along with its failure status
+                let _ = unsafe {
+                    nt_read_virtual_memory(
+                        self.raw,
+                        *address as *const _,
+                        buf.as_mut_ptr() as *mut _,
+                        buf.len(),
+                        &mut n,
+                    )
+                };
+                n
+            })
+            .collect())
+    }
+
+    /// read with `ReadProcessMemory` whatever the [ReadBackend] is, returning bytes read
+    pub(crate) fn read_process_memory(&self, address: usize, buf: &mut [u8]) -> Result<usize> {
+        let mut n = 0usize;
+        unsafe {
+            ReadProcessMemory(
+                self.raw,
+                address as *const _,
+                buf.as_mut_ptr() as *mut _,
+                buf.len(),
+                Some(&mut n),
+            )
+        }
+        .map_err(|e| Error::win32("ReadProcessMemory", e))?;
+
+        Ok(n)
+    }
+
+    /// read every `(address, len)` request, each request succeed or fail on its own
+    pub fn read_many(&self, requests: &[(usize, usize)]) -> Vec<Result<Vec<u8>>> {
+        requests
+            .iter()
+            .map(|(address, len)| {
+                let mut buf = vec![0u8; *len];
+                self.read_memory_into(*address, &mut buf).map(|_| buf)
+            })
+            .collect()
+    }
+
+    /// read every `(address, len)` request into reused `buffer`, calling `f` with the request
+    /// index and its bytes.
+    ///
+    /// `buffer` only grow so repeated calls with similar requests do not allocate.
+    pub fn read_many_with<F>(&self, requests: &[(usize, usize)], buffer: &mut Vec<u8>, mut f: F)
+    where
+        F: FnMut(usize, Result<&[u8]>),
+    {
+        for (index, (address, len)) in requests.iter().enumerate() {
+            if buffer.len() < *len {
+                buffer.resize(*len, 0);
+            }
+
+            let buf = &mut buffer[..*len];
+            match self.read_memory_into(*address, buf) {
+                Ok(_) => f(index, Ok(buf)),
+                Err(e) => f(index, Err(e)),
+            }
+        }
+    }
+
+    /// stream `size` bytes of memory starting from `address` into `writer`
+    pub fn dump_region<W: Write>(&self, address: usize, size: usize, writer: &mut W) -> Result<()> {
+        dump::dump_region(self, address, size, writer)
+    }
+
+    /// stream every committed readable region into `writer` after an index header, returning
+    /// the number of regions.
+    ///
+    /// look at [dump::read_dump_index] for reading it back.
+    pub fn dump_all<W: Write>(&self, writer: &mut W) -> Result<usize> {
+        dump::dump_all(self, writer)
+    }
+
+    /// read value of type `T` at `address`
+    pub fn read<T: Pod>(&self, address: usize) -> Result<T> {
+        let buf = self.read_memory(address, size_of::<T>())?;
+
+        memory::from_bytes(&buf).ok_or(Error::InvalidInput)
+    }
+
+    /// read nul terminated narrow string at `address`, up to `max_len` bytes.
+    ///
+    /// the string is cut short instead of failing when it runs into an unreadable page.
+    pub fn read_cstring(&self, address: usize, max_len: usize) -> Result<String> {
+        let bytes = memory::read_until_nul(
+            &|address, len| self.read_memory(address, len),
+            address,
+            1,
+            max_len,
+        )?;
+
+        Ok(String::from_utf8_lossy(&bytes).to_string())
+    }
+
+    /// read nul terminated wide string at `address`, up to `max_len` utf-16 code units.
+    ///
+    /// the string is cut short instead of failing when it runs into an unreadable page.
+    pub fn read_wstring(&self, address: usize, max_len: usize) -> Result<String> {
+        let bytes = memory::read_until_nul(
+            &|address, len| self.read_memory(address, len),
+            address,
+            2,
+            max_len,
+        )?;
+        let units = bytes
+            .chunks_exact(2)
+            .map(|e| u16::from_le_bytes([e[0], e[1]]))
+            .collect::<Vec<_>>();
+
+        Ok(String::from_utf16_lossy(&units))
+    }
+
+    /// read nul terminated wide string at `address`, up to the longest length of `UNICODE_STRING`
+    pub fn read_string_utf16_until_nul(&self, address: usize) -> Result<String> {
+        self.read_wstring(address, MAX_UNICODE_STRING_LEN)
+    }
+
+    /// write `bytes` to memory starting from `address`.
+    ///
+    /// when the page is not writable, its protection is lifted for the duration of the write
+    /// and restored afterward.
+    pub fn write_memory(&self, address: usize, bytes: &[u8]) -> Result<()> {
+        let end_address = address
+            .checked_add(bytes.len())
+            .ok_or(Error::InvalidInput)?;
+
+        if Memory::new(self, address, end_address)
+            .write_all(bytes)
+            .is_ok()
+        {
+            return Ok(());
+        }
+
+        let _guard = self.protect(address, bytes.len(), PageProtectionFlags::ExecuteReadWrite)?;
+
+        Memory::new(self, address, end_address).write_all(bytes)?;
+
+        Ok(())
+    }
+
+    /// whether the process is 32 bit process running under WOW64
+    pub fn is_wow64(&self) -> Result<bool> {
+        let mut is_wow64 = BOOL(0);
+        unsafe { IsWow64Process(self.raw, &mut is_wow64) }
+            .map_err(|e| Error::win32("IsWow64Process", e))?;
+
+        Ok(is_wow64.as_bool())
+    }
+
+    /// size in bytes of a pointer in the process
+    pub fn get_pointer_size(&self) -> Result<usize> {
+        if self.is_wow64()? {
+            return Ok(size_of::<u32>());
+        }
+
+        Ok(size_of::<usize>())
+    }
+
+    /// protection of the process, protected process deny memory access even with full rights.
+    ///
+    /// handle need `QueryLimitedInformation` access.
+    pub fn protection_level(&self) -> Result<ProtectionLevel> {
+        peb::query_process::<u8>(self, PROCESS_PROTECTION_INFORMATION).map(ProtectionLevel::from)
+    }
+
+    /// read pointer at `address` with the pointer size of the process
+    pub fn read_pointer(&self, address: usize) -> Result<usize> {
+        self.read_pointer_sized(address, self.get_pointer_size()?)
+    }
+
+    /// follow pointer chain starting at `base`, each level dereference the address then add its offset.
+    ///
+    /// offsets can be negative by wrapping, e.g. `0usize.wrapping_sub(8)`.
+    pub fn resolve_pointer_chain(&self, base: usize, offsets: &[usize]) -> Result<usize> {
+        let pointer_size = self.get_pointer_size()?;
+
+        let mut address = base;
+        for offset in offsets {
+            address = self
+                .read_pointer_sized(address, pointer_size)?
+                .wrapping_add(*offset);
+        }
+
+        Ok(address)
+    }
+
+    /// whether `len` bytes from `address` are committed and readable, checked from regions of
+    /// memory without reading it
+    pub fn is_valid_ptr(&self, address: usize, len: usize) -> bool {
+        memory::is_readable_range(
+            |address| self.query_memory_basic_information(address),
+            address,
+            len,
+        )
+    }
+
+    /// follow pointer chain like [Handle::resolve_pointer_chain], checking each pointer with
+    /// [Handle::is_valid_ptr] before dereferencing it.
+    ///
+    /// fail with [Error::BrokenChain] telling the index of the offset whose pointer is not
+    /// valid.
+    pub fn validate_chain(&self, base: usize, offsets: &[usize]) -> Result<usize> {
+        let pointer_size = self.get_pointer_size()?;
+
+        let mut address = base;
+        for (level, offset) in offsets.iter().enumerate() {
+            let broken = Error::BrokenChain { level, address };
+            if !self.is_valid_ptr(address, pointer_size) {
+                return Err(broken);
+            }
+            address = self
+                .read_pointer_sized(address, pointer_size)
+                .map_err(|_| broken)?
+                .wrapping_add(*offset);
+        }
+
+        Ok(address)
+    }
+
+    pub(crate) fn read_pointer_sized(&self, address: usize, pointer_size: usize) -> Result<usize> {
+        if pointer_size == size_of::<u32>() {
+            return Ok(self.read::<u32>(address)? as usize);
+        }
+
+        self.read::<usize>(address)
+    }
+
+    /// write `value` of type `T` at `address`
+    pub fn write<T: Pod>(&self, address: usize, value: T) -> Result<()> {
+        self.write_memory(address, memory::bytes_of(&value))
+    }
+
+    /// decode up to `count` instructions starting from `address`, stop at invalid one
+    #[cfg(feature = "iced-x86")]
+    pub fn disassemble(&self, address: usize, count: usize) -> Result<Vec<Instruction>> {
+        let len = count.saturating_mul(disasm::MAX_INSTRUCTION_LEN);
+        // NOTE: instructions may end before an unreadable page, read up to the page instead
+        let bytes = match self.read_memory(address, len) {
+            Ok(bytes) => bytes,
+            Err(_) => self.read_memory(address, len.min(0x1000 - address % 0x1000))?,
+        };
+        let bitness = self.get_pointer_size()? as u32 * 8;
+
+        Ok(disasm::decode(&bytes, address, bitness, count))
+    }
+
+    /// residency of pages containing `addresses`, in the same order.
+    ///
+    /// scanner can skip pages which is not valid to avoid faulting them in.
+    pub fn query_working_set(&self, addresses: &[usize]) -> Result<Vec<WorkingSetInfo>> {
+        let mut infos = addresses
+            .iter()
+            .map(|address| PSAPI_WORKING_SET_EX_INFORMATION {
+                VirtualAddress: *address as *mut _,
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+
+        let size = size_of::<PSAPI_WORKING_SET_EX_INFORMATION>() * infos.len();
+        let size = u32::try_from(size).map_err(|_| Error::InvalidInput)?;
+        if !unsafe { K32QueryWorkingSetEx(self.raw, infos.as_mut_ptr() as *mut _, size) }.as_bool()
+        {
+            return Err(Error::last_win32("K32QueryWorkingSetEx"));
+        }
+
+        Ok(infos
+            .iter()
+            .map(|e| {
+                WorkingSetInfo::new(e.VirtualAddress as usize, unsafe {
+                    e.VirtualAttributes.Flags
+                })
+            })
+            .collect())
+    }
+
+    /// allocation containing `address` as a whole, including its committed size
+    pub fn query_region_information(&self, address: usize) -> Result<MemoryRegionInformation> {
+        self.query_virtual_memory::<RawMemoryRegionInformation>(
+            address,
+            MEMORY_REGION_INFORMATION_CLASS,
+        )
+        .map(MemoryRegionInformation::from)
+    }
+
+    /// image mapped at the region containing `address`, the region must be of type image
+    pub fn query_image_information(&self, address: usize) -> Result<MemoryImageInformation> {
+        self.query_virtual_memory::<RawMemoryImageInformation>(
+            address,
+            MEMORY_IMAGE_INFORMATION_CLASS,
+        )
+        .map(MemoryImageInformation::from)
+    }
+
+    /// device path of the file mapped at `address`, like `\Device\HarddiskVolume3\a.dll`
+    pub fn get_mapped_file_name(&self, address: usize) -> Result<String> {
+        let mut buf = vec![0u16; MAX_UNICODE_STRING_LEN];
+        let len = unsafe { K32GetMappedFileNameW(self.raw, address as *const _, &mut buf) };
+        if len == 0 {
+            return Err(Error::last_win32("K32GetMappedFileNameW"));
+        }
+
+        Ok(String::from_utf16_lossy(&buf[..len as usize]))
+    }
+
+    fn query_virtual_memory<T: Default>(
+        &self,
+        address: usize,
+        information_class: u32,
+    ) -> Result<T> {
+        let query = ntdll::NtQueryVirtualMemory().ok_or(Error::Unsupported)?;
+
+        let mut information = T::default();
+        unsafe {
+            query(
+                self.raw,
+                address as *const _,
+                information_class,
+                &mut information as *mut T as *mut _,
+                size_of::<T>(),
+                std::ptr::null_mut(),
+            )
+        }
+        .ok()
+        .map_err(|e| Error::win32("NtQueryVirtualMemory", e))?;
+
+        Ok(information)
+    }
+
+    /// working set, commit and page fault statistics of the process
+    pub fn get_memory_counters(&self) -> Result<MemoryCounters> {
+        let mut counters = PROCESS_MEMORY_COUNTERS_EX {
+            cb: size_of::<PROCESS_MEMORY_COUNTERS_EX>() as u32,
+            ..Default::default()
+        };
+
+        if !unsafe {
+            K32GetProcessMemoryInfo(
+                self.raw,
+                &mut counters as *mut _ as *mut PROCESS_MEMORY_COUNTERS,
+                counters.cb,
+            )
+        }
+        .as_bool()
+        {
+            return Err(Error::last_win32("K32GetProcessMemoryInfo"));
+        }
+
+        Ok(MemoryCounters::from(counters))
+    }
+
+    /// make the process see code written to `address` before it execute it
+    pub fn flush_instruction_cache(&self, address: usize, size: usize) -> Result<()> {
+        unsafe { FlushInstructionCache(self.raw, Some(address as *const _), size) }
+            .map_err(|e| Error::win32("FlushInstructionCache", e))
+    }
+
+    /// allocate memory in the process, released when the allocation dropped
+    pub fn alloc(
+        &self,
+        size: usize,
+        protection: PageProtectionFlags,
+        allocation_type: VirtualAllocationType,
+    ) -> Result<RemoteAllocation<'_>> {
+        RemoteAllocation::try_new(self, size, protection, allocation_type)
+    }
+
+    /// change protection of the memory, restored when the guard dropped
+    pub fn protect(
+        &self,
+        address: usize,
+        size: usize,
+        protection: PageProtectionFlags,
+    ) -> Result<ProtectionGuard<'_>> {
+        ProtectionGuard::try_new(self, address, size, protection)
+    }
+
+    /// create thread in the process starting at `start_address` with `parameter` as its argument
+    pub fn create_remote_thread(&self, start_address: usize, parameter: usize) -> Result<Thread> {
+        let mut thread_id = 0u32;
+        let raw = unsafe {
+            CreateRemoteThread(
+                self.raw,
+                None,
+                0,
+                std::mem::transmute::<usize, LPTHREAD_START_ROUTINE>(start_address),
+                Some(parameter as *const _),
+                0,
+                Some(&mut thread_id),
+            )
+        }
+        .map_err(|e| Error::win32("CreateRemoteThread", e))?;
+
+        Ok(Thread::from_raw(raw, thread_id))
+    }
+
+    /// call function at `address` in the process with integer or pointer arguments,
+    /// waiting for it to return.
+    ///
+    /// use microsoft x64 calling convention for 64 bit process. for 32 bit process, arguments
+    /// are pushed on stack, and call with one argument use the thread routine directly.
+    ///
+    /// return the whole `rax` of 64 bit process, and `eax` of 32 bit process.
+    pub fn call_remote(&self, address: usize, args: &[RemoteArg]) -> Result<u64> {
+        call::call_remote(self, address, args)
+    }
 }
 
 impl Default for Handle {
@@ -91,52 +849,114 @@ impl Drop for Handle {
     }
 }
 
+/// open process with the most access rights allowed, falling back to fewer rights
 impl TryFrom<u32> for Handle {
-    type Error = ErrorKind;
+    type Error = Error;
 
     fn try_from(value: u32) -> Result<Handle, Self::Error> {
-        let h = {
-            let mut h: HANDLE = HANDLE(0);
-
-            h = unsafe { OpenProcess(PROCESS_ACCESS_RIGHTS(0xFFFF), BOOL(0), value) }
-                .map_err(|_| ErrorKind::Other)?;
-
-            if h.is_invalid() {
-                h = unsafe { OpenProcess(PROCESS_ACCESS_RIGHTS(0x10 | 0x20), BOOL(0), value) }
-                    .map_err(|_| ErrorKind::Other)?;
-            }
-
-            if h.is_invalid() {
-                return Err(ErrorKind::Other);
-            }
-
-            h
-        };
+        Handle::open(value, HandleAccess::All)
+            .or_else(|_| Handle::open(value, HandleAccess::ReadWrite))
+            .or_else(|_| Handle::open(value, HandleAccess::Read))
+    }
+}
 
-        return Ok(Self {
-            raw: h,
-            process_id: value,
-        });
+/// duplicate `raw` owned by the current process with the same access
+fn duplicate_handle(raw: HANDLE) -> Result<HANDLE> {
+    let mut duplicate = HANDLE::default();
+    unsafe {
+        DuplicateHandle(
+            GetCurrentProcess(),
+            raw,
+            GetCurrentProcess(),
+            &mut duplicate,
+            0,
+            BOOL(0),
+            DUPLICATE_SAME_ACCESS,
+        )
     }
+    .map_err(|e| Error::win32("DuplicateHandle", e))?;
+
+    Ok(duplicate)
 }
 
-/// Process Handle Snapshot
+/// Process Handle Snapshot.
+///
+/// iterating keeps its position inside the snapshot, so it can be moved to another thread but
+/// not shared between threads.
 pub struct HandleSnapshot {
     raw: HANDLE,
     process_id: u32,
+    is_wow64: bool,
+    _not_sync: PhantomData<Cell<()>>,
 }
 
 impl HandleSnapshot {
+    pub(crate) fn try_new(flag: HandleSnapshotFlag, process_id: u32) -> Result<Self> {
+        let new_handle = HandleSnapshot {
+            raw: unsafe { CreateToolhelp32Snapshot(flag.into(), process_id) }
+                .map_err(|e| Error::win32("CreateToolhelp32Snapshot", e))?,
+            process_id,
+            is_wow64: false,
+            _not_sync: PhantomData,
+        };
+        return Ok(new_handle);
+    }
+
     /// get process id
     pub fn get_process_id(&self) -> u32 {
         self.process_id
     }
 
-    /// get modules
+    /// get modules, without the 64 bit ones of a wow64 process
     pub fn get_modules(&self) -> HandleSnapshotModuleIter {
         HandleSnapshotModuleIter {
             handle: self,
             is_first: true,
+            skip_64bit: self.is_wow64,
+        }
+    }
+
+    /// get modules, along with the 64 bit ones of a wow64 process
+    pub fn get_all_modules(&self) -> HandleSnapshotModuleIter<'_> {
+        HandleSnapshotModuleIter {
+            handle: self,
+            is_first: true,
+            skip_64bit: false,
+        }
+    }
+
+    /// find module by its name ignoring case, snapshot need to be created with `SnapModule`
+    pub fn find_module(&self, name: &str) -> Option<Module> {
+        self.get_modules()
+            .find(|module| module.get_name().eq_ignore_ascii_case(name))
+    }
+
+    /// find module which memory contains `address`, snapshot need to be created with `SnapModule`
+    pub fn module_containing(&self, address: usize) -> Option<Module> {
+        self.get_modules().find(|module| module.contains(address))
+    }
+
+    /// get processes, snapshot need to be created with `SnapProcess`
+    pub fn get_processes(&self) -> HandleSnapshotProcessIter<'_> {
+        HandleSnapshotProcessIter {
+            handle: self,
+            is_first: true,
+        }
+    }
+
+    /// get heaps, snapshot need to be created with `SnapHeapList`
+    pub fn get_heaps(&self) -> HandleSnapshotHeapListIter<'_> {
+        HandleSnapshotHeapListIter {
+            handle: self,
+            is_first: true,
+        }
+    }
+
+    /// get threads, snapshot need to be created with `SnapThread`
+    pub fn get_threads(&self) -> HandleSnapshotThreadIter<'_> {
+        HandleSnapshotThreadIter {
+            handle: self,
+            is_first: true,
         }
     }
 }
@@ -161,12 +981,11 @@ impl Drop for HandleSnapshot {
 pub struct HandleSnapshotModuleIter<'a> {
     handle: &'a HandleSnapshot,
     is_first: bool,
+    skip_64bit: bool,
 }
 
-impl<'a> Iterator for HandleSnapshotModuleIter<'a> {
-    type Item = Module;
-
-    fn next(&mut self) -> Option<Self::Item> {
+impl<'a> HandleSnapshotModuleIter<'a> {
+    fn next_entry(&mut self) -> Option<Module> {
         let mut module_entry_32w = MODULEENTRY32W {
             dwSize: size_of::<MODULEENTRY32W>() as u32,
             GlblcntUsage: 0,
@@ -203,6 +1022,97 @@ impl<'a> Iterator for HandleSnapshotModuleIter<'a> {
     }
 }
 
+impl<'a> Iterator for HandleSnapshotModuleIter<'a> {
+    type Item = Module;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let module = self.next_entry()?;
+
+            // NOTE: wow64 process also has 64 bit modules loaded which is unreachable by its own code
+            if self.skip_64bit && module.get_address() as u64 + module.get_size() as u64 > 1 << 32 {
+                continue;
+            }
+
+            return Some(module);
+        }
+    }
+}
+
+/// Process Handle Snapshot -> Process Iterator
+pub struct HandleSnapshotProcessIter<'a> {
+    handle: &'a HandleSnapshot,
+    is_first: bool,
+}
+
+impl<'a> Iterator for HandleSnapshotProcessIter<'a> {
+    type Item = ProcessEntry;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        next_process_entry(self.handle, &mut self.is_first)
+    }
+}
+
+/// Process Handle Snapshot -> Heap List Iterator
+pub struct HandleSnapshotHeapListIter<'a> {
+    handle: &'a HandleSnapshot,
+    is_first: bool,
+}
+
+impl<'a> Iterator for HandleSnapshotHeapListIter<'a> {
+    type Item = HeapList;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut heap_list_32 = HEAPLIST32 {
+            dwSize: size_of::<HEAPLIST32>(),
+            ..Default::default()
+        };
+
+        let result = if self.is_first {
+            self.is_first = false;
+            unsafe { Heap32ListFirst(**self.handle, &mut heap_list_32 as *mut _) }
+        } else {
+            unsafe { Heap32ListNext(**self.handle, &mut heap_list_32 as *mut _) }
+        };
+
+        result.ok().map(|_| HeapList::from(heap_list_32))
+    }
+}
+
+/// Process Handle Snapshot -> Thread Iterator
+pub struct HandleSnapshotThreadIter<'a> {
+    handle: &'a HandleSnapshot,
+    is_first: bool,
+}
+
+impl<'a> Iterator for HandleSnapshotThreadIter<'a> {
+    type Item = ThreadEntry;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let mut thread_entry_32 = THREADENTRY32 {
+                dwSize: size_of::<THREADENTRY32>() as u32,
+                ..Default::default()
+            };
+
+            let result = if self.is_first {
+                self.is_first = false;
+                unsafe { Thread32First(**self.handle, &mut thread_entry_32 as *mut _) }
+            } else {
+                unsafe { Thread32Next(**self.handle, &mut thread_entry_32 as *mut _) }
+            };
+            result.ok()?;
+
+            // NOTE: thread snapshot always contain threads of every process in the system
+            if self.handle.process_id == 0
+                || thread_entry_32.th32OwnerProcessID == self.handle.process_id
+            {
+                return Some(ThreadEntry::from(thread_entry_32));
+            }
+        }
+    }
+}
+
 /// Process Handle -> Memory Basic Information Iterator
 pub struct HandleMemoryBasicInformationIter<'a> {
     handle: &'a Handle,
@@ -213,35 +1123,34 @@ impl<'a> Iterator for HandleMemoryBasicInformationIter<'a> {
     type Item = MemoryBasicInformation;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let mut mbi = MEMORY_BASIC_INFORMATION {
-            BaseAddress: std::ptr::null_mut(),
-            AllocationBase: std::ptr::null_mut(),
-            AllocationProtect: PAGE_PROTECTION_FLAGS(0),
-            #[cfg(target_arch = "x86_64")]
-            PartitionId: 0,
-            RegionSize: 0,
-            State: VIRTUAL_ALLOCATION_TYPE(0),
-            Protect: PAGE_PROTECTION_FLAGS(0),
-            Type: PAGE_TYPE(0),
-        };
+        let mbi = self
+            .handle
+            .query_memory_basic_information(self.current_address.unwrap_or(0))?;
+        self.current_address = Some(mbi.get_region_size() + self.current_address.unwrap_or(0));
 
-        let n = unsafe {
-            VirtualQueryEx(
-                **self.handle,
-                self.current_address.map(|v| v as *const _),
-                &mut mbi as *mut _,
-                size_of::<MEMORY_BASIC_INFORMATION>(),
-            )
-        };
-
-        if n != 0 {
-            let mbi = MemoryBasicInformation::from(mbi);
+        Some(mbi)
+    }
+}
 
-            self.current_address = Some(mbi.get_region_size() + self.current_address.unwrap_or(0));
+#[cfg(test)]
+mod tests {
+    use super::{Handle, HandleMemoryBasicInformationIter, HandleSnapshot};
+    use crate::memory::MemoryBasicInformation;
+    use crate::module::Module;
+    use crate::process::ProcessEntryIter;
+    use crate::thread::Thread;
 
-            return Some(mbi);
-        }
+    fn assert_send<T: Send>() {}
+    fn assert_send_sync<T: Send + Sync>() {}
 
-        None
+    #[test]
+    fn sharing_across_threads() {
+        assert_send_sync::<Handle>();
+        assert_send_sync::<HandleMemoryBasicInformationIter>();
+        assert_send_sync::<Module>();
+        assert_send_sync::<MemoryBasicInformation>();
+        assert_send_sync::<Thread>();
+        assert_send::<HandleSnapshot>();
+        assert_send::<ProcessEntryIter>();
     }
 }
@@ -0,0 +1,362 @@
+use std::ffi::c_void;
+use std::mem::size_of;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::Duration;
+
+use windows::Win32::Foundation::{
+    CloseHandle, DuplicateHandle, BOOL, DUPLICATE_CLOSE_SOURCE, DUPLICATE_SAME_ACCESS, HANDLE,
+    NTSTATUS,
+};
+use windows::Win32::System::Threading::GetCurrentProcess;
+
+use crate::error::{Error, Result};
+use crate::handle::Handle;
+use crate::ntdll;
+
+/// `SystemExtendedHandleInformation` of `SYSTEM_INFORMATION_CLASS`
+const SYSTEM_EXTENDED_HANDLE_INFORMATION: u32 = 64;
+/// `ObjectNameInformation` of `OBJECT_INFORMATION_CLASS`
+const OBJECT_NAME_INFORMATION: u32 = 1;
+/// `ObjectTypeInformation` of `OBJECT_INFORMATION_CLASS`
+const OBJECT_TYPE_INFORMATION: u32 = 2;
+/// `STATUS_INFO_LENGTH_MISMATCH`, buffer too small for the handle table
+const STATUS_INFO_LENGTH_MISMATCH: NTSTATUS = NTSTATUS(0xC0000004_u32 as i32);
+/// size of `SYSTEM_HANDLE_TABLE_ENTRY_INFO_EX`
+const ENTRY_SIZE: usize = size_of::<usize>() * 3 + 16;
+/// size of buffer for the name or type of an object
+const OBJECT_INFORMATION_SIZE: usize = 0x1000;
+/// access of file handle which name query may never return, like synchronous named pipes
+const BLOCKING_FILE_ACCESSES: [u32; 3] = [0x0012019F, 0x001A019F, 0x00120189];
+
+/// longest wait for the name of an object, before giving up on it
+const NAME_QUERY_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Look at `SYSTEM_HANDLE_TABLE_ENTRY_INFO_EX`, a handle opened by a process
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HandleEntry {
+    object: usize,
+    process_id: u32,
+    value: usize,
+    granted_access: u32,
+    type_index: u16,
+    attributes: u32,
+}
+
+impl HandleEntry {
+    /// kernel address of the object, same object opened by different handles share it
+    pub fn get_object(&self) -> usize {
+        self.object
+    }
+
+    /// id of the process owning the handle
+    pub fn get_process_id(&self) -> u32 {
+        self.process_id
+    }
+
+    /// value of the handle in the owning process
+    pub fn get_value(&self) -> usize {
+        self.value
+    }
+
+    /// access rights granted to the handle
+    pub fn get_granted_access(&self) -> u32 {
+        self.granted_access
+    }
+
+    /// index of the object type, same for every object of the same type
+    pub fn get_type_index(&self) -> u16 {
+        self.type_index
+    }
+
+    /// attributes of the handle, like `OBJ_INHERIT`
+    pub fn get_attributes(&self) -> u32 {
+        self.attributes
+    }
+}
+
+/// handle opened by a process with the type and name of its object
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HandleInfo {
+    entry: HandleEntry,
+    type_name: Option<String>,
+    name: Option<String>,
+}
+
+impl HandleInfo {
+    /// entry of the handle in the handle table
+    pub fn get_entry(&self) -> &HandleEntry {
+        &self.entry
+    }
+
+    /// type of the object, like `File`, `Mutant` or `Section`
+    pub fn get_type_name(&self) -> Option<&str> {
+        self.type_name.as_deref()
+    }
+
+    /// name of the object, like `\BaseNamedObjects\Game_SingleInstance`
+    pub fn get_name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+}
+
+/// every handle opened on the system
+pub fn system_handles() -> Result<Vec<HandleEntry>> {
+    let query = ntdll::NtQuerySystemInformation().ok_or(Error::Unsupported)?;
+
+    let mut buffer = vec![0usize; 0x10000];
+    loop {
+        let mut len = 0u32;
+        let status = unsafe {
+            query(
+                SYSTEM_EXTENDED_HANDLE_INFORMATION,
+                buffer.as_mut_ptr() as *mut c_void,
+                (buffer.len() * size_of::<usize>()) as u32,
+                &mut len,
+            )
+        };
+
+        match status {
+            STATUS_INFO_LENGTH_MISMATCH => {
+                // NOTE: handles keep being opened between queries, leave some room
+                let len = (len as usize).max(buffer.len() * size_of::<usize>()) * 2;
+                buffer.resize(len / size_of::<usize>(), 0);
+            }
+            status => {
+                status
+                    .ok()
+                    .map_err(|e| Error::win32("NtQuerySystemInformation", e))?;
+                let bytes = unsafe {
+                    std::slice::from_raw_parts(
+                        buffer.as_ptr() as *const u8,
+                        buffer.len() * size_of::<usize>(),
+                    )
+                };
+                return Ok(parse_handle_table(bytes));
+            }
+        }
+    }
+}
+
+impl Handle {
+    /// handles opened by the process, with the type and name of their object.
+    ///
+    /// handle need `DupHandle` access, object of handle that cannot be duplicated has no type
+    /// and name. object whose name query hang has no name.
+    pub fn get_handles(&self) -> Result<Vec<HandleInfo>> {
+        let process_id = self.get_process_id();
+        let mut name_query = NameQuery::spawn();
+
+        Ok(system_handles()?
+            .into_iter()
+            .filter(|e| e.process_id == process_id)
+            .map(|entry| {
+                let (type_name, name) = match self.duplicate_remote_handle(entry.value) {
+                    Ok(duplicate) => {
+                        let type_name = query_object_string(duplicate, OBJECT_TYPE_INFORMATION);
+                        let name = match type_name.as_deref() {
+                            Some("File")
+                                if BLOCKING_FILE_ACCESSES.contains(&entry.granted_access) =>
+                            {
+                                let _ = unsafe { CloseHandle(duplicate) };
+                                None
+                            }
+                            _ => name_query.query(duplicate),
+                        };
+                        (type_name, name.filter(|e| !e.is_empty()))
+                    }
+                    Err(_) => (None, None),
+                };
+
+                HandleInfo {
+                    entry,
+                    type_name,
+                    name,
+                }
+            })
+            .collect())
+    }
+
+    /// close handle with `value` owned by the process, like a mutex keeping a single instance.
+    ///
+    /// handle need `DupHandle` access.
+    pub fn close_remote_handle(&self, value: usize) -> Result<()> {
+        unsafe {
+            DuplicateHandle(
+                **self,
+                HANDLE(value as isize),
+                HANDLE::default(),
+                std::ptr::null_mut(),
+                0,
+                BOOL(0),
+                DUPLICATE_CLOSE_SOURCE,
+            )
+        }
+        .map_err(|e| Error::win32("DuplicateHandle", e))
+    }
+
+    /// duplicate handle with `value` owned by the process into the current process
+    pub(crate) fn duplicate_remote_handle(&self, value: usize) -> Result<HANDLE> {
+        let mut duplicate = HANDLE::default();
+        unsafe {
+            DuplicateHandle(
+                **self,
+                HANDLE(value as isize),
+                GetCurrentProcess(),
+                &mut duplicate,
+                0,
+                BOOL(0),
+                DUPLICATE_SAME_ACCESS,
+            )
+        }
+        .map_err(|e| Error::win32("DuplicateHandle", e))?;
+
+        Ok(duplicate)
+    }
+}
+
+/// Thread querying object names, replaced when a query does not return in time.
+///
+/// name query of some objects, like pipes waited on synchronously, block forever.
+struct NameQuery {
+    sender: Sender<isize>,
+    receiver: Receiver<Option<String>>,
+}
+
+impl NameQuery {
+    fn spawn() -> Self {
+        let (sender, requests) = mpsc::channel::<isize>();
+        let (results, receiver) = mpsc::channel();
+
+        // NOTE: detached, a blocked query can not be interrupted. once unblocked it find the
+        // channels dropped and exit
+        std::thread::spawn(move || {
+            for raw in requests {
+                let raw = HANDLE(raw);
+                let name = query_object_string(raw, OBJECT_NAME_INFORMATION);
+                let _ = unsafe { CloseHandle(raw) };
+                if results.send(name).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self { sender, receiver }
+    }
+
+    /// name of the object of `duplicate`, taking ownership of the handle
+    fn query(&mut self, duplicate: HANDLE) -> Option<String> {
+        if self.sender.send(duplicate.0).is_err() {
+            let _ = unsafe { CloseHandle(duplicate) };
+            *self = Self::spawn();
+            return None;
+        }
+
+        match self.receiver.recv_timeout(NAME_QUERY_TIMEOUT) {
+            Ok(name) => name,
+            Err(_) => {
+                *self = Self::spawn();
+                None
+            }
+        }
+    }
+}
+
+/// string at the start of object information of `class`, both name and type start with it
+fn query_object_string(raw: HANDLE, class: u32) -> Option<String> {
+    let query = ntdll::NtQueryObject()?;
+
+    let mut buffer = vec![0usize; OBJECT_INFORMATION_SIZE / size_of::<usize>()];
+    unsafe {
+        query(
+            raw,
+            class,
+            buffer.as_mut_ptr() as *mut c_void,
+            OBJECT_INFORMATION_SIZE as u32,
+            std::ptr::null_mut(),
+        )
+    }
+    .ok()
+    .ok()?;
+
+    // NOTE: UNICODE_STRING with its buffer pointing inside our buffer
+    let len = (buffer[0] & 0xFFFF) / 2;
+    let pointer = buffer[1] as *const u16;
+    if pointer.is_null() {
+        return Some(String::new());
+    }
+
+    Some(String::from_utf16_lossy(unsafe {
+        std::slice::from_raw_parts(pointer, len)
+    }))
+}
+
+/// entries of `SYSTEM_HANDLE_INFORMATION_EX` laid out for the pointer size of the current process
+fn parse_handle_table(bytes: &[u8]) -> Vec<HandleEntry> {
+    const POINTER_SIZE: usize = size_of::<usize>();
+
+    let pointer_at = |offset: usize| -> Option<usize> {
+        bytes
+            .get(offset..offset + POINTER_SIZE)
+            .map(|e| usize::from_le_bytes(e.try_into().unwrap()))
+    };
+    let u32_at = |offset: usize| -> Option<u32> {
+        bytes
+            .get(offset..offset + 4)
+            .map(|e| u32::from_le_bytes(e.try_into().unwrap()))
+    };
+
+    let count = pointer_at(0).unwrap_or(0);
+    (0..count)
+        .map_while(|index| {
+            let offset = POINTER_SIZE * 2 + index * ENTRY_SIZE;
+            Some(HandleEntry {
+                object: pointer_at(offset)?,
+                process_id: pointer_at(offset + POINTER_SIZE)? as u32,
+                value: pointer_at(offset + POINTER_SIZE * 2)?,
+                granted_access: u32_at(offset + POINTER_SIZE * 3)?,
+                type_index: (u32_at(offset + POINTER_SIZE * 3 + 4)? >> 16) as u16,
+                attributes: u32_at(offset + POINTER_SIZE * 3 + 8)?,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem::size_of;
+
+    use super::{parse_handle_table, ENTRY_SIZE};
+
+    #[test]
+    fn parsing_handle_table() {
+        const POINTER_SIZE: usize = size_of::<usize>();
+
+        let mut bytes = vec![0u8; POINTER_SIZE * 2 + ENTRY_SIZE * 2];
+        bytes[..POINTER_SIZE].copy_from_slice(&2usize.to_le_bytes());
+
+        let entry = POINTER_SIZE * 2 + ENTRY_SIZE;
+        let mut put = |offset: usize, value: &[u8]| {
+            bytes[entry + offset..entry + offset + value.len()].copy_from_slice(value)
+        };
+        put(0, &0xFFFF_8000usize.to_le_bytes());
+        put(POINTER_SIZE, &1234usize.to_le_bytes());
+        put(POINTER_SIZE * 2, &0x1A4usize.to_le_bytes());
+        put(POINTER_SIZE * 3, &0x1F0001u32.to_le_bytes());
+        // CreatorBackTraceIndex then ObjectTypeIndex
+        put(POINTER_SIZE * 3 + 6, &17u16.to_le_bytes());
+        put(POINTER_SIZE * 3 + 8, &2u32.to_le_bytes());
+
+        let entries = parse_handle_table(&bytes);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].get_object(), 0xFFFF_8000);
+        assert_eq!(entries[1].get_process_id(), 1234);
+        assert_eq!(entries[1].get_value(), 0x1A4);
+        assert_eq!(entries[1].get_granted_access(), 0x1F0001);
+        assert_eq!(entries[1].get_type_index(), 17);
+        assert_eq!(entries[1].get_attributes(), 2);
+
+        // count claiming more entries than the buffer hold
+        bytes[..POINTER_SIZE].copy_from_slice(&3usize.to_le_bytes());
+        assert_eq!(parse_handle_table(&bytes).len(), 2);
+    }
+}
@@ -0,0 +1,137 @@
+use std::mem::size_of;
+use std::ops::Deref;
+
+use bitflags::bitflags;
+use windows::Win32::System::Diagnostics::ToolHelp::{
+    Heap32First, Heap32Next, HEAPENTRY32, HEAPLIST32,
+};
+
+bitflags! {
+    /// state of a heap block
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct HeapBlockFlags: u32 {
+        /// block has fixed location
+        const Fixed = 0x1;
+        /// block is not used
+        const Free = 0x2;
+        /// block can be moved
+        const Moveable = 0x4;
+    }
+}
+
+/// Look at [HEAPLIST32 structure (tlhelp32.h) - Win32 API](https://learn.microsoft.com/en-us/windows/win32/api/tlhelp32/ns-tlhelp32-heaplist32)
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct HeapList(HEAPLIST32);
+
+impl HeapList {
+    /// get `th32ProcessID`
+    pub fn get_process_id(&self) -> u32 {
+        self.0.th32ProcessID
+    }
+
+    /// get `th32HeapID`
+    pub fn get_heap_id(&self) -> usize {
+        self.0.th32HeapID
+    }
+
+    /// whether the heap is the default heap of the process
+    pub fn is_default(&self) -> bool {
+        // HF32_DEFAULT
+        self.0.dwFlags & 0x1 != 0
+    }
+
+    /// get blocks allocated in the heap
+    pub fn get_blocks(&self) -> HeapBlockIter {
+        HeapBlockIter {
+            heap_list: *self,
+            is_first: true,
+            heap_entry_32: HEAPENTRY32 {
+                dwSize: size_of::<HEAPENTRY32>(),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl Deref for HeapList {
+    type Target = HEAPLIST32;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<HEAPLIST32> for HeapList {
+    fn from(value: HEAPLIST32) -> Self {
+        Self(value)
+    }
+}
+
+/// Look at [HEAPENTRY32 structure (tlhelp32.h) - Win32 API](https://learn.microsoft.com/en-us/windows/win32/api/tlhelp32/ns-tlhelp32-heapentry32)
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct HeapBlock(HEAPENTRY32);
+
+impl HeapBlock {
+    /// get `dwAddress`
+    pub fn get_address(&self) -> usize {
+        self.0.dwAddress
+    }
+
+    /// get `dwBlockSize`
+    pub fn get_size(&self) -> usize {
+        self.0.dwBlockSize
+    }
+
+    /// get `dwFlags`
+    pub fn get_flags(&self) -> HeapBlockFlags {
+        HeapBlockFlags::from_bits_retain(self.0.dwFlags.0)
+    }
+
+    /// get `th32HeapID`
+    pub fn get_heap_id(&self) -> usize {
+        self.0.th32HeapID
+    }
+}
+
+impl Deref for HeapBlock {
+    type Target = HEAPENTRY32;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<HEAPENTRY32> for HeapBlock {
+    fn from(value: HEAPENTRY32) -> Self {
+        Self(value)
+    }
+}
+
+/// Heap List -> Heap Block Iterator
+pub struct HeapBlockIter {
+    heap_list: HeapList,
+    is_first: bool,
+    heap_entry_32: HEAPENTRY32,
+}
+
+impl Iterator for HeapBlockIter {
+    type Item = HeapBlock;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // NOTE: Heap32Next continue from the previous entry so it has to be kept around
+        let result = if self.is_first {
+            self.is_first = false;
+            unsafe {
+                Heap32First(
+                    &mut self.heap_entry_32 as *mut _,
+                    self.heap_list.get_process_id(),
+                    self.heap_list.get_heap_id(),
+                )
+            }
+        } else {
+            unsafe { Heap32Next(&mut self.heap_entry_32 as *mut _) }
+        };
+
+        result.ok().map(|_| HeapBlock::from(self.heap_entry_32))
+    }
+}
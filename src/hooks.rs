@@ -0,0 +1,180 @@
+#[cfg(feature = "iced-x86")]
+use crate::disasm;
+use crate::error::{Error, Result};
+use crate::handle::Handle;
+use crate::memory::{PageProtectionFlags, RemoteAllocation, VirtualAllocationType};
+
+/// `nop` instruction
+const NOP: u8 = 0x90;
+
+/// size allocated for trampoline, relocated prologue may grow longer than the original
+const TRAMPOLINE_SIZE: usize = 0x1000;
+
+/// Detour installed over the prologue of a function, restored on drop.
+///
+/// the trampoline is freed along with the guard, so no thread should be executing it by then.
+pub struct DetourGuard<'a> {
+    handle: &'a Handle,
+    target: usize,
+    original: Vec<u8>,
+    trampoline: RemoteAllocation<'a>,
+}
+
+impl DetourGuard<'_> {
+    /// address of the hooked function
+    pub fn get_target(&self) -> usize {
+        self.target
+    }
+
+    /// address that run the original function, call it from the detour to pass through
+    pub fn get_trampoline(&self) -> usize {
+        self.trampoline.get_address()
+    }
+
+    /// bytes of the prologue overwritten by the jump
+    pub fn get_original_bytes(&self) -> &[u8] {
+        &self.original
+    }
+
+    /// restore the prologue, same as dropping the guard
+    pub fn uninstall(self) {}
+
+    /// keep the detour installed and the trampoline allocated after drop, returning address of
+    /// the trampoline
+    pub fn leak(self) -> usize {
+        let this = std::mem::ManuallyDrop::new(self);
+        // NOTE: the guard is never dropped, move its fields out to free or leak them
+        drop(unsafe { std::ptr::read(&this.original) });
+        unsafe { std::ptr::read(&this.trampoline) }.leak()
+    }
+}
+
+impl Drop for DetourGuard<'_> {
+    fn drop(&mut self) {
+        let _ = self.handle.write_memory(self.target, &self.original);
+        let _ = self
+            .handle
+            .flush_instruction_cache(self.target, self.original.len());
+    }
+}
+
+/// redirect function at `target` to `detour` by overwriting its first `stolen_len` bytes with a jump.
+///
+/// `stolen_len` must end on an instruction boundary and hold the jump, 5 bytes when `detour`
+/// is within 2GB of `target` or 14 bytes otherwise. the stolen instructions are copied
+/// verbatim to the trampoline, so they must not be relative to instruction pointer.
+pub fn install_detour(
+    handle: &Handle,
+    target: usize,
+    detour: usize,
+    stolen_len: usize,
+) -> Result<DetourGuard<'_>> {
+    let original = handle.read_memory(target, stolen_len)?;
+    install_detour_with_prologue(handle, target, detour, original.clone(), |_| Ok(original))
+}
+
+/// redirect function at `target` to `detour`, stealing whole instructions of its prologue.
+///
+/// the stolen instructions are relocated to the trampoline, fixing up operands relative to
+/// instruction pointer.
+#[cfg(feature = "iced-x86")]
+pub fn install_detour_auto(
+    handle: &Handle,
+    target: usize,
+    detour: usize,
+) -> Result<DetourGuard<'_>> {
+    let pointer_size = handle.get_pointer_size()?;
+    let bitness = pointer_size as u32 * 8;
+    let jump_len = encode_jump(target, detour, pointer_size).len();
+
+    let bytes = handle.read_memory(target, jump_len + disasm::MAX_INSTRUCTION_LEN)?;
+    let stolen = disasm::steal_instructions(&bytes, target, bitness, jump_len)?;
+    let stolen_len = stolen.iter().map(|e| e.len()).sum();
+
+    install_detour_with_prologue(
+        handle,
+        target,
+        detour,
+        bytes[..stolen_len].to_vec(),
+        |address| disasm::relocate(&stolen, bitness, address),
+    )
+}
+
+/// install the detour, `relocate` give `original` prologue fixed up to run from the trampoline
+fn install_detour_with_prologue<F>(
+    handle: &Handle,
+    target: usize,
+    detour: usize,
+    original: Vec<u8>,
+    relocate: F,
+) -> Result<DetourGuard<'_>>
+where
+    F: FnOnce(usize) -> Result<Vec<u8>>,
+{
+    let pointer_size = handle.get_pointer_size()?;
+
+    let patch = encode_jump(target, detour, pointer_size);
+    if patch.len() > original.len() {
+        return Err(Error::InvalidInput);
+    }
+
+    let trampoline = handle.alloc(
+        TRAMPOLINE_SIZE,
+        PageProtectionFlags::ExecuteReadWrite,
+        VirtualAllocationType::Commit | VirtualAllocationType::Reserve,
+    )?;
+    let mut trampoline_code = relocate(trampoline.get_address())?;
+    let jump_back_address = trampoline.get_address() + trampoline_code.len();
+    trampoline_code.extend(encode_jump(
+        jump_back_address,
+        target + original.len(),
+        pointer_size,
+    ));
+    if trampoline_code.len() > TRAMPOLINE_SIZE {
+        return Err(Error::InvalidInput);
+    }
+    handle.write_memory(trampoline.get_address(), &trampoline_code)?;
+    handle.flush_instruction_cache(trampoline.get_address(), trampoline_code.len())?;
+
+    let mut patch = patch;
+    patch.resize(original.len(), NOP);
+    handle.write_memory(target, &patch)?;
+    handle.flush_instruction_cache(target, patch.len())?;
+
+    Ok(DetourGuard {
+        handle,
+        target,
+        original,
+        trampoline,
+    })
+}
+
+/// `jmp rel32` when reachable, otherwise `jmp [rip]` followed by the absolute address
+pub(crate) fn encode_jump(from: usize, to: usize, pointer_size: usize) -> Vec<u8> {
+    let relative = (to as i64).wrapping_sub(from as i64 + 5);
+
+    match i32::try_from(relative) {
+        Ok(relative) if pointer_size == 8 => [&[0xE9], &relative.to_le_bytes()[..]].concat(),
+        _ if pointer_size == 4 => [&[0xE9], &(relative as i32).to_le_bytes()[..]].concat(),
+        _ => [&[0xFF, 0x25, 0, 0, 0, 0], &(to as u64).to_le_bytes()[..]].concat(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::encode_jump;
+
+    #[test]
+    fn encoding_jumps() {
+        assert_eq!(encode_jump(0x1000, 0x2000, 8), vec![0xE9, 0xFB, 0x0F, 0, 0]);
+        assert_eq!(
+            encode_jump(0x2000, 0x1000, 4),
+            vec![0xE9, 0xFB, 0xEF, 0xFF, 0xFF]
+        );
+
+        let far = encode_jump(0x1000, 0x7FF0_0000_0000, 8);
+        assert_eq!(far.len(), 14);
+        assert_eq!(&far[..6], &[0xFF, 0x25, 0, 0, 0, 0]);
+        assert_eq!(&far[6..], &0x7FF0_0000_0000u64.to_le_bytes());
+    }
+}
@@ -0,0 +1,320 @@
+use std::str::FromStr;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+use bitflags::bitflags;
+use windows::Win32::Foundation::{HWND, LPARAM, WPARAM};
+use windows::Win32::System::Threading::GetCurrentThreadId;
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    RegisterHotKey, UnregisterHotKey, HOT_KEY_MODIFIERS,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    GetMessageW, PeekMessageW, PostThreadMessageW, MSG, PM_NOREMOVE, WM_HOTKEY, WM_QUIT,
+};
+
+use crate::error::{Error, Result};
+use crate::session::Session;
+
+/// virtual key codes of keys named by more than their character
+const NAMED_KEYS: [(&str, u32); 20] = [
+    ("backspace", 0x08),
+    ("tab", 0x09),
+    ("enter", 0x0D),
+    ("pause", 0x13),
+    ("capslock", 0x14),
+    ("esc", 0x1B),
+    ("escape", 0x1B),
+    ("space", 0x20),
+    ("pageup", 0x21),
+    ("pagedown", 0x22),
+    ("end", 0x23),
+    ("home", 0x24),
+    ("left", 0x25),
+    ("up", 0x26),
+    ("right", 0x27),
+    ("down", 0x28),
+    ("insert", 0x2D),
+    ("ins", 0x2D),
+    ("delete", 0x2E),
+    ("del", 0x2E),
+];
+
+/// called when the hotkey pressed, on the thread running the message loop
+type HotkeyAction = dyn FnMut() + Send;
+
+bitflags! {
+    /// Look at [RegisterHotKey function (winuser.h) - Win32 API](https://learn.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-registerhotkey)
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct HotkeyModifiers: u32 {
+        /// `MOD_ALT`
+        const Alt = 0x1;
+        /// `MOD_CONTROL`
+        const Control = 0x2;
+        /// `MOD_SHIFT`
+        const Shift = 0x4;
+        /// `MOD_WIN`
+        const Win = 0x8;
+        /// `MOD_NOREPEAT`, holding the keys down does not repeat the hotkey
+        const NoRepeat = 0x4000;
+    }
+}
+
+impl From<HotkeyModifiers> for HOT_KEY_MODIFIERS {
+    fn from(value: HotkeyModifiers) -> Self {
+        HOT_KEY_MODIFIERS(value.bits())
+    }
+}
+
+/// combination of modifiers and a key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Hotkey {
+    modifiers: HotkeyModifiers,
+    key: u32,
+}
+
+impl Hotkey {
+    /// create hotkey of the virtual key code, like `0x70` for `F1`
+    pub fn new(modifiers: HotkeyModifiers, key: u32) -> Self {
+        Self { modifiers, key }
+    }
+
+    /// modifiers to be held down
+    pub fn get_modifiers(&self) -> HotkeyModifiers {
+        self.modifiers
+    }
+
+    /// virtual key code of the key
+    pub fn get_key(&self) -> u32 {
+        self.key
+    }
+}
+
+/// parse keys joined by `+` ignoring case, like `Ctrl+Shift+F1`, `Alt+Numpad5` or `Insert`.
+///
+/// the hotkey does not repeat while held down.
+impl FromStr for Hotkey {
+    type Err = Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let mut modifiers = HotkeyModifiers::NoRepeat;
+        let mut key = None;
+
+        for part in s.split('+').map(|e| e.trim().to_lowercase()) {
+            let modifier = match part.as_str() {
+                "ctrl" | "control" => Some(HotkeyModifiers::Control),
+                "alt" => Some(HotkeyModifiers::Alt),
+                "shift" => Some(HotkeyModifiers::Shift),
+                "win" => Some(HotkeyModifiers::Win),
+                _ => None,
+            };
+
+            match (modifier, key) {
+                (Some(modifier), _) => modifiers |= modifier,
+                (None, None) => key = Some(parse_key(&part).ok_or(Error::InvalidInput)?),
+                (None, Some(_)) => return Err(Error::InvalidInput),
+            }
+        }
+
+        Ok(Self {
+            modifiers,
+            key: key.ok_or(Error::InvalidInput)?,
+        })
+    }
+}
+
+/// virtual key code of lowercase key name
+fn parse_key(name: &str) -> Option<u32> {
+    if let [c] = name.as_bytes() {
+        if c.is_ascii_alphanumeric() {
+            return Some(c.to_ascii_uppercase() as u32);
+        }
+    }
+
+    let number = |prefix: &str| name.strip_prefix(prefix)?.parse::<u32>().ok();
+    match (number("f"), number("numpad")) {
+        (Some(n @ 1..=24), _) => return Some(0x70 + n - 1),
+        (_, Some(n @ 0..=9)) => return Some(0x60 + n),
+        _ => (),
+    }
+
+    NAMED_KEYS
+        .iter()
+        .find(|(e, _)| *e == name)
+        .map(|(_, key)| *key)
+}
+
+/// Actions bound to system wide hotkeys, run by a message loop.
+///
+/// actions are called on the thread running the loop, one at a time.
+#[derive(Default)]
+pub struct Hotkeys {
+    bindings: Vec<(Hotkey, Box<HotkeyAction>)>,
+}
+
+impl Hotkeys {
+    /// create instance without any binding
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// call `action` whenever the hotkey pressed
+    pub fn bind<F>(&mut self, hotkey: Hotkey, action: F)
+    where
+        F: FnMut() + Send + 'static,
+    {
+        self.bindings.push((hotkey, Box::new(action)));
+    }
+
+    /// call `action` with the session whenever the hotkey pressed, like freezing a value or
+    /// applying patches
+    pub fn bind_session<F>(&mut self, hotkey: Hotkey, session: Arc<Mutex<Session>>, mut action: F)
+    where
+        F: FnMut(&mut Session) + Send + 'static,
+    {
+        self.bind(hotkey, move || {
+            if let Ok(mut session) = session.lock() {
+                action(&mut session);
+            }
+        });
+    }
+
+    /// register every hotkey and run the message loop on the current thread until it receive
+    /// `WM_QUIT`.
+    ///
+    /// registering fail when the hotkey is already registered by any process.
+    pub fn run(mut self) -> Result<()> {
+        self.register()?;
+        let result = self.message_loop();
+        self.unregister();
+
+        result
+    }
+
+    /// run the message loop on a background thread, stopped when the returned thread dropped
+    pub fn spawn(mut self) -> Result<HotkeyThread> {
+        let (sender, receiver) = mpsc::channel();
+
+        let thread = std::thread::spawn(move || {
+            // NOTE: peeking create the message queue, WM_QUIT posted before it exist is lost
+            let mut message = MSG::default();
+            let _ = unsafe { PeekMessageW(&mut message, HWND::default(), 0, 0, PM_NOREMOVE) };
+            let registered = self.register();
+            let is_registered = registered.is_ok();
+            let _ = sender.send(registered.map(|_| unsafe { GetCurrentThreadId() }));
+            if !is_registered {
+                return Ok(());
+            }
+
+            let result = self.message_loop();
+            self.unregister();
+            result
+        });
+
+        match receiver.recv() {
+            Ok(Ok(thread_id)) => Ok(HotkeyThread {
+                thread_id,
+                thread: Some(thread),
+            }),
+            Ok(Err(e)) => Err(e),
+            Err(_) => Err(Error::RemoteFailed("hotkey thread")),
+        }
+    }
+
+    fn register(&self) -> Result<()> {
+        for (id, (hotkey, _)) in self.bindings.iter().enumerate() {
+            if let Err(e) = unsafe {
+                RegisterHotKey(
+                    HWND::default(),
+                    id as i32,
+                    hotkey.modifiers.into(),
+                    hotkey.key,
+                )
+            } {
+                (0..id).for_each(|id| {
+                    let _ = unsafe { UnregisterHotKey(HWND::default(), id as i32) };
+                });
+                return Err(Error::win32("RegisterHotKey", e));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn unregister(&self) {
+        for id in 0..self.bindings.len() {
+            let _ = unsafe { UnregisterHotKey(HWND::default(), id as i32) };
+        }
+    }
+
+    fn message_loop(&mut self) -> Result<()> {
+        let mut message = MSG::default();
+        loop {
+            match unsafe { GetMessageW(&mut message, HWND::default(), 0, 0) }.0 {
+                0 => return Ok(()),
+                -1 => return Err(Error::last_win32("GetMessageW")),
+                _ => (),
+            }
+
+            if message.message == WM_HOTKEY {
+                if let Some((_, action)) = self.bindings.get_mut(message.wParam.0) {
+                    action();
+                }
+            }
+        }
+    }
+}
+
+/// Background thread running the message loop of [Hotkeys], stopped when dropped.
+pub struct HotkeyThread {
+    thread_id: u32,
+    thread: Option<JoinHandle<Result<()>>>,
+}
+
+impl HotkeyThread {
+    /// stop the message loop and wait for the thread to exit
+    pub fn stop(mut self) -> Result<()> {
+        self.quit()
+    }
+
+    fn quit(&mut self) -> Result<()> {
+        let Some(thread) = self.thread.take() else {
+            return Ok(());
+        };
+
+        unsafe { PostThreadMessageW(self.thread_id, WM_QUIT, WPARAM(0), LPARAM(0)) }
+            .map_err(|e| Error::win32("PostThreadMessageW", e))?;
+
+        thread
+            .join()
+            .unwrap_or(Err(Error::RemoteFailed("hotkey thread")))
+    }
+}
+
+impl Drop for HotkeyThread {
+    fn drop(&mut self) {
+        let _ = self.quit();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Hotkey, HotkeyModifiers};
+
+    #[test]
+    fn parsing_hotkeys() {
+        let hotkey: Hotkey = "Ctrl+Shift+F1".parse().unwrap();
+        assert_eq!(
+            hotkey.get_modifiers(),
+            HotkeyModifiers::Control | HotkeyModifiers::Shift | HotkeyModifiers::NoRepeat
+        );
+        assert_eq!(hotkey.get_key(), 0x70);
+
+        assert_eq!("alt+numpad5".parse::<Hotkey>().unwrap().get_key(), 0x65);
+        assert_eq!("Insert".parse::<Hotkey>().unwrap().get_key(), 0x2D);
+        assert_eq!("ctrl + x".parse::<Hotkey>().unwrap().get_key(), b'X' as u32);
+        assert!("Ctrl+Shift".parse::<Hotkey>().is_err());
+        assert!("A+B".parse::<Hotkey>().is_err());
+        assert!("F25".parse::<Hotkey>().is_err());
+    }
+}
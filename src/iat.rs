@@ -0,0 +1,75 @@
+use crate::error::{Error, Result};
+use crate::handle::Handle;
+use crate::module::Module;
+
+/// Import address table slot pointing to a replacement, restoring the original pointer on drop.
+pub struct IatHook<'a> {
+    handle: &'a Handle,
+    slot_address: usize,
+    original: usize,
+    pointer_size: usize,
+}
+
+impl<'a> IatHook<'a> {
+    /// point the slot importing `function_name` from `import_module` of `module` to `replacement`.
+    ///
+    /// `import_module` is matched case insensitive, like `kernel32.dll`.
+    pub fn try_new(
+        handle: &'a Handle,
+        module: &Module,
+        import_module: &str,
+        function_name: &str,
+        replacement: usize,
+    ) -> Result<Self> {
+        let pe = module.pe(handle)?;
+        let import = pe
+            .imports(handle)?
+            .into_iter()
+            .find(|e| {
+                e.get_module_name().eq_ignore_ascii_case(import_module)
+                    && e.get_name() == Some(function_name)
+            })
+            .ok_or(Error::NotFound)?;
+
+        let pointer_size = if pe.is_64bit() { 8 } else { 4 };
+        let slot_address = import.get_slot_address();
+        let original = handle.read_pointer_sized(slot_address, pointer_size)?;
+
+        let hook = Self {
+            handle,
+            slot_address,
+            original,
+            pointer_size,
+        };
+        hook.write_slot(replacement)?;
+
+        Ok(hook)
+    }
+
+    /// address of the hooked import address table slot
+    pub fn get_slot_address(&self) -> usize {
+        self.slot_address
+    }
+
+    /// address of the function the slot pointed to before hooked
+    pub fn get_original(&self) -> usize {
+        self.original
+    }
+
+    /// restore the original pointer, same as dropping the hook
+    pub fn restore(self) {}
+
+    /// point the slot to `address`, thread calling through the slot meanwhile may see a torn
+    /// pointer
+    fn write_slot(&self, address: usize) -> Result<()> {
+        let bytes = address.to_le_bytes();
+        self.handle
+            .write_memory(self.slot_address, &bytes[..self.pointer_size])
+    }
+}
+
+impl Drop for IatHook<'_> {
+    fn drop(&mut self) {
+        let _ = self.write_slot(self.original);
+    }
+}
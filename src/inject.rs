@@ -0,0 +1,26 @@
+use std::path::Path;
+
+use windows::Win32::Foundation::HMODULE;
+
+use crate::call::RemoteArg;
+use crate::error::{Error, Result};
+use crate::handle::Handle;
+use crate::module::ModuleFilter;
+
+/// load the dll at `path` into the process of the handle, returning its `HMODULE` in the process
+pub fn inject_dll(handle: &Handle, path: &Path) -> Result<HMODULE> {
+    let path = path.to_str().ok_or(Error::InvalidInput)?;
+
+    // NOTE: WOW64 process load both 32 bit and 64 bit kernel32.dll
+    let filter = ModuleFilter::for_pointer_size(handle.get_pointer_size()?);
+    let load_library = handle.resolve_export_in("kernel32.dll", "LoadLibraryW", filter)?;
+
+    // NOTE: thread exit code hold only the lower 32 bit, which may be zero for a 64 bit
+    // HMODULE. calling through the stub return the whole register
+    let module = handle.call_remote(load_library, &[RemoteArg::wide_str(path)])?;
+    if module == 0 {
+        return Err(Error::RemoteFailed("LoadLibraryW"));
+    }
+
+    Ok(HMODULE(module as isize))
+}
@@ -0,0 +1,114 @@
+use std::mem::size_of;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use windows::core::{HSTRING, PCWSTR};
+use windows::Win32::System::LibraryLoader::{GetModuleFileNameW, GetModuleHandleW};
+use windows::Win32::System::ProcessStatus::{K32GetModuleInformation, MODULEINFO};
+use windows::Win32::System::Threading::{GetCurrentProcess, GetCurrentProcessId};
+
+use crate::error::{Error, Result};
+use crate::handle::{Handle, HandleAccess};
+use crate::hooks::{self, DetourGuard};
+use crate::memory::{PageProtectionFlags, ProtectionGuard};
+use crate::module::{Module, MAX_MODULE_PATH};
+use crate::patch::{MemorySection, PatchSet};
+use crate::scanner::{Scanner, Signature};
+
+/// handle for the current process, shared by everything in this module.
+///
+/// it is the pseudo handle, so it has every access right and never need closing. memory is
+/// read and written through syscalls, so bad addresses fail instead of faulting.
+pub fn current() -> &'static Handle {
+    static CURRENT: OnceLock<Handle> = OnceLock::new();
+
+    CURRENT.get_or_init(|| {
+        Handle::from_raw(
+            unsafe { GetCurrentProcess() },
+            unsafe { GetCurrentProcessId() },
+            HandleAccess::All,
+        )
+    })
+}
+
+/// module loaded in the current process with the given name, the executable when `None`
+pub fn get_module(name: Option<&str>) -> Result<Module> {
+    let wide_name = name.map(HSTRING::from);
+    let hmodule = match &wide_name {
+        Some(name) => unsafe { GetModuleHandleW(name) },
+        None => unsafe { GetModuleHandleW(PCWSTR::null()) },
+    }
+    .map_err(|e| Error::win32("GetModuleHandleW", e))?;
+
+    let mut info = MODULEINFO::default();
+    unsafe {
+        K32GetModuleInformation(
+            GetCurrentProcess(),
+            hmodule,
+            &mut info,
+            size_of::<MODULEINFO>() as u32,
+        )
+    }
+    .ok()
+    .map_err(|e| Error::win32("GetModuleInformation", e))?;
+
+    let mut path = vec![0u16; MAX_MODULE_PATH];
+    let len = unsafe { GetModuleFileNameW(hmodule, &mut path) } as usize;
+    if len == 0 {
+        return Err(Error::last_win32("GetModuleFileNameW"));
+    }
+    let path = String::from_utf16_lossy(&path[..len]);
+    let name = Path::new(&path)
+        .file_name()
+        .map(|e| e.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    Ok(Module::from_loader_entry(
+        current().get_process_id(),
+        info.lpBaseOfDll as usize,
+        info.SizeOfImage,
+        &name,
+        &path,
+    ))
+}
+
+/// modules loaded in the current process, walking the loader list without any snapshot
+pub fn get_modules() -> Result<Vec<Module>> {
+    current().get_modules_via_peb()
+}
+
+/// every address in the memory section of the current process that matches the signature
+pub fn scan(signature: &Signature, section: MemorySection) -> Result<Vec<usize>> {
+    Scanner::new(current()).scan(signature, section)
+}
+
+/// change protection of memory in the current process, restored when the guard dropped
+pub fn protect(
+    address: usize,
+    size: usize,
+    protection: PageProtectionFlags,
+) -> Result<ProtectionGuard<'static>> {
+    current().protect(address, size, protection)
+}
+
+/// empty patch set for the current process, every patch is restored on drop
+pub fn patch_set() -> PatchSet<'static> {
+    PatchSet::new(current())
+}
+
+/// redirect function at `target` in the current process to `detour`, look at
+/// [hooks::install_detour]
+pub fn install_detour(
+    target: usize,
+    detour: usize,
+    stolen_len: usize,
+) -> Result<DetourGuard<'static>> {
+    hooks::install_detour(current(), target, detour, stolen_len)
+}
+
+/// redirect function at `target` in the current process to `detour`, look at
+/// [hooks::install_detour_auto]
+#[cfg(feature = "iced-x86")]
+pub fn install_detour_auto(target: usize, detour: usize) -> Result<DetourGuard<'static>> {
+    hooks::install_detour_auto(current(), target, detour)
+}
@@ -37,13 +37,100 @@
 //! }
 //! ```
 
+/// relating to addresses that survive relocation of modules across restarts.
+pub mod address;
+/// offloading blocking calls of a process out of async executors.
+#[cfg(feature = "async")]
+pub mod r#async;
+/// relating to access to memory of a process, decoupled from how it is accessed.
+pub mod backend;
+/// walking call stacks of threads of a process.
+pub mod backtrace;
+/// polling typed values of a process into shared state for overlays.
+pub mod binding;
+/// relating to exchanging requests with payloads injected into a process.
+#[cfg(feature = "serde")]
+pub mod bridge;
+/// caching reads of memory of a process.
+pub mod cache;
+/// relating to calling functions in a process.
+pub mod call;
+mod dbghelp;
+/// relating to debugging a process.
+pub mod debug;
+/// relating to decoding instructions of a process.
+#[cfg(feature = "iced-x86")]
+pub mod disasm;
+/// relating to saving memory of a process for offline analysis.
+pub mod dump;
+/// relating to errors of the crate.
+pub mod error;
+/// keeping values in memory of a process from changing.
+pub mod freeze;
 /// relating to the process of a process.
 pub mod handle;
+/// relating to handles opened by processes.
+pub mod handles;
+/// relating to heaps of a process.
+pub mod heap;
+/// relating to redirecting functions of a process.
+pub mod hooks;
+/// binding actions to system wide keyboard hotkeys.
+#[cfg(feature = "hotkeys")]
+pub mod hotkeys;
+/// relating to hooking import address table of modules.
+pub mod iat;
+/// relating to loading code into a process.
+pub mod inject;
+/// relating to code running inside the process it works on.
+pub mod internal;
 /// relating to physical memory and virtual memory.
 pub mod memory;
+/// labeled map of address space of a process.
+pub mod memory_map;
 /// relating to bytes that loaded by a process.
 pub mod module;
+mod ntdll;
 /// relating to helper to patch memory.
 pub mod patch;
 /// simple matching hopefuly fast for bytes.
 pub mod pattern;
+/// relating to headers of PE image like exe or dll.
+pub mod pe;
+/// relating to environment blocks and loader data of processes and threads.
+pub mod peb;
+/// relating to privileges of the current process.
+pub mod privileges;
+/// relating to processes running on the system.
+pub mod process;
+/// relating to streaming logs from code injected into a process.
+pub mod remote_log;
+/// relating to MSVC run-time type information of C++ classes.
+pub mod rtti;
+/// searching signature across memory of a process.
+pub mod scanner;
+/// relating to attaching to a process across its restarts.
+pub mod session;
+/// relating to memory shared between processes.
+pub mod shared;
+mod simd;
+/// comparing memory of a process between points in time.
+pub mod snapshot;
+/// relating to scaling time seen by a process, like speeding up a game.
+pub mod speedhack;
+/// relating to resolving addresses to names with DbgHelp.
+#[cfg(feature = "symbols")]
+pub mod symbols;
+/// relating to tables of named addresses kept between sessions.
+pub mod table;
+/// relating to threads of a process.
+pub mod thread;
+/// relating to access tokens of processes.
+pub mod token;
+/// iterative searching of typed value across memory of a process.
+pub mod value_scanner;
+/// polling memory and modules of a process for changes.
+pub mod watch;
+mod worker;
+
+pub use error::{Error, Result};
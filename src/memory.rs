@@ -1,11 +1,106 @@
 use bitflags::bitflags;
+use std::fmt::{self, Display};
 use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+use std::mem::size_of;
+use std::ops::{Deref, Range};
+use std::path::PathBuf;
+
+use windows::core::HSTRING;
+use windows::Win32::Storage::FileSystem::{GetLogicalDriveStringsW, QueryDosDeviceW};
 use windows::Win32::System::Diagnostics::Debug::{ReadProcessMemory, WriteProcessMemory};
 use windows::Win32::System::Memory::{
-    MEMORY_BASIC_INFORMATION, PAGE_PROTECTION_FLAGS, PAGE_TYPE, VIRTUAL_ALLOCATION_TYPE,
+    VirtualAllocEx, VirtualFreeEx, VirtualProtectEx, MEMORY_BASIC_INFORMATION, MEM_RELEASE,
+    PAGE_PROTECTION_FLAGS, PAGE_TYPE, VIRTUAL_ALLOCATION_TYPE,
 };
+use windows::Win32::System::ProcessStatus::PROCESS_MEMORY_COUNTERS_EX;
 
+use crate::backend::MemoryBackend;
+use crate::error::Error;
 use crate::handle::Handle;
+use crate::module::Module;
+use crate::pe::ReadFn;
+
+/// marker for types which all zero bit pattern is a valid value
+///
+/// # Safety
+///
+/// implementor must be valid when all of its bytes are zero.
+pub unsafe trait Zeroable: Sized {
+    /// value with all bytes zero
+    fn zeroed() -> Self {
+        unsafe { std::mem::zeroed() }
+    }
+}
+
+/// marker for plain types that can be read from raw bytes of memory
+///
+/// enable `bytemuck` feature to have every `bytemuck::Pod` be one, along with its derive.
+///
+/// # Safety
+///
+/// implementor must have no padding, no pointer or reference inside it
+/// and must be valid for any bit pattern.
+pub unsafe trait Pod: Zeroable + Copy + 'static {}
+
+#[cfg(not(feature = "bytemuck"))]
+macro_rules! impl_pod {
+    ($($t:ty),*) => {
+        $(unsafe impl Zeroable for $t {}
+        unsafe impl Pod for $t {})*
+    };
+}
+
+#[cfg(not(feature = "bytemuck"))]
+impl_pod!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
+
+#[cfg(not(feature = "bytemuck"))]
+unsafe impl<T: Zeroable, const N: usize> Zeroable for [T; N] {}
+
+#[cfg(not(feature = "bytemuck"))]
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+
+// NOTE: numbers and arrays are covered by bytemuck, implementing them again would overlap
+#[cfg(feature = "bytemuck")]
+unsafe impl<T: bytemuck::Zeroable> Zeroable for T {}
+
+#[cfg(feature = "bytemuck")]
+unsafe impl<T: bytemuck::Pod> Pod for T {}
+
+/// bytes of the value as laid out in memory
+pub fn bytes_of<T: Pod>(value: &T) -> &[u8] {
+    unsafe { std::slice::from_raw_parts(value as *const T as *const u8, size_of::<T>()) }
+}
+
+/// value from bytes laid out in memory, `None` when length of `bytes` is not size of `T`
+pub fn from_bytes<T: Pod>(bytes: &[u8]) -> Option<T> {
+    if bytes.len() != size_of::<T>() {
+        return None;
+    }
+
+    let mut value = T::zeroed();
+    unsafe {
+        std::ptr::copy_nonoverlapping(
+            bytes.as_ptr(),
+            &mut value as *mut T as *mut u8,
+            size_of::<T>(),
+        )
+    };
+    Some(value)
+}
+
+/// struct mapped onto memory of a process from a base address
+///
+/// enable `derive` feature to derive it from fields annotated with `#[offset(..)]`.
+pub trait RemoteStruct: Sized {
+    /// read the struct from memory of `backend` at `base`
+    fn read_from<B: MemoryBackend>(backend: &B, base: usize) -> Result<Self, Error>;
+
+    /// write the struct to memory of `backend` at `base`
+    fn write_to<B: MemoryBackend>(&self, backend: &B, base: usize) -> Result<(), Error>;
+}
+
+#[cfg(feature = "derive")]
+pub use winmem_derive::RemoteStruct;
 
 /// Wrapper for memory that act like io
 pub struct Memory<'a> {
@@ -39,7 +134,7 @@ impl<'a> Read for Memory<'a> {
                 Some(&mut n),
             )
         }
-        .map_err(|_| ErrorKind::Other)?;
+        .map_err(|e| Error::win32("ReadProcessMemory", e))?;
 
         self.current_address += n;
 
@@ -63,7 +158,7 @@ impl<'a> Write for Memory<'a> {
                 Some(&mut n),
             )
         }
-        .map_err(|_| ErrorKind::Other)?;
+        .map_err(|e| Error::win32("WriteProcessMemory", e))?;
 
         self.current_address += n;
 
@@ -119,8 +214,124 @@ impl<'a> Seek for Memory<'a> {
     }
 }
 
+/// memory allocated in a process, released when dropped
+pub struct RemoteAllocation<'a> {
+    handle: &'a Handle,
+    address: usize,
+    size: usize,
+}
+
+impl<'a> RemoteAllocation<'a> {
+    pub(crate) fn try_new(
+        handle: &'a Handle,
+        size: usize,
+        protection: PageProtectionFlags,
+        allocation_type: VirtualAllocationType,
+    ) -> Result<Self, Error> {
+        let address = unsafe {
+            VirtualAllocEx(
+                **handle,
+                None,
+                size,
+                allocation_type.into(),
+                protection.into(),
+            )
+        };
+
+        if address.is_null() {
+            return Err(Error::last_win32("VirtualAllocEx"));
+        }
+
+        Ok(Self {
+            handle,
+            address: address as usize,
+            size,
+        })
+    }
+
+    /// base address of the allocation
+    pub fn get_address(&self) -> usize {
+        self.address
+    }
+
+    /// requested size of the allocation
+    pub fn get_size(&self) -> usize {
+        self.size
+    }
+
+    /// keep the memory allocated after drop, returning its base address
+    pub fn leak(self) -> usize {
+        let address = self.address;
+        std::mem::forget(self);
+        address
+    }
+}
+
+impl<'a> Drop for RemoteAllocation<'a> {
+    fn drop(&mut self) {
+        let _ = unsafe { VirtualFreeEx(**self.handle, self.address as *mut _, 0, MEM_RELEASE) };
+    }
+}
+
+/// changed protection of memory in a process, restored when dropped
+pub struct ProtectionGuard<'a> {
+    handle: &'a Handle,
+    address: usize,
+    size: usize,
+    old_protection: PAGE_PROTECTION_FLAGS,
+}
+
+impl<'a> ProtectionGuard<'a> {
+    pub(crate) fn try_new(
+        handle: &'a Handle,
+        address: usize,
+        size: usize,
+        protection: PageProtectionFlags,
+    ) -> Result<Self, Error> {
+        let mut old_protection = PAGE_PROTECTION_FLAGS(0);
+        unsafe {
+            VirtualProtectEx(
+                **handle,
+                address as *const _,
+                size,
+                protection.into(),
+                &mut old_protection,
+            )
+        }
+        .map_err(|e| Error::win32("VirtualProtectEx", e))?;
+
+        Ok(Self {
+            handle,
+            address,
+            size,
+            old_protection,
+        })
+    }
+
+    /// protection of the memory before changed
+    pub fn get_old_protection(&self) -> PageProtectionFlags {
+        PageProtectionFlags::from_bits_retain(self.old_protection.0)
+    }
+}
+
+impl<'a> Drop for ProtectionGuard<'a> {
+    fn drop(&mut self) {
+        let mut old_protection = PAGE_PROTECTION_FLAGS(0);
+        let _ = unsafe {
+            VirtualProtectEx(
+                **self.handle,
+                self.address as *const _,
+                self.size,
+                self.old_protection,
+                &mut old_protection,
+            )
+        };
+    }
+}
+
 bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct PageProtectionFlags: u32 {
         const Execute = 0x10;
         const ExecuteRead = 0x20;
@@ -139,6 +350,88 @@ bitflags! {
     }
 }
 
+impl PageProtectionFlags {
+    /// whether pages can be touched at all, not `NoAccess` nor `Guard`
+    pub fn is_accessible(&self) -> bool {
+        !self.intersects(Self::NoAccess | Self::Guard)
+    }
+
+    /// whether pages can be read
+    pub fn is_readable(&self) -> bool {
+        self.is_accessible()
+            && self.intersects(
+                Self::ReadOnly
+                    | Self::ReadWrite
+                    | Self::WriteCopy
+                    | Self::ExecuteRead
+                    | Self::ExecuteReadWrite
+                    | Self::ExecuteWriteCopy,
+            )
+    }
+
+    /// whether pages can be written
+    pub fn is_writable(&self) -> bool {
+        self.is_accessible()
+            && self.intersects(
+                Self::ReadWrite | Self::WriteCopy | Self::ExecuteReadWrite | Self::ExecuteWriteCopy,
+            )
+    }
+
+    /// whether pages can be executed
+    pub fn is_executable(&self) -> bool {
+        self.is_accessible()
+            && self.intersects(
+                Self::Execute | Self::ExecuteRead | Self::ExecuteReadWrite | Self::ExecuteWriteCopy,
+            )
+    }
+
+    /// whether writing pages make a private copy
+    pub fn is_copy_on_write(&self) -> bool {
+        self.intersects(Self::WriteCopy | Self::ExecuteWriteCopy)
+    }
+}
+
+/// `rwx` style, `c` for copy on write, followed by modifiers like `+guard`
+impl Display for PageProtectionFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let readable = self.intersects(
+            Self::ReadOnly
+                | Self::ReadWrite
+                | Self::WriteCopy
+                | Self::ExecuteRead
+                | Self::ExecuteReadWrite
+                | Self::ExecuteWriteCopy,
+        );
+        let write = match self.is_copy_on_write() {
+            true => 'c',
+            false if self.intersects(Self::ReadWrite | Self::ExecuteReadWrite) => 'w',
+            false => '-',
+        };
+        let executable = self.intersects(
+            Self::Execute | Self::ExecuteRead | Self::ExecuteReadWrite | Self::ExecuteWriteCopy,
+        );
+
+        write!(
+            f,
+            "{}{}{}",
+            if readable { 'r' } else { '-' },
+            write,
+            if executable { 'x' } else { '-' }
+        )?;
+        for (flag, name) in [
+            (Self::Guard, "+guard"),
+            (Self::NoCache, "+nocache"),
+            (Self::WriteCombine, "+writecombine"),
+        ] {
+            if self.contains(flag) {
+                f.write_str(name)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
 impl Into<PAGE_PROTECTION_FLAGS> for PageProtectionFlags {
     fn into(self) -> PAGE_PROTECTION_FLAGS {
         PAGE_PROTECTION_FLAGS(self.bits())
@@ -146,15 +439,16 @@ impl Into<PAGE_PROTECTION_FLAGS> for PageProtectionFlags {
 }
 
 impl TryFrom<PAGE_PROTECTION_FLAGS> for PageProtectionFlags {
-    type Error = ErrorKind;
+    type Error = Error;
 
     fn try_from(value: PAGE_PROTECTION_FLAGS) -> Result<Self, Self::Error> {
-        Self::from_bits(value.0).ok_or(ErrorKind::Unsupported)
+        Self::from_bits(value.0).ok_or(Error::Unsupported)
     }
 }
 
 bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct VirtualAllocationType: u32 {
         const Commit = 0x1000;
         const Free = 0x10000;
@@ -162,6 +456,13 @@ bitflags! {
     }
 }
 
+/// names of the flags joined by `|`, like `commit|reserve`
+impl Display for VirtualAllocationType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_flag_names(f, self.iter_names())
+    }
+}
+
 impl Into<VIRTUAL_ALLOCATION_TYPE> for VirtualAllocationType {
     fn into(self) -> VIRTUAL_ALLOCATION_TYPE {
         VIRTUAL_ALLOCATION_TYPE(self.bits())
@@ -169,15 +470,16 @@ impl Into<VIRTUAL_ALLOCATION_TYPE> for VirtualAllocationType {
 }
 
 impl TryFrom<VIRTUAL_ALLOCATION_TYPE> for VirtualAllocationType {
-    type Error = ErrorKind;
+    type Error = Error;
 
     fn try_from(value: VIRTUAL_ALLOCATION_TYPE) -> Result<Self, Self::Error> {
-        Self::from_bits(value.0).ok_or(ErrorKind::Unsupported)
+        Self::from_bits(value.0).ok_or(Error::Unsupported)
     }
 }
 
 bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct PageType: u32 {
         const Image = 0x1000000;
         const Mapped = 0x40000;
@@ -185,6 +487,25 @@ bitflags! {
     }
 }
 
+/// names of the flags joined by `|`, like `private`
+impl Display for PageType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_flag_names(f, self.iter_names())
+    }
+}
+
+/// lowercase `names` joined by `|`, `-` when there is none
+fn write_flag_names<'a, T>(
+    f: &mut fmt::Formatter<'_>,
+    names: impl Iterator<Item = (&'a str, T)>,
+) -> fmt::Result {
+    let names: Vec<String> = names.map(|(name, _)| name.to_lowercase()).collect();
+    match names.is_empty() {
+        true => f.write_str("-"),
+        false => f.write_str(&names.join("|")),
+    }
+}
+
 impl Into<PAGE_TYPE> for PageType {
     fn into(self) -> PAGE_TYPE {
         PAGE_TYPE(self.bits())
@@ -192,16 +513,29 @@ impl Into<PAGE_TYPE> for PageType {
 }
 
 impl TryFrom<PAGE_TYPE> for PageType {
-    type Error = ErrorKind;
+    type Error = Error;
 
     fn try_from(value: PAGE_TYPE) -> Result<Self, Self::Error> {
-        Self::from_bits(value.0).ok_or(ErrorKind::Unsupported)
+        Self::from_bits(value.0).ok_or(Error::Unsupported)
     }
 }
 
 /// Look at [MEMORY_BASIC_INFORMATION (winnt.h) Win32 API](https://learn.microsoft.com/en-us/windows/win32/api/winnt/ns-winnt-memory_basic_information)
+#[derive(Clone, Copy)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(
+        from = "MemoryBasicInformationRepr",
+        into = "MemoryBasicInformationRepr"
+    )
+)]
 pub struct MemoryBasicInformation(MEMORY_BASIC_INFORMATION);
 
+// NOTE: pointers inside are addresses in the queried process, never dereferenced
+unsafe impl Send for MemoryBasicInformation {}
+unsafe impl Sync for MemoryBasicInformation {}
+
 impl MemoryBasicInformation {
     /// get `BaseAddress`
     pub fn get_base_address(&self) -> usize {
@@ -215,7 +549,7 @@ impl MemoryBasicInformation {
 
     /// get `AllocationProtect`
     pub fn get_allocation_protect(&self) -> PageProtectionFlags {
-        PageProtectionFlags::try_from(self.0.AllocationProtect).unwrap()
+        PageProtectionFlags::from_bits_retain(self.0.AllocationProtect.0)
     }
 
     /// get `PatitionId`
@@ -231,17 +565,60 @@ impl MemoryBasicInformation {
 
     /// get `State`
     pub fn get_state(&self) -> VirtualAllocationType {
-        VirtualAllocationType::try_from(self.0.State).unwrap()
+        VirtualAllocationType::from_bits_retain(self.0.State.0)
     }
 
     /// get `Protect`
     pub fn get_protect(&self) -> PageProtectionFlags {
-        PageProtectionFlags::try_from(self.0.Protect).unwrap()
+        PageProtectionFlags::from_bits_retain(self.0.Protect.0)
     }
 
     /// get `Type`
     pub fn get_type(&self) -> PageType {
-        PageType::try_from(self.0.Type).unwrap()
+        PageType::from_bits_retain(self.0.Type.0)
+    }
+
+    /// whether the region is committed
+    pub fn is_committed(&self) -> bool {
+        self.get_state().contains(VirtualAllocationType::Commit)
+    }
+
+    /// whether the region is accessible and can be read
+    pub fn is_readable(&self) -> bool {
+        self.get_protect().is_readable()
+    }
+
+    /// whether the region is accessible and can be written
+    pub fn is_writable(&self) -> bool {
+        self.get_protect().is_writable()
+    }
+
+    /// whether the region is accessible and can be executed
+    pub fn is_executable(&self) -> bool {
+        self.get_protect().is_executable()
+    }
+
+    /// whether the region is private to the process
+    pub fn is_private(&self) -> bool {
+        self.get_type().contains(PageType::Private)
+    }
+
+    /// whether the region is mapped from an image like exe or dll
+    pub fn is_image(&self) -> bool {
+        self.get_type().contains(PageType::Image)
+    }
+
+    /// whether the region start inside the module
+    pub fn is_within_module(&self, module: &Module) -> bool {
+        module.contains(self.get_base_address())
+    }
+
+    /// path of the file mapped at the region with drive letter, `None` when not file backed
+    pub fn mapped_file(&self, handle: &Handle) -> Option<PathBuf> {
+        let device_path = handle.get_mapped_file_name(self.get_base_address()).ok()?;
+
+        let path = translate_device_path(&device_path, &get_dos_devices()).unwrap_or(device_path);
+        Some(PathBuf::from(path))
     }
 }
 
@@ -250,3 +627,799 @@ impl From<MEMORY_BASIC_INFORMATION> for MemoryBasicInformation {
         Self(value)
     }
 }
+
+/// like `0x7ff6_1000_0000 - 0x7ff6_1001_0000 rw- private commit 64 KiB`
+impl Display for MemoryBasicInformation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let start = self.get_base_address();
+        write!(
+            f,
+            "{} - {} {} {} {} {}",
+            format_address(start),
+            format_address(start.wrapping_add(self.get_region_size())),
+            self.get_protect(),
+            self.get_type(),
+            self.get_state(),
+            format_size(self.get_region_size())
+        )
+    }
+}
+
+impl fmt::Debug for MemoryBasicInformation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoryBasicInformation")
+            .field("base_address", &format_address(self.get_base_address()))
+            .field(
+                "allocation_base",
+                &format_address(self.get_allocation_base()),
+            )
+            .field("allocation_protect", &self.get_allocation_protect())
+            .field("region_size", &self.get_region_size())
+            .field("state", &self.get_state())
+            .field("protect", &self.get_protect())
+            .field("type", &self.get_type())
+            .finish()
+    }
+}
+
+/// hex address with `_` between every 4 digits, like `0x7ff6_1000_0000`
+pub(crate) fn format_address(address: usize) -> String {
+    let digits = format!("{:x}", address);
+    let mut formatted = String::from("0x");
+    for (index, c) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(4) {
+            formatted.push('_');
+        }
+        formatted.push(c);
+    }
+
+    formatted
+}
+
+/// size in the largest binary unit keeping it at least 1, like `64 KiB` or `1.5 MiB`
+pub(crate) fn format_size(size: usize) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+
+    let mut unit = 0;
+    while unit + 1 < UNITS.len() && size >= 1 << (10 * (unit + 1)) {
+        unit += 1;
+    }
+
+    let scale = 1usize << (10 * unit);
+    match size.is_multiple_of(scale) {
+        true => format!("{} {}", size / scale, UNITS[unit]),
+        false => format!("{:.1} {}", size as f64 / scale as f64, UNITS[unit]),
+    }
+}
+
+/// serialized form of [MemoryBasicInformation]
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct MemoryBasicInformationRepr {
+    base_address: usize,
+    allocation_base: usize,
+    allocation_protect: u32,
+    region_size: usize,
+    state: u32,
+    protect: u32,
+    page_type: u32,
+}
+
+#[cfg(feature = "serde")]
+impl From<MemoryBasicInformation> for MemoryBasicInformationRepr {
+    fn from(value: MemoryBasicInformation) -> Self {
+        Self {
+            base_address: value.0.BaseAddress as usize,
+            allocation_base: value.0.AllocationBase as usize,
+            allocation_protect: value.0.AllocationProtect.0,
+            region_size: value.0.RegionSize,
+            state: value.0.State.0,
+            protect: value.0.Protect.0,
+            page_type: value.0.Type.0,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<MemoryBasicInformationRepr> for MemoryBasicInformation {
+    fn from(value: MemoryBasicInformationRepr) -> Self {
+        Self(MEMORY_BASIC_INFORMATION {
+            BaseAddress: value.base_address as *mut _,
+            AllocationBase: value.allocation_base as *mut _,
+            AllocationProtect: PAGE_PROTECTION_FLAGS(value.allocation_protect),
+            RegionSize: value.region_size,
+            State: VIRTUAL_ALLOCATION_TYPE(value.state),
+            Protect: PAGE_PROTECTION_FLAGS(value.protect),
+            Type: PAGE_TYPE(value.page_type),
+            ..Default::default()
+        })
+    }
+}
+
+/// `(drive, device)` of every drive letter, like `("C:", "\\Device\\HarddiskVolume3")`
+pub(crate) fn get_dos_devices() -> Vec<(String, String)> {
+    let mut drives = [0u16; 0x200];
+    let len = unsafe { GetLogicalDriveStringsW(Some(&mut drives)) } as usize;
+
+    String::from_utf16_lossy(&drives[..len.min(drives.len())])
+        .split('\0')
+        .filter_map(|root| {
+            let drive = root.trim_end_matches('\\');
+            if drive.is_empty() {
+                return None;
+            }
+
+            let mut device = [0u16; 0x400];
+            let len = unsafe { QueryDosDeviceW(&HSTRING::from(drive), Some(&mut device)) } as usize;
+            // NOTE: result is a list of nul terminated target, the first one is the current
+            let device = String::from_utf16_lossy(&device[..len.min(device.len())]);
+            let device = device.split('\0').next()?;
+
+            Some((drive.to_string(), device.to_string()))
+        })
+        .collect()
+}
+
+/// replace device prefix of `path` with the drive letter mapped to it
+pub(crate) fn translate_device_path(path: &str, devices: &[(String, String)]) -> Option<String> {
+    devices.iter().find_map(|(drive, device)| {
+        let rest = path.strip_prefix(device.as_str())?;
+        rest.starts_with('\\').then(|| format!("{}{}", drive, rest))
+    })
+}
+
+/// filter helpers for iterator of [MemoryBasicInformation]
+pub trait MemoryBasicInformationFilter: Iterator<Item = MemoryBasicInformation> + Sized {
+    /// keep committed regions
+    fn committed(self) -> impl Iterator<Item = MemoryBasicInformation> {
+        self.filter(MemoryBasicInformation::is_committed)
+    }
+
+    /// keep readable regions
+    fn readable(self) -> impl Iterator<Item = MemoryBasicInformation> {
+        self.filter(MemoryBasicInformation::is_readable)
+    }
+
+    /// keep writable regions
+    fn writable(self) -> impl Iterator<Item = MemoryBasicInformation> {
+        self.filter(MemoryBasicInformation::is_writable)
+    }
+
+    /// keep executable regions
+    fn executable(self) -> impl Iterator<Item = MemoryBasicInformation> {
+        self.filter(MemoryBasicInformation::is_executable)
+    }
+
+    /// keep regions private to the process
+    fn private(self) -> impl Iterator<Item = MemoryBasicInformation> {
+        self.filter(MemoryBasicInformation::is_private)
+    }
+
+    /// keep regions mapped from an image
+    fn image(self) -> impl Iterator<Item = MemoryBasicInformation> {
+        self.filter(MemoryBasicInformation::is_image)
+    }
+
+    /// keep regions inside the module
+    fn within_module(self, module: &Module) -> impl Iterator<Item = MemoryBasicInformation> {
+        let module = *module;
+        self.filter(move |mbi| mbi.is_within_module(&module))
+    }
+}
+
+impl<I: Iterator<Item = MemoryBasicInformation>> MemoryBasicInformationFilter for I {}
+
+/// Look at [PSAPI_WORKING_SET_EX_BLOCK union (psapi.h) - Win32 API](https://learn.microsoft.com/en-us/windows/win32/api/psapi/ns-psapi-psapi_working_set_ex_block)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WorkingSetInfo {
+    address: usize,
+    flags: usize,
+}
+
+impl WorkingSetInfo {
+    pub(crate) fn new(address: usize, flags: usize) -> Self {
+        Self { address, flags }
+    }
+
+    /// get `VirtualAddress`
+    pub fn get_address(&self) -> usize {
+        self.address
+    }
+
+    /// whether the page is resident in physical memory, reading it cause no page fault
+    pub fn is_valid(&self) -> bool {
+        self.flags & 1 != 0
+    }
+
+    /// get `ShareCount`, number of processes sharing the page
+    pub fn get_share_count(&self) -> u32 {
+        ((self.flags >> 1) & 0b111) as u32
+    }
+
+    /// get `Win32Protection`, protection of the page
+    pub fn get_protection(&self) -> PageProtectionFlags {
+        PageProtectionFlags::from_bits_retain(((self.flags >> 4) & 0x7FF) as u32)
+    }
+
+    /// get `Shared`
+    pub fn is_shared(&self) -> bool {
+        self.flags & (1 << 15) != 0
+    }
+
+    /// get `Locked`
+    pub fn is_locked(&self) -> bool {
+        self.flags & (1 << 22) != 0
+    }
+
+    /// get `LargePage`
+    pub fn is_large_page(&self) -> bool {
+        self.flags & (1 << 23) != 0
+    }
+}
+
+/// Look at [PROCESS_MEMORY_COUNTERS_EX structure (psapi.h) - Win32 API](https://learn.microsoft.com/en-us/windows/win32/api/psapi/ns-psapi-process_memory_counters_ex)
+#[derive(Clone, Copy)]
+pub struct MemoryCounters(PROCESS_MEMORY_COUNTERS_EX);
+
+impl MemoryCounters {
+    /// get `PageFaultCount`
+    pub fn get_page_fault_count(&self) -> u32 {
+        self.0.PageFaultCount
+    }
+
+    /// get `WorkingSetSize`
+    pub fn get_working_set_size(&self) -> usize {
+        self.0.WorkingSetSize
+    }
+
+    /// get `PeakWorkingSetSize`
+    pub fn get_peak_working_set_size(&self) -> usize {
+        self.0.PeakWorkingSetSize
+    }
+
+    /// get `QuotaPagedPoolUsage`
+    pub fn get_paged_pool_usage(&self) -> usize {
+        self.0.QuotaPagedPoolUsage
+    }
+
+    /// get `QuotaPeakPagedPoolUsage`
+    pub fn get_peak_paged_pool_usage(&self) -> usize {
+        self.0.QuotaPeakPagedPoolUsage
+    }
+
+    /// get `QuotaNonPagedPoolUsage`
+    pub fn get_non_paged_pool_usage(&self) -> usize {
+        self.0.QuotaNonPagedPoolUsage
+    }
+
+    /// get `QuotaPeakNonPagedPoolUsage`
+    pub fn get_peak_non_paged_pool_usage(&self) -> usize {
+        self.0.QuotaPeakNonPagedPoolUsage
+    }
+
+    /// get `PagefileUsage`, commit charge of the process
+    pub fn get_pagefile_usage(&self) -> usize {
+        self.0.PagefileUsage
+    }
+
+    /// get `PeakPagefileUsage`
+    pub fn get_peak_pagefile_usage(&self) -> usize {
+        self.0.PeakPagefileUsage
+    }
+
+    /// get `PrivateUsage`, private bytes of the process
+    pub fn get_private_usage(&self) -> usize {
+        self.0.PrivateUsage
+    }
+}
+
+impl Deref for MemoryCounters {
+    type Target = PROCESS_MEMORY_COUNTERS_EX;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<PROCESS_MEMORY_COUNTERS_EX> for MemoryCounters {
+    fn from(value: PROCESS_MEMORY_COUNTERS_EX) -> Self {
+        Self(value)
+    }
+}
+
+/// `MEMORY_REGION_INFORMATION` as returned by `NtQueryVirtualMemory`
+#[repr(C)]
+#[derive(Default)]
+pub(crate) struct RawMemoryRegionInformation {
+    allocation_base: usize,
+    allocation_protect: u32,
+    region_type: u32,
+    region_size: usize,
+    commit_size: usize,
+    partition_id: usize,
+    node_preference: usize,
+}
+
+/// Look at [WIN32_MEMORY_REGION_INFORMATION structure (memoryapi.h) - Win32 API](https://learn.microsoft.com/en-us/windows/win32/api/memoryapi/ns-memoryapi-win32_memory_region_information)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryRegionInformation {
+    allocation_base: usize,
+    allocation_protect: u32,
+    region_type: u32,
+    region_size: usize,
+    commit_size: usize,
+}
+
+impl MemoryRegionInformation {
+    /// get `AllocationBase`
+    pub fn get_allocation_base(&self) -> usize {
+        self.allocation_base
+    }
+
+    /// get `AllocationProtect`
+    pub fn get_allocation_protect(&self) -> PageProtectionFlags {
+        PageProtectionFlags::from_bits_retain(self.allocation_protect)
+    }
+
+    /// get `RegionSize`, size of the whole allocation
+    pub fn get_region_size(&self) -> usize {
+        self.region_size
+    }
+
+    /// get `CommitSize`, committed bytes of the allocation
+    pub fn get_commit_size(&self) -> usize {
+        self.commit_size
+    }
+
+    /// get `Private`
+    pub fn is_private(&self) -> bool {
+        self.region_type & 1 != 0
+    }
+
+    /// get `MappedDataFile`
+    pub fn is_mapped_data_file(&self) -> bool {
+        self.region_type & (1 << 1) != 0
+    }
+
+    /// get `MappedImage`
+    pub fn is_mapped_image(&self) -> bool {
+        self.region_type & (1 << 2) != 0
+    }
+
+    /// get `MappedPageFile`
+    pub fn is_mapped_page_file(&self) -> bool {
+        self.region_type & (1 << 3) != 0
+    }
+
+    /// get `MappedPhysical`
+    pub fn is_mapped_physical(&self) -> bool {
+        self.region_type & (1 << 4) != 0
+    }
+
+    /// get `DirectMapped`
+    pub fn is_direct_mapped(&self) -> bool {
+        self.region_type & (1 << 5) != 0
+    }
+}
+
+impl From<RawMemoryRegionInformation> for MemoryRegionInformation {
+    fn from(value: RawMemoryRegionInformation) -> Self {
+        Self {
+            allocation_base: value.allocation_base,
+            allocation_protect: value.allocation_protect,
+            region_type: value.region_type,
+            region_size: value.region_size,
+            commit_size: value.commit_size,
+        }
+    }
+}
+
+/// `MEMORY_IMAGE_INFORMATION` as returned by `NtQueryVirtualMemory`
+#[repr(C)]
+#[derive(Default)]
+pub(crate) struct RawMemoryImageInformation {
+    image_base: usize,
+    size_of_image: usize,
+    image_flags: u32,
+}
+
+/// image backing a region, look at `MEMORY_IMAGE_INFORMATION` of `NtQueryVirtualMemory`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryImageInformation {
+    image_base: usize,
+    size_of_image: usize,
+    image_flags: u32,
+}
+
+impl MemoryImageInformation {
+    /// get `ImageBase`
+    pub fn get_image_base(&self) -> usize {
+        self.image_base
+    }
+
+    /// get `SizeOfImage`
+    pub fn get_size_of_image(&self) -> usize {
+        self.size_of_image
+    }
+
+    /// get `ImagePartialMap`, only part of the image is mapped
+    pub fn is_partial_map(&self) -> bool {
+        self.image_flags & 1 != 0
+    }
+
+    /// get `ImageNotExecutable`, image mapped as data
+    pub fn is_not_executable(&self) -> bool {
+        self.image_flags & (1 << 1) != 0
+    }
+
+    /// get `ImageSigningLevel`
+    pub fn get_signing_level(&self) -> u32 {
+        (self.image_flags >> 2) & 0xF
+    }
+}
+
+impl From<RawMemoryImageInformation> for MemoryImageInformation {
+    fn from(value: RawMemoryImageInformation) -> Self {
+        Self {
+            image_base: value.image_base,
+            size_of_image: value.size_of_image,
+            image_flags: value.image_flags,
+        }
+    }
+}
+
+/// granularity which reads never cross, so an unmapped page only cut the string short
+pub(crate) const PAGE_SIZE: usize = 0x1000;
+
+/// read code units of `unit_size` bytes until a zero unit or `max_units`, excluding the terminator.
+///
+/// the string is truncated at the first unreadable page following the start address.
+pub(crate) fn read_until_nul(
+    read: &ReadFn,
+    address: usize,
+    unit_size: usize,
+    max_units: usize,
+) -> Result<Vec<u8>, Error> {
+    let max_len = max_units
+        .checked_mul(unit_size)
+        .ok_or(Error::InvalidInput)?;
+    let mut bytes = Vec::new();
+    let mut searched_units = 0usize;
+
+    while bytes.len() < max_len {
+        let current_address = address
+            .checked_add(bytes.len())
+            .ok_or(Error::InvalidInput)?;
+        let len = (PAGE_SIZE - current_address % PAGE_SIZE).min(max_len - bytes.len());
+
+        match read(current_address, len) {
+            Ok(chunk) => bytes.extend_from_slice(&chunk),
+            Err(e) if bytes.is_empty() => return Err(e),
+            Err(_) => break,
+        }
+
+        let terminator = bytes
+            .chunks_exact(unit_size)
+            .skip(searched_units)
+            .position(|unit| unit.iter().all(|e| *e == 0));
+        if let Some(n) = terminator {
+            bytes.truncate((searched_units + n) * unit_size);
+            return Ok(bytes);
+        }
+        searched_units = bytes.len() / unit_size;
+    }
+
+    bytes.truncate(bytes.len() / unit_size * unit_size);
+    Ok(bytes)
+}
+
+/// offsets of `len` bytes from `address` split at page boundaries, relative to `address`
+pub(crate) fn page_ranges(address: usize, len: usize) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+
+    let mut offset = 0usize;
+    while offset < len {
+        let current_address = address.wrapping_add(offset);
+        let end = (offset + PAGE_SIZE - current_address % PAGE_SIZE).min(len);
+        ranges.push(offset..end);
+        offset = end;
+    }
+
+    ranges
+}
+
+/// address ranges where `actual` differ from `expected`, both laid out from `address`
+pub(crate) fn diff_ranges(address: usize, expected: &[u8], actual: &[u8]) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+
+    for (offset, _) in expected
+        .iter()
+        .zip(actual)
+        .enumerate()
+        .filter(|(_, (e, a))| e != a)
+    {
+        match ranges.last_mut() {
+            Some(range) if range.end == address + offset => range.end += 1,
+            _ => ranges.push(address + offset..address + offset + 1),
+        }
+    }
+
+    ranges
+}
+
+/// whether every byte of `len` bytes from `address` is in a committed readable region, regions
+/// given by `query` for the address they contain
+pub(crate) fn is_readable_range<F>(query: F, address: usize, len: usize) -> bool
+where
+    F: Fn(usize) -> Option<MemoryBasicInformation>,
+{
+    let end_address = match address.checked_add(len.max(1)) {
+        Some(end_address) => end_address,
+        None => return false,
+    };
+
+    let mut current_address = address;
+    while current_address < end_address {
+        let mbi = match query(current_address) {
+            Some(mbi) if mbi.is_committed() && mbi.is_readable() => mbi,
+            _ => return false,
+        };
+
+        let region_end = mbi.get_base_address() + mbi.get_region_size();
+        if region_end <= current_address {
+            return false;
+        }
+        current_address = region_end;
+    }
+
+    true
+}
+
+#[cfg(test)]
+pub(crate) mod testing {
+    use windows::Win32::System::Memory::{
+        MEMORY_BASIC_INFORMATION, PAGE_PROTECTION_FLAGS, PAGE_TYPE, VIRTUAL_ALLOCATION_TYPE,
+    };
+
+    use super::MemoryBasicInformation;
+
+    /// region of a page at `base_address` inside the allocation at `allocation_base`
+    pub(crate) fn region(
+        base_address: usize,
+        allocation_base: usize,
+        state: u32,
+        protect: u32,
+        page_type: u32,
+    ) -> MemoryBasicInformation {
+        MemoryBasicInformation::from(MEMORY_BASIC_INFORMATION {
+            BaseAddress: base_address as *mut _,
+            AllocationBase: allocation_base as *mut _,
+            RegionSize: 0x1000,
+            State: VIRTUAL_ALLOCATION_TYPE(state),
+            Protect: PAGE_PROTECTION_FLAGS(protect),
+            Type: PAGE_TYPE(page_type),
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::testing::region;
+    use super::{
+        bytes_of, diff_ranges, format_address, format_size, from_bytes, is_readable_range,
+        page_ranges, read_until_nul, translate_device_path, MemoryBasicInformation,
+        MemoryBasicInformationFilter, PageProtectionFlags, VirtualAllocationType, WorkingSetInfo,
+    };
+    use crate::error::Error;
+
+    #[test]
+    fn filtering_regions() {
+        let regions = vec![
+            region(0x1000, 0x1000, 0x1000, 0x04, 0x20000),
+            region(0x2000, 0x2000, 0x1000, 0x20, 0x1000000),
+            region(0x3000, 0x3000, 0x1000, 0x104, 0x20000),
+            region(0x4000, 0x4000, 0x2000, 0x01, 0x20000),
+            region(0x5000, 0x5000, 0x10000, 0x00, 0x00),
+        ];
+
+        let addresses = |regions: Vec<MemoryBasicInformation>| -> Vec<usize> {
+            regions.iter().map(|e| e.get_base_address()).collect()
+        };
+
+        assert_eq!(
+            addresses(regions.into_iter().committed().readable().collect()),
+            vec![0x1000, 0x2000]
+        );
+    }
+
+    #[test]
+    fn region_predicates() {
+        let code = region(0x2000, 0x2000, 0x1000, 0x20, 0x1000000);
+
+        assert!(code.is_executable());
+        assert!(!code.is_writable());
+        assert!(code.is_image());
+        assert!(!code.is_private());
+        assert!(!(PageProtectionFlags::ReadWrite | PageProtectionFlags::Guard).is_readable());
+    }
+
+    #[test]
+    fn displaying_flags() {
+        let code = region(0x2000, 0x2000, 0x1000, 0x20, 0x1000000);
+
+        assert_eq!(code.get_protect().to_string(), "r-x");
+        assert_eq!(code.get_state().to_string(), "commit");
+        assert_eq!(
+            (PageProtectionFlags::WriteCopy | PageProtectionFlags::Guard).to_string(),
+            "rc-+guard"
+        );
+        assert_eq!(
+            (VirtualAllocationType::Commit | VirtualAllocationType::Reserve).to_string(),
+            "commit|reserve"
+        );
+    }
+
+    #[test]
+    fn formatting_regions() {
+        let code = region(0x2000, 0x2000, 0x1000, 0x20, 0x1000000);
+
+        assert_eq!(code.to_string(), "0x2000 - 0x3000 r-x image commit 4 KiB");
+        assert_eq!(format_address(0x7FF6_1000_0000), "0x7ff6_1000_0000");
+        assert_eq!(format_size(0x18_0000), "1.5 MiB");
+    }
+
+    #[test]
+    fn reading_until_nul() {
+        // string running into an unmapped page at 0x2000
+        let mut memory = vec![b'a'; 0x1000];
+        memory[0xFF8..].copy_from_slice(&[b'h', 0, b'i', 0, 0, 0, b'x', 0]);
+        let read = |address: usize, len: usize| {
+            let start = address.checked_sub(0x1000).ok_or(Error::InvalidInput)?;
+            memory
+                .get(start..start + len)
+                .map(|e| e.to_vec())
+                .ok_or(Error::InvalidInput)
+        };
+
+        assert_eq!(read_until_nul(&read, 0x1FF8, 2, 16).unwrap(), b"h\0i\0");
+        assert_eq!(read_until_nul(&read, 0x1FF8, 1, 16).unwrap(), b"h");
+        assert_eq!(read_until_nul(&read, 0x1000, 1, 4).unwrap(), b"aaaa");
+        assert_eq!(read_until_nul(&read, 0x1FFE, 1, 16).unwrap(), b"x");
+        assert_eq!(read_until_nul(&read, 0x1FFF, 2, 16).unwrap(), b"");
+        assert!(read_until_nul(&read, 0x2000, 1, 16).is_err());
+    }
+
+    #[test]
+    fn splitting_at_pages() {
+        assert_eq!(
+            page_ranges(0x1FF0, 0x2020),
+            vec![0..0x10, 0x10..0x1010, 0x1010..0x2010, 0x2010..0x2020]
+        );
+        assert_eq!(page_ranges(0x1000, 0x10), vec![0..0x10]);
+        assert!(page_ranges(0x1000, 0).is_empty());
+    }
+
+    #[test]
+    fn diffing_ranges() {
+        let expected = vec![0u8; 0x20];
+        let mut actual = expected.clone();
+        actual[2] = 0xCC;
+        actual[3] = 0xCC;
+        actual[0x1F] = 0xCC;
+        assert_eq!(
+            diff_ranges(0x1000, &expected, &actual),
+            vec![0x1002..0x1004, 0x101F..0x1020]
+        );
+        assert!(diff_ranges(0x1000, &expected, &expected).is_empty());
+    }
+
+    #[test]
+    fn checking_readable_range() {
+        let regions = [
+            region(0x1000, 0x1000, 0x1000, 0x04, 0x20000),
+            region(0x2000, 0x2000, 0x1000, 0x02, 0x20000),
+            region(0x3000, 0x3000, 0x1000, 0x101, 0x20000),
+        ];
+        let query = |address: usize| {
+            regions
+                .iter()
+                .find(|e| (e.get_base_address()..e.get_base_address() + 0x1000).contains(&address))
+                .copied()
+        };
+
+        assert!(is_readable_range(query, 0x1FF0, 0x20));
+        assert!(is_readable_range(query, 0x2FFF, 0));
+        assert!(!is_readable_range(query, 0x2FF0, 0x20));
+        assert!(!is_readable_range(query, 0x4000, 8));
+        assert!(!is_readable_range(query, usize::MAX, 8));
+    }
+
+    #[test]
+    fn pod_round_trip() {
+        let value = [0x1122_3344u32, 0x5566_7788];
+
+        assert_eq!(from_bytes::<[u32; 2]>(bytes_of(&value)), Some(value));
+        assert_eq!(from_bytes::<u64>(&[0u8; 4]), None);
+    }
+
+    #[test]
+    fn decoding_working_set_flags() {
+        // valid, share count 2, PAGE_READWRITE, shared, locked
+        let info = WorkingSetInfo::new(0x1000, 1 | 2 << 1 | 0x04 << 4 | 1 << 15 | 1 << 22);
+
+        assert!(info.is_valid());
+        assert_eq!(info.get_share_count(), 2);
+        assert_eq!(info.get_protection(), PageProtectionFlags::ReadWrite);
+        assert!(info.is_shared());
+        assert!(info.is_locked());
+        assert!(!info.is_large_page());
+    }
+
+    #[test]
+    fn translating_device_path() {
+        let devices = vec![
+            ("C:".to_string(), "\\Device\\HarddiskVolume1".to_string()),
+            ("D:".to_string(), "\\Device\\HarddiskVolume10".to_string()),
+        ];
+
+        assert_eq!(
+            translate_device_path("\\Device\\HarddiskVolume10\\a.dll", &devices).as_deref(),
+            Some("D:\\a.dll")
+        );
+        assert_eq!(
+            translate_device_path("\\Device\\Mup\\server\\a.dll", &devices),
+            None
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serializing_regions() {
+        let mbi = region(0x1000, 0x1000, 0x1000, 0x04, 0x20000);
+
+        let json = serde_json::to_string(&mbi).unwrap();
+        let decoded = serde_json::from_str::<MemoryBasicInformation>(&json).unwrap();
+        assert_eq!(format!("{:?}", decoded), format!("{:?}", mbi));
+        assert_eq!(serde_json::to_string(&decoded).unwrap(), json);
+    }
+
+    #[test]
+    fn mapping_remote_struct_over_backend() {
+        use std::cell::RefCell;
+
+        use super::RemoteStruct;
+        use crate::backend::testing::BufferBackend;
+        use crate::backend::MemoryBackend;
+
+        #[derive(Debug, PartialEq)]
+        struct Player {
+            health: i32,
+            speed: f32,
+        }
+
+        impl RemoteStruct for Player {
+            fn read_from<B: MemoryBackend>(backend: &B, base: usize) -> Result<Self, Error> {
+                Ok(Self {
+                    health: backend.read(base + 0x10)?,
+                    speed: backend.read(base + 0x18)?,
+                })
+            }
+
+            fn write_to<B: MemoryBackend>(&self, backend: &B, base: usize) -> Result<(), Error> {
+                backend.write(base + 0x10, self.health)?;
+                backend.write(base + 0x18, self.speed)
+            }
+        }
+
+        let backend = BufferBackend(RefCell::new(vec![0u8; 0x20]));
+        let player = Player {
+            health: 100,
+            speed: 1.5,
+        };
+        player.write_to(&backend, 0x1000).unwrap();
+        assert_eq!(backend.read::<i32>(0x1010).unwrap(), 100);
+        assert_eq!(Player::read_from(&backend, 0x1000).unwrap(), player);
+        assert!(Player::read_from(&backend, 0x1010).is_err());
+    }
+}
@@ -0,0 +1,303 @@
+use std::fmt::{self, Display};
+use std::path::PathBuf;
+
+#[cfg(feature = "serde")]
+use crate::error::Error;
+use crate::error::Result;
+use crate::handle::{Handle, HandleSnapshotFlag};
+use crate::memory::{
+    format_address, format_size, get_dos_devices, translate_device_path, MemoryBasicInformation,
+    PageProtectionFlags, PageType, VirtualAllocationType,
+};
+use crate::module::Module;
+
+/// what an allocation is used for
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RegionLabel {
+    /// image of a loaded module
+    Module(String),
+    /// heap of the process
+    Heap,
+    /// stack of the thread
+    Stack(u32),
+    /// file mapped into the process
+    MappedFile(PathBuf),
+    /// private memory not recognized as anything else
+    Private,
+    /// shared memory not backed by a file
+    Shared,
+}
+
+impl Display for RegionLabel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Module(name) => write!(f, "module {}", name),
+            Self::Heap => write!(f, "heap"),
+            Self::Stack(thread_id) => write!(f, "stack of thread {}", thread_id),
+            Self::MappedFile(path) => write!(f, "mapped {}", path.display()),
+            Self::Private => write!(f, "private"),
+            Self::Shared => write!(f, "shared"),
+        }
+    }
+}
+
+/// region of an allocation with the same state and protection
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MapRegion {
+    address: usize,
+    size: usize,
+    state: VirtualAllocationType,
+    protect: PageProtectionFlags,
+    page_type: PageType,
+}
+
+impl MapRegion {
+    /// base address of the region
+    pub fn get_address(&self) -> usize {
+        self.address
+    }
+
+    /// size of the region
+    pub fn get_size(&self) -> usize {
+        self.size
+    }
+
+    /// whether the region is committed or only reserved
+    pub fn get_state(&self) -> VirtualAllocationType {
+        self.state
+    }
+
+    /// protection of the region
+    pub fn get_protect(&self) -> PageProtectionFlags {
+        self.protect
+    }
+
+    /// type of the region
+    pub fn get_type(&self) -> PageType {
+        self.page_type
+    }
+}
+
+impl From<&MemoryBasicInformation> for MapRegion {
+    fn from(value: &MemoryBasicInformation) -> Self {
+        Self {
+            address: value.get_base_address(),
+            size: value.get_region_size(),
+            state: value.get_state(),
+            protect: value.get_protect(),
+            page_type: value.get_type(),
+        }
+    }
+}
+
+/// regions reserved together by one allocation
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MapAllocation {
+    address: usize,
+    label: RegionLabel,
+    regions: Vec<MapRegion>,
+}
+
+impl MapAllocation {
+    /// allocation base shared by the regions
+    pub fn get_address(&self) -> usize {
+        self.address
+    }
+
+    /// size from the allocation base to the end of the last region
+    pub fn get_size(&self) -> usize {
+        self.regions
+            .last()
+            .map_or(0, |e| e.address + e.size - self.address)
+    }
+
+    /// what the allocation is used for
+    pub fn get_label(&self) -> &RegionLabel {
+        &self.label
+    }
+
+    /// regions of the allocation ordered by address
+    pub fn get_regions(&self) -> &[MapRegion] {
+        &self.regions
+    }
+}
+
+/// Allocated address space of a process, like `vmmap` of Sysinternals.
+///
+/// display it for text report, or render it with [MemoryMap::to_json].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MemoryMap {
+    allocations: Vec<MapAllocation>,
+}
+
+impl MemoryMap {
+    /// allocations ordered by address, free regions are left out
+    pub fn get_allocations(&self) -> &[MapAllocation] {
+        &self.allocations
+    }
+
+    /// allocation containing `address`
+    pub fn find(&self, address: usize) -> Option<&MapAllocation> {
+        self.allocations
+            .iter()
+            .find(|e| (e.address..e.address + e.get_size()).contains(&address))
+    }
+
+    /// map as json
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(|e| Error::Io(e.into()))
+    }
+}
+
+/// table of allocations with their label, each followed by its regions indented
+impl Display for MemoryMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:<22} {:>10} label / protect state", "address", "size")?;
+        for allocation in &self.allocations {
+            writeln!(
+                f,
+                "{:<22} {:>10} {}",
+                format_address(allocation.address),
+                format_size(allocation.get_size()),
+                allocation.label
+            )?;
+            for region in &allocation.regions {
+                writeln!(
+                    f,
+                    "  {:<20} {:>10} {:<9} {}",
+                    format_address(region.address),
+                    format_size(region.size),
+                    region.protect.to_string(),
+                    region.state
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// regions grouped by allocation base, regions must be ordered by address
+fn group_allocations(regions: &[MemoryBasicInformation]) -> Vec<(usize, Vec<MapRegion>)> {
+    let mut allocations: Vec<(usize, Vec<MapRegion>)> = Vec::new();
+
+    for mbi in regions
+        .iter()
+        .filter(|e| !e.get_state().contains(VirtualAllocationType::Free))
+    {
+        match allocations.last_mut() {
+            Some((address, regions)) if *address == mbi.get_allocation_base() => {
+                regions.push(MapRegion::from(mbi))
+            }
+            _ => allocations.push((mbi.get_allocation_base(), vec![MapRegion::from(mbi)])),
+        }
+    }
+
+    allocations
+}
+
+impl Handle {
+    /// every allocation of the process labeled with the module, heap, stack or file using it
+    pub fn memory_map(&self) -> Result<MemoryMap> {
+        let regions: Vec<MemoryBasicInformation> = self.get_memory_basic_informations().collect();
+
+        let snapshot = self.create_snapshot(
+            HandleSnapshotFlag::SnapModule
+                | HandleSnapshotFlag::SnapModule32
+                | HandleSnapshotFlag::SnapHeapList,
+        )?;
+        let modules: Vec<Module> = snapshot.get_modules().collect();
+        let heaps: Vec<usize> = snapshot.get_heaps().map(|e| e.get_heap_id()).collect();
+        let stacks = self.stacks()?;
+        let devices = get_dos_devices();
+
+        let allocations = group_allocations(&regions)
+            .into_iter()
+            .map(|(address, regions)| {
+                let end = regions.last().map_or(address, |e| e.address + e.size);
+                let contains = |e: usize| (address..end).contains(&e);
+
+                let label = if let Some(module) = modules.iter().find(|e| contains(e.get_address()))
+                {
+                    RegionLabel::Module(module.get_name())
+                } else if heaps.iter().any(|e| contains(*e)) {
+                    RegionLabel::Heap
+                } else if let Some(stack) = stacks.iter().find(|e| contains(e.get_limit())) {
+                    // NOTE: stack limit is inside the committed part of the stack allocation
+                    RegionLabel::Stack(stack.get_thread_id())
+                } else if regions
+                    .iter()
+                    .all(|e| e.page_type.contains(PageType::Private))
+                {
+                    RegionLabel::Private
+                } else if let Ok(path) = self.get_mapped_file_name(address) {
+                    let path = translate_device_path(&path, &devices).unwrap_or(path);
+                    RegionLabel::MappedFile(PathBuf::from(path))
+                } else {
+                    RegionLabel::Shared
+                };
+
+                MapAllocation {
+                    address,
+                    label,
+                    regions,
+                }
+            })
+            .collect();
+
+        Ok(MemoryMap { allocations })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{group_allocations, MapAllocation, MemoryMap, RegionLabel};
+    use crate::memory::testing;
+
+    #[test]
+    fn grouping_and_rendering_allocations() {
+        let regions = [
+            testing::region(0x1000, 0x1000, 0x1000, 0x04, 0x20000),
+            testing::region(0x2000, 0x1000, 0x2000, 0x04, 0x20000),
+            testing::region(0x3000, 0, 0x10000, 0x04, 0x20000),
+            testing::region(0x4000, 0x4000, 0x1000, 0x04, 0x20000),
+        ];
+
+        let allocations = group_allocations(&regions);
+        assert_eq!(allocations.len(), 2);
+        assert_eq!(allocations[0].1.len(), 2);
+
+        let map = MemoryMap {
+            allocations: vec![MapAllocation {
+                address: 0x4000,
+                label: RegionLabel::Module("a\"b".to_string()),
+                regions: allocations[1].1.clone(),
+            }],
+        };
+        assert_eq!(map.find(0x4FFF).unwrap().get_size(), 0x1000);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serializing_memory_map() {
+        let map = MemoryMap {
+            allocations: vec![MapAllocation {
+                address: 0x4000,
+                label: RegionLabel::Module("a\"b".to_string()),
+                regions: group_allocations(&[testing::region(
+                    0x4000, 0x4000, 0x1000, 0x04, 0x20000,
+                )])[0]
+                    .1
+                    .clone(),
+            }],
+        };
+
+        let json = map.to_json().unwrap();
+        assert_eq!(serde_json::from_str::<MemoryMap>(&json).unwrap(), map);
+    }
+}
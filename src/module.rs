@@ -1,10 +1,31 @@
-use std::ops::Deref;
+use std::mem::size_of;
+use std::ops::{Deref, Range};
+use std::path::Path;
 use windows::Win32::Foundation::HMODULE;
 use windows::Win32::System::Diagnostics::ToolHelp::MODULEENTRY32W;
+use windows::Win32::System::ProcessStatus::{
+    K32EnumProcessModulesEx, K32GetModuleFileNameExW, K32GetModuleInformation,
+    ENUM_PROCESS_MODULES_EX_FLAGS, LIST_MODULES_32BIT, LIST_MODULES_64BIT, LIST_MODULES_ALL,
+    MODULEINFO,
+};
+
+use crate::error::{Error, Result};
+use crate::handle::{Handle, HandleSnapshotFlag};
+use crate::memory::{diff_ranges, PAGE_SIZE};
+use crate::pe::{self, Export, ExportKey, Import, PeImage, Section};
 
 /// Look at [MODULEENTRY32W structure (tlhelp32.h) - Win32 API](https://learn.microsoft.com/en-us/windows/win32/api/tlhelp32/ns-tlhelp32-moduleentry32w)
 #[derive(Clone, Copy, PartialEq, Eq)]
-pub struct Module(MODULEENTRY32W);
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "ModuleRepr", into = "ModuleRepr")
+)]
+pub struct Module(MODULEENTRY32W, Option<ModuleBitness>);
+
+// NOTE: `modBaseAddr` is an address in the process of the module, never dereferenced
+unsafe impl Send for Module {}
+unsafe impl Sync for Module {}
 
 impl Module {
     /// get `th32ModuleID`
@@ -27,6 +48,12 @@ impl Module {
         self.0.modBaseSize
     }
 
+    /// whether `address` is inside memory of the module
+    pub fn contains(&self, address: usize) -> bool {
+        let start_address = self.get_address();
+        (start_address..start_address + self.get_size() as usize).contains(&address)
+    }
+
     /// get `hModule`
     pub fn get_hmodule(&self) -> HMODULE {
         self.0.hModule
@@ -45,6 +72,434 @@ impl Module {
             .trim_end_matches("\u{0}")
             .to_string()
     }
+
+    /// parse PE headers of the module from memory of `handle`
+    pub fn pe(&self, handle: &Handle) -> Result<PeImage> {
+        PeImage::read(handle, self.get_address())
+    }
+
+    /// whether the module image is 32 bit or 64 bit, as told when it was listed by
+    /// [Handle::list_modules]. `None` when it was not told, look at [Module::bitness]
+    pub fn get_bitness(&self) -> Option<ModuleBitness> {
+        self.1
+    }
+
+    /// whether the module image is 32 bit or 64 bit, read from its headers unless told when it
+    /// was listed
+    pub fn bitness(&self, handle: &Handle) -> Result<ModuleBitness> {
+        if let Some(bitness) = self.1 {
+            return Ok(bitness);
+        }
+
+        match self.pe(handle)?.is_64bit() {
+            true => Ok(ModuleBitness::Bit64),
+            false => Ok(ModuleBitness::Bit32),
+        }
+    }
+
+    /// tls index the loader gave to the module, `None` when it use no static tls
+    pub fn tls_index(&self, handle: &Handle) -> Result<Option<u32>> {
+        match self.pe(handle)?.tls_directory(handle)? {
+            Some(directory) => Ok(Some(handle.read::<u32>(directory.get_address_of_index())?)),
+            None => Ok(None),
+        }
+    }
+
+    /// address ranges where `.text` section in memory differ from the file of the module.
+    ///
+    /// bytes fixed up by base relocations are compared against their relocated value.
+    pub fn verify_integrity(&self, handle: &Handle) -> Result<Vec<Range<usize>>> {
+        let file = std::fs::read(self.get_path())?;
+        let file_pe = PeImage::parse(0, &file)?;
+        let section = file_pe.find_section(".text").ok_or(Error::NotFound)?;
+
+        let start = section.get_raw_offset() as usize;
+        let len = section.get_raw_size().min(section.get_virtual_size()) as usize;
+        let mut expected = file
+            .get(start..start + len)
+            .ok_or(Error::Unsupported)?
+            .to_vec();
+
+        let delta = (self.get_address() as u64).wrapping_sub(file_pe.get_image_base());
+        file_pe.apply_file_relocations(
+            &file,
+            section.get_virtual_address(),
+            &mut expected,
+            delta,
+        )?;
+
+        let address = self.get_address() + section.get_virtual_address() as usize;
+        let actual = handle.read_memory(address, len)?;
+
+        Ok(diff_ranges(address, &expected, &actual))
+    }
+
+    /// write the module in memory of `handle` back to a PE file at `path`.
+    ///
+    /// sections are written as laid out in memory, so the dump keep runtime changes like
+    /// unpacked code. when `rebuild_imports`, the import address table is reset to the import
+    /// name table so the file can be loaded again.
+    pub fn dump_to_file<P: AsRef<Path>>(
+        &self,
+        handle: &Handle,
+        path: P,
+        rebuild_imports: bool,
+    ) -> Result<()> {
+        let pe = self.pe(handle)?;
+        let size = pe.get_size_of_image() as usize;
+        // NOTE: headers may be tampered with, the loader told the real size of the module
+        if size > self.get_size() as usize {
+            return Err(Error::Unsupported);
+        }
+
+        let mut image = vec![0u8; size];
+        for (index, page) in image.chunks_mut(PAGE_SIZE).enumerate() {
+            // NOTE: pages may be reserved only or guarded, keep the layout by leaving zero
+            let _ = handle.read_memory_into(self.get_address() + index * PAGE_SIZE, page);
+        }
+
+        pe.unmap_image(&mut image, rebuild_imports)?;
+        std::fs::write(path, image)?;
+
+        Ok(())
+    }
+
+    /// functions exported by the module, read from its export directory in memory of `handle`
+    pub fn exports(&self, handle: &Handle) -> Result<impl Iterator<Item = Export>> {
+        Ok(self.pe(handle)?.exports(handle)?.into_iter())
+    }
+
+    /// functions imported by the module, read from its import directory in memory of `handle`
+    pub fn imports(&self, handle: &Handle) -> Result<impl Iterator<Item = Import>> {
+        Ok(self.pe(handle)?.imports(handle)?.into_iter())
+    }
+
+    /// sections of the module, read from its headers in memory of `handle`.
+    ///
+    /// absolute range of a section is given by [Section::get_address_range] with the address
+    /// of the module.
+    pub fn sections(&self, handle: &Handle) -> Result<impl Iterator<Item = Section>> {
+        Ok(self.pe(handle)?.get_sections().to_vec().into_iter())
+    }
+
+    /// absolute address range of the section with the given name, like `.text`
+    pub fn get_section_range(&self, handle: &Handle, name: &str) -> Option<Range<usize>> {
+        self.sections(handle)
+            .ok()?
+            .find(|section| section.get_name() == name)
+            .map(|section| section.get_address_range(self.get_address()))
+    }
+
+    /// address of the function exported by the module with the given name or ordinal.
+    ///
+    /// forwarded export is followed to the module it is forwarded to, look at
+    /// [Handle::resolve_export].
+    pub fn get_export<K: Into<ExportKey>>(&self, handle: &Handle, key: K) -> Option<usize> {
+        let key = key.into();
+        let export = self
+            .exports(handle)
+            .ok()?
+            .find(|export| key.matches(export))?;
+
+        match export.get_forwarder() {
+            Some(forwarder) => handle.resolve_forwarder(forwarder).ok(),
+            None => Some(export.get_address()),
+        }
+    }
+}
+
+/// where modules of a process are listed from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ModuleListSource {
+    /// ToolHelp snapshot, falling back to `EnumProcessModulesEx` when the snapshot fail
+    #[default]
+    Auto,
+    /// ToolHelp snapshot
+    Snapshot,
+    /// `EnumProcessModulesEx` with `GetModuleInformation` and `GetModuleFileNameExW`, usable
+    /// when snapshot is denied or fail across bitness
+    Psapi,
+    /// loader list in the process environment block
+    Peb,
+}
+
+/// instruction set a module image is built for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ModuleBitness {
+    /// 32 bit image, like modules of a WOW64 process loaded from `SysWOW64`
+    Bit32,
+    /// 64 bit image, like `ntdll.dll` loaded into every WOW64 process
+    Bit64,
+}
+
+/// modules kept by [Handle::list_modules], look at `LIST_MODULES_32BIT` and `LIST_MODULES_64BIT`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ModuleFilter {
+    /// every module
+    #[default]
+    All,
+    /// 32 bit modules only
+    Bit32,
+    /// 64 bit modules only
+    Bit64,
+}
+
+impl ModuleFilter {
+    /// whether module with `bitness` is kept
+    pub fn matches(&self, bitness: ModuleBitness) -> bool {
+        match self {
+            Self::All => true,
+            Self::Bit32 => bitness == ModuleBitness::Bit32,
+            Self::Bit64 => bitness == ModuleBitness::Bit64,
+        }
+    }
+}
+
+impl ModuleFilter {
+    /// modules of the same bitness as a process with pointers of `pointer_size` bytes
+    pub fn for_pointer_size(pointer_size: usize) -> Self {
+        match pointer_size {
+            4 => Self::Bit32,
+            _ => Self::Bit64,
+        }
+    }
+}
+
+impl From<ModuleFilter> for ENUM_PROCESS_MODULES_EX_FLAGS {
+    fn from(value: ModuleFilter) -> Self {
+        match value {
+            ModuleFilter::All => LIST_MODULES_ALL,
+            ModuleFilter::Bit32 => LIST_MODULES_32BIT,
+            ModuleFilter::Bit64 => LIST_MODULES_64BIT,
+        }
+    }
+}
+
+/// longest path of a module file
+pub(crate) const MAX_MODULE_PATH: usize = 0x8000;
+/// forwarded exports followed before giving up, forwarder may loop back to itself
+const MAX_FORWARD_DEPTH: usize = 16;
+
+impl Handle {
+    /// modules loaded in the process, listed from `source` and kept by `filter`.
+    ///
+    /// with a filter other than [ModuleFilter::All], bitness of every module is stored, told by
+    /// `EnumProcessModulesEx` for [ModuleListSource::Psapi] and otherwise read from headers.
+    /// module which headers cannot be read is kept with unknown [Module::get_bitness]. 64 bit
+    /// modules of a wow64 process, like its 64 bit `ntdll.dll`, are listed too.
+    pub fn list_modules(
+        &self,
+        source: ModuleListSource,
+        filter: ModuleFilter,
+    ) -> Result<Vec<Module>> {
+        let snapshot = || -> Result<Vec<Module>> {
+            Ok(self
+                .create_snapshot(HandleSnapshotFlag::SnapModule | HandleSnapshotFlag::SnapModule32)?
+                .get_all_modules()
+                .collect())
+        };
+        let keep = |modules: Vec<Module>| keep_modules(modules, filter, |e| e.bitness(self).ok());
+
+        match source {
+            ModuleListSource::Auto => match snapshot() {
+                Ok(modules) => Ok(keep(modules)),
+                Err(_) => self.enum_process_modules(filter).map(keep),
+            },
+            ModuleListSource::Snapshot => snapshot().map(keep),
+            ModuleListSource::Psapi => self.enum_process_modules(filter).map(keep),
+            ModuleListSource::Peb => self.get_modules_via_peb().map(keep),
+        }
+    }
+
+    fn enum_process_modules(&self, filter: ModuleFilter) -> Result<Vec<Module>> {
+        let mut hmodules = vec![HMODULE::default(); 256];
+        loop {
+            let size = (hmodules.len() * size_of::<HMODULE>()) as u32;
+            let mut needed = 0u32;
+            unsafe {
+                K32EnumProcessModulesEx(
+                    **self,
+                    hmodules.as_mut_ptr(),
+                    size,
+                    &mut needed,
+                    ENUM_PROCESS_MODULES_EX_FLAGS::from(filter).0,
+                )
+            }
+            .ok()
+            .map_err(|e| Error::win32("EnumProcessModulesEx", e))?;
+
+            let count = needed as usize / size_of::<HMODULE>();
+            if needed <= size {
+                hmodules.truncate(count);
+                break;
+            }
+            // NOTE: modules may be loaded between calls, grow past what was asked
+            hmodules.resize(count * 2, HMODULE::default());
+        }
+
+        let bitness = match filter {
+            ModuleFilter::All => None,
+            ModuleFilter::Bit32 => Some(ModuleBitness::Bit32),
+            ModuleFilter::Bit64 => Some(ModuleBitness::Bit64),
+        };
+
+        // NOTE: module unloaded since enumerating can no longer be queried, it is skipped
+        Ok(hmodules
+            .into_iter()
+            .filter_map(|hmodule| {
+                let mut info = MODULEINFO::default();
+                unsafe {
+                    K32GetModuleInformation(
+                        **self,
+                        hmodule,
+                        &mut info,
+                        size_of::<MODULEINFO>() as u32,
+                    )
+                }
+                .ok()
+                .ok()?;
+
+                let mut path = vec![0u16; MAX_MODULE_PATH];
+                let len = unsafe { K32GetModuleFileNameExW(**self, hmodule, &mut path) } as usize;
+                if len == 0 {
+                    return None;
+                }
+                let path = String::from_utf16_lossy(&path[..len]);
+                let name = Path::new(&path)
+                    .file_name()
+                    .map(|e| e.to_string_lossy().to_string())
+                    .unwrap_or_default();
+
+                Some(
+                    Module::from_loader_entry(
+                        self.get_process_id(),
+                        info.lpBaseOfDll as usize,
+                        info.SizeOfImage,
+                        &name,
+                        &path,
+                    )
+                    .with_bitness(bitness),
+                )
+            })
+            .collect())
+    }
+
+    /// address of the function exported by the loaded module with the given name, like
+    /// `kernel32.dll`, following forwarded exports across modules
+    pub fn resolve_export<K: Into<ExportKey>>(&self, module_name: &str, key: K) -> Result<usize> {
+        self.resolve_export_in(module_name, key, ModuleFilter::All)
+    }
+
+    /// same as [Handle::resolve_export], looking only at modules kept by `filter`.
+    ///
+    /// fail with [Error::Unsupported] when the export is forwarded to an api set, like
+    /// `api-ms-win-core-heap-l1-1-0.dll`.
+    ///
+    /// WOW64 process load both 32 bit and 64 bit `ntdll.dll`, code running in the process
+    /// need the one of [ModuleFilter::for_pointer_size].
+    pub fn resolve_export_in<K: Into<ExportKey>>(
+        &self,
+        module_name: &str,
+        key: K,
+        filter: ModuleFilter,
+    ) -> Result<usize> {
+        let modules = self.list_modules(ModuleListSource::Auto, filter)?;
+
+        let mut module_name = module_name.to_string();
+        let mut key = key.into();
+        for _ in 0..MAX_FORWARD_DEPTH {
+            // NOTE: resolving api set need the ApiSetMap of the process, which is not parsed
+            if pe::is_api_set(&module_name) {
+                return Err(Error::Unsupported);
+            }
+
+            // NOTE: module of unknown bitness is only used when no other has the name
+            let export = modules
+                .iter()
+                .filter(|module| module.get_name().eq_ignore_ascii_case(&module_name))
+                .min_by_key(|module| module.get_bitness().is_none())
+                .ok_or(Error::NotFound)?
+                .exports(self)?
+                .find(|export| key.matches(export))
+                .ok_or(Error::NotFound)?;
+
+            match export.get_forwarder() {
+                Some(forwarder) => {
+                    (module_name, key) = pe::parse_forwarder(forwarder).ok_or(Error::Unsupported)?
+                }
+                None => return Ok(export.get_address()),
+            }
+        }
+
+        Err(Error::NotFound)
+    }
+
+    /// address of the function a forwarder string refer to, like `NTDLL.RtlAllocateHeap`
+    pub fn resolve_forwarder(&self, forwarder: &str) -> Result<usize> {
+        let (module_name, key) = pe::parse_forwarder(forwarder).ok_or(Error::InvalidInput)?;
+        self.resolve_export(&module_name, key)
+    }
+}
+
+/// modules kept by `filter`, tagged with bitness told by `bitness` unless already known.
+///
+/// module of unknown bitness is kept, [ModuleFilter::All] keep every module untouched.
+fn keep_modules<F>(modules: Vec<Module>, filter: ModuleFilter, bitness: F) -> Vec<Module>
+where
+    F: Fn(&Module) -> Option<ModuleBitness>,
+{
+    if filter == ModuleFilter::All {
+        return modules;
+    }
+
+    modules
+        .into_iter()
+        .map(|e| {
+            let bitness = e.get_bitness().or_else(|| bitness(&e));
+            e.with_bitness(bitness)
+        })
+        .filter(|e| e.get_bitness().is_none_or(|e| filter.matches(e)))
+        .collect()
+}
+
+/// copy `s` into nul terminated fixed size wide string, cut short when too long
+pub(crate) fn to_wide_array<const N: usize>(s: &str) -> [u16; N] {
+    let mut array = [0u16; N];
+    for (unit, c) in array.iter_mut().take(N - 1).zip(s.encode_utf16()) {
+        *unit = c;
+    }
+
+    array
+}
+
+impl Module {
+    pub(crate) fn from_loader_entry(
+        process_id: u32,
+        address: usize,
+        size: u32,
+        name: &str,
+        path: &str,
+    ) -> Self {
+        Self(
+            MODULEENTRY32W {
+                dwSize: size_of::<MODULEENTRY32W>() as u32,
+                th32ProcessID: process_id,
+                modBaseAddr: address as *mut u8,
+                modBaseSize: size,
+                hModule: HMODULE(address as isize),
+                szModule: to_wide_array(name),
+                szExePath: to_wide_array(path),
+                ..Default::default()
+            },
+            None,
+        )
+    }
+
+    /// same module with its bitness told
+    pub(crate) fn with_bitness(self, bitness: Option<ModuleBitness>) -> Self {
+        Self(self.0, bitness)
+    }
 }
 
 impl Deref for Module {
@@ -57,6 +512,101 @@ impl Deref for Module {
 
 impl From<MODULEENTRY32W> for Module {
     fn from(value: MODULEENTRY32W) -> Self {
-        Self(value)
+        Self(value, None)
+    }
+}
+
+/// serialized form of [Module]
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct ModuleRepr {
+    process_id: u32,
+    address: usize,
+    size: u32,
+    name: String,
+    path: String,
+    #[serde(default)]
+    bitness: Option<ModuleBitness>,
+}
+
+#[cfg(feature = "serde")]
+impl From<Module> for ModuleRepr {
+    fn from(value: Module) -> Self {
+        Self {
+            process_id: value.get_process_id(),
+            address: value.get_address(),
+            size: value.get_size(),
+            name: value.get_name(),
+            path: value.get_path(),
+            bitness: value.get_bitness(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<ModuleRepr> for Module {
+    fn from(value: ModuleRepr) -> Self {
+        Module::from_loader_entry(
+            value.process_id,
+            value.address,
+            value.size,
+            &value.name,
+            &value.path,
+        )
+        .with_bitness(value.bitness)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{keep_modules, to_wide_array, Module, ModuleBitness, ModuleFilter};
+
+    #[test]
+    fn keeping_modules_by_bitness() {
+        // 64 bit ntdll of a wow64 process, above 4 GiB
+        let ntdll = Module::from_loader_entry(4, 0x7FFA_0000_0000, 0x1000, "ntdll.dll", "");
+        let game = Module::from_loader_entry(4, 0x40_0000, 0x1000, "game.exe", "");
+        let unknown = Module::from_loader_entry(4, 0x50_0000, 0x1000, "x.dll", "");
+        let bitness = |e: &Module| match e.get_name().as_str() {
+            "ntdll.dll" => Some(ModuleBitness::Bit64),
+            "game.exe" => Some(ModuleBitness::Bit32),
+            _ => None,
+        };
+        let names = |modules: Vec<Module>| -> Vec<String> {
+            modules.iter().map(|e| e.get_name()).collect()
+        };
+        let modules = vec![ntdll, game, unknown];
+
+        let kept = keep_modules(modules.clone(), ModuleFilter::Bit64, bitness);
+        assert_eq!(names(kept.clone()), vec!["ntdll.dll", "x.dll"]);
+        assert_eq!(kept[0].get_bitness(), Some(ModuleBitness::Bit64));
+        assert_eq!(
+            names(keep_modules(modules.clone(), ModuleFilter::Bit32, bitness)),
+            vec!["game.exe", "x.dll"]
+        );
+        let all = keep_modules(modules, ModuleFilter::All, bitness);
+        assert_eq!(all.len(), 3);
+        assert!(all.iter().all(|e| e.get_bitness().is_none()));
+    }
+
+    #[test]
+    fn truncating_wide_arrays() {
+        assert_eq!(to_wide_array::<4>("ab"), [0x61, 0x62, 0, 0]);
+        assert_eq!(to_wide_array::<4>("abcdef"), [0x61, 0x62, 0x63, 0]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serializing_modules() {
+        let module = Module::from_loader_entry(4, 0x7FF0_0000, 0x1000, "a.dll", "C:\\a.dll")
+            .with_bitness(Some(ModuleBitness::Bit64));
+
+        let json = serde_json::to_string(&module).unwrap();
+        assert!(serde_json::from_str::<Module>(&json).unwrap() == module);
+
+        let json = json.replace(",\"bitness\":\"Bit64\"", "");
+        let module = serde_json::from_str::<Module>(&json).unwrap();
+        assert_eq!(module.get_bitness(), None);
+        assert_eq!(module.get_path(), "C:\\a.dll");
     }
 }
@@ -0,0 +1,42 @@
+use std::ffi::c_void;
+
+use windows::core::{w, PCSTR};
+use windows::Win32::Foundation::{HANDLE, NTSTATUS};
+use windows::Win32::System::LibraryLoader::{GetModuleHandleW, GetProcAddress};
+
+/// resolve function exported by ntdll at runtime, the crate does not link to ntdll directly
+fn get_proc_address(name: &'static str) -> Option<unsafe extern "system" fn() -> isize> {
+    let module = unsafe { GetModuleHandleW(w!("ntdll.dll")) }.ok()?;
+    unsafe { GetProcAddress(module, PCSTR(name.as_ptr())) }
+}
+
+macro_rules! ntdll_functions {
+    ($($name:ident: fn($($arg:ty),*) -> $ret:ty;)*) => {
+        $(
+            #[allow(non_snake_case)]
+            pub(crate) fn $name() -> Option<unsafe extern "system" fn($($arg),*) -> $ret> {
+                static FUNCTION: std::sync::OnceLock<Option<usize>> = std::sync::OnceLock::new();
+
+                let address = *FUNCTION.get_or_init(|| {
+                    get_proc_address(concat!(stringify!($name), "\0")).map(|e| e as usize)
+                });
+
+                address.map(|e| unsafe {
+                    std::mem::transmute::<usize, unsafe extern "system" fn($($arg),*) -> $ret>(e)
+                })
+            }
+        )*
+    };
+}
+
+ntdll_functions! {
+    NtReadVirtualMemory: fn(HANDLE, *const c_void, *mut c_void, usize, *mut usize) -> NTSTATUS;
+    NtQueryInformationProcess: fn(HANDLE, u32, *mut c_void, u32, *mut u32) -> NTSTATUS;
+    NtQueryInformationThread: fn(HANDLE, u32, *mut c_void, u32, *mut u32) -> NTSTATUS;
+    NtQueryVirtualMemory: fn(HANDLE, *const c_void, u32, *mut c_void, usize, *mut usize) -> NTSTATUS;
+    NtQuerySystemInformation: fn(u32, *mut c_void, u32, *mut u32) -> NTSTATUS;
+    NtQueryObject: fn(HANDLE, u32, *mut c_void, u32, *mut u32) -> NTSTATUS;
+    NtMapViewOfSection: fn(HANDLE, HANDLE, *mut *mut c_void, usize, usize, *mut i64, *mut usize, u32, u32, u32) -> NTSTATUS;
+    NtUnmapViewOfSection: fn(HANDLE, *mut c_void) -> NTSTATUS;
+    NtQuerySection: fn(HANDLE, u32, *mut c_void, usize, *mut usize) -> NTSTATUS;
+}
@@ -1,10 +1,13 @@
+use crate::backend::MemoryBackend;
+use crate::error::{Error, Result};
 use crate::handle::{Handle, HandleSnapshotFlag};
 use crate::memory::{Memory, PageProtectionFlags};
 use crate::pattern::Pattern;
 
-use std::io::{ErrorKind, Read, Write};
+use std::io::{Read, Write};
 
 /// memory section available for pattern matching
+#[derive(Debug, Clone, Copy)]
 pub enum MemorySection<'a> {
     /// all memory section that patchable
     All,
@@ -39,7 +42,7 @@ impl<'a> PatchHandle<'a> {
         base_address: BaseAddress<N>,
         offsets: Option<&[usize; K]>,
         value: &[u8; M],
-    ) -> Result<&Self, ErrorKind> {
+    ) -> Result<&Self> {
         let mut addr: usize = match base_address {
             BaseAddress::Direct(addr) => Ok(addr),
             BaseAddress::DirectVerify(addr, pattern) => {
@@ -48,7 +51,7 @@ impl<'a> PatchHandle<'a> {
                 if pattern == data.as_slice() {
                     Ok(addr)
                 } else {
-                    Err(ErrorKind::NotFound)
+                    Err(Error::NotFound)
                 }
             }
             BaseAddress::Search(pattern, mem_section) => {
@@ -78,9 +81,8 @@ impl<'a> PatchHandle<'a> {
                             .create_snapshot(
                                 HandleSnapshotFlag::SnapModule | HandleSnapshotFlag::SnapModule32,
                             )?
-                            .get_modules()
-                            .find(|e| e.get_name() == *module_name)
-                            .ok_or(ErrorKind::NotFound)?;
+                            .find_module(module_name)
+                            .ok_or(Error::NotFound)?;
 
                         vec![(module.get_address(), module.get_size() as usize)]
                     }
@@ -106,31 +108,230 @@ impl<'a> PatchHandle<'a> {
                     }
                 }
 
-                addr.ok_or(ErrorKind::NotFound)
+                addr.ok_or(Error::NotFound)
             }
         }?;
 
-        match offsets {
-            Some(offsets) => {
-                let mut data = Vec::from(0usize.to_ne_bytes());
-
-                for offset in offsets {
-                    let _ = self.read(addr, &mut data);
-                    addr = usize::from_ne_bytes(*data.as_slice().first_chunk().unwrap()) + offset;
-                }
-            }
-            None => (),
+        if let Some(offsets) = offsets {
+            addr = self.handle.resolve_pointer_chain(addr, offsets)?;
         }
 
         let mut memory = Memory::new(self.handle, addr, addr + M);
-        let _ = memory.write(value).map_err(|e| e.kind())?;
+        let _ = memory.write(value)?;
 
         Ok(self)
     }
 
-    fn read(&self, addr: usize, buf: &mut [u8]) -> Result<usize, ErrorKind> {
+    fn read(&self, addr: usize, buf: &mut [u8]) -> Result<usize> {
         let mut memory = Memory::new(self.handle, addr, usize::MAX);
-        let n = memory.read(buf).map_err(|e| e.kind())?;
+        let n = memory.read(buf)?;
         Ok(n)
     }
 }
+
+/// `nop` instruction
+const NOP: u8 = 0x90;
+
+/// bytes patched by [PatchSet] along with the bytes they replaced
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PatchEntry {
+    address: usize,
+    original: Vec<u8>,
+    patched: Vec<u8>,
+    enabled: bool,
+}
+
+impl PatchEntry {
+    /// address of the patch
+    pub fn get_address(&self) -> usize {
+        self.address
+    }
+
+    /// bytes before patched
+    pub fn get_original(&self) -> &[u8] {
+        &self.original
+    }
+
+    /// bytes written by the patch
+    pub fn get_patched(&self) -> &[u8] {
+        &self.patched
+    }
+
+    /// whether the patch is currently written
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+/// Group of patches applied and restored together through any [MemoryBackend], every patch is
+/// restored on drop.
+pub struct PatchSet<'a, B: MemoryBackend + ?Sized = Handle> {
+    handle: &'a B,
+    entries: Vec<PatchEntry>,
+}
+
+impl<'a, B: MemoryBackend + ?Sized> PatchSet<'a, B> {
+    /// create empty set for patching memory of the handle
+    pub fn new(handle: &'a B) -> Self {
+        Self {
+            handle,
+            entries: Vec::new(),
+        }
+    }
+
+    /// record patch writing `bytes` at `address` without applying it, returning its index.
+    ///
+    /// the original bytes are read now.
+    pub fn add(&mut self, address: usize, bytes: &[u8]) -> Result<usize> {
+        if bytes.is_empty() {
+            return Err(Error::InvalidInput);
+        }
+
+        self.entries.push(PatchEntry {
+            address,
+            original: self.handle.read_memory(address, bytes.len())?,
+            patched: bytes.to_vec(),
+            enabled: false,
+        });
+
+        Ok(self.entries.len() - 1)
+    }
+
+    /// record patch filling `len` bytes at `address` with `nop`, returning its index
+    pub fn add_nop(&mut self, address: usize, len: usize) -> Result<usize> {
+        self.add(address, &vec![NOP; len])
+    }
+
+    /// recorded patches
+    pub fn get_entries(&self) -> &[PatchEntry] {
+        &self.entries
+    }
+
+    /// write every disabled patch, undoing the ones written by this call when any of them fail
+    pub fn apply(&mut self) -> Result<()> {
+        let indexes = (0..self.entries.len())
+            .filter(|index| !self.entries[*index].enabled)
+            .collect::<Vec<_>>();
+
+        for (count, index) in indexes.iter().enumerate() {
+            if let Err(e) = self.set_enabled(*index, true) {
+                for index in &indexes[..count] {
+                    let _ = self.set_enabled(*index, false);
+                }
+                return Err(e);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// write original bytes back for every enabled patch, in reverse order of applying
+    pub fn restore(&mut self) -> Result<()> {
+        let mut result = Ok(());
+        for index in (0..self.entries.len()).rev() {
+            if let Err(e) = self.set_enabled(index, false) {
+                result = Err(e);
+            }
+        }
+
+        result
+    }
+
+    /// flip the patch at `index`, returning whether it is now enabled
+    pub fn toggle(&mut self, index: usize) -> Result<bool> {
+        let enabled = !self.entries.get(index).ok_or(Error::NotFound)?.enabled;
+        self.set_enabled(index, enabled)?;
+
+        Ok(enabled)
+    }
+
+    /// write the patch at `index` when `enabled`, otherwise write its original bytes back
+    pub fn set_enabled(&mut self, index: usize, enabled: bool) -> Result<()> {
+        let entry = self.entries.get_mut(index).ok_or(Error::NotFound)?;
+        if entry.enabled == enabled {
+            return Ok(());
+        }
+
+        let bytes = match enabled {
+            true => &entry.patched,
+            false => &entry.original,
+        };
+        self.handle.write_memory(entry.address, bytes)?;
+        entry.enabled = enabled;
+
+        self.handle
+            .flush_instruction_cache(entry.address, bytes.len())
+    }
+}
+
+impl<B: MemoryBackend + ?Sized> Drop for PatchSet<'_, B> {
+    fn drop(&mut self) {
+        let _ = self.restore();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::PatchSet;
+    use crate::backend::testing::BufferBackend;
+    use crate::backend::MemoryBackend;
+
+    #[test]
+    fn applying_and_restoring_patches() {
+        let backend = BufferBackend(RefCell::new((0..0x10).collect()));
+        let mut patches = PatchSet::new(&backend);
+        assert_eq!(patches.add(0x1000, &[0xAA, 0xBB]).unwrap(), 0);
+        assert_eq!(patches.add_nop(0x1004, 2).unwrap(), 1);
+        assert!(patches.add(0x1000, &[]).is_err());
+
+        patches.apply().unwrap();
+        assert_eq!(
+            backend.read_memory(0x1000, 6).unwrap(),
+            vec![0xAA, 0xBB, 2, 3, 0x90, 0x90]
+        );
+        assert!(patches.get_entries().iter().all(|e| e.is_enabled()));
+
+        assert!(!patches.toggle(0).unwrap());
+        assert_eq!(backend.read_memory(0x1000, 2).unwrap(), vec![0, 1]);
+        assert!(patches.toggle(2).is_err());
+
+        drop(patches);
+        assert_eq!(
+            backend.read_memory(0x1000, 0x10).unwrap(),
+            (0..0x10).collect::<Vec<u8>>()
+        );
+    }
+
+    #[test]
+    fn restoring_overlapping_patches() {
+        let backend = BufferBackend(RefCell::new(vec![0u8; 0x10]));
+        let mut patches = PatchSet::new(&backend);
+        patches.add(0x1000, &[1, 2, 3]).unwrap();
+        patches.apply().unwrap();
+
+        // original bytes of the later patch are the earlier patch
+        patches.add(0x1001, &[9]).unwrap();
+        assert_eq!(patches.get_entries()[1].get_original(), &[2]);
+        patches.apply().unwrap();
+        assert_eq!(backend.read_memory(0x1000, 3).unwrap(), vec![1, 9, 3]);
+
+        patches.restore().unwrap();
+        assert_eq!(backend.read_memory(0x1000, 3).unwrap(), vec![0, 0, 0]);
+    }
+
+    #[test]
+    fn undoing_failed_apply() {
+        let backend = BufferBackend(RefCell::new(vec![0u8; 0x10]));
+        let mut patches = PatchSet::new(&backend);
+        patches.add(0x1000, &[1]).unwrap();
+        patches.add(0x100F, &[2]).unwrap();
+
+        // second patch can no longer be written
+        backend.0.borrow_mut().truncate(0x8);
+        assert!(patches.apply().is_err());
+        assert_eq!(backend.read_memory(0x1000, 1).unwrap(), vec![0]);
+        assert!(patches.get_entries().iter().all(|e| !e.is_enabled()));
+    }
+}
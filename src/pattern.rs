@@ -5,6 +5,138 @@ use std::ops::Deref;
 #[derive(PartialEq, Eq, Clone, Copy, Debug)]
 pub struct Pattern<const N: usize>([Option<u8>; N]);
 
+impl<const N: usize> Pattern<N> {
+    /// parse IDA style pattern like `48 8B ?? ?? E8 ?`, panicking when it is malformed or does
+    /// not have `N` bytes.
+    ///
+    /// inside a const the panic is a compile error, look at [pattern!](crate::pattern!).
+    pub const fn parse(s: &str) -> Self {
+        let s = s.as_bytes();
+        let mut bytes = [None; N];
+
+        let mut count = 0usize;
+        let mut index = 0usize;
+        while let Some((start, end)) = next_token(s, index) {
+            if count == N {
+                panic!("pattern has more bytes than its length");
+            }
+            bytes[count] = parse_token(s, start, end);
+            count += 1;
+            index = end;
+        }
+
+        if count != N {
+            panic!("pattern has less bytes than its length");
+        }
+
+        Self(bytes)
+    }
+
+    /// bytes to match, zero for wildcard
+    pub const fn get_bytes(&self) -> [u8; N] {
+        let mut bytes = [0u8; N];
+        let mut index = 0usize;
+        while index < N {
+            if let Some(byte) = self.0[index] {
+                bytes[index] = byte;
+            }
+            index += 1;
+        }
+
+        bytes
+    }
+
+    /// whether every byte has to match, `false` for wildcard
+    pub const fn get_mask(&self) -> [bool; N] {
+        let mut mask = [false; N];
+        let mut index = 0usize;
+        while index < N {
+            mask[index] = self.0[index].is_some();
+            index += 1;
+        }
+
+        mask
+    }
+}
+
+/// number of bytes of IDA style pattern, panicking when it is malformed or empty
+pub const fn pattern_len(s: &str) -> usize {
+    let s = s.as_bytes();
+
+    let mut count = 0usize;
+    let mut index = 0usize;
+    while let Some((start, end)) = next_token(s, index) {
+        parse_token(s, start, end);
+        count += 1;
+        index = end;
+    }
+
+    if count == 0 {
+        panic!("pattern is empty");
+    }
+
+    count
+}
+
+/// IDA style pattern like `48 8B ?? ?? E8 ?` checked and parsed at compile time into
+/// [Pattern](crate::pattern::Pattern).
+///
+/// ```
+/// let pattern = winmem::pattern!("48 8B ?? ?? E8 ?");
+/// assert_eq!(pattern.len(), 6);
+/// ```
+///
+/// malformed pattern fail to compile instead of failing the scan.
+///
+/// ```compile_fail
+/// let pattern = winmem::pattern!("48 8G ??");
+/// ```
+#[macro_export]
+macro_rules! pattern {
+    ($pattern:expr) => {{
+        const PATTERN: $crate::pattern::Pattern<{ $crate::pattern::pattern_len($pattern) }> =
+            $crate::pattern::Pattern::parse($pattern);
+        PATTERN
+    }};
+}
+
+/// start and end of the token of `s` from `index`, `None` past the last one
+const fn next_token(s: &[u8], index: usize) -> Option<(usize, usize)> {
+    let mut start = index;
+    while start < s.len() && s[start].is_ascii_whitespace() {
+        start += 1;
+    }
+    if start == s.len() {
+        return None;
+    }
+
+    let mut end = start;
+    while end < s.len() && !s[end].is_ascii_whitespace() {
+        end += 1;
+    }
+
+    Some((start, end))
+}
+
+/// byte of the token, `None` for wildcard
+const fn parse_token(s: &[u8], start: usize, end: usize) -> Option<u8> {
+    match end - start {
+        1 if s[start] == b'?' => None,
+        2 if s[start] == b'?' && s[start + 1] == b'?' => None,
+        2 => Some(hex_digit(s[start]) << 4 | hex_digit(s[start + 1])),
+        _ => panic!("pattern has token which is not a byte or wildcard"),
+    }
+}
+
+const fn hex_digit(c: u8) -> u8 {
+    match c {
+        b'0'..=b'9' => c - b'0',
+        b'a'..=b'f' => c - b'a' + 10,
+        b'A'..=b'F' => c - b'A' + 10,
+        _ => panic!("pattern has byte which is not hex"),
+    }
+}
+
 impl<const N: usize> Deref for Pattern<N> {
     type Target = [Option<u8>; N];
     fn deref(&self) -> &Self::Target {
@@ -70,6 +202,29 @@ impl<const N: usize> PartialEq<Pattern<N>> for &[u8; N] {
 
 #[cfg(test)]
 mod tests {
+    use super::Pattern;
+    use crate::scanner::Signature;
+
+    #[test]
+    fn parsing_at_compile_time() {
+        const PATTERN: Pattern<6> = Pattern::parse("48 8b ?? ?? E8 ?");
+        assert_eq!(
+            *PATTERN,
+            [Some(0x48), Some(0x8B), None, None, Some(0xE8), None]
+        );
+        assert_eq!(PATTERN.get_bytes(), [0x48, 0x8B, 0, 0, 0xE8, 0]);
+        assert_eq!(PATTERN.get_mask(), [true, true, false, false, true, false]);
+
+        assert_eq!(
+            crate::pattern!(" 90\tC3 "),
+            Pattern::from([Some(0x90), Some(0xC3)])
+        );
+        assert_eq!(
+            Signature::from(crate::pattern!("48 8B ?? ?? E8 ?")),
+            "48 8B ?? ?? E8 ?".parse::<Signature>().unwrap()
+        );
+    }
+
     #[test]
     fn comparing_to_slice_u8_defined_size() {
         let a = super::Pattern::from([Some(8), None, Some(20)]);
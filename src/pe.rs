@@ -0,0 +1,1099 @@
+use std::ops::Range;
+
+use crate::error::{Error, Result};
+use crate::handle::Handle;
+use crate::memory;
+
+/// size read first when parsing headers, headers of most image fit in a page
+const HEADER_PAGE_SIZE: usize = 0x1000;
+/// largest headers read from an image, larger declared size is taken as garbage
+const MAX_HEADERS_SIZE: usize = 0x10000;
+/// longest name read from an image, longer one is taken as garbage
+const MAX_NAME_LEN: usize = 1024;
+/// most functions an export directory can have, ordinals are 16 bit
+const MAX_EXPORT_COUNT: usize = 0x10000;
+/// most functions imported from one module, longer thunk table is taken as garbage
+const MAX_IMPORT_THUNKS: usize = 0x10000;
+/// size of `IMAGE_IMPORT_DESCRIPTOR`
+const IMPORT_DESCRIPTOR_SIZE: usize = 20;
+
+pub(crate) type ReadFn<'a> = dyn Fn(usize, usize) -> Result<Vec<u8>> + 'a;
+
+pub(crate) fn u16_at(data: &[u8], offset: usize) -> Result<u16> {
+    data.get(offset..offset + 2)
+        .map(|e| u16::from_le_bytes([e[0], e[1]]))
+        .ok_or(Error::Unsupported)
+}
+
+pub(crate) fn u32_at(data: &[u8], offset: usize) -> Result<u32> {
+    data.get(offset..offset + 4)
+        .map(|e| u32::from_le_bytes([e[0], e[1], e[2], e[3]]))
+        .ok_or(Error::Unsupported)
+}
+
+pub(crate) fn u64_at(data: &[u8], offset: usize) -> Result<u64> {
+    data.get(offset..offset + 8)
+        .map(|e| u64::from_le_bytes(e.try_into().unwrap()))
+        .ok_or(Error::Unsupported)
+}
+
+fn put_bytes(data: &mut [u8], offset: usize, bytes: &[u8]) -> Result<()> {
+    data.get_mut(offset..offset + bytes.len())
+        .ok_or(Error::Unsupported)?
+        .copy_from_slice(bytes);
+    Ok(())
+}
+
+pub(crate) fn read_u32_array(read: &ReadFn, address: usize, count: usize) -> Result<Vec<u32>> {
+    let data = read(address, count.checked_mul(4).ok_or(Error::InvalidInput)?)?;
+    Ok(data
+        .chunks_exact(4)
+        .map(|e| u32::from_le_bytes([e[0], e[1], e[2], e[3]]))
+        .collect())
+}
+
+pub(crate) fn read_cstr(read: &ReadFn, address: usize) -> Result<String> {
+    let bytes = memory::read_until_nul(read, address, 1, MAX_NAME_LEN)?;
+    Ok(String::from_utf8_lossy(&bytes).to_string())
+}
+
+/// index of entries in the data directory of an image
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DataDirectoryEntry {
+    /// export directory
+    Export = 0,
+    /// import directory
+    Import = 1,
+    /// resource directory
+    Resource = 2,
+    /// exception directory
+    Exception = 3,
+    /// security directory
+    Security = 4,
+    /// base relocation table
+    BaseRelocation = 5,
+    /// debug directory
+    Debug = 6,
+    /// architecture specific data
+    Architecture = 7,
+    /// global pointer register
+    GlobalPointer = 8,
+    /// thread local storage directory
+    Tls = 9,
+    /// load configuration directory
+    LoadConfig = 10,
+    /// bound import directory
+    BoundImport = 11,
+    /// import address table
+    Iat = 12,
+    /// delay import descriptors
+    DelayImport = 13,
+    /// com runtime descriptor
+    ComDescriptor = 14,
+}
+
+/// Look at [IMAGE_DATA_DIRECTORY structure (winnt.h) - Win32 API](https://learn.microsoft.com/en-us/windows/win32/api/winnt/ns-winnt-image_data_directory)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DataDirectory {
+    virtual_address: u32,
+    size: u32,
+}
+
+impl DataDirectory {
+    /// get `VirtualAddress`, relative to the image base
+    pub fn get_virtual_address(&self) -> u32 {
+        self.virtual_address
+    }
+
+    /// get `Size`
+    pub fn get_size(&self) -> u32 {
+        self.size
+    }
+
+    /// whether the rva is inside the directory
+    pub fn contains(&self, rva: u32) -> bool {
+        self.virtual_address
+            .checked_add(self.size)
+            .is_some_and(|end| (self.virtual_address..end).contains(&rva))
+    }
+}
+
+/// Look at [IMAGE_SECTION_HEADER structure (winnt.h) - Win32 API](https://learn.microsoft.com/en-us/windows/win32/api/winnt/ns-winnt-image_section_header)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    name: String,
+    virtual_address: u32,
+    virtual_size: u32,
+    raw_size: u32,
+    raw_offset: u32,
+    characteristics: u32,
+}
+
+impl Section {
+    /// get `Name`
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// get `VirtualAddress`, relative to the image base
+    pub fn get_virtual_address(&self) -> u32 {
+        self.virtual_address
+    }
+
+    /// get `VirtualSize`
+    pub fn get_virtual_size(&self) -> u32 {
+        self.virtual_size
+    }
+
+    /// get `SizeOfRawData`
+    pub fn get_raw_size(&self) -> u32 {
+        self.raw_size
+    }
+
+    /// get `PointerToRawData`
+    pub fn get_raw_offset(&self) -> u32 {
+        self.raw_offset
+    }
+
+    /// get `Characteristics`
+    pub fn get_characteristics(&self) -> u32 {
+        self.characteristics
+    }
+
+    /// absolute address range of the section in memory of image loaded at `base`
+    pub fn get_address_range(&self, base: usize) -> Range<usize> {
+        let start_address = base + self.virtual_address as usize;
+        start_address..start_address + self.virtual_size.max(self.raw_size) as usize
+    }
+}
+
+/// function exported by an image
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Export {
+    name: Option<String>,
+    ordinal: u32,
+    address: usize,
+    forwarder: Option<String>,
+}
+
+impl Export {
+    /// name of the export, `None` when exported by ordinal only
+    pub fn get_name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// biased ordinal of the export
+    pub fn get_ordinal(&self) -> u32 {
+        self.ordinal
+    }
+
+    /// absolute address of the export, address of the forwarder string when forwarded
+    pub fn get_address(&self) -> usize {
+        self.address
+    }
+
+    /// forwarder string when the export is forwarded to another module, like
+    /// `NTDLL.RtlAllocateHeap` or `NTDLL.#12`
+    pub fn get_forwarder(&self) -> Option<&str> {
+        self.forwarder.as_deref()
+    }
+
+    /// whether the export is forwarded to another module
+    pub fn is_forwarded(&self) -> bool {
+        self.forwarder.is_some()
+    }
+}
+
+/// function looked up in exports of a module
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ExportKey {
+    /// export name, like `RtlAllocateHeap`
+    Name(String),
+    /// biased ordinal of the export
+    Ordinal(u32),
+}
+
+impl ExportKey {
+    /// whether `export` is the one looked up
+    pub fn matches(&self, export: &Export) -> bool {
+        match self {
+            Self::Name(name) => export.get_name() == Some(name),
+            Self::Ordinal(ordinal) => export.get_ordinal() == *ordinal,
+        }
+    }
+}
+
+impl From<&str> for ExportKey {
+    fn from(value: &str) -> Self {
+        Self::Name(value.to_string())
+    }
+}
+
+impl From<u32> for ExportKey {
+    fn from(value: u32) -> Self {
+        Self::Ordinal(value)
+    }
+}
+
+/// file name of module and export a forwarder string refer to, `NTDLL.#12` refer to ordinal 12
+pub(crate) fn parse_forwarder(forwarder: &str) -> Option<(String, ExportKey)> {
+    let (module, function) = forwarder.rsplit_once('.')?;
+    if module.is_empty() || function.is_empty() {
+        return None;
+    }
+
+    let module = match module.contains('.') {
+        true => module.to_string(),
+        false => format!("{}.dll", module),
+    };
+    let key = match function.strip_prefix('#') {
+        Some(ordinal) => ExportKey::Ordinal(ordinal.parse().ok()?),
+        None => ExportKey::Name(function.to_string()),
+    };
+
+    Some((module, key))
+}
+
+/// whether the module name is an api set contract, like `api-ms-win-core-heap-l1-1-0.dll`,
+/// which the loader map to a host module instead of loading a file of that name
+pub(crate) fn is_api_set(module_name: &str) -> bool {
+    let module_name = module_name.to_ascii_lowercase();
+    module_name.starts_with("api-") || module_name.starts_with("ext-")
+}
+
+/// function imported by an image
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Import {
+    module_name: String,
+    name: Option<String>,
+    ordinal: Option<u16>,
+    slot_address: usize,
+}
+
+impl Import {
+    /// name of the module the function imported from, like `KERNEL32.dll`
+    pub fn get_module_name(&self) -> &str {
+        &self.module_name
+    }
+
+    /// name of the function, `None` when imported by ordinal
+    pub fn get_name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// ordinal of the function, `None` when imported by name
+    pub fn get_ordinal(&self) -> Option<u16> {
+        self.ordinal
+    }
+
+    /// absolute address of the import address table slot holding the resolved function
+    pub fn get_slot_address(&self) -> usize {
+        self.slot_address
+    }
+}
+
+/// thread local storage directory of an image, addresses are absolute in the loaded image
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TlsDirectory {
+    start_of_raw_data: usize,
+    end_of_raw_data: usize,
+    address_of_index: usize,
+    address_of_callbacks: usize,
+    size_of_zero_fill: u32,
+}
+
+impl TlsDirectory {
+    /// get `StartAddressOfRawData`, template copied into the block of every thread
+    pub fn get_start_of_raw_data(&self) -> usize {
+        self.start_of_raw_data
+    }
+
+    /// get `EndAddressOfRawData`
+    pub fn get_end_of_raw_data(&self) -> usize {
+        self.end_of_raw_data
+    }
+
+    /// get `AddressOfIndex`, where the loader store the tls index of the image
+    pub fn get_address_of_index(&self) -> usize {
+        self.address_of_index
+    }
+
+    /// get `AddressOfCallBacks`, null terminated array of tls callbacks
+    pub fn get_address_of_callbacks(&self) -> usize {
+        self.address_of_callbacks
+    }
+
+    /// get `SizeOfZeroFill`, zeroed bytes following the template in every block
+    pub fn get_size_of_zero_fill(&self) -> u32 {
+        self.size_of_zero_fill
+    }
+
+    /// size of the block every thread has for the image
+    pub fn get_block_size(&self) -> usize {
+        self.end_of_raw_data.saturating_sub(self.start_of_raw_data)
+            + self.size_of_zero_fill as usize
+    }
+}
+
+/// headers of a PE image loaded in memory of a process
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeImage {
+    base: usize,
+    machine: u16,
+    is_64bit: bool,
+    entry_point: u32,
+    image_base: u64,
+    size_of_image: u32,
+    size_of_headers: u32,
+    data_directories: Vec<DataDirectory>,
+    sections: Vec<Section>,
+}
+
+impl PeImage {
+    /// read and parse headers of the image loaded at `base` in memory of `handle`
+    pub fn read(handle: &Handle, base: usize) -> Result<Self> {
+        Self::read_with(&|address, len| handle.read_memory(address, len), base)
+    }
+
+    pub(crate) fn read_with(read: &ReadFn, base: usize) -> Result<Self> {
+        let headers = read(base, HEADER_PAGE_SIZE)?;
+        match Self::parse(base, &headers) {
+            Err(Error::Unsupported) if headers.len() >= 0x40 => {
+                // NOTE: headers bigger than a page, retry with its declared size
+                let nt_header = u32_at(&headers, 0x3C)? as usize;
+                let size_of_headers = u32_at(&headers, nt_header + 0x18 + 60)? as usize;
+                if size_of_headers <= headers.len() || size_of_headers > MAX_HEADERS_SIZE {
+                    return Err(Error::Unsupported);
+                }
+                Self::parse(base, &read(base, size_of_headers)?)
+            }
+            result => result,
+        }
+    }
+
+    /// parse headers of the image loaded at `base` from bytes at the start of the image
+    pub fn parse(base: usize, headers: &[u8]) -> Result<Self> {
+        if u16_at(headers, 0)? != 0x5A4D {
+            return Err(Error::Unsupported);
+        }
+
+        let nt_header = u32_at(headers, 0x3C)? as usize;
+        if u32_at(headers, nt_header)? != 0x4550 {
+            return Err(Error::Unsupported);
+        }
+
+        let file_header = nt_header + 4;
+        let machine = u16_at(headers, file_header)?;
+        let section_count = u16_at(headers, file_header + 2)? as usize;
+        let size_of_optional_header = u16_at(headers, file_header + 16)? as usize;
+
+        let optional_header = file_header + 20;
+        let (is_64bit, image_base, directory_count_offset) = match u16_at(headers, optional_header)?
+        {
+            0x10B => (false, u32_at(headers, optional_header + 28)? as u64, 92),
+            0x20B => (true, u64_at(headers, optional_header + 24)?, 108),
+            _ => return Err(Error::Unsupported),
+        };
+
+        let directory_count = u32_at(headers, optional_header + directory_count_offset)? as usize;
+        let data_directories = (0..directory_count.min(16))
+            .map(|index| {
+                let offset = optional_header + directory_count_offset + 4 + index * 8;
+                Ok(DataDirectory {
+                    virtual_address: u32_at(headers, offset)?,
+                    size: u32_at(headers, offset + 4)?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let section_table = optional_header + size_of_optional_header;
+        let sections = (0..section_count)
+            .map(|index| {
+                let offset = section_table + index * 40;
+                let name = headers.get(offset..offset + 8).ok_or(Error::Unsupported)?;
+                Ok(Section {
+                    name: String::from_utf8_lossy(name)
+                        .trim_end_matches('\u{0}')
+                        .to_string(),
+                    virtual_size: u32_at(headers, offset + 8)?,
+                    virtual_address: u32_at(headers, offset + 12)?,
+                    raw_size: u32_at(headers, offset + 16)?,
+                    raw_offset: u32_at(headers, offset + 20)?,
+                    characteristics: u32_at(headers, offset + 36)?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            base,
+            machine,
+            is_64bit,
+            entry_point: u32_at(headers, optional_header + 16)?,
+            image_base,
+            size_of_image: u32_at(headers, optional_header + 56)?,
+            size_of_headers: u32_at(headers, optional_header + 60)?,
+            data_directories,
+            sections,
+        })
+    }
+
+    /// address the image loaded at
+    pub fn get_base(&self) -> usize {
+        self.base
+    }
+
+    /// get `FileHeader.Machine`
+    pub fn get_machine(&self) -> u16 {
+        self.machine
+    }
+
+    /// whether the image is PE32+
+    pub fn is_64bit(&self) -> bool {
+        self.is_64bit
+    }
+
+    /// get `AddressOfEntryPoint`, relative to the image base
+    pub fn get_entry_point(&self) -> u32 {
+        self.entry_point
+    }
+
+    /// get `ImageBase`, the preferred base address of the image
+    pub fn get_image_base(&self) -> u64 {
+        self.image_base
+    }
+
+    /// get `SizeOfImage`
+    pub fn get_size_of_image(&self) -> u32 {
+        self.size_of_image
+    }
+
+    /// get `SizeOfHeaders`
+    pub fn get_size_of_headers(&self) -> u32 {
+        self.size_of_headers
+    }
+
+    /// get entry of the data directory, `None` when the entry is empty
+    pub fn get_data_directory(&self, entry: DataDirectoryEntry) -> Option<DataDirectory> {
+        self.data_directories
+            .get(entry as usize)
+            .filter(|e| e.virtual_address != 0)
+            .copied()
+    }
+
+    /// get section headers
+    pub fn get_sections(&self) -> &[Section] {
+        &self.sections
+    }
+
+    /// find section by its name, like `.text`
+    pub fn find_section(&self, name: &str) -> Option<&Section> {
+        self.sections.iter().find(|e| e.name == name)
+    }
+
+    /// offset in the file of the image for the rva, when the image is parsed from a file
+    pub fn rva_to_file_offset(&self, rva: u32) -> Option<usize> {
+        if rva < self.size_of_headers {
+            return Some(rva as usize);
+        }
+
+        let section = self.sections.iter().find(|e| {
+            e.virtual_address
+                .checked_add(e.raw_size)
+                .is_some_and(|end| (e.virtual_address..end).contains(&rva))
+        })?;
+
+        (rva - section.virtual_address)
+            .checked_add(section.raw_offset)
+            .map(|e| e as usize)
+    }
+
+    /// apply base relocations of the image `file` to `bytes` laid out from `rva`, when the
+    /// image is loaded `delta` bytes away from its preferred base
+    pub(crate) fn apply_file_relocations(
+        &self,
+        file: &[u8],
+        rva: u32,
+        bytes: &mut [u8],
+        delta: u64,
+    ) -> Result<()> {
+        const IMAGE_REL_BASED_HIGHLOW: u16 = 3;
+        const IMAGE_REL_BASED_DIR64: u16 = 10;
+
+        let directory = match self.get_data_directory(DataDirectoryEntry::BaseRelocation) {
+            Some(directory) if delta != 0 => directory,
+            _ => return Ok(()),
+        };
+        let start = self
+            .rva_to_file_offset(directory.virtual_address)
+            .ok_or(Error::Unsupported)?;
+        let blocks = file
+            .get(start..start + directory.size as usize)
+            .ok_or(Error::Unsupported)?;
+
+        let mut offset = 0usize;
+        while offset + 8 <= blocks.len() {
+            let page = u32_at(blocks, offset)?;
+            let block_size = u32_at(blocks, offset + 4)? as usize;
+            if block_size < 8 {
+                break;
+            }
+
+            for entry_offset in (offset + 8..offset + block_size).step_by(2) {
+                let entry = u16_at(blocks, entry_offset)?;
+                let size = match entry >> 12 {
+                    IMAGE_REL_BASED_HIGHLOW => 4,
+                    IMAGE_REL_BASED_DIR64 => 8,
+                    _ => continue,
+                };
+
+                let target = (page + (entry & 0xFFF) as u32).wrapping_sub(rva) as usize;
+                let value = match bytes.get_mut(target..target + size) {
+                    Some(value) => value,
+                    None => continue,
+                };
+                match size {
+                    4 => {
+                        let relocated = u32_at(value, 0)?.wrapping_add(delta as u32);
+                        value.copy_from_slice(&relocated.to_le_bytes());
+                    }
+                    _ => {
+                        let relocated = u64_at(value, 0)?.wrapping_add(delta);
+                        value.copy_from_slice(&relocated.to_le_bytes());
+                    }
+                }
+            }
+
+            offset += block_size;
+        }
+
+        Ok(())
+    }
+
+    /// turn `image`, the bytes of the image as laid out in memory, into a loadable file.
+    ///
+    /// raw offset and size of each section is set to its memory layout, with file alignment
+    /// same as section alignment, and the preferred base is set to the address the image
+    /// loaded at since its base relocations are already applied. when `rebuild_imports`, slots
+    /// of the import address table are reset from the import name table the loader bound them
+    /// from.
+    pub(crate) fn unmap_image(&self, image: &mut [u8], rebuild_imports: bool) -> Result<()> {
+        let nt_header = u32_at(image, 0x3C)? as usize;
+        let file_header = nt_header + 4;
+        let optional_header = file_header + 20;
+        let size_of_optional_header = u16_at(image, file_header + 16)? as usize;
+
+        let section_alignment = u32_at(image, optional_header + 32)?;
+        put_bytes(
+            image,
+            optional_header + 36,
+            &section_alignment.to_le_bytes(),
+        )?;
+        match self.is_64bit {
+            true => put_bytes(
+                image,
+                optional_header + 24,
+                &(self.base as u64).to_le_bytes(),
+            )?,
+            false => put_bytes(
+                image,
+                optional_header + 28,
+                &(self.base as u32).to_le_bytes(),
+            )?,
+        }
+
+        let section_table = optional_header + size_of_optional_header;
+        for (index, section) in self.sections.iter().enumerate() {
+            let offset = section_table + index * 40;
+            let size = section.virtual_size.max(section.raw_size);
+            put_bytes(image, offset + 16, &size.to_le_bytes())?;
+            put_bytes(image, offset + 20, &section.virtual_address.to_le_bytes())?;
+        }
+
+        if rebuild_imports {
+            let read = |address: usize, len: usize| {
+                image
+                    .get(address..address + len)
+                    .map(|e| e.to_vec())
+                    .ok_or(Error::Unsupported)
+            };
+            let unbound = PeImage {
+                base: 0,
+                ..self.clone()
+            };
+            let tables = unbound.read_thunk_tables(&read)?;
+            for (name_table, address_table, len) in tables {
+                let thunks = image[name_table..name_table + len].to_vec();
+                put_bytes(image, address_table, &thunks)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// functions exported by the image, read from its export directory in memory of `handle`
+    pub fn exports(&self, handle: &Handle) -> Result<Vec<Export>> {
+        self.read_exports(&|address, len| handle.read_memory(address, len))
+    }
+
+    /// functions imported by the image with their import address table slot, read from its
+    /// import directory in memory of `handle`
+    pub fn imports(&self, handle: &Handle) -> Result<Vec<Import>> {
+        self.read_imports(&|address, len| handle.read_memory(address, len))
+    }
+
+    /// thread local storage directory of the image, `None` when it use no static tls
+    pub fn tls_directory(&self, handle: &Handle) -> Result<Option<TlsDirectory>> {
+        self.read_tls_directory(&|address, len| handle.read_memory(address, len))
+    }
+
+    pub(crate) fn read_tls_directory(&self, read: &ReadFn) -> Result<Option<TlsDirectory>> {
+        let directory = match self.get_data_directory(DataDirectoryEntry::Tls) {
+            Some(directory) => directory,
+            None => return Ok(None),
+        };
+
+        let pointer_size = if self.is_64bit { 8 } else { 4 };
+        let data = read(
+            self.base + directory.virtual_address as usize,
+            pointer_size * 4 + 8,
+        )?;
+        let pointer_at = |index: usize| match pointer_size {
+            8 => u64_at(&data, index * 8).map(|e| e as usize),
+            _ => u32_at(&data, index * 4).map(|e| e as usize),
+        };
+
+        Ok(Some(TlsDirectory {
+            start_of_raw_data: pointer_at(0)?,
+            end_of_raw_data: pointer_at(1)?,
+            address_of_index: pointer_at(2)?,
+            address_of_callbacks: pointer_at(3)?,
+            size_of_zero_fill: u32_at(&data, pointer_size * 4)?,
+        }))
+    }
+
+    /// every import descriptor up to the zero one ending them
+    fn read_import_descriptors(&self, read: &ReadFn) -> Result<Vec<Vec<u8>>> {
+        let directory = match self.get_data_directory(DataDirectoryEntry::Import) {
+            Some(directory) => directory,
+            None => return Ok(Vec::new()),
+        };
+
+        let mut descriptors = Vec::new();
+        // NOTE: size of the directory usually counts the terminator, accept one right past it
+        for index in 0..=directory.size as usize / IMPORT_DESCRIPTOR_SIZE {
+            let descriptor = read(
+                self.base + directory.virtual_address as usize + index * IMPORT_DESCRIPTOR_SIZE,
+                IMPORT_DESCRIPTOR_SIZE,
+            )?;
+            if descriptor.iter().all(|e| *e == 0) {
+                return Ok(descriptors);
+            }
+            descriptors.push(descriptor);
+        }
+
+        Err(Error::InvalidInput)
+    }
+
+    pub(crate) fn read_imports(&self, read: &ReadFn) -> Result<Vec<Import>> {
+        let base = self.base;
+        let pointer_size = if self.is_64bit { 8 } else { 4 };
+        let ordinal_flag = 1u64 << (pointer_size * 8 - 1);
+        let read_thunk = |address: usize| -> Result<u64> {
+            let data = read(address, pointer_size)?;
+            match self.is_64bit {
+                true => u64_at(&data, 0),
+                false => u32_at(&data, 0).map(|e| e as u64),
+            }
+        };
+
+        let mut imports = Vec::new();
+        for descriptor in self.read_import_descriptors(read)? {
+            let name_table = u32_at(&descriptor, 0)? as usize;
+            let module_name = read_cstr(read, base + u32_at(&descriptor, 12)? as usize)?;
+            let address_table = u32_at(&descriptor, 16)? as usize;
+            // NOTE: without import name table, names are lost once the loader bound the IAT
+            let name_table = if name_table != 0 {
+                name_table
+            } else {
+                address_table
+            };
+
+            let mut is_terminated = false;
+            for slot in 0..MAX_IMPORT_THUNKS {
+                let thunk = read_thunk(base + name_table + slot * pointer_size)?;
+                if thunk == 0 {
+                    is_terminated = true;
+                    break;
+                }
+
+                let (name, ordinal) = match thunk & ordinal_flag {
+                    0 => (Some(read_cstr(read, base + thunk as usize + 2)?), None),
+                    _ => (None, Some(thunk as u16)),
+                };
+
+                imports.push(Import {
+                    module_name: module_name.clone(),
+                    name,
+                    ordinal,
+                    slot_address: base + address_table + slot * pointer_size,
+                });
+            }
+            if !is_terminated {
+                return Err(Error::InvalidInput);
+            }
+        }
+
+        Ok(imports)
+    }
+
+    /// absolute address of import name table, import address table and their size in bytes
+    /// of every imported module having an import name table
+    fn read_thunk_tables(&self, read: &ReadFn) -> Result<Vec<(usize, usize, usize)>> {
+        let base = self.base;
+        let pointer_size = if self.is_64bit { 8 } else { 4 };
+
+        let mut tables = Vec::new();
+        for descriptor in self.read_import_descriptors(read)? {
+            let name_table = u32_at(&descriptor, 0)? as usize;
+            let address_table = u32_at(&descriptor, 16)? as usize;
+            if name_table == 0 {
+                continue;
+            }
+
+            let len = (0..MAX_IMPORT_THUNKS)
+                .map(|slot| slot * pointer_size)
+                .find_map(
+                    |offset| match read(base + name_table + offset, pointer_size) {
+                        Ok(thunk) if thunk.iter().all(|e| *e == 0) => Some(Ok(offset)),
+                        Ok(_) => None,
+                        Err(e) => Some(Err(e)),
+                    },
+                )
+                .unwrap_or(Err(Error::InvalidInput))?;
+            tables.push((base + name_table, base + address_table, len));
+        }
+
+        Ok(tables)
+    }
+
+    pub(crate) fn read_exports(&self, read: &ReadFn) -> Result<Vec<Export>> {
+        let directory = match self.get_data_directory(DataDirectoryEntry::Export) {
+            Some(directory) => directory,
+            None => return Ok(Vec::new()),
+        };
+
+        let base = self.base;
+        let export_directory = read(base + directory.virtual_address as usize, 0x28)?;
+        let field = |offset: usize| u32_at(&export_directory, offset).map(|e| e as usize);
+        let ordinal_base = field(0x10)?;
+        let function_count = field(0x14)?;
+        let name_count = field(0x18)?;
+        if function_count > MAX_EXPORT_COUNT || name_count > MAX_EXPORT_COUNT {
+            return Err(Error::Unsupported);
+        }
+
+        let functions = read_u32_array(read, base + field(0x1C)?, function_count)?;
+        let names = read_u32_array(read, base + field(0x20)?, name_count)?;
+        let name_ordinals = read(base + field(0x24)?, name_count * 2)?;
+
+        let mut function_names = vec![None; function_count];
+        for (name_rva, ordinal) in names.iter().zip(name_ordinals.chunks_exact(2)) {
+            let index = u16::from_le_bytes([ordinal[0], ordinal[1]]) as usize;
+            if let Some(function_name) = function_names.get_mut(index) {
+                *function_name = Some(read_cstr(read, base + *name_rva as usize)?);
+            }
+        }
+
+        let exports = functions
+            .into_iter()
+            .zip(function_names)
+            .enumerate()
+            .filter(|(_, (rva, _))| *rva != 0)
+            .filter_map(|(index, (rva, name))| {
+                // NOTE: rva inside the export directory point to a forwarder string, entry
+                // whose string can not be read is skipped instead of failing every export
+                let forwarder = match directory.contains(rva) {
+                    true => Some(read_cstr(read, base + rva as usize).ok()?),
+                    false => None,
+                };
+
+                Some(Export {
+                    name,
+                    ordinal: (ordinal_base + index) as u32,
+                    address: base + rva as usize,
+                    forwarder,
+                })
+            })
+            .collect();
+
+        Ok(exports)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::{is_api_set, parse_forwarder, u32_at, DataDirectoryEntry, ExportKey, PeImage};
+    use crate::error::{Error, Result};
+
+    pub(crate) fn put(image: &mut [u8], offset: usize, bytes: &[u8]) {
+        image[offset..offset + bytes.len()].copy_from_slice(bytes);
+    }
+
+    /// PE32+ image with `.text` and `.rdata` section, export directory at 0x200
+    pub(crate) fn build_image() -> Vec<u8> {
+        let mut image = vec![0u8; 0x1000];
+
+        put(&mut image, 0, b"MZ");
+        put(&mut image, 0x3C, &0x80u32.to_le_bytes());
+        put(&mut image, 0x80, b"PE\0\0");
+        put(&mut image, 0x84, &0x8664u16.to_le_bytes());
+        put(&mut image, 0x86, &2u16.to_le_bytes());
+        put(&mut image, 0x94, &0xF0u16.to_le_bytes());
+
+        // optional header
+        put(&mut image, 0x98, &0x20Bu16.to_le_bytes());
+        put(&mut image, 0x98 + 16, &0x1000u32.to_le_bytes());
+        put(&mut image, 0x98 + 24, &0x1_4000_0000u64.to_le_bytes());
+        put(&mut image, 0x98 + 56, &0x3000u32.to_le_bytes());
+        put(&mut image, 0x98 + 60, &0x400u32.to_le_bytes());
+        put(&mut image, 0x98 + 108, &16u32.to_le_bytes());
+        put(&mut image, 0x108, &0x200u32.to_le_bytes());
+        put(&mut image, 0x10C, &0x100u32.to_le_bytes());
+
+        // section table
+        put(&mut image, 0x188, b".text\0\0\0");
+        put(&mut image, 0x188 + 8, &0x800u32.to_le_bytes());
+        put(&mut image, 0x188 + 12, &0x1000u32.to_le_bytes());
+        put(&mut image, 0x188 + 16, &0xA00u32.to_le_bytes());
+        put(&mut image, 0x188 + 20, &0x400u32.to_le_bytes());
+        put(&mut image, 0x188 + 36, &0x60000020u32.to_le_bytes());
+        put(&mut image, 0x1B0, b".rdata\0\0");
+        put(&mut image, 0x1B0 + 8, &0x100u32.to_le_bytes());
+        put(&mut image, 0x1B0 + 12, &0x2000u32.to_le_bytes());
+
+        // export directory
+        put(&mut image, 0x210, &5u32.to_le_bytes());
+        put(&mut image, 0x214, &2u32.to_le_bytes());
+        put(&mut image, 0x218, &1u32.to_le_bytes());
+        put(&mut image, 0x21C, &0x240u32.to_le_bytes());
+        put(&mut image, 0x220, &0x250u32.to_le_bytes());
+        put(&mut image, 0x224, &0x260u32.to_le_bytes());
+
+        put(&mut image, 0x240, &0x1000u32.to_le_bytes());
+        put(&mut image, 0x244, &0x2000u32.to_le_bytes());
+        put(&mut image, 0x250, &0x270u32.to_le_bytes());
+        put(&mut image, 0x260, &1u16.to_le_bytes());
+        put(&mut image, 0x270, b"LoadLibraryW\0");
+
+        // base relocation of a pointer at rva 0x1010
+        put(&mut image, 0x130, &0x3A0u32.to_le_bytes());
+        put(&mut image, 0x134, &0xCu32.to_le_bytes());
+        put(&mut image, 0x3A0, &0x1000u32.to_le_bytes());
+        put(&mut image, 0x3A4, &0xCu32.to_le_bytes());
+        put(&mut image, 0x3A8, &(10u16 << 12 | 0x10).to_le_bytes());
+        put(&mut image, 0x410, &0x1_4000_2000u64.to_le_bytes());
+
+        image
+    }
+
+    /// [build_image] importing `Sleep` and ordinal 7 from `KERNEL32.dll`, import directory at
+    /// 0x300
+    fn build_image_with_imports() -> Vec<u8> {
+        let mut image = build_image();
+
+        put(&mut image, 0x110, &0x300u32.to_le_bytes());
+        put(&mut image, 0x114, &0x28u32.to_le_bytes());
+        put(&mut image, 0x300, &0x340u32.to_le_bytes());
+        put(&mut image, 0x30C, &0x380u32.to_le_bytes());
+        put(&mut image, 0x310, &0x360u32.to_le_bytes());
+
+        for table in [0x340, 0x360] {
+            put(&mut image, table, &0x390u64.to_le_bytes());
+            put(&mut image, table + 8, &(1u64 << 63 | 7).to_le_bytes());
+        }
+        put(&mut image, 0x380, b"KERNEL32.dll\0");
+        put(&mut image, 0x392, b"Sleep\0");
+
+        image
+    }
+
+    pub(crate) fn reader(
+        base: usize,
+        image: &[u8],
+    ) -> impl Fn(usize, usize) -> Result<Vec<u8>> + '_ {
+        move |address: usize, len: usize| {
+            let start = address.checked_sub(base).ok_or(Error::InvalidInput)?;
+            image
+                .get(start..start + len)
+                .map(|e| e.to_vec())
+                .ok_or(Error::InvalidInput)
+        }
+    }
+
+    #[test]
+    fn parsing_headers() {
+        let image = build_image();
+        let pe = PeImage::parse(0x10000, &image).unwrap();
+
+        assert!(pe.is_64bit());
+        assert_eq!(pe.get_machine(), 0x8664);
+        assert_eq!(pe.get_entry_point(), 0x1000);
+        assert_eq!(pe.get_image_base(), 0x1_4000_0000);
+        assert_eq!(pe.get_size_of_image(), 0x3000);
+        assert_eq!(
+            pe.get_data_directory(DataDirectoryEntry::Export)
+                .map(|e| (e.get_virtual_address(), e.get_size())),
+            Some((0x200, 0x100))
+        );
+        assert_eq!(pe.get_data_directory(DataDirectoryEntry::Import), None);
+
+        let text = pe.find_section(".text").unwrap();
+        assert_eq!(text.get_virtual_address(), 0x1000);
+        assert_eq!(text.get_address_range(0x10000), 0x11000..0x11A00);
+        assert_eq!(pe.get_sections()[1].get_name(), ".rdata");
+    }
+
+    #[test]
+    fn rejecting_non_pe() {
+        assert!(PeImage::parse(0, &[0u8; 0x100]).is_err());
+    }
+
+    #[test]
+    fn parsing_export_directory() {
+        let base = 0x10000;
+        let image = build_image();
+        let read = reader(base, &image);
+
+        let exports = PeImage::read_with(&read, base)
+            .unwrap()
+            .read_exports(&read)
+            .unwrap();
+
+        assert_eq!(exports.len(), 2);
+        assert_eq!(exports[0].get_name(), None);
+        assert_eq!(exports[0].get_ordinal(), 5);
+        assert_eq!(exports[0].get_address(), base + 0x1000);
+        assert_eq!(exports[1].get_name(), Some("LoadLibraryW"));
+        assert_eq!(exports[1].get_ordinal(), 6);
+        assert_eq!(exports[1].get_address(), base + 0x2000);
+    }
+
+    #[test]
+    fn parsing_forwarder() {
+        assert_eq!(
+            parse_forwarder("NTDLL.RtlAllocateHeap"),
+            Some((
+                "NTDLL.dll".to_string(),
+                ExportKey::Name("RtlAllocateHeap".to_string())
+            ))
+        );
+        assert_eq!(
+            parse_forwarder("KERNELBASE.#12"),
+            Some(("KERNELBASE.dll".to_string(), ExportKey::Ordinal(12)))
+        );
+        assert_eq!(parse_forwarder("NTDLL"), None);
+        assert_eq!(parse_forwarder("NTDLL.#x"), None);
+    }
+
+    #[test]
+    fn detecting_api_set() {
+        assert!(is_api_set("api-ms-win-core-heap-l1-1-0.dll"));
+        assert!(is_api_set("API-MS-Win-Core-Synch-L1-2-0.dll"));
+        assert!(is_api_set("ext-ms-win-ntuser-window-l1-1-0.dll"));
+        assert!(!is_api_set("kernelbase.dll"));
+    }
+
+    #[test]
+    fn parsing_import_directory() {
+        let base = 0x10000;
+        let image = build_image_with_imports();
+        let read = reader(base, &image);
+
+        let imports = PeImage::read_with(&read, base)
+            .unwrap()
+            .read_imports(&read)
+            .unwrap();
+
+        assert_eq!(imports.len(), 2);
+        assert_eq!(imports[0].get_module_name(), "KERNEL32.dll");
+        assert_eq!(imports[0].get_name(), Some("Sleep"));
+        assert_eq!(imports[0].get_slot_address(), base + 0x360);
+        assert_eq!(imports[1].get_name(), None);
+        assert_eq!(imports[1].get_ordinal(), Some(7));
+        assert_eq!(imports[1].get_slot_address(), base + 0x368);
+    }
+
+    #[test]
+    fn bounding_import_tables() {
+        let base = 0x10000;
+
+        // descriptors past the directory size without a terminator
+        let mut image = build_image_with_imports();
+        put(&mut image, 0x114, &0x14u32.to_le_bytes());
+        let descriptor = image[0x300..0x314].to_vec();
+        put(&mut image, 0x314, &descriptor);
+        let read = reader(base, &image);
+        let pe = PeImage::read_with(&read, base).unwrap();
+        assert!(matches!(pe.read_imports(&read), Err(Error::InvalidInput)));
+        assert!(matches!(
+            pe.read_thunk_tables(&read),
+            Err(Error::InvalidInput)
+        ));
+
+        // name table never ending
+        let mut image = build_image_with_imports();
+        put(&mut image, 0x300, &0x2000u32.to_le_bytes());
+        let image_read = reader(base, &image);
+        let read = |address: usize, len: usize| match address >= base + 0x2000 {
+            true => Ok((1u64 << 63 | 7).to_le_bytes()[..len].to_vec()),
+            false => image_read(address, len),
+        };
+        let pe = PeImage::read_with(&read, base).unwrap();
+        assert!(matches!(pe.read_imports(&read), Err(Error::InvalidInput)));
+        assert!(matches!(
+            pe.read_thunk_tables(&read),
+            Err(Error::InvalidInput)
+        ));
+    }
+
+    #[test]
+    fn unmapping_image() {
+        let mut image = build_image_with_imports();
+        image.resize(0x3000, 0);
+        put(&mut image, 0x98 + 32, &0x1000u32.to_le_bytes());
+        put(&mut image, 0x360, &0x7FF8_0000_1000u64.to_le_bytes());
+        let pe = PeImage::parse(0x10000, &image).unwrap();
+
+        pe.unmap_image(&mut image, true).unwrap();
+        let file = PeImage::parse(0, &image).unwrap();
+
+        assert_eq!(file.get_image_base(), 0x10000);
+        assert_eq!(u32_at(&image, 0x98 + 36).unwrap(), 0x1000);
+        let text = file.find_section(".text").unwrap();
+        assert_eq!(
+            (text.get_raw_offset(), text.get_raw_size()),
+            (0x1000, 0xA00)
+        );
+        assert_eq!(file.rva_to_file_offset(0x2010), Some(0x2010));
+        assert_eq!(&image[0x360..0x370], &image[0x340..0x350]);
+    }
+
+    #[test]
+    fn comparing_relocated_section() {
+        let image = build_image();
+        let pe = PeImage::parse(0, &image).unwrap();
+        let text = pe.find_section(".text").unwrap();
+        assert_eq!(pe.rva_to_file_offset(0x1010), Some(0x410));
+        assert_eq!(pe.rva_to_file_offset(0x1A00), None);
+
+        let mut expected = image[0x400..0x420].to_vec();
+        pe.apply_file_relocations(
+            &image,
+            text.get_virtual_address(),
+            &mut expected,
+            0xC000_0000,
+        )
+        .unwrap();
+        assert_eq!(&expected[0x10..0x18], &0x2_0000_2000u64.to_le_bytes());
+    }
+}
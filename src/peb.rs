@@ -0,0 +1,589 @@
+use std::ffi::c_void;
+use std::mem::size_of;
+
+use crate::error::{Error, Result};
+use crate::handle::{Handle, HandleSnapshotFlag};
+use crate::module::Module;
+use crate::ntdll;
+use crate::pe::{u16_at, u32_at, u64_at, ReadFn};
+use crate::thread::Thread;
+
+/// `ProcessBasicInformation` of `PROCESSINFOCLASS`
+const PROCESS_BASIC_INFORMATION: u32 = 0;
+/// `ProcessWow64Information` of `PROCESSINFOCLASS`
+const PROCESS_WOW64_INFORMATION: u32 = 26;
+/// `ThreadBasicInformation` of `THREADINFOCLASS`
+const THREAD_BASIC_INFORMATION: u32 = 0;
+/// distance from the native TEB of a WOW64 thread to its 32 bit TEB
+const WOW64_TEB_OFFSET: usize = 0x2000;
+/// `TLS_MINIMUM_AVAILABLE`, tls slots held inside the TEB
+const TLS_MINIMUM_AVAILABLE: u32 = 64;
+/// `TLS_EXPANSION_SLOTS`, tls slots held in the expansion array after the ones inside the TEB
+const TLS_EXPANSION_SLOTS: u32 = 1024;
+
+/// offsets of fields in structures which differ between 32 bit and 64 bit process
+struct Layout {
+    pointer_size: usize,
+    peb_image_base: usize,
+    peb_ldr: usize,
+    peb_process_parameters: usize,
+    parameters_image_path: usize,
+    parameters_command_line: usize,
+    parameters_environment: usize,
+    parameters_environment_size: usize,
+    ldr_load_order_list: usize,
+    entry_dll_base: usize,
+    entry_size_of_image: usize,
+    entry_full_name: usize,
+    entry_base_name: usize,
+    teb_stack_base: usize,
+    teb_stack_limit: usize,
+    teb_tls_pointer: usize,
+    teb_peb: usize,
+    teb_tls_slots: usize,
+    teb_tls_expansion_slots: usize,
+}
+
+const LAYOUT_32: Layout = Layout {
+    pointer_size: 4,
+    peb_image_base: 0x8,
+    peb_ldr: 0xC,
+    peb_process_parameters: 0x10,
+    parameters_image_path: 0x38,
+    parameters_command_line: 0x40,
+    parameters_environment: 0x48,
+    parameters_environment_size: 0x290,
+    ldr_load_order_list: 0xC,
+    entry_dll_base: 0x18,
+    entry_size_of_image: 0x20,
+    entry_full_name: 0x24,
+    entry_base_name: 0x2C,
+    teb_stack_base: 0x4,
+    teb_stack_limit: 0x8,
+    teb_tls_pointer: 0x2C,
+    teb_peb: 0x30,
+    teb_tls_slots: 0xE10,
+    teb_tls_expansion_slots: 0xF94,
+};
+
+const LAYOUT_64: Layout = Layout {
+    pointer_size: 8,
+    peb_image_base: 0x10,
+    peb_ldr: 0x18,
+    peb_process_parameters: 0x20,
+    parameters_image_path: 0x60,
+    parameters_command_line: 0x70,
+    parameters_environment: 0x80,
+    parameters_environment_size: 0x3F0,
+    ldr_load_order_list: 0x10,
+    entry_dll_base: 0x30,
+    entry_size_of_image: 0x40,
+    entry_full_name: 0x48,
+    entry_base_name: 0x58,
+    teb_stack_base: 0x8,
+    teb_stack_limit: 0x10,
+    teb_tls_pointer: 0x58,
+    teb_peb: 0x60,
+    teb_tls_slots: 0x1480,
+    teb_tls_expansion_slots: 0x1780,
+};
+
+impl Layout {
+    fn of(pointer_size: usize) -> &'static Layout {
+        match pointer_size {
+            4 => &LAYOUT_32,
+            _ => &LAYOUT_64,
+        }
+    }
+
+    fn pointer_at(&self, data: &[u8], offset: usize) -> Result<usize> {
+        match self.pointer_size {
+            4 => u32_at(data, offset).map(|e| e as usize),
+            _ => u64_at(data, offset).map(|e| e as usize),
+        }
+    }
+
+    /// content of `UNICODE_STRING` at `address`
+    fn read_unicode_string(&self, read: &ReadFn, address: usize) -> Result<String> {
+        let header = read(address, self.pointer_size * 2)?;
+        let len = u16_at(&header, 0)? as usize;
+        let buffer = self.pointer_at(&header, self.pointer_size)?;
+        if len == 0 {
+            return Ok(String::new());
+        }
+
+        Ok(utf16_from_bytes(&read(buffer, len)?))
+    }
+}
+
+/// most entries walked in a loader list, stop walking a corrupted or cyclic list
+const MAX_LOADER_ENTRIES: usize = 0x1000;
+
+/// modules of `InLoadOrderModuleList` in `PEB_LDR_DATA` at `ldr`
+fn read_loader_entries(
+    read: &ReadFn,
+    layout: &Layout,
+    ldr: usize,
+    process_id: u32,
+) -> Result<Vec<Module>> {
+    let head = ldr + layout.ldr_load_order_list;
+    let mut entry = layout.pointer_at(&read(head, layout.pointer_size)?, 0)?;
+
+    let mut modules = Vec::new();
+    while entry != head && entry != 0 {
+        if modules.len() >= MAX_LOADER_ENTRIES {
+            return Err(Error::Unsupported);
+        }
+
+        let data = read(entry, layout.entry_base_name + layout.pointer_size * 2)?;
+        let address = layout.pointer_at(&data, layout.entry_dll_base)?;
+        let size = u32_at(&data, layout.entry_size_of_image)?;
+        let path = layout.read_unicode_string(read, entry + layout.entry_full_name)?;
+        let name = layout.read_unicode_string(read, entry + layout.entry_base_name)?;
+        modules.push(Module::from_loader_entry(
+            process_id, address, size, &name, &path,
+        ));
+
+        // NOTE: InLoadOrderLinks is the first field, so the link is the entry itself
+        entry = layout.pointer_at(&data, 0)?;
+    }
+
+    Ok(modules)
+}
+
+fn utf16_from_bytes(bytes: &[u8]) -> String {
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|e| u16::from_le_bytes([e[0], e[1]]))
+        .collect();
+
+    String::from_utf16_lossy(&units)
+}
+
+/// query `information_class` of the process into `T`
+pub(crate) fn query_process<T: Default>(handle: &Handle, information_class: u32) -> Result<T> {
+    let query = ntdll::NtQueryInformationProcess().ok_or(Error::Unsupported)?;
+
+    let mut information = T::default();
+    unsafe {
+        query(
+            **handle,
+            information_class,
+            &mut information as *mut T as *mut c_void,
+            size_of::<T>() as u32,
+            std::ptr::null_mut(),
+        )
+    }
+    .ok()
+    .map_err(|e| Error::win32("NtQueryInformationProcess", e))?;
+
+    Ok(information)
+}
+
+impl Handle {
+    /// address of the process environment block, the 32 bit one for WOW64 process
+    pub fn get_peb_address(&self) -> Result<usize> {
+        if self.is_wow64()? {
+            return query_process::<usize>(self, PROCESS_WOW64_INFORMATION);
+        }
+
+        // NOTE: PebBaseAddress follow ExitStatus which padded to pointer size
+        let information = query_process::<[usize; 6]>(self, PROCESS_BASIC_INFORMATION)?;
+        Ok(information[1])
+    }
+
+    /// modules found by walking the loader list in the process environment block
+    pub fn get_modules_via_peb(&self) -> Result<Vec<Module>> {
+        Peb::read(self)?.read_modules(self)
+    }
+
+    /// compare modules of the loader list against modules of the ToolHelp snapshot
+    pub fn diff_module_lists(&self) -> Result<ModuleListDiff> {
+        let peb = self.get_modules_via_peb()?;
+        let snapshot: Vec<Module> = self
+            .create_snapshot(HandleSnapshotFlag::SnapModule | HandleSnapshotFlag::SnapModule32)?
+            .get_modules()
+            .collect();
+
+        Ok(diff_module_lists(&peb, &snapshot))
+    }
+}
+
+/// Modules seen by only one of the PEB loader list and the ToolHelp snapshot.
+///
+/// module unlinked from the loader list after the snapshot taken show up in the snapshot only.
+pub struct ModuleListDiff {
+    only_in_peb: Vec<Module>,
+    only_in_snapshot: Vec<Module>,
+}
+
+impl ModuleListDiff {
+    /// modules in the loader list but missing from the snapshot
+    pub fn get_only_in_peb(&self) -> &[Module] {
+        &self.only_in_peb
+    }
+
+    /// modules in the snapshot but missing from the loader list
+    pub fn get_only_in_snapshot(&self) -> &[Module] {
+        &self.only_in_snapshot
+    }
+
+    /// whether both lists agree
+    pub fn is_empty(&self) -> bool {
+        self.only_in_peb.is_empty() && self.only_in_snapshot.is_empty()
+    }
+}
+
+fn diff_module_lists(peb: &[Module], snapshot: &[Module]) -> ModuleListDiff {
+    let missing_from = |modules: &[Module], others: &[Module]| {
+        modules
+            .iter()
+            .filter(|e| !others.iter().any(|o| o.get_address() == e.get_address()))
+            .copied()
+            .collect()
+    };
+
+    ModuleListDiff {
+        only_in_peb: missing_from(peb, snapshot),
+        only_in_snapshot: missing_from(snapshot, peb),
+    }
+}
+
+/// Process environment block of a process, fields are read when the view created.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Peb {
+    address: usize,
+    pointer_size: usize,
+    image_base: usize,
+    ldr: usize,
+    process_parameters: usize,
+}
+
+impl Peb {
+    /// read the process environment block of the process, honoring WOW64
+    pub fn read(handle: &Handle) -> Result<Self> {
+        Self::read_with(
+            &|address, len| handle.read_memory(address, len),
+            handle.get_peb_address()?,
+            handle.get_pointer_size()?,
+        )
+    }
+
+    pub(crate) fn read_with(read: &ReadFn, address: usize, pointer_size: usize) -> Result<Self> {
+        let layout = Layout::of(pointer_size);
+        let data = read(address, layout.peb_process_parameters + pointer_size)?;
+
+        Ok(Self {
+            address,
+            pointer_size,
+            image_base: layout.pointer_at(&data, layout.peb_image_base)?,
+            ldr: layout.pointer_at(&data, layout.peb_ldr)?,
+            process_parameters: layout.pointer_at(&data, layout.peb_process_parameters)?,
+        })
+    }
+
+    /// address of the block
+    pub fn get_address(&self) -> usize {
+        self.address
+    }
+
+    /// get `ImageBaseAddress`, base of the executable even when hidden from module list
+    pub fn get_image_base(&self) -> usize {
+        self.image_base
+    }
+
+    /// get `Ldr`, address of `PEB_LDR_DATA` holding loaded module lists
+    pub fn get_ldr_address(&self) -> usize {
+        self.ldr
+    }
+
+    /// get `ProcessParameters`, address of `RTL_USER_PROCESS_PARAMETERS`
+    pub fn get_process_parameters_address(&self) -> usize {
+        self.process_parameters
+    }
+
+    /// modules linked in the loader list of the process, in load order
+    pub fn read_modules(&self, handle: &Handle) -> Result<Vec<Module>> {
+        read_loader_entries(
+            &|address, len| handle.read_memory(address, len),
+            Layout::of(self.pointer_size),
+            self.ldr,
+            handle.get_process_id(),
+        )
+    }
+
+    /// path of the executable the process started from
+    pub fn read_image_path(&self, handle: &Handle) -> Result<String> {
+        self.read_parameter_string(handle, Layout::of(self.pointer_size).parameters_image_path)
+    }
+
+    /// command line the process started with
+    pub fn read_command_line(&self, handle: &Handle) -> Result<String> {
+        self.read_parameter_string(
+            handle,
+            Layout::of(self.pointer_size).parameters_command_line,
+        )
+    }
+
+    /// environment variables of the process as `(name, value)`
+    pub fn read_environment(&self, handle: &Handle) -> Result<Vec<(String, String)>> {
+        let layout = Layout::of(self.pointer_size);
+
+        let data = handle.read_memory(
+            self.process_parameters + layout.parameters_environment,
+            self.pointer_size,
+        )?;
+        let environment = layout.pointer_at(&data, 0)?;
+        let data = handle.read_memory(
+            self.process_parameters + layout.parameters_environment_size,
+            self.pointer_size,
+        )?;
+        let size = layout.pointer_at(&data, 0)?;
+
+        Ok(parse_environment(&utf16_from_bytes(
+            &handle.read_memory(environment, size)?,
+        )))
+    }
+
+    fn read_parameter_string(&self, handle: &Handle, offset: usize) -> Result<String> {
+        Layout::of(self.pointer_size).read_unicode_string(
+            &|address, len| handle.read_memory(address, len),
+            self.process_parameters + offset,
+        )
+    }
+}
+
+/// `name=value` entries of an environment block separated by nul
+fn parse_environment(block: &str) -> Vec<(String, String)> {
+    block
+        .split('\0')
+        .take_while(|e| !e.is_empty())
+        .filter_map(|entry| {
+            // NOTE: hidden variables like `=C:` start with `=`
+            let first = entry.chars().next()?.len_utf8();
+            let split = entry[first..].find('=')? + first;
+            Some((entry[..split].to_string(), entry[split + 1..].to_string()))
+        })
+        .collect()
+}
+
+/// Thread environment block of a thread, fields are read when the view created.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Teb {
+    address: usize,
+    pointer_size: usize,
+    stack_base: usize,
+    stack_limit: usize,
+    tls_pointer: usize,
+    peb: usize,
+}
+
+impl Teb {
+    /// read the thread environment block of `thread` in the process, honoring WOW64
+    pub fn read(handle: &Handle, thread: &Thread) -> Result<Self> {
+        let query = ntdll::NtQueryInformationThread().ok_or(Error::Unsupported)?;
+
+        // NOTE: TebBaseAddress follow ExitStatus which padded to pointer size
+        let mut information = [0usize; 6];
+        unsafe {
+            query(
+                **thread,
+                THREAD_BASIC_INFORMATION,
+                information.as_mut_ptr() as *mut c_void,
+                size_of::<[usize; 6]>() as u32,
+                std::ptr::null_mut(),
+            )
+        }
+        .ok()
+        .map_err(|e| Error::win32("NtQueryInformationThread", e))?;
+
+        let pointer_size = handle.get_pointer_size()?;
+        let address = match handle.is_wow64()? {
+            true => information[1] + WOW64_TEB_OFFSET,
+            false => information[1],
+        };
+
+        Self::read_with(
+            &|address, len| handle.read_memory(address, len),
+            address,
+            pointer_size,
+        )
+    }
+
+    pub(crate) fn read_with(read: &ReadFn, address: usize, pointer_size: usize) -> Result<Self> {
+        let layout = Layout::of(pointer_size);
+        let data = read(address, layout.teb_peb + pointer_size)?;
+
+        Ok(Self {
+            address,
+            pointer_size,
+            stack_base: layout.pointer_at(&data, layout.teb_stack_base)?,
+            stack_limit: layout.pointer_at(&data, layout.teb_stack_limit)?,
+            tls_pointer: layout.pointer_at(&data, layout.teb_tls_pointer)?,
+            peb: layout.pointer_at(&data, layout.teb_peb)?,
+        })
+    }
+
+    /// address of the block
+    pub fn get_address(&self) -> usize {
+        self.address
+    }
+
+    /// get `NtTib.StackBase`, highest address of the stack
+    pub fn get_stack_base(&self) -> usize {
+        self.stack_base
+    }
+
+    /// get `NtTib.StackLimit`, lowest committed address of the stack
+    pub fn get_stack_limit(&self) -> usize {
+        self.stack_limit
+    }
+
+    /// get `ThreadLocalStoragePointer`
+    pub fn get_tls_pointer(&self) -> usize {
+        self.tls_pointer
+    }
+
+    /// get `ProcessEnvironmentBlock`
+    pub fn get_peb_address(&self) -> usize {
+        self.peb
+    }
+
+    /// value of tls `slot` allocated by `TlsAlloc`, zero when never set by the thread
+    pub fn read_tls_slot(&self, handle: &Handle, slot: u32) -> Result<usize> {
+        self.read_tls_slot_with(&|address, len| handle.read_memory(address, len), slot)
+    }
+
+    /// address of the static tls block of the image with tls `index`, look at
+    /// `Module::tls_index`
+    pub fn read_static_tls_block(&self, handle: &Handle, index: u32) -> Result<usize> {
+        if self.tls_pointer == 0 {
+            return Err(Error::NotFound);
+        }
+
+        let address = self.tls_pointer + index as usize * self.pointer_size;
+        Layout::of(self.pointer_size)
+            .pointer_at(&handle.read_memory(address, self.pointer_size)?, 0)
+    }
+
+    pub(crate) fn read_tls_slot_with(&self, read: &ReadFn, slot: u32) -> Result<usize> {
+        let layout = Layout::of(self.pointer_size);
+
+        let address = match slot.checked_sub(TLS_MINIMUM_AVAILABLE) {
+            None => self.address + layout.teb_tls_slots + slot as usize * self.pointer_size,
+            Some(slot) if slot < TLS_EXPANSION_SLOTS => {
+                let expansion = self.address + layout.teb_tls_expansion_slots;
+                let expansion = layout.pointer_at(&read(expansion, self.pointer_size)?, 0)?;
+                // NOTE: expansion array is allocated on first use of a slot past the TEB ones
+                if expansion == 0 {
+                    return Ok(0);
+                }
+                expansion + slot as usize * self.pointer_size
+            }
+            Some(_) => return Err(Error::InvalidInput),
+        };
+
+        layout.pointer_at(&read(address, self.pointer_size)?, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff_module_lists, parse_environment, read_loader_entries, Layout, Peb, Teb};
+    use crate::pe::tests::put;
+
+    #[test]
+    fn reading_blocks_of_both_bitness() {
+        let mut memory = vec![0u8; 0x400];
+        // 32 bit PEB at 0x100 and its parameters at 0x200
+        put(&mut memory, 0x108, &0x40_0000u32.to_le_bytes());
+        put(&mut memory, 0x10C, &0x7700u32.to_le_bytes());
+        put(&mut memory, 0x110, &0x200u32.to_le_bytes());
+        put(&mut memory, 0x240, &[4, 0, 6, 0, 0x80, 0x03, 0, 0]);
+        put(&mut memory, 0x380, &[b'a', 0, b'b', 0]);
+        // 64 bit TEB at 0x0
+        put(&mut memory, 0x8, &0x9000u64.to_le_bytes());
+        put(&mut memory, 0x60, &0x100u64.to_le_bytes());
+        let read = |address: usize, len: usize| Ok(memory[address..address + len].to_vec());
+
+        let peb = Peb::read_with(&read, 0x100, 4).unwrap();
+        assert_eq!(peb.get_image_base(), 0x40_0000);
+        assert_eq!(peb.get_ldr_address(), 0x7700);
+        assert_eq!(
+            Layout::of(4)
+                .read_unicode_string(&read, peb.get_process_parameters_address() + 0x40)
+                .unwrap(),
+            "ab"
+        );
+
+        let teb = Teb::read_with(&read, 0, 8).unwrap();
+        assert_eq!(teb.get_stack_base(), 0x9000);
+        assert_eq!(teb.get_peb_address(), 0x100);
+    }
+
+    #[test]
+    fn reading_tls_slots() {
+        let mut memory = vec![0u8; 0x1900];
+        // 64 bit TEB at 0x0 with expansion slots at 0x1800
+        put(&mut memory, 0x1480 + 3 * 8, &0xAAAAu64.to_le_bytes());
+        put(&mut memory, 0x1780, &0x1800u64.to_le_bytes());
+        put(&mut memory, 0x1800 + 6 * 8, &0xBBBBu64.to_le_bytes());
+        let read = |address: usize, len: usize| Ok(memory[address..address + len].to_vec());
+
+        let teb = Teb::read_with(&read, 0, 8).unwrap();
+        assert_eq!(teb.read_tls_slot_with(&read, 3).unwrap(), 0xAAAA);
+        assert_eq!(teb.read_tls_slot_with(&read, 70).unwrap(), 0xBBBB);
+        assert!(teb.read_tls_slot_with(&read, 64 + 1024).is_err());
+
+        memory[0x1780..0x1788].fill(0);
+        let read = |address: usize, len: usize| Ok(memory[address..address + len].to_vec());
+        assert_eq!(teb.read_tls_slot_with(&read, 70).unwrap(), 0);
+    }
+
+    #[test]
+    fn parsing_environment_block() {
+        assert_eq!(
+            parse_environment("=C:=C:\\\0PATH=a=b\0\0garbage"),
+            vec![
+                ("=C:".to_string(), "C:\\".to_string()),
+                ("PATH".to_string(), "a=b".to_string()),
+            ]
+        );
+        assert_eq!(
+            parse_environment("ÉCOLE=été\0é\0ü=\0\0"),
+            vec![
+                ("ÉCOLE".to_string(), "été".to_string()),
+                ("ü".to_string(), String::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn walking_loader_list() {
+        let mut memory = vec![0u8; 0x400];
+        // PEB_LDR_DATA at 0x0 with list head at 0x10, entries at 0x100 and 0x200
+        put(&mut memory, 0x10, &0x100u64.to_le_bytes());
+        for (entry, next, base, name) in [
+            (0x100, 0x200u64, 0x40_0000u64, 0x300),
+            (0x200, 0x10, 0x7FF0_0000, 0x380),
+        ] {
+            put(&mut memory, entry, &next.to_le_bytes());
+            put(&mut memory, entry + 0x30, &base.to_le_bytes());
+            put(&mut memory, entry + 0x40, &0x1000u32.to_le_bytes());
+            put(&mut memory, entry + 0x58, &[2, 0, 2, 0, 0, 0, 0, 0]);
+            put(&mut memory, entry + 0x60, &(name as u64).to_le_bytes());
+            put(&mut memory, name, &[b'm', 0]);
+        }
+        let read = |address: usize, len: usize| Ok(memory[address..address + len].to_vec());
+
+        let modules = read_loader_entries(&read, Layout::of(8), 0, 1).unwrap();
+        assert_eq!(modules.len(), 2);
+        assert_eq!(modules[1].get_address(), 0x7FF0_0000);
+        assert_eq!(modules[1].get_name(), "m");
+        assert_eq!(modules[1].get_path(), "");
+
+        let diff = diff_module_lists(&modules[..1], &modules[1..]);
+        assert_eq!(diff.get_only_in_peb()[0].get_address(), 0x40_0000);
+        assert_eq!(diff.get_only_in_snapshot()[0].get_address(), 0x7FF0_0000);
+    }
+}
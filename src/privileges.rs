@@ -0,0 +1,61 @@
+use windows::core::{HSTRING, PCWSTR};
+use windows::Win32::Foundation::{
+    CloseHandle, GetLastError, BOOL, ERROR_NOT_ALL_ASSIGNED, HANDLE, LUID,
+};
+use windows::Win32::Security::{
+    AdjustTokenPrivileges, LookupPrivilegeValueW, LUID_AND_ATTRIBUTES, SE_PRIVILEGE_ENABLED,
+    TOKEN_ADJUST_PRIVILEGES, TOKEN_PRIVILEGES, TOKEN_QUERY,
+};
+use windows::Win32::System::Threading::{GetCurrentProcess, OpenProcessToken};
+
+use crate::error::{Error, Result};
+
+/// name of the privilege needed to open processes of other users, including system processes
+pub const SE_DEBUG_NAME: &str = "SeDebugPrivilege";
+
+/// enable `SeDebugPrivilege` on the token of the current process.
+///
+/// only succeed when the process is elevated.
+pub fn enable_debug_privilege() -> Result<()> {
+    enable_privilege(SE_DEBUG_NAME)
+}
+
+/// enable privilege with the given name, like `SeDebugPrivilege`, on the token of the current process
+pub fn enable_privilege(name: &str) -> Result<()> {
+    let mut luid = LUID::default();
+    unsafe { LookupPrivilegeValueW(PCWSTR::null(), &HSTRING::from(name), &mut luid) }
+        .map_err(|e| Error::win32("LookupPrivilegeValueW", e))?;
+
+    let mut token = HANDLE::default();
+    unsafe {
+        OpenProcessToken(
+            GetCurrentProcess(),
+            TOKEN_ADJUST_PRIVILEGES | TOKEN_QUERY,
+            &mut token,
+        )
+    }
+    .map_err(|e| Error::win32("OpenProcessToken", e))?;
+
+    let token_privileges = TOKEN_PRIVILEGES {
+        PrivilegeCount: 1,
+        Privileges: [LUID_AND_ATTRIBUTES {
+            Luid: luid,
+            Attributes: SE_PRIVILEGE_ENABLED,
+        }],
+    };
+
+    let result =
+        unsafe { AdjustTokenPrivileges(token, BOOL(0), Some(&token_privileges), 0, None, None) }
+            .map_err(|e| Error::win32("AdjustTokenPrivileges", e))
+            .and_then(|_| {
+                // NOTE: AdjustTokenPrivileges succeed even when the token does not hold the privilege
+                if unsafe { GetLastError() } == ERROR_NOT_ALL_ASSIGNED {
+                    return Err(Error::last_win32("AdjustTokenPrivileges"));
+                }
+                Ok(())
+            });
+
+    let _ = unsafe { CloseHandle(token) };
+
+    result
+}
@@ -0,0 +1,527 @@
+use std::collections::{HashMap, HashSet};
+use std::mem::size_of;
+use std::ops::Deref;
+use std::time::{Duration, Instant};
+
+use windows::core::{HSTRING, PCWSTR, PWSTR};
+use windows::Win32::Foundation::BOOL;
+use windows::Win32::System::Diagnostics::ToolHelp::{
+    Process32FirstW, Process32NextW, PROCESSENTRY32W,
+};
+use windows::Win32::System::Threading::{
+    CreateProcessW, CREATE_SUSPENDED, PROCESS_INFORMATION, STARTUPINFOW,
+};
+
+use crate::error::{Error, Result};
+use crate::handle::{Handle, HandleAccess, HandleSnapshot, HandleSnapshotFlag};
+#[cfg(feature = "serde")]
+use crate::module::to_wide_array;
+use crate::thread::Thread;
+
+/// interval between polls of [Process::wait_for]
+const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// running processes of the system
+pub struct Process;
+
+impl Process {
+    /// iterator for every process running on the system
+    pub fn enumerate() -> Result<ProcessEntryIter> {
+        Ok(ProcessEntryIter {
+            snapshot: HandleSnapshot::try_new(HandleSnapshotFlag::SnapProcess, 0)?,
+            is_first: true,
+        })
+    }
+
+    /// processes started by the process with id `process_id`
+    pub fn children(process_id: u32) -> Result<Vec<ProcessEntry>> {
+        Ok(Self::enumerate()?
+            .filter(|e| e.get_parent_process_id() == process_id && e.get_process_id() != process_id)
+            .collect())
+    }
+
+    /// every running process arranged by parent, the roots are processes whose parent has
+    /// exited
+    pub fn tree() -> Result<Vec<ProcessTree>> {
+        Ok(build_tree(Self::enumerate()?.collect()))
+    }
+
+    /// open the first process named `name` ignoring case, waiting for it to start.
+    ///
+    /// wait forever when `timeout` is `None`.
+    pub fn wait_for(name: &str, timeout: Option<Duration>) -> Result<Handle> {
+        let deadline = timeout.map(|e| Instant::now() + e);
+
+        loop {
+            match Handle::try_from_name(name) {
+                Err(Error::NotFound) => (),
+                result => return result,
+            }
+
+            if deadline.is_some_and(|e| Instant::now() >= e) {
+                return Err(Error::Timeout);
+            }
+            std::thread::sleep(WAIT_POLL_INTERVAL);
+        }
+    }
+
+    /// start executable at `path` with `args` without running its first instruction.
+    ///
+    /// returns handle of the process and its main thread, resume the thread to start it.
+    pub fn spawn_suspended(path: &str, args: &[&str]) -> Result<(Handle, Thread)> {
+        let mut command_line: Vec<u16> = build_command_line(path, args)
+            .encode_utf16()
+            .chain([0])
+            .collect();
+
+        let startup_info = STARTUPINFOW {
+            cb: size_of::<STARTUPINFOW>() as u32,
+            ..Default::default()
+        };
+        let mut process_information = PROCESS_INFORMATION::default();
+
+        unsafe {
+            CreateProcessW(
+                &HSTRING::from(path),
+                PWSTR(command_line.as_mut_ptr()),
+                None,
+                None,
+                BOOL(0),
+                CREATE_SUSPENDED,
+                None,
+                PCWSTR::null(),
+                &startup_info,
+                &mut process_information,
+            )
+        }
+        .map_err(|e| Error::win32("CreateProcessW", e))?;
+
+        Ok((
+            Handle::from_raw(
+                process_information.hProcess,
+                process_information.dwProcessId,
+                HandleAccess::All,
+            ),
+            Thread::from_raw(process_information.hThread, process_information.dwThreadId),
+        ))
+    }
+}
+
+/// command line of `path` and `args`, quoted the way `CommandLineToArgvW` split it back
+fn build_command_line(path: &str, args: &[&str]) -> String {
+    std::iter::once(path)
+        .chain(args.iter().copied())
+        .map(quote_argument)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn quote_argument(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains([' ', '\t', '\n', '"']) {
+        return arg.to_string();
+    }
+
+    let mut quoted = String::from('"');
+    let mut backslashes = 0usize;
+    for c in arg.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                quoted.extend(std::iter::repeat_n('\\', backslashes * 2 + 1));
+                quoted.push('"');
+                backslashes = 0;
+            }
+            _ => {
+                quoted.extend(std::iter::repeat_n('\\', backslashes));
+                quoted.push(c);
+                backslashes = 0;
+            }
+        }
+    }
+    quoted.extend(std::iter::repeat_n('\\', backslashes * 2));
+    quoted.push('"');
+
+    quoted
+}
+
+/// Look at [PROCESSENTRY32W structure (tlhelp32.h) - Win32 API](https://learn.microsoft.com/en-us/windows/win32/api/tlhelp32/ns-tlhelp32-processentry32w)
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "ProcessEntryRepr", into = "ProcessEntryRepr")
+)]
+pub struct ProcessEntry(PROCESSENTRY32W);
+
+impl ProcessEntry {
+    /// get `th32ProcessID`
+    pub fn get_process_id(&self) -> u32 {
+        self.0.th32ProcessID
+    }
+
+    /// get `th32ParentProcessID`
+    pub fn get_parent_process_id(&self) -> u32 {
+        self.0.th32ParentProcessID
+    }
+
+    /// get `cntThreads`
+    pub fn get_thread_count(&self) -> u32 {
+        self.0.cntThreads
+    }
+
+    /// get `szExeFile`
+    pub fn get_name(&self) -> String {
+        String::from_utf16_lossy(&self.0.szExeFile)
+            .trim_end_matches("\u{0}")
+            .to_string()
+    }
+}
+
+impl Deref for ProcessEntry {
+    type Target = PROCESSENTRY32W;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<PROCESSENTRY32W> for ProcessEntry {
+    fn from(value: PROCESSENTRY32W) -> Self {
+        Self(value)
+    }
+}
+
+/// serialized form of [ProcessEntry]
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct ProcessEntryRepr {
+    process_id: u32,
+    parent_process_id: u32,
+    thread_count: u32,
+    name: String,
+}
+
+#[cfg(feature = "serde")]
+impl From<ProcessEntry> for ProcessEntryRepr {
+    fn from(value: ProcessEntry) -> Self {
+        Self {
+            process_id: value.get_process_id(),
+            parent_process_id: value.get_parent_process_id(),
+            thread_count: value.get_thread_count(),
+            name: value.get_name(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<ProcessEntryRepr> for ProcessEntry {
+    fn from(value: ProcessEntryRepr) -> Self {
+        Self(PROCESSENTRY32W {
+            dwSize: size_of::<PROCESSENTRY32W>() as u32,
+            th32ProcessID: value.process_id,
+            th32ParentProcessID: value.parent_process_id,
+            cntThreads: value.thread_count,
+            szExeFile: to_wide_array(&value.name),
+            ..Default::default()
+        })
+    }
+}
+
+/// System Snapshot -> Process Entry Iterator
+pub struct ProcessEntryIter {
+    snapshot: HandleSnapshot,
+    is_first: bool,
+}
+
+impl Iterator for ProcessEntryIter {
+    type Item = ProcessEntry;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        next_process_entry(&self.snapshot, &mut self.is_first)
+    }
+}
+
+pub(crate) fn next_process_entry(
+    snapshot: &HandleSnapshot,
+    is_first: &mut bool,
+) -> Option<ProcessEntry> {
+    let mut process_entry_32w = PROCESSENTRY32W {
+        dwSize: size_of::<PROCESSENTRY32W>() as u32,
+        ..Default::default()
+    };
+
+    let result = if *is_first {
+        *is_first = false;
+        unsafe { Process32FirstW(**snapshot, &mut process_entry_32w as *mut _) }
+    } else {
+        unsafe { Process32NextW(**snapshot, &mut process_entry_32w as *mut _) }
+    };
+
+    result.ok().map(|_| ProcessEntry::from(process_entry_32w))
+}
+
+/// kind of protection of a protected process
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProtectionType {
+    /// not protected
+    None,
+    /// protected process light, PPL
+    Light,
+    /// protected process
+    Full,
+    /// type unknown to the crate
+    Other(u8),
+}
+
+/// signer that decide which protected process can access which
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ProtectionSigner {
+    /// no signer
+    None,
+    /// `PsProtectedSignerAuthenticode`
+    Authenticode,
+    /// `PsProtectedSignerCodeGen`
+    CodeGen,
+    /// `PsProtectedSignerAntimalware`
+    Antimalware,
+    /// `PsProtectedSignerLsa`
+    Lsa,
+    /// `PsProtectedSignerWindows`
+    Windows,
+    /// `PsProtectedSignerWinTcb`
+    WinTcb,
+    /// `PsProtectedSignerWinSystem`
+    WinSystem,
+    /// `PsProtectedSignerApp`
+    App,
+    /// signer unknown to the crate
+    Other(u8),
+}
+
+/// process with the processes it started
+#[derive(Clone)]
+pub struct ProcessTree {
+    entry: ProcessEntry,
+    children: Vec<ProcessTree>,
+}
+
+impl ProcessTree {
+    /// the process
+    pub fn get_entry(&self) -> &ProcessEntry {
+        &self.entry
+    }
+
+    /// processes started by the process
+    pub fn get_children(&self) -> &[ProcessTree] {
+        &self.children
+    }
+
+    /// the process followed by every process below it, depth first
+    pub fn flatten(&self) -> Vec<&ProcessEntry> {
+        let mut entries = vec![&self.entry];
+        for child in &self.children {
+            entries.extend(child.flatten());
+        }
+
+        entries
+    }
+
+    /// find the tree of the process with id `process_id` below the process
+    pub fn find(&self, process_id: u32) -> Option<&ProcessTree> {
+        if self.entry.get_process_id() == process_id {
+            return Some(self);
+        }
+
+        self.children.iter().find_map(|e| e.find(process_id))
+    }
+}
+
+/// arrange `entries` by parent.
+///
+/// parent process id may be reused by a newer process after the parent exited, so a process
+/// is attached once and loops end up as roots.
+fn build_tree(entries: Vec<ProcessEntry>) -> Vec<ProcessTree> {
+    let mut children: HashMap<u32, Vec<usize>> = HashMap::new();
+    let process_ids: HashSet<u32> = entries.iter().map(|e| e.get_process_id()).collect();
+    for (index, entry) in entries.iter().enumerate() {
+        children
+            .entry(entry.get_parent_process_id())
+            .or_default()
+            .push(index);
+    }
+
+    fn attach(
+        index: usize,
+        entries: &[ProcessEntry],
+        children: &HashMap<u32, Vec<usize>>,
+        is_attached: &mut [bool],
+    ) -> ProcessTree {
+        is_attached[index] = true;
+        let entry = entries[index];
+
+        let mut trees = Vec::new();
+        for child in children.get(&entry.get_process_id()).into_iter().flatten() {
+            if !is_attached[*child] {
+                trees.push(attach(*child, entries, children, is_attached));
+            }
+        }
+
+        ProcessTree {
+            entry,
+            children: trees,
+        }
+    }
+
+    let mut is_attached = vec![false; entries.len()];
+    let mut roots = Vec::new();
+    let is_root = |entry: &ProcessEntry| {
+        entry.get_parent_process_id() == entry.get_process_id()
+            || !process_ids.contains(&entry.get_parent_process_id())
+    };
+    let (root_indexes, other_indexes): (Vec<usize>, Vec<usize>) =
+        (0..entries.len()).partition(|e| is_root(&entries[*e]));
+    for index in root_indexes.into_iter().chain(other_indexes) {
+        if !is_attached[index] {
+            roots.push(attach(index, &entries, &children, &mut is_attached));
+        }
+    }
+
+    roots
+}
+
+/// `PS_PROTECTION` of a process, packing its protection type, audit flag and signer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ProtectionLevel(u8);
+
+impl ProtectionLevel {
+    /// kind of protection
+    pub fn get_type(&self) -> ProtectionType {
+        match self.0 & 0x7 {
+            0 => ProtectionType::None,
+            1 => ProtectionType::Light,
+            2 => ProtectionType::Full,
+            other => ProtectionType::Other(other),
+        }
+    }
+
+    /// signer of the protection
+    pub fn get_signer(&self) -> ProtectionSigner {
+        match self.0 >> 4 {
+            0 => ProtectionSigner::None,
+            1 => ProtectionSigner::Authenticode,
+            2 => ProtectionSigner::CodeGen,
+            3 => ProtectionSigner::Antimalware,
+            4 => ProtectionSigner::Lsa,
+            5 => ProtectionSigner::Windows,
+            6 => ProtectionSigner::WinTcb,
+            7 => ProtectionSigner::WinSystem,
+            8 => ProtectionSigner::App,
+            other => ProtectionSigner::Other(other),
+        }
+    }
+
+    /// whether violation of the protection is only audited
+    pub fn is_audit(&self) -> bool {
+        self.0 & 0x8 != 0
+    }
+
+    /// whether the process is protected, memory access is denied to unprotected callers
+    pub fn is_protected(&self) -> bool {
+        self.get_type() != ProtectionType::None
+    }
+}
+
+impl From<u8> for ProtectionLevel {
+    fn from(value: u8) -> Self {
+        Self(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        build_command_line, build_tree, ProcessEntry, ProtectionLevel, ProtectionSigner,
+        ProtectionType,
+    };
+    use windows::Win32::System::Diagnostics::ToolHelp::PROCESSENTRY32W;
+
+    #[test]
+    fn quoting_command_line() {
+        assert_eq!(
+            build_command_line(
+                "C:\\Program Files\\game.exe",
+                &["-w", "", "say \"hi\"", "dir\\"]
+            ),
+            r#""C:\Program Files\game.exe" -w "" "say \"hi\"" dir\"#
+        );
+        assert_eq!(build_command_line("a.exe", &["x y\\"]), r#"a.exe "x y\\""#);
+    }
+
+    #[test]
+    fn decoding_protection_level() {
+        // PsProtectedSignerWinTcb-Light, like csrss.exe
+        let level = ProtectionLevel::from(0x61);
+        assert_eq!(level.get_type(), ProtectionType::Light);
+        assert_eq!(level.get_signer(), ProtectionSigner::WinTcb);
+        assert!(level.is_protected());
+        assert!(!level.is_audit());
+
+        assert!(!ProtectionLevel::from(0).is_protected());
+    }
+
+    #[test]
+    fn building_process_tree() {
+        let entry = |process_id: u32, parent_process_id: u32| {
+            ProcessEntry::from(PROCESSENTRY32W {
+                th32ProcessID: process_id,
+                th32ParentProcessID: parent_process_id,
+                ..Default::default()
+            })
+        };
+        // 0 is its own parent, 30 parent exited, 40 and 50 parent each other by reused id
+        let entries = vec![
+            entry(0, 0),
+            entry(4, 0),
+            entry(10, 4),
+            entry(11, 10),
+            entry(30, 99),
+            entry(40, 50),
+            entry(50, 40),
+        ];
+
+        let roots = build_tree(entries);
+        let ids = |tree: &super::ProcessTree| -> Vec<u32> {
+            tree.flatten().iter().map(|e| e.get_process_id()).collect()
+        };
+
+        assert_eq!(roots.len(), 3);
+        assert_eq!(ids(&roots[0]), vec![0, 4, 10, 11]);
+        assert_eq!(ids(&roots[1]), vec![30]);
+        assert_eq!(ids(&roots[2]), vec![40, 50]);
+        assert_eq!(roots[0].find(10).map(|e| e.get_children().len()), Some(1));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serializing_process_entries() {
+        use std::mem::size_of;
+
+        let mut exe_file = [0u16; 260];
+        exe_file[..6].copy_from_slice(&"a.exe\0".encode_utf16().collect::<Vec<_>>());
+        let entry = ProcessEntry::from(PROCESSENTRY32W {
+            dwSize: size_of::<PROCESSENTRY32W>() as u32,
+            th32ProcessID: 10,
+            th32ParentProcessID: 4,
+            cntThreads: 3,
+            szExeFile: exe_file,
+            ..Default::default()
+        });
+
+        let json = serde_json::to_string(&entry).unwrap();
+        assert_eq!(
+            json,
+            r#"{"process_id":10,"parent_process_id":4,"thread_count":3,"name":"a.exe"}"#
+        );
+        assert!(serde_json::from_str::<ProcessEntry>(&json).unwrap() == entry);
+    }
+}
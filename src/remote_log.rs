@@ -0,0 +1,479 @@
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+
+use windows::core::HSTRING;
+use windows::Win32::Foundation::{CloseHandle, BOOL, HANDLE, WAIT_OBJECT_0, WAIT_TIMEOUT};
+use windows::Win32::System::Threading::{
+    CreateEventW, GetCurrentProcessId, OpenEventW, SetEvent, WaitForSingleObject,
+    EVENT_MODIFY_STATE, INFINITE,
+};
+
+use crate::error::{Error, Result};
+use crate::shared::SharedSection;
+
+/// bytes of records the ring buffer hold, power of two so positions can wrap around
+const LOG_CAPACITY: usize = 0x10000;
+/// size of the header before the ring buffer, write position, read position, dropped count,
+/// writer lock and the last lock token handed out
+const HEADER_SIZE: usize = 20;
+/// size of the header of every record, length of the message followed by its level
+const RECORD_HEADER_SIZE: usize = 4;
+/// longest message in bytes, longer one is cut short
+const MAX_MESSAGE_LEN: usize = 0x1000;
+/// longest a writer wait for the lock held by the same writer, before taking it over
+const LOCK_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// how important a log record is
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LogLevel {
+    /// step by step tracing
+    Trace = 0,
+    /// detail useful while debugging
+    Debug = 1,
+    /// normal operation
+    Info = 2,
+    /// something unexpected but recoverable
+    Warn = 3,
+    /// something failed
+    Error = 4,
+}
+
+impl TryFrom<u8> for LogLevel {
+    type Error = Error;
+
+    fn try_from(value: u8) -> std::result::Result<Self, Error> {
+        match value {
+            0 => Ok(Self::Trace),
+            1 => Ok(Self::Debug),
+            2 => Ok(Self::Info),
+            3 => Ok(Self::Warn),
+            4 => Ok(Self::Error),
+            _ => Err(Error::InvalidInput),
+        }
+    }
+}
+
+/// message written by [RemoteLogWriter]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogRecord {
+    level: LogLevel,
+    message: String,
+}
+
+impl LogRecord {
+    /// how important the message is
+    pub fn get_level(&self) -> LogLevel {
+        self.level
+    }
+
+    /// text of the message
+    pub fn get_message(&self) -> &str {
+        &self.message
+    }
+}
+
+/// Reading side of a log channel from code injected into a process.
+///
+/// the channel is a ring buffer in a named section shared with the process, plus a named
+/// event signaled on every record. both are named after the id of the process, so the
+/// injected code open them with [RemoteLogWriter::open] without being told anything.
+pub struct RemoteLog {
+    section: SharedSection,
+    event: HANDLE,
+}
+
+impl RemoteLog {
+    /// create the channel of the process with the id, before the writer is injected into it
+    pub fn attach(process_id: u32) -> Result<Self> {
+        let section = SharedSection::create_shared(
+            Some(&section_name(process_id)),
+            HEADER_SIZE + LOG_CAPACITY,
+        )?;
+        let event = unsafe {
+            CreateEventW(
+                None,
+                BOOL(0),
+                BOOL(0),
+                &HSTRING::from(event_name(process_id)),
+            )
+        }
+        .map_err(|e| Error::win32("CreateEventW", e))?;
+
+        Ok(Self { section, event })
+    }
+
+    /// next record when there is one, without waiting
+    pub fn try_recv(&self) -> Option<LogRecord> {
+        ring(&self.section).pop()
+    }
+
+    /// next record, waiting for it to be written.
+    ///
+    /// wait forever when `timeout` is `None`.
+    pub fn recv(&self, timeout: Option<Duration>) -> Result<LogRecord> {
+        let deadline = timeout.map(|e| Instant::now() + e);
+
+        loop {
+            if let Some(record) = self.try_recv() {
+                return Ok(record);
+            }
+
+            let milliseconds = deadline.map_or(INFINITE, |e| {
+                let remaining = e.saturating_duration_since(Instant::now());
+                remaining.as_millis().min(INFINITE as u128 - 1) as u32
+            });
+            match unsafe { WaitForSingleObject(self.event, milliseconds) } {
+                WAIT_OBJECT_0 => (),
+                WAIT_TIMEOUT => return Err(Error::Timeout),
+                _ => return Err(Error::last_win32("WaitForSingleObject")),
+            }
+        }
+    }
+
+    /// number of records the writer dropped because the ring buffer was full
+    pub fn get_dropped(&self) -> u32 {
+        ring(&self.section).dropped().load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for RemoteLog {
+    fn drop(&mut self) {
+        let _ = unsafe { CloseHandle(self.event) };
+    }
+}
+
+/// Writing side of a log channel, embedded in code injected into a process.
+///
+/// writing only wait for other threads writing at the same time, record is dropped when the
+/// reader is not keeping up.
+pub struct RemoteLogWriter {
+    section: SharedSection,
+    event: HANDLE,
+}
+
+impl RemoteLogWriter {
+    /// open the channel [RemoteLog::attach] created for the current process
+    pub fn open() -> Result<Self> {
+        let process_id = unsafe { GetCurrentProcessId() };
+
+        let section = SharedSection::open(&section_name(process_id), HEADER_SIZE + LOG_CAPACITY)?;
+        let event = unsafe {
+            OpenEventW(
+                EVENT_MODIFY_STATE,
+                BOOL(0),
+                &HSTRING::from(event_name(process_id)),
+            )
+        }
+        .map_err(|e| Error::win32("OpenEventW", e))?;
+
+        Ok(Self { section, event })
+    }
+
+    /// write `message` cut short to 4096 bytes, `false` when it was dropped
+    pub fn write(&self, level: LogLevel, message: &str) -> bool {
+        let is_written = ring(&self.section).push(level, message);
+        if is_written {
+            let _ = unsafe { SetEvent(self.event) };
+        }
+
+        is_written
+    }
+}
+
+impl Drop for RemoteLogWriter {
+    fn drop(&mut self) {
+        let _ = unsafe { CloseHandle(self.event) };
+    }
+}
+
+fn section_name(process_id: u32) -> String {
+    format!("Local\\winmem-log-{}", process_id)
+}
+
+fn event_name(process_id: u32) -> String {
+    format!("Local\\winmem-log-event-{}", process_id)
+}
+
+fn ring(section: &SharedSection) -> Ring<'_> {
+    Ring {
+        base: section.as_ptr(),
+        lifetime: PhantomData,
+    }
+}
+
+/// ring buffer of records shared between writers taking turns and one reader, laid out as the
+/// header followed by [LOG_CAPACITY] bytes of records
+struct Ring<'a> {
+    base: *mut u8,
+    lifetime: PhantomData<&'a ()>,
+}
+
+impl Ring<'_> {
+    fn position(&self, index: usize) -> &AtomicU32 {
+        unsafe { &*(self.base.add(index * size_of::<u32>()) as *const AtomicU32) }
+    }
+
+    /// total bytes written, wrapping around
+    fn write_position(&self) -> &AtomicU32 {
+        self.position(0)
+    }
+
+    /// total bytes read, wrapping around
+    fn read_position(&self) -> &AtomicU32 {
+        self.position(1)
+    }
+
+    fn dropped(&self) -> &AtomicU32 {
+        self.position(2)
+    }
+
+    /// token of the writer appending, zero when none
+    fn writer_lock(&self) -> &AtomicU32 {
+        self.position(3)
+    }
+
+    /// last token handed out to a writer, shared by every writer of the section
+    fn lock_sequence(&self) -> &AtomicU32 {
+        self.position(4)
+    }
+
+    /// take the writer lock, returning the token it is held with
+    fn lock(&self) -> u32 {
+        let next_token = || {
+            self.lock_sequence()
+                .fetch_add(1, Ordering::Relaxed)
+                .wrapping_add(1)
+        };
+        let token = match next_token() {
+            0 => next_token(),
+            token => token,
+        };
+
+        let mut holder = 0;
+        let mut held_since = Instant::now();
+        loop {
+            let current = match self.writer_lock().compare_exchange_weak(
+                0,
+                token,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return token,
+                Err(current) => current,
+            };
+
+            if current != holder {
+                holder = current;
+                held_since = Instant::now();
+            } else if held_since.elapsed() >= LOCK_TIMEOUT
+                // NOTE: the same writer holding it that long died or was killed meanwhile
+                && self
+                    .writer_lock()
+                    .compare_exchange(current, token, Ordering::Acquire, Ordering::Relaxed)
+                    .is_ok()
+            {
+                return token;
+            }
+            std::thread::yield_now();
+        }
+    }
+
+    /// release the writer lock, unless it was taken over meanwhile
+    fn unlock(&self, token: u32) {
+        let _ = self
+            .writer_lock()
+            .compare_exchange(token, 0, Ordering::Release, Ordering::Relaxed);
+    }
+
+    /// append the record, `false` when there is no room for it
+    fn push(&self, level: LogLevel, message: &str) -> bool {
+        let message = truncate(message, MAX_MESSAGE_LEN).as_bytes();
+        let len = RECORD_HEADER_SIZE + message.len();
+
+        // NOTE: writers may come from separate modules each opening its own section view, only
+        // the section itself is shared between them
+        let token = self.lock();
+
+        let write = self.write_position().load(Ordering::Relaxed);
+        let read = self.read_position().load(Ordering::Acquire);
+        let is_room = write.wrapping_sub(read) as usize + len <= LOG_CAPACITY;
+        if is_room {
+            let [len_low, len_high] = (message.len() as u16).to_le_bytes();
+            self.copy_in(write, &[len_low, len_high, level as u8, 0]);
+            self.copy_in(write.wrapping_add(RECORD_HEADER_SIZE as u32), message);
+            self.write_position()
+                .store(write.wrapping_add(len as u32), Ordering::Release);
+        } else {
+            self.dropped().fetch_add(1, Ordering::Relaxed);
+        }
+
+        self.unlock(token);
+
+        is_room
+    }
+
+    /// take the oldest record
+    fn pop(&self) -> Option<LogRecord> {
+        let read = self.read_position().load(Ordering::Relaxed);
+        let write = self.write_position().load(Ordering::Acquire);
+        let available = write.wrapping_sub(read) as usize;
+        if available == 0 {
+            return None;
+        }
+
+        let header = self.copy_out(read, RECORD_HEADER_SIZE);
+        let len = u16::from_le_bytes([header[0], header[1]]) as usize;
+        // NOTE: positions are written by the other process, skip everything on garbage
+        if RECORD_HEADER_SIZE + len > available {
+            self.read_position().store(write, Ordering::Release);
+            return None;
+        }
+
+        let message = self.copy_out(read.wrapping_add(RECORD_HEADER_SIZE as u32), len);
+        self.read_position().store(
+            read.wrapping_add((RECORD_HEADER_SIZE + len) as u32),
+            Ordering::Release,
+        );
+
+        Some(LogRecord {
+            level: LogLevel::try_from(header[2]).unwrap_or(LogLevel::Info),
+            message: String::from_utf8_lossy(&message).into_owned(),
+        })
+    }
+
+    fn copy_in(&self, position: u32, bytes: &[u8]) {
+        let offset = position as usize % LOG_CAPACITY;
+        let first = bytes.len().min(LOG_CAPACITY - offset);
+
+        unsafe {
+            let data = self.base.add(HEADER_SIZE);
+            std::ptr::copy_nonoverlapping(bytes.as_ptr(), data.add(offset), first);
+            std::ptr::copy_nonoverlapping(bytes[first..].as_ptr(), data, bytes.len() - first);
+        }
+    }
+
+    fn copy_out(&self, position: u32, len: usize) -> Vec<u8> {
+        let offset = position as usize % LOG_CAPACITY;
+        let first = len.min(LOG_CAPACITY - offset);
+
+        let mut buf = vec![0u8; len];
+        unsafe {
+            let data = self.base.add(HEADER_SIZE);
+            std::ptr::copy_nonoverlapping(data.add(offset), buf.as_mut_ptr(), first);
+            std::ptr::copy_nonoverlapping(data, buf[first..].as_mut_ptr(), len - first);
+        }
+
+        buf
+    }
+}
+
+/// longest prefix of `s` up to `max_len` bytes ending on a char boundary
+fn truncate(s: &str, max_len: usize) -> &str {
+    let mut end = s.len().min(max_len);
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    &s[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::marker::PhantomData;
+    use std::sync::atomic::Ordering;
+    use std::time::Instant;
+
+    use super::{LogLevel, Ring, HEADER_SIZE, LOCK_TIMEOUT, LOG_CAPACITY, MAX_MESSAGE_LEN};
+
+    #[test]
+    fn passing_records_through_ring() {
+        let mut buffer = vec![0u32; (HEADER_SIZE + LOG_CAPACITY) / 4];
+        let ring = Ring {
+            base: buffer.as_mut_ptr() as *mut u8,
+            lifetime: PhantomData,
+        };
+
+        assert!(ring.pop().is_none());
+        assert!(ring.push(LogLevel::Warn, "hello"));
+        let record = ring.pop().unwrap();
+        assert_eq!(record.get_level(), LogLevel::Warn);
+        assert_eq!(record.get_message(), "hello");
+
+        // fill it up, then wrap around the end after making room
+        let message = "x".repeat(MAX_MESSAGE_LEN + 10);
+        let mut pushed = 0;
+        while ring.push(LogLevel::Info, &message) {
+            pushed += 1;
+        }
+        assert_eq!(ring.dropped().load(Ordering::Relaxed), 1);
+        assert_eq!(ring.pop().unwrap().get_message().len(), MAX_MESSAGE_LEN);
+        assert!(ring.push(LogLevel::Info, &message));
+        assert!(ring.push(LogLevel::Error, "é".repeat(10).as_str()));
+
+        for _ in 0..pushed {
+            assert_eq!(ring.pop().unwrap().get_level(), LogLevel::Info);
+        }
+        assert_eq!(ring.pop().unwrap().get_message(), "é".repeat(10));
+        assert!(ring.pop().is_none());
+    }
+
+    #[test]
+    fn pushing_from_many_threads() {
+        let mut buffer = vec![0u32; (HEADER_SIZE + LOG_CAPACITY) / 4];
+        let base = buffer.as_mut_ptr() as usize;
+
+        std::thread::scope(|scope| {
+            for index in 0..4 {
+                scope.spawn(move || {
+                    // NOTE: every thread open its own ring over the same memory, like writers
+                    // from separate modules
+                    let ring = Ring {
+                        base: base as *mut u8,
+                        lifetime: PhantomData,
+                    };
+                    for _ in 0..100 {
+                        assert!(ring.push(LogLevel::Info, &format!("thread {}", index)));
+                    }
+                });
+            }
+        });
+
+        let ring = Ring {
+            base: buffer.as_mut_ptr() as *mut u8,
+            lifetime: PhantomData,
+        };
+        let mut count = 0;
+        while let Some(record) = ring.pop() {
+            assert!(record.get_message().starts_with("thread "));
+            count += 1;
+        }
+        assert_eq!(count, 400);
+        assert_eq!(ring.writer_lock().load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn taking_over_abandoned_lock() {
+        let mut buffer = vec![0u32; (HEADER_SIZE + LOG_CAPACITY) / 4];
+        let ring = Ring {
+            base: buffer.as_mut_ptr() as *mut u8,
+            lifetime: PhantomData,
+        };
+
+        // writer holding the lock is gone without releasing it
+        ring.writer_lock().store(7, Ordering::Relaxed);
+        let start = Instant::now();
+        assert!(ring.push(LogLevel::Info, "after"));
+        assert!(start.elapsed() >= LOCK_TIMEOUT);
+        assert_eq!(ring.writer_lock().load(Ordering::Relaxed), 0);
+        assert_eq!(ring.pop().unwrap().get_message(), "after");
+
+        // only the token holding it release it
+        let token = ring.lock();
+        ring.unlock(token.wrapping_add(1));
+        assert_eq!(ring.writer_lock().load(Ordering::Relaxed), token);
+        ring.unlock(token);
+        assert_eq!(ring.writer_lock().load(Ordering::Relaxed), 0);
+    }
+}
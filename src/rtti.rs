@@ -0,0 +1,439 @@
+use std::fmt::{Display, Formatter};
+
+use crate::backend::MemoryBackend;
+use crate::error::{Error, Result};
+use crate::handle::{Handle, HandleSnapshotFlag};
+use crate::memory::format_address;
+use crate::module::Module;
+use crate::patch::MemorySection;
+use crate::pe::{read_cstr, u32_at, ReadFn};
+use crate::scanner::{Scanner, Signature};
+
+/// `Signature` of complete object locator in 32 bit image, holding absolute addresses
+const SIGNATURE_32: u32 = 0;
+/// `Signature` of complete object locator in 64 bit image, holding image relative addresses
+const SIGNATURE_64: u32 = 1;
+/// size of `RTTICompleteObjectLocator` with `pSelf` of 64 bit image
+const LOCATOR_SIZE: usize = 24;
+
+/// Look at `RTTICompleteObjectLocator`, pointed to by the slot right before a vtable
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CompleteObjectLocator {
+    address: usize,
+    offset: u32,
+    constructor_offset: u32,
+    type_descriptor: usize,
+    class_descriptor: usize,
+}
+
+impl CompleteObjectLocator {
+    /// address of the locator
+    pub fn get_address(&self) -> usize {
+        self.address
+    }
+
+    /// get `offset`, offset of the vtable pointer inside the complete object
+    pub fn get_offset(&self) -> u32 {
+        self.offset
+    }
+
+    /// get `cdOffset`, constructor displacement offset
+    pub fn get_constructor_offset(&self) -> u32 {
+        self.constructor_offset
+    }
+
+    /// absolute address of `pTypeDescriptor`, holding the mangled class name
+    pub fn get_type_descriptor(&self) -> usize {
+        self.type_descriptor
+    }
+
+    /// absolute address of `pClassDescriptor`, describing the class hierarchy
+    pub fn get_class_descriptor(&self) -> usize {
+        self.class_descriptor
+    }
+}
+
+/// class a vtable belongs to, read from MSVC RTTI
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RttiClass {
+    mangled_name: String,
+    locator: CompleteObjectLocator,
+}
+
+impl RttiClass {
+    /// decorated name of the type descriptor, like `.?AVPlayer@Game@@`
+    pub fn get_mangled_name(&self) -> &str {
+        &self.mangled_name
+    }
+
+    /// readable name like `Game::Player`, the decorated name when it cannot be undecorated
+    pub fn get_name(&self) -> String {
+        demangle_type_name(&self.mangled_name).unwrap_or_else(|| self.mangled_name.clone())
+    }
+
+    /// complete object locator of the vtable
+    pub fn get_locator(&self) -> &CompleteObjectLocator {
+        &self.locator
+    }
+}
+
+/// function pointer of a vtable
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VTableFunction {
+    address: usize,
+    module_name: Option<String>,
+    module_offset: usize,
+}
+
+impl VTableFunction {
+    /// address of the function
+    pub fn get_address(&self) -> usize {
+        self.address
+    }
+
+    /// name of the module containing the function, `None` for code outside of any module
+    pub fn get_module_name(&self) -> Option<&str> {
+        self.module_name.as_deref()
+    }
+
+    /// offset of the function from the start of its module
+    pub fn get_module_offset(&self) -> usize {
+        self.module_offset
+    }
+}
+
+/// format as `0x7ff6_1000_1234 game.exe+0x1234`
+impl Display for VTableFunction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", format_address(self.address))?;
+        if let Some(module_name) = &self.module_name {
+            write!(f, " {}+{:#x}", module_name, self.module_offset)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// vtable of an object with its first functions
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VTable {
+    address: usize,
+    class: Option<RttiClass>,
+    functions: Vec<VTableFunction>,
+}
+
+impl VTable {
+    /// address of the vtable
+    pub fn get_address(&self) -> usize {
+        self.address
+    }
+
+    /// class of the vtable, `None` when the image has no RTTI for it
+    pub fn get_class(&self) -> Option<&RttiClass> {
+        self.class.as_ref()
+    }
+
+    /// function pointers of the vtable, in slot order
+    pub fn get_functions(&self) -> &[VTableFunction] {
+        &self.functions
+    }
+}
+
+/// vtable of a class found by [Scanner::find_vtables]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClassVTable {
+    address: usize,
+    class: RttiClass,
+}
+
+impl ClassVTable {
+    /// address of the vtable, what objects of the class point to at their start
+    pub fn get_address(&self) -> usize {
+        self.address
+    }
+
+    /// class of the vtable
+    pub fn get_class(&self) -> &RttiClass {
+        &self.class
+    }
+}
+
+impl<B: MemoryBackend + ?Sized> Scanner<'_, B> {
+    /// every vtable in the memory section of the class with the decorated name, like
+    /// `.?AVPlayer@@`.
+    ///
+    /// type descriptors are found by their name, then the complete object locators pointing to
+    /// them, then the vtables right after a pointer to a locator. class with multiple inheritance
+    /// has a vtable for each base, told apart by [CompleteObjectLocator::get_offset].
+    pub fn find_vtables(
+        &self,
+        mangled_name: &str,
+        section: MemorySection,
+    ) -> Result<Vec<ClassVTable>> {
+        if !mangled_name.starts_with(".?A") {
+            return Err(Error::InvalidInput);
+        }
+        let backend = self.get_backend();
+        let pointer_size = backend.get_pointer_size()?;
+
+        let mut name = mangled_name.as_bytes().to_vec();
+        name.push(0);
+        let name = Signature::from_mask(&name, &"x".repeat(name.len()))?;
+
+        // NOTE: every stage scan for all of its signatures at once, a class may have many
+        // type descriptors and locators
+        let (type_descriptors, signatures): (Vec<usize>, Vec<Signature>) = self
+            .scan(&name, section)?
+            .into_iter()
+            .filter_map(|e| e.checked_sub(pointer_size * 2))
+            .filter_map(|type_descriptor| {
+                let image_base = backend
+                    .query_memory(type_descriptor)
+                    .map_or(0, |e| e.get_allocation_base());
+                let signature = locator_signature(type_descriptor, image_base, pointer_size)?;
+                Some((type_descriptor, signature))
+            })
+            .unzip();
+
+        let mut locators = Vec::new();
+        for (type_descriptor, addresses) in type_descriptors
+            .into_iter()
+            .zip(self.scan_many(&signatures, section)?)
+        {
+            locators.extend(addresses.into_iter().filter_map(|address| {
+                let data = backend.read_memory(address, LOCATOR_SIZE).ok()?;
+                parse_complete_object_locator(&data, address, pointer_size)
+                    .filter(|e| e.type_descriptor == type_descriptor)
+                    .map(|locator| (address, locator))
+            }));
+        }
+
+        let signatures = locators
+            .iter()
+            .map(|(address, _)| {
+                let pointer = &address.to_le_bytes()[..pointer_size];
+                Signature::from_mask(pointer, &"x".repeat(pointer_size))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut vtables = Vec::new();
+        for ((_, locator), addresses) in locators
+            .into_iter()
+            .zip(self.scan_many(&signatures, section)?)
+        {
+            vtables.extend(addresses.into_iter().map(|e| ClassVTable {
+                address: e + pointer_size,
+                class: RttiClass {
+                    mangled_name: mangled_name.to_string(),
+                    locator,
+                },
+            }));
+        }
+
+        Ok(vtables)
+    }
+}
+
+impl Handle {
+    /// vtable of the object at `object` with its first `count` function pointers, annotated
+    /// with their module and the RTTI class of the vtable.
+    ///
+    /// fail with [Error::InvalidInput] when `count` pointers run past the end of the address
+    /// space.
+    pub fn read_vtable(&self, object: usize, count: usize) -> Result<VTable> {
+        let address = self.read_pointer(object)?;
+        let pointer_size = self.get_pointer_size()?;
+        count
+            .checked_mul(pointer_size)
+            .and_then(|e| address.checked_add(e))
+            .ok_or(Error::InvalidInput)?;
+
+        let modules: Vec<Module> = self
+            .create_snapshot(HandleSnapshotFlag::SnapModule | HandleSnapshotFlag::SnapModule32)?
+            .get_modules()
+            .collect();
+
+        let functions = (0..count)
+            .map(|index| {
+                let function = self.read_pointer(address + index * pointer_size)?;
+                let module = modules.iter().find(|e| e.contains(function));
+
+                Ok(VTableFunction {
+                    address: function,
+                    module_name: module.map(|e| e.get_name()),
+                    module_offset: module.map_or(0, |e| function - e.get_address()),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(VTable {
+            address,
+            class: self.rtti_class(address).ok(),
+            functions,
+        })
+    }
+
+    /// class of the vtable at `vtable`, read from the complete object locator before it
+    pub fn rtti_class(&self, vtable: usize) -> Result<RttiClass> {
+        let pointer_size = self.get_pointer_size()?;
+        let read = |address, len| self.read_memory(address, len);
+
+        let address = self.read_pointer(
+            vtable
+                .checked_sub(pointer_size)
+                .ok_or(Error::InvalidInput)?,
+        )?;
+        let locator =
+            parse_complete_object_locator(&read(address, LOCATOR_SIZE)?, address, pointer_size)
+                .ok_or(Error::NotFound)?;
+
+        Ok(RttiClass {
+            mangled_name: read_type_name(&read, locator.type_descriptor, pointer_size)?,
+            locator,
+        })
+    }
+}
+
+/// decorated name inside type descriptor at `type_descriptor`, after `pVFTable` and `spare`
+pub(crate) fn read_type_name(
+    read: &ReadFn,
+    type_descriptor: usize,
+    pointer_size: usize,
+) -> Result<String> {
+    let name = read_cstr(read, type_descriptor + pointer_size * 2)?;
+    if !name.starts_with(".?A") {
+        return Err(Error::NotFound);
+    }
+
+    Ok(name)
+}
+
+/// signature of the start of complete object locator pointing to `type_descriptor`, image
+/// relative to `image_base` for 64 bit image, `None` when it cannot be pointed to
+fn locator_signature(
+    type_descriptor: usize,
+    image_base: usize,
+    pointer_size: usize,
+) -> Option<Signature> {
+    let (signature, type_descriptor) = match pointer_size {
+        8 => (
+            SIGNATURE_64,
+            u32::try_from(type_descriptor.checked_sub(image_base)?).ok()?,
+        ),
+        4 => (SIGNATURE_32, u32::try_from(type_descriptor).ok()?),
+        _ => return None,
+    };
+
+    let mut bytes = vec![0u8; 16];
+    bytes[..4].copy_from_slice(&signature.to_le_bytes());
+    bytes[12..].copy_from_slice(&type_descriptor.to_le_bytes());
+
+    // NOTE: `offset` and `cdOffset` vary between the vtables of a class
+    Signature::from_mask(&bytes, "xxxx????????xxxx").ok()
+}
+
+/// complete object locator at `address` from its bytes, `None` when the signature does not match
+/// the pointer size
+pub(crate) fn parse_complete_object_locator(
+    data: &[u8],
+    address: usize,
+    pointer_size: usize,
+) -> Option<CompleteObjectLocator> {
+    let field = |offset: usize| u32_at(data, offset).ok();
+
+    let (type_descriptor, class_descriptor) = match (pointer_size, field(0)?) {
+        (8, SIGNATURE_64) => {
+            // NOTE: pSelf is the rva of the locator itself, giving base of the image
+            let image_base = address.checked_sub(field(20)? as usize)?;
+            (
+                image_base + field(12)? as usize,
+                image_base + field(16)? as usize,
+            )
+        }
+        (4, SIGNATURE_32) => (field(12)? as usize, field(16)? as usize),
+        _ => return None,
+    };
+
+    Some(CompleteObjectLocator {
+        address,
+        offset: field(4)?,
+        constructor_offset: field(8)?,
+        type_descriptor,
+        class_descriptor,
+    })
+}
+
+/// readable name of decorated class or struct name, like `Game::Player` for
+/// `.?AVPlayer@Game@@`, `None` for names like templates it cannot undecorate
+pub fn demangle_type_name(mangled: &str) -> Option<String> {
+    let name = mangled
+        .strip_prefix(".?AV")
+        .or_else(|| mangled.strip_prefix(".?AU"))?
+        .strip_suffix("@@")?;
+    if name.is_empty() || name.contains(['?', '$']) {
+        return None;
+    }
+
+    Some(name.rsplit('@').collect::<Vec<_>>().join("::"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{demangle_type_name, locator_signature, parse_complete_object_locator};
+    use crate::pe::tests::put;
+
+    #[test]
+    fn parsing_complete_object_locator() {
+        let mut data = vec![0u8; 24];
+        put(&mut data, 0, &1u32.to_le_bytes());
+        put(&mut data, 4, &0x10u32.to_le_bytes());
+        put(&mut data, 12, &0x5000u32.to_le_bytes());
+        put(&mut data, 16, &0x3000u32.to_le_bytes());
+        put(&mut data, 20, &0x2000u32.to_le_bytes());
+
+        let locator = parse_complete_object_locator(&data, 0x1_4000_2000, 8).unwrap();
+        assert_eq!(locator.get_offset(), 0x10);
+        assert_eq!(locator.get_type_descriptor(), 0x1_4000_5000);
+        assert_eq!(locator.get_class_descriptor(), 0x1_4000_3000);
+
+        // 64 bit signature read as 32 bit image
+        assert_eq!(parse_complete_object_locator(&data, 0x2000, 4), None);
+
+        put(&mut data, 0, &0u32.to_le_bytes());
+        let locator = parse_complete_object_locator(&data, 0x40_2000, 4).unwrap();
+        assert_eq!(locator.get_type_descriptor(), 0x5000);
+    }
+
+    #[test]
+    fn matching_locator_signature() {
+        let mut data = vec![0u8; 24];
+        put(&mut data, 0, &1u32.to_le_bytes());
+        put(&mut data, 4, &0x10u32.to_le_bytes());
+        put(&mut data, 12, &0x5000u32.to_le_bytes());
+
+        let signature = locator_signature(0x1_4000_5000, 0x1_4000_0000, 8).unwrap();
+        assert!(signature.matches(&data));
+        let signature = locator_signature(0x1_4000_6000, 0x1_4000_0000, 8).unwrap();
+        assert!(!signature.matches(&data));
+
+        // type descriptor below image base
+        assert!(locator_signature(0x1000, 0x1_4000_0000, 8).is_none());
+    }
+
+    #[test]
+    fn demangling_type_names() {
+        assert_eq!(
+            demangle_type_name(".?AVPlayer@@").as_deref(),
+            Some("Player")
+        );
+        assert_eq!(
+            demangle_type_name(".?AUEntity@World@Game@@").as_deref(),
+            Some("Game::World::Entity")
+        );
+        assert_eq!(
+            demangle_type_name(".?AV?$vector@HV?$allocator@H@std@@@std@@"),
+            None
+        );
+        assert_eq!(demangle_type_name(".?AW4State@@"), None);
+    }
+}
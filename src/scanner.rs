@@ -0,0 +1,856 @@
+use std::ops::{Deref, Range};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::backend::MemoryBackend;
+use crate::error::{Error, Result};
+use crate::handle::Handle;
+use crate::memory::{MemoryBasicInformation, MemoryBasicInformationFilter, PageType};
+use crate::module::Module;
+use crate::patch::MemorySection;
+use crate::pattern::Pattern;
+use crate::pe::PeImage;
+use crate::simd::{ByteMatches, MaskedBytes};
+
+/// byte signature with wildcard which length known at runtime
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Signature(Vec<Option<u8>>);
+
+impl Signature {
+    /// create signature from bytes and code style mask, `x` for matching byte and `?` for wildcard
+    pub fn from_mask(bytes: &[u8], mask: &str) -> Result<Self> {
+        if bytes.len() != mask.chars().count() || bytes.is_empty() {
+            return Err(Error::InvalidInput);
+        }
+
+        mask.chars()
+            .zip(bytes)
+            .map(|(m, b)| match m {
+                'x' | 'X' => Ok(Some(*b)),
+                '?' => Ok(None),
+                _ => Err(Error::InvalidInput),
+            })
+            .collect::<Result<Vec<_>>>()
+            .map(Self)
+    }
+
+    /// check whether `data` start with bytes matching the signature
+    pub fn matches(&self, data: &[u8]) -> bool {
+        if data.len() < self.0.len() {
+            return false;
+        }
+
+        self.0
+            .iter()
+            .zip(data)
+            .all(|(expected, actual)| expected.is_none_or(|e| e == *actual))
+    }
+
+    /// offsets in `data` where the signature matches.
+    ///
+    /// candidates are found by searching the first non wildcard byte with simd when the cpu
+    /// support it, then compared against the whole signature.
+    pub fn find_all<'a>(&'a self, data: &'a [u8]) -> Box<dyn Iterator<Item = usize> + 'a> {
+        let anchor = self.0.iter().position(|e| e.is_some());
+        let (anchor, byte) = match anchor {
+            Some(anchor) if data.len() >= self.0.len() => (anchor, self.0[anchor].unwrap()),
+            // NOTE: every window matches signature of wildcards only
+            _ => {
+                return Box::new(
+                    data.windows(self.0.len())
+                        .enumerate()
+                        .filter(|(_, window)| self.matches(window))
+                        .map(|(offset, _)| offset),
+                )
+            }
+        };
+
+        let masked = MaskedBytes::new(&self.0);
+        let haystack = &data[anchor..data.len() - (self.0.len() - 1 - anchor)];
+
+        Box::new(ByteMatches::new(haystack, byte).filter(move |offset| {
+            let window = &data[*offset..];
+            masked
+                .as_ref()
+                .and_then(|e| e.matches(window))
+                .unwrap_or_else(|| self.matches(window))
+        }))
+    }
+}
+
+impl Deref for Signature {
+    type Target = [Option<u8>];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// parse IDA style signature like `48 8B ?? ?? 05`
+impl FromStr for Signature {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let bytes = s
+            .split_whitespace()
+            .map(|token| match token {
+                "?" | "??" => Ok(None),
+                // NOTE: `from_str_radix` alone would accept sign like `+1`
+                _ if token.len() == 2 && token.bytes().all(|e| e.is_ascii_hexdigit()) => {
+                    u8::from_str_radix(token, 16)
+                        .map(Some)
+                        .map_err(|_| Error::InvalidInput)
+                }
+                _ => Err(Error::InvalidInput),
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        if bytes.is_empty() {
+            return Err(Error::InvalidInput);
+        }
+
+        Ok(Self(bytes))
+    }
+}
+
+impl<const N: usize> From<Pattern<N>> for Signature {
+    fn from(value: Pattern<N>) -> Self {
+        Self(value.to_vec())
+    }
+}
+
+/// text encoding of strings searched by [Scanner::find_string]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// utf-8 bytes
+    Utf8,
+    /// utf-16 little endian code units, like wide string of windows api
+    Utf16Le,
+    /// single byte per character, only character up to `U+00FF` can be encoded
+    Ansi,
+}
+
+impl Encoding {
+    /// bytes of the string in the encoding
+    pub fn encode(&self, s: &str) -> Result<Vec<u8>> {
+        match self {
+            Self::Utf8 => Ok(s.as_bytes().to_vec()),
+            Self::Utf16Le => Ok(s.encode_utf16().flat_map(u16::to_le_bytes).collect()),
+            Self::Ansi => s
+                .chars()
+                .map(|c| u8::try_from(c).map_err(|_| Error::InvalidInput))
+                .collect(),
+        }
+    }
+
+    fn get_unit_size(&self) -> usize {
+        match self {
+            Self::Utf16Le => 2,
+            Self::Utf8 | Self::Ansi => 1,
+        }
+    }
+}
+
+/// offsets in `data` of the encoded string, case insensitive matching only fold ascii letters
+fn find_string_in<'a>(
+    data: &'a [u8],
+    needle: &'a [u8],
+    unit_size: usize,
+    case_sensitive: bool,
+) -> impl Iterator<Item = usize> + 'a {
+    let fold = move |unit: &[u8]| -> u16 {
+        let value = match unit {
+            [a, b] => u16::from_le_bytes([*a, *b]),
+            [a] => *a as u16,
+            _ => unreachable!(),
+        };
+        match (case_sensitive, u8::try_from(value)) {
+            (false, Ok(e)) => e.to_ascii_lowercase() as u16,
+            _ => value,
+        }
+    };
+
+    data.windows(needle.len())
+        .enumerate()
+        .step_by(unit_size)
+        .filter(move |(_, window)| {
+            window
+                .chunks_exact(unit_size)
+                .zip(needle.chunks_exact(unit_size))
+                .all(|(actual, expected)| fold(actual) == fold(expected))
+        })
+        .map(|(offset, _)| offset)
+}
+
+/// how a [Reference] refers to its target
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReferenceKind {
+    /// pointer sized absolute address, like global pointer or `mov rax, imm64`
+    Absolute,
+    /// 32 bit displacement relative to the end of itself, like `lea rcx, [rip+disp]` or
+    /// `call rel32`
+    Relative,
+}
+
+/// place in memory referring to an address, found by [Scanner::find_references]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reference {
+    address: usize,
+    kind: ReferenceKind,
+}
+
+impl Reference {
+    /// address of the pointer or displacement, inside the referring instruction for code
+    pub fn get_address(&self) -> usize {
+        self.address
+    }
+
+    /// how the target is referred to
+    pub fn get_kind(&self) -> ReferenceKind {
+        self.kind
+    }
+}
+
+/// references in `data` starting at `base` to `target`, ordered by offset
+fn find_references_in(
+    data: &[u8],
+    base: usize,
+    target: usize,
+    pointer_size: usize,
+) -> impl Iterator<Item = Reference> + '_ {
+    let pointer = target.to_le_bytes();
+
+    (0..data.len()).flat_map(move |offset| {
+        let address = base + offset;
+        let absolute = data
+            .get(offset..offset + pointer_size)
+            .filter(|e| *e == &pointer[..pointer_size])
+            .map(|_| ReferenceKind::Absolute);
+        // NOTE: instruction with immediate after the displacement end past it, those are missed
+        let relative = data
+            .get(offset..offset + 4)
+            .map(|e| i32::from_le_bytes(e.try_into().unwrap()) as isize)
+            .filter(|e| (address + 4).wrapping_add_signed(*e) == target)
+            .map(|_| ReferenceKind::Relative);
+
+        absolute
+            .into_iter()
+            .chain(relative)
+            .map(move |kind| Reference { address, kind })
+    })
+}
+
+/// size of chunks big regions are split into when scanned
+const CHUNK_SIZE: usize = 0x100_0000;
+
+/// split `(start_address, size)` ranges into chunks of at most `chunk_size` bytes.
+///
+/// each chunk is `(start_address, size, read_size)` where `read_size` extend `size` by up to
+/// `overlap` bytes within the range, so matches crossing chunks are found exactly once.
+fn partition_ranges(
+    ranges: &[(usize, usize)],
+    chunk_size: usize,
+    overlap: usize,
+) -> Vec<(usize, usize, usize)> {
+    ranges
+        .iter()
+        .flat_map(|(start_address, size)| {
+            let end_address = start_address + size;
+            (*start_address..end_address)
+                .step_by(chunk_size)
+                .map(move |chunk_start| {
+                    let chunk_size = chunk_size.min(end_address - chunk_start);
+                    let read_size = (chunk_size + overlap).min(end_address - chunk_start);
+                    (chunk_start, chunk_size, read_size)
+                })
+        })
+        .collect()
+}
+
+/// progress of a scan reported by [Scanner::scan_with_progress]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScanProgress {
+    bytes_scanned: usize,
+    bytes_total: usize,
+    region: (usize, usize),
+}
+
+impl ScanProgress {
+    /// bytes scanned so far
+    pub fn get_bytes_scanned(&self) -> usize {
+        self.bytes_scanned
+    }
+
+    /// bytes to scan in total
+    pub fn get_bytes_total(&self) -> usize {
+        self.bytes_total
+    }
+
+    /// `(start_address, size)` of the chunk just scanned
+    pub fn get_region(&self) -> (usize, usize) {
+        self.region
+    }
+}
+
+/// request to stop a running scan, clones share the same request
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// request the scan to stop
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// whether stopping is requested
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// constraints applied to every scan of [Scanner] and [ValueScanner], built by chaining like
+/// `ScanOptions::new().alignment(8).exclude_stacks()`
+///
+/// [ValueScanner]: crate::value_scanner::ValueScanner
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ScanOptions {
+    alignment: Option<usize>,
+    region_types: Option<PageType>,
+    exclude_stacks: bool,
+}
+
+impl ScanOptions {
+    /// options without any constraint
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// keep only addresses multiple of `alignment`, like 4, 8 or 16
+    pub fn alignment(mut self, alignment: usize) -> Self {
+        self.alignment = Some(alignment.max(1));
+        self
+    }
+
+    /// scan only regions of any of the types, like `PageType::Private` for heap allocations
+    pub fn region_types(mut self, region_types: PageType) -> Self {
+        self.region_types = Some(region_types);
+        self
+    }
+
+    /// skip regions holding the stack of any thread
+    pub fn exclude_stacks(mut self) -> Self {
+        self.exclude_stacks = true;
+        self
+    }
+
+    /// alignment of addresses, `None` when not constrained
+    pub fn get_alignment(&self) -> Option<usize> {
+        self.alignment
+    }
+
+    /// types of regions scanned, `None` for every type
+    pub fn get_region_types(&self) -> Option<PageType> {
+        self.region_types
+    }
+
+    /// whether regions holding stacks are skipped
+    pub fn is_excluding_stacks(&self) -> bool {
+        self.exclude_stacks
+    }
+
+    pub(crate) fn is_aligned(&self, address: usize) -> bool {
+        self.alignment.is_none_or(|e| address.is_multiple_of(e))
+    }
+
+    /// whether the region is scanned, `stacks` given by [ScanOptions::stack_ranges]
+    pub(crate) fn keeps(&self, mbi: &MemoryBasicInformation, stacks: &[Range<usize>]) -> bool {
+        let start = mbi.get_base_address();
+        let end = start + mbi.get_region_size();
+
+        self.region_types
+            .is_none_or(|e| mbi.get_type().intersects(e))
+            && !stacks.iter().any(|e| e.start < end && start < e.end)
+    }
+
+    /// stacks to skip, empty unless excluding them
+    pub(crate) fn stack_ranges<B: MemoryBackend + ?Sized>(
+        &self,
+        backend: &B,
+    ) -> Result<Vec<Range<usize>>> {
+        if !self.exclude_stacks {
+            return Ok(Vec::new());
+        }
+
+        backend.get_stack_ranges()
+    }
+}
+
+/// Searching signature in memory of a process, through any [MemoryBackend]
+pub struct Scanner<'a, B: MemoryBackend + ?Sized = Handle> {
+    handle: &'a B,
+    options: ScanOptions,
+}
+
+impl<'a, B: MemoryBackend + ?Sized> Scanner<'a, B> {
+    /// create new instance for scanning memory of the handle
+    pub fn new(handle: &'a B) -> Self {
+        Self {
+            handle,
+            options: ScanOptions::default(),
+        }
+    }
+
+    /// constrain every scan by the options
+    pub fn with_options(mut self, options: ScanOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// options constraining every scan
+    pub fn get_options(&self) -> &ScanOptions {
+        &self.options
+    }
+
+    /// backend memory is read through
+    pub fn get_backend(&self) -> &'a B {
+        self.handle
+    }
+
+    /// every address in the memory section that matches the signature
+    pub fn scan(&self, signature: &Signature, section: MemorySection) -> Result<Vec<usize>> {
+        self.scan_with_progress(signature, section, |_| (), &CancellationToken::default())
+    }
+
+    /// same as [Scanner::scan], calling `progress` after every chunk scanned.
+    ///
+    /// stop with [Error::Cancelled] once `cancel` is cancelled.
+    pub fn scan_with_progress<F>(
+        &self,
+        signature: &Signature,
+        section: MemorySection,
+        mut progress: F,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<usize>>
+    where
+        F: FnMut(&ScanProgress),
+    {
+        let mut addresses = Vec::new();
+
+        let ranges = self.get_ranges(section)?;
+        let mut scan_progress = ScanProgress {
+            bytes_scanned: 0,
+            bytes_total: ranges.iter().map(|(_, size)| size).sum(),
+            region: (0, 0),
+        };
+
+        for (start_address, size, read_size) in
+            partition_ranges(&ranges, CHUNK_SIZE, signature.len().saturating_sub(1))
+        {
+            if cancel.is_cancelled() {
+                return Err(Error::Cancelled);
+            }
+
+            if let Ok(data) = self.handle.read_memory(start_address, read_size) {
+                addresses.extend(
+                    signature
+                        .find_all(&data)
+                        .take_while(|offset| *offset < size)
+                        .map(|offset| start_address + offset)
+                        .filter(|e| self.options.is_aligned(*e)),
+                );
+            }
+
+            scan_progress.bytes_scanned += size;
+            scan_progress.region = (start_address, size);
+            progress(&scan_progress);
+        }
+
+        Ok(addresses)
+    }
+
+    /// every address in the memory section that matches each of the signatures, reading memory
+    /// once for all of them
+    pub(crate) fn scan_many(
+        &self,
+        signatures: &[Signature],
+        section: MemorySection,
+    ) -> Result<Vec<Vec<usize>>> {
+        let mut addresses = vec![Vec::new(); signatures.len()];
+        if signatures.is_empty() {
+            return Ok(addresses);
+        }
+
+        let ranges = self.get_ranges(section)?;
+        let overlap = signatures.iter().map(|e| e.len()).max().unwrap_or(0);
+        for (start_address, size, read_size) in
+            partition_ranges(&ranges, CHUNK_SIZE, overlap.saturating_sub(1))
+        {
+            let Ok(data) = self.handle.read_memory(start_address, read_size) else {
+                continue;
+            };
+
+            for (signature, addresses) in signatures.iter().zip(addresses.iter_mut()) {
+                addresses.extend(
+                    signature
+                        .find_all(&data)
+                        .take_while(|offset| *offset < size)
+                        .map(|offset| start_address + offset)
+                        .filter(|e| self.options.is_aligned(*e)),
+                );
+            }
+        }
+
+        Ok(addresses)
+    }
+
+    /// every address in the memory section that matches the signature, scanning chunks of
+    /// regions across rayon thread pool.
+    ///
+    /// addresses are in ascending order, same as [Scanner::scan].
+    #[cfg(feature = "rayon")]
+    pub fn scan_parallel(&self, signature: &Signature, section: MemorySection) -> Result<Vec<usize>>
+    where
+        B: Sync,
+    {
+        use rayon::prelude::*;
+
+        let mut ranges = self.get_ranges(section)?;
+        ranges.sort_unstable();
+        let chunks = partition_ranges(&ranges, CHUNK_SIZE, signature.len().saturating_sub(1));
+
+        let addresses = chunks
+            .par_iter()
+            .map(|(start_address, size, read_size)| {
+                let data = match self.handle.read_memory(*start_address, *read_size) {
+                    Ok(data) => data,
+                    Err(_) => return Vec::new(),
+                };
+
+                signature
+                    .find_all(&data)
+                    .take_while(|offset| offset < size)
+                    .map(|offset| start_address + offset)
+                    .filter(|e| self.options.is_aligned(*e))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        Ok(addresses.into_iter().flatten().collect())
+    }
+
+    /// first address in the memory section that matches the signature
+    pub fn scan_first(&self, signature: &Signature, section: MemorySection) -> Result<usize> {
+        for (start_address, size) in self.get_ranges(section)? {
+            let data = match self.handle.read_memory(start_address, size) {
+                Ok(data) => data,
+                Err(_) => continue,
+            };
+
+            let address = signature
+                .find_all(&data)
+                .map(|offset| start_address + offset)
+                .find(|e| self.options.is_aligned(*e));
+            if let Some(address) = address {
+                return Ok(address);
+            }
+        }
+
+        Err(Error::NotFound)
+    }
+
+    /// every address in readable memory where the string is found, without terminator
+    pub fn find_string(
+        &self,
+        s: &str,
+        encoding: Encoding,
+        case_sensitive: bool,
+    ) -> Result<Vec<usize>> {
+        let needle = encoding.encode(s)?;
+        if needle.is_empty() {
+            return Err(Error::InvalidInput);
+        }
+
+        let mut addresses = Vec::new();
+
+        for (start_address, size) in self.get_ranges(MemorySection::All)? {
+            let data = match self.handle.read_memory(start_address, size) {
+                Ok(data) => data,
+                Err(_) => continue,
+            };
+
+            addresses.extend(
+                find_string_in(&data, &needle, encoding.get_unit_size(), case_sensitive)
+                    .map(|offset| start_address + offset)
+                    .filter(|e| self.options.is_aligned(*e)),
+            );
+        }
+
+        Ok(addresses)
+    }
+
+    /// every reference in the memory section to `target`, either its absolute address or a
+    /// relative displacement resolving to it.
+    ///
+    /// references are not decoded as instructions, any bytes that happen to resolve to the
+    /// target are reported too.
+    pub fn find_references(&self, target: usize, section: MemorySection) -> Result<Vec<Reference>> {
+        let pointer_size = self.handle.get_pointer_size()?;
+        let mut references = Vec::new();
+
+        let ranges = self.get_ranges(section)?;
+        for (start_address, size, read_size) in
+            partition_ranges(&ranges, CHUNK_SIZE, pointer_size.max(4) - 1)
+        {
+            if let Ok(data) = self.handle.read_memory(start_address, read_size) {
+                references.extend(
+                    find_references_in(&data, start_address, target, pointer_size)
+                        .take_while(|e| e.address - start_address < size)
+                        .filter(|e| self.options.is_aligned(e.address)),
+                );
+            }
+        }
+
+        Ok(references)
+    }
+
+    /// every address in the section of the module, like `.text`, that matches the signature
+    pub fn in_section(
+        &self,
+        module: &Module,
+        section_name: &str,
+        signature: &Signature,
+    ) -> Result<Vec<usize>> {
+        let range = PeImage::read_with(
+            &|address, len| self.handle.read_memory(address, len),
+            module.get_address(),
+        )?
+        .find_section(section_name)
+        .ok_or(Error::NotFound)?
+        .get_address_range(module.get_address());
+
+        let data = self.handle.read_memory(range.start, range.len())?;
+
+        Ok(signature
+            .find_all(&data)
+            .map(|offset| range.start + offset)
+            .filter(|e| self.options.is_aligned(*e))
+            .collect())
+    }
+
+    fn get_ranges(&self, section: MemorySection) -> Result<Vec<(usize, usize)>> {
+        let ranges = match section {
+            MemorySection::All => {
+                let stacks = self.options.stack_ranges(self.handle)?;
+
+                self.handle
+                    .memory_regions()
+                    .committed()
+                    .readable()
+                    .filter(|mbi| self.options.keeps(mbi, &stacks))
+                    .map(|mbi| (mbi.get_base_address(), mbi.get_region_size()))
+                    .collect()
+            }
+            MemorySection::Module(module_name) => {
+                let range = self.handle.get_module_range(module_name)?;
+
+                vec![(range.start, range.len())]
+            }
+        };
+
+        Ok(ranges)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::{
+        find_references_in, find_string_in, partition_ranges, CancellationToken, Encoding,
+        ReferenceKind, ScanOptions, Scanner, Signature, CHUNK_SIZE,
+    };
+    use crate::backend::testing::BufferBackend;
+    use crate::error::Error;
+    use crate::memory::{testing, PageType};
+    use crate::patch::MemorySection;
+
+    #[test]
+    fn parsing_ida_style() {
+        let signature: Signature = "48 8B ?? ? 05".parse().unwrap();
+
+        assert_eq!(
+            signature.to_vec(),
+            vec![Some(0x48), Some(0x8B), None, None, Some(0x05)]
+        );
+        assert!("48 8G".parse::<Signature>().is_err());
+        assert!("488B".parse::<Signature>().is_err());
+        assert!("48 +1".parse::<Signature>().is_err());
+        assert!("".parse::<Signature>().is_err());
+    }
+
+    #[test]
+    fn parsing_bytes_and_mask() {
+        let signature = Signature::from_mask(&[0x48, 0x8B, 0x00, 0x05], "xx?x").unwrap();
+
+        assert_eq!(signature, "48 8B ?? 05".parse().unwrap());
+        assert!(Signature::from_mask(&[0x48, 0x8B], "x").is_err());
+        assert!(Signature::from_mask(&[0x48, 0x8B], "é").is_err());
+    }
+
+    #[test]
+    fn finding_all_matches() {
+        let signature: Signature = "AA ?? CC".parse().unwrap();
+        let data = [0xAA, 0x00, 0xCC, 0xAA, 0xBB, 0xCC, 0xAA, 0xBB];
+
+        assert_eq!(signature.find_all(&data).collect::<Vec<_>>(), vec![0, 3]);
+    }
+
+    #[test]
+    fn finding_encoded_strings() {
+        let mut data = b"..Hello...".to_vec();
+        data.extend(Encoding::Utf16Le.encode("hELLO").unwrap());
+
+        let needle = Encoding::Utf16Le.encode("Hello").unwrap();
+        assert_eq!(
+            find_string_in(&data, &needle, 2, false).collect::<Vec<_>>(),
+            vec![10]
+        );
+        assert_eq!(find_string_in(&data, &needle, 2, true).count(), 0);
+
+        let needle = Encoding::Ansi.encode("hello").unwrap();
+        assert_eq!(
+            find_string_in(&data, &needle, 1, false).collect::<Vec<_>>(),
+            vec![2]
+        );
+        assert!(Encoding::Ansi.encode("\u{3042}").is_err());
+    }
+
+    #[test]
+    fn finding_references() {
+        let mut data = vec![0u8; 0x20];
+        data[0x4..0xC].copy_from_slice(&0x1_4000_1000usize.to_le_bytes());
+        // lea rcx, [rip+disp] at 0x1_4000_0010 with displacement ending at 0x1_4000_0017
+        data[0x10..0x13].copy_from_slice(&[0x48, 0x8D, 0x0D]);
+        data[0x13..0x17].copy_from_slice(&(0x1000i32 - 0x17).to_le_bytes());
+
+        let references = find_references_in(&data, 0x1_4000_0000, 0x1_4000_1000, 8)
+            .map(|e| (e.get_address(), e.get_kind()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            references,
+            vec![
+                (0x1_4000_0004, ReferenceKind::Absolute),
+                (0x1_4000_0013, ReferenceKind::Relative),
+            ]
+        );
+    }
+
+    #[test]
+    fn constraining_scan_options() {
+        // committed read write page, of an image or private
+        let region = |base: usize, image: bool| {
+            testing::region(
+                base,
+                base,
+                0x1000,
+                0x04,
+                if image { 0x1000000 } else { 0x20000 },
+            )
+        };
+
+        let options = ScanOptions::new()
+            .alignment(8)
+            .region_types(PageType::Private);
+        assert!(options.is_aligned(0x1008));
+        assert!(!options.is_aligned(0x1004));
+        let stacks = [0x8800..0x9000, 0x20000..0x30000];
+        assert!(options.keeps(&region(0x1000, false), &stacks));
+        assert!(!options.keeps(&region(0x1000, true), &[]));
+        assert!(!options.keeps(&region(0x8000, false), &stacks));
+        assert!(ScanOptions::new().keeps(&region(0x1000, true), &[]));
+    }
+
+    #[test]
+    fn partitioning_ranges() {
+        let chunks = partition_ranges(&[(0x1000, 0x2500), (0x8000, 0x100)], 0x1000, 3);
+
+        assert_eq!(
+            chunks,
+            vec![
+                (0x1000, 0x1000, 0x1003),
+                (0x2000, 0x1000, 0x1003),
+                (0x3000, 0x500, 0x500),
+                (0x8000, 0x100, 0x100),
+            ]
+        );
+    }
+
+    #[test]
+    fn reporting_scan_progress() {
+        let mut data = vec![0u8; CHUNK_SIZE + 0x10];
+        // match straddling the chunk boundary
+        data[CHUNK_SIZE - 1..CHUNK_SIZE + 1].copy_from_slice(&[0xAA, 0xBB]);
+        data[CHUNK_SIZE + 4..CHUNK_SIZE + 6].copy_from_slice(&[0xAA, 0xBB]);
+        let backend = BufferBackend(RefCell::new(data));
+        let scanner = Scanner::new(&backend);
+        let signature: Signature = "AA BB".parse().unwrap();
+
+        let mut reports = Vec::new();
+        let addresses = scanner
+            .scan_with_progress(
+                &signature,
+                MemorySection::All,
+                |e| reports.push(*e),
+                &CancellationToken::default(),
+            )
+            .unwrap();
+        assert_eq!(
+            addresses,
+            vec![0x1000 + CHUNK_SIZE - 1, 0x1000 + CHUNK_SIZE + 4]
+        );
+        assert_eq!(
+            reports
+                .iter()
+                .map(|e| (e.get_bytes_scanned(), e.get_region()))
+                .collect::<Vec<_>>(),
+            vec![
+                (CHUNK_SIZE, (0x1000, CHUNK_SIZE)),
+                (CHUNK_SIZE + 0x10, (0x1000 + CHUNK_SIZE, 0x10)),
+            ]
+        );
+        assert!(reports
+            .iter()
+            .all(|e| e.get_bytes_total() == CHUNK_SIZE + 0x10));
+    }
+
+    #[test]
+    fn cancelling_scans() {
+        let backend = BufferBackend(RefCell::new(vec![0u8; CHUNK_SIZE + 0x10]));
+        let scanner = Scanner::new(&backend);
+        let signature: Signature = "AA BB".parse().unwrap();
+
+        let cancel = CancellationToken::default();
+        let mut reports = 0;
+        let result = scanner.scan_with_progress(
+            &signature,
+            MemorySection::All,
+            |_| {
+                reports += 1;
+                cancel.cancel();
+            },
+            &cancel,
+        );
+        assert!(matches!(result, Err(Error::Cancelled)));
+        assert_eq!(reports, 1);
+        assert!(cancel.is_cancelled());
+
+        let result = scanner.scan_with_progress(
+            &signature,
+            MemorySection::All,
+            |_| panic!("nothing is scanned once cancelled"),
+            &cancel,
+        );
+        assert!(matches!(result, Err(Error::Cancelled)));
+    }
+}
@@ -0,0 +1,720 @@
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::address::Address;
+#[cfg(feature = "serde")]
+use crate::bridge::Bridge;
+use crate::debug::{DebugEventKind, Debugger};
+use crate::error::{Error, Result};
+use crate::freeze::{Freezer, FrozenValue};
+use crate::handle::Handle;
+use crate::hooks;
+use crate::process::Process;
+use crate::speedhack::{SpeedHack, TimeFunction};
+use crate::table::{AddressTable, Value};
+use crate::worker::StoppableThread;
+
+/// interval frozen values of [Session] are rewritten at
+const FREEZE_INTERVAL: Duration = Duration::from_millis(50);
+/// interval the monitor of [Session] check whether it is asked to stop
+const MONITOR_POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// lowest `NTSTATUS` of error severity, exit code of process killed by unhandled exception
+const STATUS_SEVERITY_ERROR: u32 = 0xC000_0000;
+
+/// called with every event of [Session] from the thread delivering it
+type SessionCallback = dyn FnMut(&SessionEvent) + Send;
+
+/// what happened to the process of [Session]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SessionEvent {
+    /// process exited with zero exit code
+    Exited {
+        /// id of the exited process
+        process_id: u32,
+    },
+    /// process exited with non-zero exit code or after an exception nobody handled
+    Crashed {
+        /// id of the crashed process
+        process_id: u32,
+        /// exit code of the process
+        exit_code: u32,
+        /// code of the unhandled exception, like `0xC0000005` for access violation
+        exception: Option<u32>,
+    },
+    /// session attached to the restarted process
+    Reattached {
+        /// id of the newly attached process
+        process_id: u32,
+    },
+    /// exit of the process can no longer be told, handle can neither wait on the process nor
+    /// query its exit code
+    Unmonitored {
+        /// id of the process
+        process_id: u32,
+    },
+}
+
+/// channels and callbacks events of [Session] are delivered to
+#[derive(Default)]
+struct Listeners {
+    senders: Vec<Sender<SessionEvent>>,
+    callbacks: Vec<Box<SessionCallback>>,
+}
+
+impl Listeners {
+    fn dispatch(&mut self, event: &SessionEvent) {
+        self.senders.retain(|e| e.send(event.clone()).is_ok());
+        for callback in &mut self.callbacks {
+            callback(event);
+        }
+    }
+}
+
+/// background thread waiting for the process of [Session] to exit
+struct Monitor {
+    _thread: StoppableThread,
+}
+
+impl Monitor {
+    fn spawn(handle: Arc<Handle>, listeners: Arc<Mutex<Listeners>>, debug: bool) -> Self {
+        let thread = StoppableThread::spawn(move |stop_receiver| {
+            // NOTE: debugger has to wait events on the thread attaching it
+            let debugger = debug
+                .then(|| Debugger::attach(handle.get_process_id()).ok())
+                .flatten();
+            let event = match &debugger {
+                Some(debugger) => wait_debug_exit(debugger, &stop_receiver)
+                    .or_else(|| wait_exit(&handle, &stop_receiver)),
+                None => wait_exit(&handle, &stop_receiver),
+            };
+
+            if let Some(event) = event {
+                listeners.lock().unwrap().dispatch(&event);
+            }
+        });
+
+        Self { _thread: thread }
+    }
+}
+
+/// value kept by [Session], re-resolved on every attach
+struct SessionFreeze {
+    address: Address,
+    offsets: Vec<usize>,
+    bytes: Vec<u8>,
+    frozen: Option<FrozenValue>,
+}
+
+/// bytes patched by [Session], original bytes are read on every attach
+struct SessionPatch {
+    address: Address,
+    bytes: Vec<u8>,
+    applied: Option<(usize, Vec<u8>)>,
+}
+
+/// function redirected by [Session], installed again on every attach
+struct SessionHook {
+    target: Address,
+    detour: Address,
+    stolen_len: usize,
+    applied: Option<(usize, Vec<u8>)>,
+    trampoline: Option<usize>,
+}
+
+/// Attachment to a process by its name that survive restart of the process.
+///
+/// addresses of frozen values, patches and hooks are kept relative to their module, so
+/// [Session::reattach] reopen the restarted process and apply them again, along with the time
+/// scale. patches, hooks and hooked time functions are restored when the session dropped.
+///
+/// exit of the process is reported as [SessionEvent] from a background thread, look at
+/// [Session::subscribe] and [Session::on_event].
+pub struct Session {
+    process_name: String,
+    handle: Arc<Handle>,
+    table: AddressTable,
+    freezer: Freezer,
+    freezes: Vec<SessionFreeze>,
+    patches: Vec<SessionPatch>,
+    hooks: Vec<SessionHook>,
+    time_scale: f64,
+    speedhack: Option<SpeedHack>,
+    #[cfg(feature = "serde")]
+    bridge: Option<Bridge>,
+    listeners: Arc<Mutex<Listeners>>,
+    debug_exceptions: bool,
+    monitor: Option<Monitor>,
+}
+
+impl Session {
+    /// attach to the first process named `process_name` ignoring case, waiting for it to start.
+    ///
+    /// wait forever when `timeout` is `None`.
+    pub fn attach(process_name: &str, timeout: Option<Duration>) -> Result<Self> {
+        let handle = Arc::new(Process::wait_for(process_name, timeout)?);
+        let listeners = Arc::new(Mutex::new(Listeners::default()));
+
+        Ok(Self {
+            process_name: process_name.to_string(),
+            freezer: Freezer::new(handle.clone(), FREEZE_INTERVAL),
+            monitor: Some(Monitor::spawn(handle.clone(), listeners.clone(), false)),
+            handle,
+            table: AddressTable::new(),
+            freezes: Vec::new(),
+            patches: Vec::new(),
+            hooks: Vec::new(),
+            time_scale: 1.0,
+            speedhack: None,
+            #[cfg(feature = "serde")]
+            bridge: None,
+            listeners,
+            debug_exceptions: false,
+        })
+    }
+
+    /// channel receiving every event from now on
+    pub fn subscribe(&self) -> Receiver<SessionEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.listeners.lock().unwrap().senders.push(sender);
+
+        receiver
+    }
+
+    /// call `callback` with every event from now on.
+    ///
+    /// exit and crash are delivered from the background thread monitoring the process,
+    /// reattach from the thread calling [Session::reattach].
+    pub fn on_event<F>(&self, callback: F)
+    where
+        F: FnMut(&SessionEvent) + Send + 'static,
+    {
+        self.listeners
+            .lock()
+            .unwrap()
+            .callbacks
+            .push(Box::new(callback));
+    }
+
+    /// whether the process is debugged to catch exception nobody handled
+    pub fn is_debugging_exceptions(&self) -> bool {
+        self.debug_exceptions
+    }
+
+    /// attach as debugger to report the code of exception nobody handled with
+    /// [SessionEvent::Crashed], instead of only looking at the exit code.
+    ///
+    /// attaching fail when the process is already debugged, then only the exit code is looked
+    /// at. the process is detached when disabled.
+    pub fn set_debug_exceptions(&mut self, enabled: bool) {
+        self.debug_exceptions = enabled;
+        self.restart_monitor();
+    }
+
+    /// name of the executable the session attach to
+    pub fn get_process_name(&self) -> &str {
+        &self.process_name
+    }
+
+    /// handle of the process currently attached to
+    pub fn get_handle(&self) -> &Arc<Handle> {
+        &self.handle
+    }
+
+    /// named addresses of the session
+    pub fn get_table(&self) -> &AddressTable {
+        &self.table
+    }
+
+    /// named addresses of the session, for adding and removing entries
+    pub fn get_table_mut(&mut self) -> &mut AddressTable {
+        &mut self.table
+    }
+
+    /// whether the process attached to is still running
+    pub fn is_attached(&self) -> bool {
+        self.handle.is_alive().unwrap_or(false)
+    }
+
+    /// write `bytes` at the end of the pointer chain from `address` and keep rewriting it, again
+    /// after every reattach.
+    ///
+    /// the chain is followed again on every rewrite, so it keep up with the game moving the
+    /// value.
+    pub fn freeze<A: Into<Address>>(
+        &mut self,
+        address: A,
+        offsets: &[usize],
+        bytes: &[u8],
+    ) -> Result<()> {
+        let mut freeze = SessionFreeze {
+            address: address.into(),
+            offsets: offsets.to_vec(),
+            bytes: bytes.to_vec(),
+            frozen: None,
+        };
+        freeze.frozen = Some(apply_freeze(&self.handle, &mut self.freezer, &freeze)?);
+        self.freezes.push(freeze);
+
+        Ok(())
+    }
+
+    /// freeze the entry of the table with the name to `value`
+    pub fn freeze_entry(&mut self, name: &str, value: &Value) -> Result<()> {
+        let entry = self.table.find(name).ok_or(Error::NotFound)?;
+        let bytes = entry.get_value_type().encode(value)?;
+
+        let (address, offsets) = (entry.get_base().clone(), entry.get_offsets().to_vec());
+        self.freeze(address, &offsets, &bytes)
+    }
+
+    /// stop rewriting every value frozen from `address`
+    pub fn unfreeze<A: Into<Address>>(&mut self, address: A) {
+        let address = address.into();
+        self.freezes.retain_mut(|freeze| {
+            if freeze.address != address {
+                return true;
+            }
+
+            if let Some(frozen) = freeze.frozen.take() {
+                frozen.unfreeze();
+            }
+            false
+        });
+    }
+
+    /// write `bytes` at `address` now, again after every reattach
+    pub fn patch<A: Into<Address>>(&mut self, address: A, bytes: &[u8]) -> Result<()> {
+        if bytes.is_empty() {
+            return Err(Error::InvalidInput);
+        }
+
+        let mut patch = SessionPatch {
+            address: address.into(),
+            bytes: bytes.to_vec(),
+            applied: None,
+        };
+        patch.applied = Some(apply_patch(&self.handle, &patch)?);
+        self.patches.push(patch);
+
+        Ok(())
+    }
+
+    /// write original bytes back for every patch at `address` and forget them
+    pub fn unpatch<A: Into<Address>>(&mut self, address: A) -> Result<()> {
+        let address = address.into();
+        let mut result = Ok(());
+        for index in (0..self.patches.len()).rev() {
+            if self.patches[index].address != address {
+                continue;
+            }
+
+            let patch = self.patches.remove(index);
+            if let Err(e) = restore_bytes(&self.handle, &patch.applied) {
+                result = Err(e);
+            }
+        }
+
+        result
+    }
+
+    /// redirect function at `target` to `detour`, again after every reattach, returning address
+    /// of the trampoline running the original function.
+    ///
+    /// the trampoline is left allocated when unhooked, a thread may still be running it. look
+    /// at [hooks::install_detour] for `stolen_len`.
+    pub fn hook<T, D>(&mut self, target: T, detour: D, stolen_len: usize) -> Result<usize>
+    where
+        T: Into<Address>,
+        D: Into<Address>,
+    {
+        let mut hook = SessionHook {
+            target: target.into(),
+            detour: detour.into(),
+            stolen_len,
+            applied: None,
+            trampoline: None,
+        };
+        let (applied, trampoline) = apply_hook(&self.handle, &hook)?;
+        (hook.applied, hook.trampoline) = (Some(applied), Some(trampoline));
+        self.hooks.push(hook);
+
+        Ok(trampoline)
+    }
+
+    /// trampoline of the function hooked at `target` in the process currently attached to,
+    /// `None` when it is not hooked
+    pub fn get_trampoline<A: Into<Address>>(&self, target: A) -> Option<usize> {
+        let target = target.into();
+        self.hooks
+            .iter()
+            .find(|e| e.target == target)
+            .and_then(|e| e.trampoline)
+    }
+
+    /// restore the start of every function hooked at `target` and forget them
+    pub fn unhook<A: Into<Address>>(&mut self, target: A) -> Result<()> {
+        let target = target.into();
+        let mut result = Ok(());
+        for index in (0..self.hooks.len()).rev() {
+            if self.hooks[index].target != target {
+                continue;
+            }
+
+            let hook = self.hooks.remove(index);
+            if let Err(e) = restore_bytes(&self.handle, &hook.applied) {
+                result = Err(e);
+            }
+        }
+
+        result
+    }
+
+    /// how many times as fast time pass in the process
+    pub fn get_time_scale(&self) -> f64 {
+        self.time_scale
+    }
+
+    /// make time pass `scale` times as fast in the process, like `0.5` for half speed, again
+    /// after every reattach.
+    ///
+    /// every [TimeFunction] of modules loaded by then is hooked on the first call, look at
+    /// [SpeedHack].
+    pub fn set_time_scale(&mut self, scale: f64) -> Result<()> {
+        let speedhack = match &mut self.speedhack {
+            Some(speedhack) => speedhack,
+            None => self
+                .speedhack
+                .insert(SpeedHack::install(self.handle.clone(), &TimeFunction::ALL)?),
+        };
+        speedhack.set_scale(scale)?;
+        self.time_scale = scale;
+
+        Ok(())
+    }
+
+    /// send `request` to the payload serving [BridgeServer](crate::bridge::BridgeServer) in
+    /// the process and wait for its response.
+    ///
+    /// connect on the first call and again after every reattach or failed call, waiting for
+    /// the payload for `timeout`, forever when it is `None`.
+    #[cfg(feature = "serde")]
+    pub fn call_bridge<Req, Resp>(
+        &mut self,
+        request: &Req,
+        timeout: Option<Duration>,
+    ) -> Result<Resp>
+    where
+        Req: serde::Serialize,
+        Resp: serde::de::DeserializeOwned,
+    {
+        let bridge = match &mut self.bridge {
+            Some(bridge) => bridge,
+            None => self
+                .bridge
+                .insert(Bridge::connect(self.handle.get_process_id(), timeout)?),
+        };
+
+        let response = bridge.call(request);
+        if response.is_err() {
+            self.bridge = None;
+        }
+
+        response
+    }
+
+    /// reopen the process by its name, waiting for it to start, then resolve and apply every
+    /// frozen value, patch and hook again.
+    ///
+    /// the process attached to is detached first, restoring its patches and hooks when it
+    /// still run. frozen value, patch or hook which address cannot be resolved yet, like module
+    /// not loaded, is kept for the next reattach. wait forever when `timeout` is `None`.
+    pub fn reattach(&mut self, timeout: Option<Duration>) -> Result<()> {
+        self.restore_all();
+        self.patches.iter_mut().for_each(|e| e.applied = None);
+        self.hooks.iter_mut().for_each(|e| {
+            e.applied = None;
+            e.trampoline = None;
+        });
+        self.freezer.unfreeze_all();
+        self.freezes.iter_mut().for_each(|e| e.frozen = None);
+        self.speedhack = None;
+        #[cfg(feature = "serde")]
+        {
+            self.bridge = None;
+        }
+
+        self.handle = Arc::new(Process::wait_for(&self.process_name, timeout)?);
+        self.freezer = Freezer::new(self.handle.clone(), FREEZE_INTERVAL);
+
+        for patch in &mut self.patches {
+            patch.applied = apply_patch(&self.handle, patch).ok();
+        }
+        for hook in &mut self.hooks {
+            if let Ok((applied, trampoline)) = apply_hook(&self.handle, hook) {
+                (hook.applied, hook.trampoline) = (Some(applied), Some(trampoline));
+            }
+        }
+        for freeze in &mut self.freezes {
+            freeze.frozen = apply_freeze(&self.handle, &mut self.freezer, freeze).ok();
+        }
+        if self.time_scale != 1.0 {
+            let _ = self.set_time_scale(self.time_scale);
+        }
+
+        self.restart_monitor();
+        self.listeners
+            .lock()
+            .unwrap()
+            .dispatch(&SessionEvent::Reattached {
+                process_id: self.handle.get_process_id(),
+            });
+
+        Ok(())
+    }
+
+    /// write original bytes back for every hook and patch, latest first
+    fn restore_all(&self) {
+        for hook in self.hooks.iter().rev() {
+            let _ = restore_bytes(&self.handle, &hook.applied);
+        }
+        for patch in self.patches.iter().rev() {
+            let _ = restore_bytes(&self.handle, &patch.applied);
+        }
+    }
+
+    fn restart_monitor(&mut self) {
+        // NOTE: the old monitor has to detach before the new one attach as debugger
+        self.monitor = None;
+        self.monitor = Some(Monitor::spawn(
+            self.handle.clone(),
+            self.listeners.clone(),
+            self.debug_exceptions,
+        ));
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        // NOTE: handle may lack `Synchronize`, only skip a process known to have exited
+        if let Ok(false) = self.handle.is_alive() {
+            return;
+        }
+
+        self.restore_all();
+    }
+}
+
+/// event of the process exiting, `None` when asked to stop before it exit
+fn wait_exit(handle: &Handle, stop: &Receiver<()>) -> Option<SessionEvent> {
+    let process_id = handle.get_process_id();
+
+    while let Err(TryRecvError::Empty) = stop.try_recv() {
+        match handle.wait_for_exit(Some(MONITOR_POLL_INTERVAL)) {
+            Ok(exit_code) => return Some(exit_event(process_id, exit_code, None)),
+            Err(Error::Timeout) => continue,
+            Err(_) => break,
+        }
+    }
+
+    // NOTE: handle without `Synchronize` cannot be waited on, poll the exit code instead
+    while let Err(RecvTimeoutError::Timeout) = stop.recv_timeout(MONITOR_POLL_INTERVAL) {
+        match handle.get_exit_code() {
+            Ok(Some(exit_code)) => return Some(exit_event(process_id, exit_code, None)),
+            Ok(None) => continue,
+            Err(_) => return Some(SessionEvent::Unmonitored { process_id }),
+        }
+    }
+
+    None
+}
+
+/// event of the debugged process exiting with the last exception nobody handled, `None` when
+/// asked to stop before it exit or waiting for debug event failed
+fn wait_debug_exit(debugger: &Debugger, stop: &Receiver<()>) -> Option<SessionEvent> {
+    let mut exception = None;
+    let mut is_attaching = true;
+
+    while let Err(TryRecvError::Empty) = stop.try_recv() {
+        let event = match debugger.wait_event(Some(MONITOR_POLL_INTERVAL)) {
+            Ok(event) => event,
+            Err(Error::Timeout) => continue,
+            Err(_) => return None,
+        };
+
+        if let DebugEventKind::ExitProcess { exit_code } = event.get_kind() {
+            let _ = debugger.continue_event(&event, true);
+            return Some(exit_event(debugger.get_process_id(), *exit_code, exception));
+        }
+
+        let handled = is_handled(event.get_kind(), &mut is_attaching, &mut exception);
+        let _ = debugger.continue_event(&event, handled);
+    }
+
+    None
+}
+
+/// whether the event is continued as handled, keeping in `exception` the code of exception
+/// none of the handlers of the process handled
+fn is_handled(kind: &DebugEventKind, is_attaching: &mut bool, exception: &mut Option<u32>) -> bool {
+    match kind {
+        // NOTE: attaching raise a breakpoint in the process, nobody there expect it
+        DebugEventKind::Breakpoint { .. } if *is_attaching => {
+            *is_attaching = false;
+            true
+        }
+        // NOTE: exception is passed on to handler of the process, second chance means none
+        // of them handled it
+        DebugEventKind::Breakpoint {
+            code, first_chance, ..
+        }
+        | DebugEventKind::SingleStep {
+            code, first_chance, ..
+        }
+        | DebugEventKind::Exception {
+            code, first_chance, ..
+        } => {
+            if !first_chance {
+                *exception = Some(*code);
+            }
+            false
+        }
+        _ => true,
+    }
+}
+
+/// exit event from the exit code, exit code of error `NTSTATUS` is taken as the exception
+/// killing the process
+fn exit_event(process_id: u32, exit_code: u32, exception: Option<u32>) -> SessionEvent {
+    let exception = exception.or((exit_code >= STATUS_SEVERITY_ERROR).then_some(exit_code));
+
+    match (exit_code, exception) {
+        (0, None) => SessionEvent::Exited { process_id },
+        _ => SessionEvent::Crashed {
+            process_id,
+            exit_code,
+            exception,
+        },
+    }
+}
+
+fn apply_freeze(
+    handle: &Handle,
+    freezer: &mut Freezer,
+    freeze: &SessionFreeze,
+) -> Result<FrozenValue> {
+    freezer.freeze_chain(
+        freeze.address.resolve(handle)?,
+        &freeze.offsets,
+        &freeze.bytes,
+    )
+}
+
+/// write the patch, returning the address written and the bytes it replaced
+fn apply_patch(handle: &Handle, patch: &SessionPatch) -> Result<(usize, Vec<u8>)> {
+    let address = patch.address.resolve(handle)?;
+    let original = handle.read_memory(address, patch.bytes.len())?;
+    handle.write_memory(address, &patch.bytes)?;
+    handle.flush_instruction_cache(address, patch.bytes.len())?;
+
+    Ok((address, original))
+}
+
+/// install the hook, returning the address written with the bytes it replaced and the
+/// trampoline
+fn apply_hook(handle: &Handle, hook: &SessionHook) -> Result<((usize, Vec<u8>), usize)> {
+    let target = hook.target.resolve(handle)?;
+    let detour = hook.detour.resolve(handle)?;
+    let guard = hooks::install_detour(handle, target, detour, hook.stolen_len)?;
+    let original = guard.get_original_bytes().to_vec();
+
+    Ok(((target, original), guard.leak()))
+}
+
+/// write back the bytes replaced at the address, nothing when not applied
+fn restore_bytes(handle: &Handle, applied: &Option<(usize, Vec<u8>)>) -> Result<()> {
+    let Some((address, original)) = applied else {
+        return Ok(());
+    };
+    handle.write_memory(*address, original)?;
+
+    handle.flush_instruction_cache(*address, original.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{exit_event, is_handled, SessionEvent};
+    use crate::debug::DebugEventKind;
+
+    #[test]
+    fn passing_unhandled_exceptions() {
+        let breakpoint = |first_chance| DebugEventKind::Breakpoint {
+            code: 0x80000003,
+            address: 0x1000,
+            first_chance,
+        };
+        let mut is_attaching = true;
+        let mut exception = None;
+
+        assert!(is_handled(
+            &breakpoint(true),
+            &mut is_attaching,
+            &mut exception
+        ));
+        assert!(!is_attaching);
+        assert!(!is_handled(
+            &breakpoint(true),
+            &mut is_attaching,
+            &mut exception
+        ));
+        assert_eq!(exception, None);
+        assert!(!is_handled(
+            &breakpoint(false),
+            &mut is_attaching,
+            &mut exception
+        ));
+        assert_eq!(exception, Some(0x80000003));
+
+        let single_step = DebugEventKind::SingleStep {
+            code: 0x4000001E,
+            address: 0x1000,
+            first_chance: false,
+        };
+        assert!(!is_handled(&single_step, &mut is_attaching, &mut exception));
+        assert_eq!(exception, Some(0x4000001E));
+        let thread = DebugEventKind::CreateThread { start_address: 0 };
+        assert!(is_handled(&thread, &mut is_attaching, &mut exception));
+    }
+
+    #[test]
+    fn classifying_exit() {
+        assert_eq!(
+            exit_event(4, 0, None),
+            SessionEvent::Exited { process_id: 4 }
+        );
+        assert_eq!(
+            exit_event(4, 1, None),
+            SessionEvent::Crashed {
+                process_id: 4,
+                exit_code: 1,
+                exception: None
+            }
+        );
+        assert_eq!(
+            exit_event(4, 0xC0000005, None),
+            SessionEvent::Crashed {
+                process_id: 4,
+                exit_code: 0xC0000005,
+                exception: Some(0xC0000005)
+            }
+        );
+        assert_eq!(
+            exit_event(4, 0, Some(0x80000003)),
+            SessionEvent::Crashed {
+                process_id: 4,
+                exit_code: 0,
+                exception: Some(0x80000003)
+            }
+        );
+    }
+}
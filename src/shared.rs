@@ -0,0 +1,417 @@
+use std::ffi::c_void;
+use std::ops::Deref;
+
+use windows::core::{HSTRING, PCWSTR};
+use windows::Win32::Foundation::{CloseHandle, BOOL, HANDLE, INVALID_HANDLE_VALUE};
+use windows::Win32::System::Memory::{
+    CreateFileMappingW, MapViewOfFile, OpenFileMappingW, UnmapViewOfFile, FILE_MAP_ALL_ACCESS,
+    MEMORY_MAPPED_VIEW_ADDRESS, PAGE_READWRITE,
+};
+use windows::Win32::System::Threading::GetCurrentProcess;
+
+use crate::error::{Error, Result};
+use crate::handle::Handle;
+use crate::memory::PageProtectionFlags;
+use crate::ntdll;
+
+/// `ViewUnmap` of `SECTION_INHERIT`, the view is not mapped into child processes
+const VIEW_UNMAP: u32 = 2;
+/// `SectionBasicInformation` of `SECTION_INFORMATION_CLASS`
+const SECTION_BASIC_INFORMATION: u32 = 0;
+/// alignment of the section offset a view start from
+const ALLOCATION_GRANULARITY: usize = 0x10000;
+
+/// Pagefile backed section mapped into the current process, unmapped and closed when dropped.
+///
+/// map the same section into another process with [Handle::map_remote_section] to share the
+/// memory without copying.
+pub struct SharedSection {
+    raw: HANDLE,
+    address: usize,
+    size: usize,
+}
+
+impl SharedSection {
+    /// create section of `size` bytes, named like `Local\\winmem` so other processes can open
+    /// it, or anonymous when `name` is `None`
+    pub fn create_shared(name: Option<&str>, size: usize) -> Result<Self> {
+        let name = name.map(HSTRING::from);
+        let name = match &name {
+            Some(name) => PCWSTR(name.as_ptr()),
+            None => PCWSTR::null(),
+        };
+
+        let size_u64 = size as u64;
+        let raw = unsafe {
+            CreateFileMappingW(
+                INVALID_HANDLE_VALUE,
+                None,
+                PAGE_READWRITE,
+                (size_u64 >> 32) as u32,
+                size_u64 as u32,
+                name,
+            )
+        }
+        .map_err(|e| Error::win32("CreateFileMappingW", e))?;
+
+        Self::map(raw, size)
+    }
+
+    /// open section named `name` created by another process, mapping its first `size` bytes
+    pub fn open(name: &str, size: usize) -> Result<Self> {
+        let raw = unsafe { OpenFileMappingW(FILE_MAP_ALL_ACCESS.0, BOOL(0), &HSTRING::from(name)) }
+            .map_err(|e| Error::win32("OpenFileMappingW", e))?;
+
+        Self::map(raw, size)
+    }
+
+    fn map(raw: HANDLE, size: usize) -> Result<Self> {
+        let view = unsafe { MapViewOfFile(raw, FILE_MAP_ALL_ACCESS, 0, 0, size) };
+        if view.Value.is_null() {
+            let error = Error::last_win32("MapViewOfFile");
+            let _ = unsafe { CloseHandle(raw) };
+            return Err(error);
+        }
+
+        Ok(Self {
+            raw,
+            address: view.Value as usize,
+            size,
+        })
+    }
+
+    /// address of the view in the current process
+    pub fn get_address(&self) -> usize {
+        self.address
+    }
+
+    /// size of the view
+    pub fn get_size(&self) -> usize {
+        self.size
+    }
+
+    /// pointer to the view, other processes may write to it at any time
+    pub fn as_ptr(&self) -> *mut u8 {
+        self.address as *mut u8
+    }
+
+    /// copy `len` bytes of the view starting from `offset`
+    pub fn read(&self, offset: usize, len: usize) -> Result<Vec<u8>> {
+        check_range(offset, len, self.size)?;
+
+        let mut buf = vec![0u8; len];
+        unsafe { std::ptr::copy(self.as_ptr().add(offset), buf.as_mut_ptr(), len) };
+        Ok(buf)
+    }
+
+    /// copy `bytes` into the view starting from `offset`
+    pub fn write(&self, offset: usize, bytes: &[u8]) -> Result<()> {
+        check_range(offset, bytes.len(), self.size)?;
+
+        unsafe { std::ptr::copy(bytes.as_ptr(), self.as_ptr().add(offset), bytes.len()) };
+        Ok(())
+    }
+}
+
+impl Deref for SharedSection {
+    type Target = HANDLE;
+
+    fn deref(&self) -> &Self::Target {
+        &self.raw
+    }
+}
+
+impl Drop for SharedSection {
+    fn drop(&mut self) {
+        let view = MEMORY_MAPPED_VIEW_ADDRESS {
+            Value: self.address as *mut c_void,
+        };
+        let _ = unsafe { UnmapViewOfFile(view) };
+        let _ = unsafe { CloseHandle(self.raw) };
+    }
+}
+
+/// view of a section mapped into a process, unmapped when dropped
+pub struct RemoteView<'a> {
+    handle: &'a Handle,
+    address: usize,
+    size: usize,
+}
+
+impl RemoteView<'_> {
+    /// address of the view in the process
+    pub fn get_address(&self) -> usize {
+        self.address
+    }
+
+    /// size of the view
+    pub fn get_size(&self) -> usize {
+        self.size
+    }
+
+    /// keep the view mapped after drop, returning its address
+    pub fn leak(self) -> usize {
+        let address = self.address;
+        std::mem::forget(self);
+        address
+    }
+}
+
+impl Drop for RemoteView<'_> {
+    fn drop(&mut self) {
+        if let Some(unmap) = ntdll::NtUnmapViewOfSection() {
+            let _ = unsafe { unmap(**self.handle, self.address as *mut c_void) };
+        }
+    }
+}
+
+impl Handle {
+    /// map the whole `section` into the process, writes on either side are seen by the other.
+    ///
+    /// handle need `VmOperation` access.
+    pub fn map_remote_section(
+        &self,
+        section: &SharedSection,
+        protection: PageProtectionFlags,
+    ) -> Result<RemoteView<'_>> {
+        let (address, size) = map_view_of_section(**section, **self, 0, 0, protection)?;
+
+        Ok(RemoteView {
+            handle: self,
+            address,
+            size,
+        })
+    }
+}
+
+/// section backed memory of a process mapped into the current process, unmapped when dropped
+pub struct MappedView {
+    section: HANDLE,
+    view: usize,
+    address: usize,
+    offset: usize,
+    size: usize,
+}
+
+impl MappedView {
+    /// address of the memory in the process it was mapped from
+    pub fn get_address(&self) -> usize {
+        self.address
+    }
+
+    /// size of the memory
+    pub fn get_size(&self) -> usize {
+        self.size
+    }
+
+    /// pointer to the memory in the current process, the process may write to it at any time
+    pub fn as_ptr(&self) -> *const u8 {
+        (self.view + self.offset) as *const u8
+    }
+
+    /// copy `len` bytes of the memory starting from `offset`
+    pub fn read(&self, offset: usize, len: usize) -> Result<Vec<u8>> {
+        let mut buf = vec![0u8; len];
+        self.read_into(offset, &mut buf)?;
+        Ok(buf)
+    }
+
+    /// copy the memory starting from `offset` into `buf`
+    pub fn read_into(&self, offset: usize, buf: &mut [u8]) -> Result<()> {
+        check_range(offset, buf.len(), self.size)?;
+
+        unsafe { std::ptr::copy(self.as_ptr().add(offset), buf.as_mut_ptr(), buf.len()) };
+        Ok(())
+    }
+}
+
+impl Drop for MappedView {
+    fn drop(&mut self) {
+        if let Some(unmap) = ntdll::NtUnmapViewOfSection() {
+            let _ = unsafe { unmap(GetCurrentProcess(), self.view as *mut c_void) };
+        }
+        let _ = unsafe { CloseHandle(self.section) };
+    }
+}
+
+/// Look at `SECTION_BASIC_INFORMATION`
+#[repr(C)]
+#[derive(Default)]
+struct SectionBasicInformation {
+    base_address: usize,
+    allocation_attributes: u32,
+    maximum_size: i64,
+}
+
+impl Handle {
+    /// map `size` bytes at `address` of section backed memory, like a file or pagefile
+    /// mapping, into the current process to read it without going through the process.
+    ///
+    /// the section is found among the handles opened by the process, so memory whose section
+    /// handle was already closed cannot be mapped. memory still all zero cannot tell sections
+    /// apart and fail with [Error::NotFound]. handle need `DupHandle` and `VmRead` access.
+    pub fn try_map_view(&self, address: usize, size: usize) -> Result<MappedView> {
+        let region = self.query_region_information(address)?;
+        if !region.is_mapped_data_file() && !region.is_mapped_page_file() {
+            return Err(Error::Unsupported);
+        }
+
+        let base = region.get_allocation_base();
+        let end = address.checked_add(size).ok_or(Error::InvalidInput)?;
+        if size == 0 || end > base + region.get_region_size() {
+            return Err(Error::InvalidInput);
+        }
+
+        let (section_offset, offset, map_size) = view_window(base, address, size);
+        let mut expected = vec![0u8; size];
+        self.read_memory_into(address, &mut expected)?;
+        // NOTE: fresh sections are all zero, any of them would match
+        if expected.iter().all(|e| *e == 0) {
+            return Err(Error::NotFound);
+        }
+        let mut actual = vec![0u8; size];
+
+        for info in self.get_handles()? {
+            if info.get_type_name() != Some("Section") {
+                continue;
+            }
+            let Ok(section) = self.duplicate_remote_handle(info.get_entry().get_value()) else {
+                continue;
+            };
+
+            // NOTE: there is no way to ask which section backs a view, assume the view start at
+            // the beginning of its section and confirm by comparing content
+            let is_large_enough =
+                query_section_size(section).is_some_and(|e| e >= section_offset + map_size as u64);
+            let view = match is_large_enough {
+                true => map_view_of_section(
+                    section,
+                    unsafe { GetCurrentProcess() },
+                    section_offset,
+                    map_size,
+                    PageProtectionFlags::ReadOnly,
+                )
+                .ok(),
+                false => None,
+            };
+
+            if let Some((view, _)) = view {
+                let view = MappedView {
+                    section,
+                    view,
+                    address,
+                    offset,
+                    size,
+                };
+                if view.read_into(0, &mut actual).is_ok() && actual == expected {
+                    return Ok(view);
+                }
+                continue;
+            }
+
+            let _ = unsafe { CloseHandle(section) };
+        }
+
+        Err(Error::NotFound)
+    }
+}
+
+/// reject `len` bytes from `offset` reaching past a view of `size` bytes
+fn check_range(offset: usize, len: usize, size: usize) -> Result<()> {
+    match offset.checked_add(len) {
+        Some(end) if end <= size => Ok(()),
+        _ => Err(Error::InvalidInput),
+    }
+}
+
+/// size of `section`
+fn query_section_size(section: HANDLE) -> Option<u64> {
+    let query = ntdll::NtQuerySection()?;
+
+    let mut info = SectionBasicInformation::default();
+    unsafe {
+        query(
+            section,
+            SECTION_BASIC_INFORMATION,
+            &mut info as *mut _ as *mut c_void,
+            std::mem::size_of::<SectionBasicInformation>(),
+            std::ptr::null_mut(),
+        )
+    }
+    .ok()
+    .ok()?;
+
+    u64::try_from(info.maximum_size).ok()
+}
+
+/// section offset aligned to allocation granularity, offset of `address` inside the view and
+/// size of the view to map `size` bytes at `address` of a view starting at `base`
+fn view_window(base: usize, address: usize, size: usize) -> (u64, usize, usize) {
+    let relative = address - base;
+    let section_offset = relative - relative % ALLOCATION_GRANULARITY;
+    let offset = relative - section_offset;
+
+    (section_offset as u64, offset, offset + size)
+}
+
+/// map `size` bytes of `section` from `offset` into `process`, whole section when `size` is
+/// zero, returning address and size of the view
+pub(crate) fn map_view_of_section(
+    section: HANDLE,
+    process: HANDLE,
+    offset: u64,
+    size: usize,
+    protection: PageProtectionFlags,
+) -> Result<(usize, usize)> {
+    let map = ntdll::NtMapViewOfSection().ok_or(Error::Unsupported)?;
+
+    let mut address = std::ptr::null_mut::<c_void>();
+    let mut offset = offset as i64;
+    let mut size = size;
+    unsafe {
+        map(
+            section,
+            process,
+            &mut address,
+            0,
+            0,
+            &mut offset,
+            &mut size,
+            VIEW_UNMAP,
+            0,
+            protection.bits(),
+        )
+    }
+    .ok()
+    .map_err(|e| Error::win32("NtMapViewOfSection", e))?;
+
+    Ok((address as usize, size))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_range, view_window};
+    use crate::error::Error;
+
+    #[test]
+    fn checking_view_range() {
+        assert!(check_range(0, 0x100, 0x100).is_ok());
+        assert!(check_range(0x100, 0, 0x100).is_ok());
+        assert!(matches!(
+            check_range(0xF0, 0x11, 0x100),
+            Err(Error::InvalidInput)
+        ));
+        assert!(check_range(0x101, 0, 0x100).is_err());
+        assert!(check_range(usize::MAX, 2, 0x100).is_err());
+    }
+
+    #[test]
+    fn aligning_view_window() {
+        assert_eq!(view_window(0x20000, 0x20000, 0x100), (0, 0, 0x100));
+        assert_eq!(
+            view_window(0x20000, 0x31234, 0x100),
+            (0x10000, 0x1234, 0x1334)
+        );
+        assert_eq!(view_window(0x20000, 0x40000, 0x2000), (0x20000, 0, 0x2000));
+    }
+}
@@ -0,0 +1,201 @@
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::*;
+
+/// bytes tested per step of [ByteMatches]
+const BLOCK_SIZE: usize = 32;
+
+/// widest instruction set available on the running cpu
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Level {
+    Scalar,
+    #[cfg(target_arch = "x86_64")]
+    Sse2,
+    #[cfg(target_arch = "x86_64")]
+    Avx2,
+}
+
+impl Level {
+    fn detect() -> Self {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("avx2") {
+                return Self::Avx2;
+            }
+            if is_x86_feature_detected!("sse2") {
+                return Self::Sse2;
+            }
+        }
+
+        Self::Scalar
+    }
+}
+
+/// offsets of `byte` in a haystack, testing a block of bytes at once
+pub(crate) struct ByteMatches<'a> {
+    haystack: &'a [u8],
+    byte: u8,
+    level: Level,
+    block_start: usize,
+    mask: u32,
+}
+
+impl<'a> ByteMatches<'a> {
+    pub(crate) fn new(haystack: &'a [u8], byte: u8) -> Self {
+        let level = Level::detect();
+        let mask = block_mask(level, haystack, byte);
+
+        Self {
+            haystack,
+            byte,
+            level,
+            block_start: 0,
+            mask,
+        }
+    }
+}
+
+impl Iterator for ByteMatches<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.mask == 0 {
+            self.block_start += BLOCK_SIZE;
+            if self.block_start >= self.haystack.len() {
+                return None;
+            }
+
+            self.mask = block_mask(self.level, &self.haystack[self.block_start..], self.byte);
+        }
+
+        let offset = self.mask.trailing_zeros() as usize;
+        self.mask &= self.mask - 1;
+        Some(self.block_start + offset)
+    }
+}
+
+/// bit set for every byte equal to `byte` in the first [BLOCK_SIZE] bytes of `block`
+fn block_mask(level: Level, block: &[u8], byte: u8) -> u32 {
+    match level {
+        #[cfg(target_arch = "x86_64")]
+        Level::Avx2 if block.len() >= BLOCK_SIZE => unsafe { block_mask_avx2(block, byte) },
+        #[cfg(target_arch = "x86_64")]
+        Level::Sse2 | Level::Avx2 if block.len() >= BLOCK_SIZE => unsafe {
+            block_mask_sse2(block, byte)
+        },
+        _ => block
+            .iter()
+            .take(BLOCK_SIZE)
+            .enumerate()
+            .filter(|(_, e)| **e == byte)
+            .fold(0, |mask, (index, _)| mask | 1 << index),
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn block_mask_avx2(block: &[u8], byte: u8) -> u32 {
+    let data = _mm256_loadu_si256(block.as_ptr() as *const __m256i);
+    let equal = _mm256_cmpeq_epi8(data, _mm256_set1_epi8(byte as i8));
+    _mm256_movemask_epi8(equal) as u32
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse2")]
+unsafe fn block_mask_sse2(block: &[u8], byte: u8) -> u32 {
+    let needle = _mm_set1_epi8(byte as i8);
+    let low = _mm_loadu_si128(block.as_ptr() as *const __m128i);
+    let high = _mm_loadu_si128(block.as_ptr().add(16) as *const __m128i);
+    let low = _mm_movemask_epi8(_mm_cmpeq_epi8(low, needle)) as u32;
+    let high = _mm_movemask_epi8(_mm_cmpeq_epi8(high, needle)) as u32;
+    low | high << 16
+}
+
+/// up to 16 bytes of signature with mask of bytes to compare, `0xFF` for compared byte
+pub(crate) struct MaskedBytes {
+    bytes: [u8; 16],
+    mask: [u8; 16],
+}
+
+impl MaskedBytes {
+    /// `None` when the signature longer than 16 bytes
+    pub(crate) fn new(signature: &[Option<u8>]) -> Option<Self> {
+        if signature.len() > 16 {
+            return None;
+        }
+
+        let mut masked = Self {
+            bytes: [0; 16],
+            mask: [0; 16],
+        };
+        for (index, byte) in signature.iter().enumerate() {
+            if let Some(byte) = byte {
+                masked.bytes[index] = *byte;
+                masked.mask[index] = 0xFF;
+            }
+        }
+
+        Some(masked)
+    }
+
+    /// whether `data` start with the masked bytes, `None` when `data` shorter than 16 bytes
+    pub(crate) fn matches(&self, data: &[u8]) -> Option<bool> {
+        if data.len() < 16 {
+            return None;
+        }
+
+        #[cfg(target_arch = "x86_64")]
+        {
+            Some(unsafe { self.matches_sse2(data) })
+        }
+        #[cfg(not(target_arch = "x86_64"))]
+        {
+            Some((0..16).all(|index| (data[index] ^ self.bytes[index]) & self.mask[index] == 0))
+        }
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[target_feature(enable = "sse2")]
+    unsafe fn matches_sse2(&self, data: &[u8]) -> bool {
+        let data = _mm_loadu_si128(data.as_ptr() as *const __m128i);
+        let bytes = _mm_loadu_si128(self.bytes.as_ptr() as *const __m128i);
+        let mask = _mm_loadu_si128(self.mask.as_ptr() as *const __m128i);
+
+        let difference = _mm_and_si128(_mm_xor_si128(data, bytes), mask);
+        _mm_movemask_epi8(_mm_cmpeq_epi8(difference, _mm_setzero_si128())) == 0xFFFF
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{block_mask, ByteMatches, Level, MaskedBytes};
+
+    #[test]
+    fn matching_bytes_across_blocks() {
+        let mut haystack = vec![0u8; 100];
+        for index in [0, 31, 32, 64, 99] {
+            haystack[index] = 0xAA;
+        }
+
+        assert_eq!(
+            ByteMatches::new(&haystack, 0xAA).collect::<Vec<_>>(),
+            vec![0, 31, 32, 64, 99]
+        );
+        assert_eq!(
+            block_mask(Level::detect(), &haystack, 0xAA),
+            block_mask(Level::Scalar, &haystack, 0xAA)
+        );
+    }
+
+    #[test]
+    fn comparing_masked_bytes() {
+        let masked = MaskedBytes::new(&[Some(0x48), None, Some(0x05)]).unwrap();
+        let mut data = [0u8; 16];
+        data[..3].copy_from_slice(&[0x48, 0xFF, 0x05]);
+
+        assert_eq!(masked.matches(&data), Some(true));
+        data[2] = 0x06;
+        assert_eq!(masked.matches(&data), Some(false));
+        assert_eq!(masked.matches(&data[..15]), None);
+        assert!(MaskedBytes::new(&[None; 17]).is_none());
+    }
+}
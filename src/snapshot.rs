@@ -0,0 +1,129 @@
+use crate::error::Result;
+use crate::handle::Handle;
+use crate::memory::{diff_ranges, MemoryBasicInformation, MemoryBasicInformationFilter};
+
+/// bytes that differ between two [MemorySnapshot]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DiffRegion {
+    address: usize,
+    old: Vec<u8>,
+    new: Vec<u8>,
+}
+
+impl DiffRegion {
+    /// address of the first changed byte
+    pub fn get_address(&self) -> usize {
+        self.address
+    }
+
+    /// bytes in the older snapshot
+    pub fn get_old(&self) -> &[u8] {
+        &self.old
+    }
+
+    /// bytes in the newer snapshot
+    pub fn get_new(&self) -> &[u8] {
+        &self.new
+    }
+}
+
+/// Copy of readable memory of a process at a point in time.
+pub struct MemorySnapshot {
+    regions: Vec<(usize, Vec<u8>)>,
+}
+
+impl MemorySnapshot {
+    /// copy every committed readable region accepted by `filter`, skipping unreadable one
+    pub fn capture<F>(handle: &Handle, mut filter: F) -> Result<Self>
+    where
+        F: FnMut(&MemoryBasicInformation) -> bool,
+    {
+        let mut regions: Vec<(usize, Vec<u8>)> = handle
+            .get_memory_basic_informations()
+            .committed()
+            .readable()
+            .filter(|mbi| filter(mbi))
+            .filter_map(|mbi| {
+                handle
+                    .read_memory(mbi.get_base_address(), mbi.get_region_size())
+                    .ok()
+                    .map(|bytes| (mbi.get_base_address(), bytes))
+            })
+            .collect();
+        regions.sort_unstable_by_key(|(address, _)| *address);
+
+        Ok(Self { regions })
+    }
+
+    /// `(address, bytes)` of every captured region ordered by address
+    pub fn get_regions(&self) -> impl Iterator<Item = (usize, &[u8])> {
+        self.regions
+            .iter()
+            .map(|(address, bytes)| (*address, bytes.as_slice()))
+    }
+
+    /// bytes changed from this snapshot to `other`, only memory captured by both is compared
+    pub fn diff(&self, other: &MemorySnapshot) -> Vec<DiffRegion> {
+        let mut diffs = Vec::new();
+        let mut old_regions = self.regions.iter().peekable();
+        let mut new_regions = other.regions.iter().peekable();
+
+        while let (Some((old_address, old)), Some((new_address, new))) =
+            (old_regions.peek(), new_regions.peek())
+        {
+            let old_end = old_address + old.len();
+            let new_end = new_address + new.len();
+            let start = *old_address.max(new_address);
+            let end = old_end.min(new_end);
+
+            if start < end {
+                let old = &old[start - old_address..end - old_address];
+                let new = &new[start - new_address..end - new_address];
+                diffs.extend(
+                    diff_ranges(start, old, new)
+                        .into_iter()
+                        .map(|range| DiffRegion {
+                            address: range.start,
+                            old: old[range.start - start..range.end - start].to_vec(),
+                            new: new[range.start - start..range.end - start].to_vec(),
+                        }),
+                );
+            }
+
+            if old_end <= new_end {
+                old_regions.next();
+            } else {
+                new_regions.next();
+            }
+        }
+
+        diffs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MemorySnapshot;
+
+    #[test]
+    fn diffing_overlapping_regions() {
+        let old = MemorySnapshot {
+            regions: vec![(0x1000, vec![0; 0x10]), (0x2000, vec![1, 2, 3, 4])],
+        };
+        let new = MemorySnapshot {
+            regions: vec![
+                (0x1008, vec![0, 9, 9, 0, 0, 0, 0, 0, 7, 7]),
+                (0x2000, vec![1, 2, 3, 5]),
+            ],
+        };
+
+        let diffs = old.diff(&new);
+        assert_eq!(diffs.len(), 2);
+        assert_eq!(diffs[0].get_address(), 0x1009);
+        assert_eq!(diffs[0].get_old(), &[0, 0]);
+        assert_eq!(diffs[0].get_new(), &[9, 9]);
+        assert_eq!(diffs[1].get_address(), 0x2003);
+        assert_eq!(diffs[1].get_new(), &[5]);
+    }
+}
@@ -0,0 +1,486 @@
+use std::sync::Arc;
+
+use windows::Win32::Media::timeGetTime;
+use windows::Win32::System::Memory::VirtualAllocEx;
+use windows::Win32::System::Performance::{QueryPerformanceCounter, QueryPerformanceFrequency};
+use windows::Win32::System::SystemInformation::{GetTickCount, GetTickCount64};
+
+use crate::backend::MemoryBackend;
+use crate::error::{Error, Result};
+use crate::handle::Handle;
+use crate::hooks::encode_jump;
+use crate::memory::{PageProtectionFlags, VirtualAllocationType};
+use crate::module::ModuleFilter;
+
+/// size of memory allocated for every hook, its state followed by its code
+const HOOK_SIZE: usize = 0x80;
+/// offset of the code of a hook from the start of its memory
+const CODE_OFFSET: usize = 0x20;
+/// granularity of addresses `VirtualAllocEx` reserve memory at
+const ALLOCATION_GRANULARITY: usize = 0x10000;
+/// farthest distance `jmp rel32` reach, leaving room for the hook itself
+const JUMP_REACH: usize = 0x7FFF_0000;
+
+/// function of a process telling the time, hooked by [SpeedHack]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TimeFunction {
+    /// `GetTickCount` of `kernel32.dll`, milliseconds since boot truncated to 32 bits
+    GetTickCount,
+    /// `GetTickCount64` of `kernel32.dll`, milliseconds since boot
+    GetTickCount64,
+    /// `QueryPerformanceCounter` of `kernel32.dll`, ticks of the performance counter
+    QueryPerformanceCounter,
+    /// `timeGetTime` of `winmm.dll`, milliseconds since boot truncated to 32 bits
+    TimeGetTime,
+}
+
+impl TimeFunction {
+    /// every function telling the time
+    pub const ALL: [Self; 4] = [
+        Self::GetTickCount,
+        Self::GetTickCount64,
+        Self::QueryPerformanceCounter,
+        Self::TimeGetTime,
+    ];
+
+    /// name of the module exporting the function
+    pub fn get_module_name(&self) -> &'static str {
+        match self {
+            Self::TimeGetTime => "winmm.dll",
+            _ => "kernel32.dll",
+        }
+    }
+
+    /// name the function exported as
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Self::GetTickCount => "GetTickCount",
+            Self::GetTickCount64 => "GetTickCount64",
+            Self::QueryPerformanceCounter => "QueryPerformanceCounter",
+            Self::TimeGetTime => "timeGetTime",
+        }
+    }
+
+    /// whether the function write the time to its argument instead of returning it
+    fn is_counter(&self) -> bool {
+        *self == Self::QueryPerformanceCounter
+    }
+
+    /// units of the function passing every tick of performance counter
+    fn units_per_tick(&self, frequency: i64) -> f64 {
+        match self {
+            Self::QueryPerformanceCounter => 1.0,
+            _ => 1000.0 / frequency as f64,
+        }
+    }
+
+    /// what the function return right now, same for every process on the system
+    fn now(&self) -> Result<i64> {
+        match self {
+            Self::GetTickCount => Ok(unsafe { GetTickCount() } as i64),
+            Self::GetTickCount64 => Ok(unsafe { GetTickCount64() } as i64),
+            Self::QueryPerformanceCounter => query_performance_counter(),
+            Self::TimeGetTime => Ok(unsafe { timeGetTime() } as i64),
+        }
+    }
+}
+
+/// where scaled time of a hook continue from, written at the start of its memory
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct TimeState {
+    base_counter: i64,
+    base_value: i64,
+    factor: f64,
+}
+
+impl TimeState {
+    /// value the hook return when the performance counter read `counter`
+    fn value_at(&self, counter: i64) -> i64 {
+        self.base_value + ((counter - self.base_counter) as f64 * self.factor).round() as i64
+    }
+
+    /// state continuing from the value at `counter` with another factor, so time does not jump
+    fn rescale(&self, counter: i64, factor: f64) -> Self {
+        Self {
+            base_counter: counter,
+            base_value: self.value_at(counter),
+            factor,
+        }
+    }
+
+    fn to_bytes(self) -> [u8; 24] {
+        let mut bytes = [0u8; 24];
+        bytes[..8].copy_from_slice(&self.base_counter.to_le_bytes());
+        bytes[8..16].copy_from_slice(&self.base_value.to_le_bytes());
+        bytes[16..].copy_from_slice(&self.factor.to_le_bytes());
+
+        bytes
+    }
+}
+
+/// function redirected to its scaling code
+struct TimeHook {
+    function: TimeFunction,
+    target: usize,
+    original: Vec<u8>,
+    address: usize,
+    units_per_tick: f64,
+    state: TimeState,
+}
+
+/// Functions telling the time of a process hooked to make time pass faster or slower, like a
+/// game running in slow motion, unhooked on drop.
+///
+/// every hook jump to code written into the process computing scaled time from
+/// `NtQueryPerformanceCounter`, so the original function is never called and only the start
+/// of it is overwritten.
+///
+/// only the exports of `kernel32.dll` and `winmm.dll` are hooked. code importing the same
+/// functions through api sets like `api-ms-win-core-sysinfo-l1-1-0.dll` call `kernelbase.dll`
+/// directly and keep running at normal speed.
+pub struct SpeedHack {
+    handle: Arc<Handle>,
+    scale: f64,
+    hooks: Vec<TimeHook>,
+}
+
+impl SpeedHack {
+    /// hook `functions` at normal speed, skipping those of modules the process has not loaded.
+    ///
+    /// fail with [Error::NotFound] when none of them hooked.
+    pub fn install(handle: Arc<Handle>, functions: &[TimeFunction]) -> Result<Self> {
+        let pointer_size = handle.get_pointer_size()?;
+        let filter = ModuleFilter::for_pointer_size(pointer_size);
+        let query = handle.resolve_export_in("ntdll.dll", "NtQueryPerformanceCounter", filter)?;
+        let frequency = query_performance_frequency()?;
+
+        // NOTE: hooks installed so far are removed when it drop on error
+        let mut speedhack = Self {
+            handle,
+            scale: 1.0,
+            hooks: Vec::new(),
+        };
+
+        for function in functions {
+            let target = match speedhack.handle.resolve_export_in(
+                function.get_module_name(),
+                function.get_name(),
+                filter,
+            ) {
+                Ok(target) => target,
+                Err(Error::NotFound) => continue,
+                Err(e) => return Err(e),
+            };
+
+            let hook = install_hook(
+                &speedhack.handle,
+                *function,
+                target,
+                query,
+                frequency,
+                pointer_size,
+            )?;
+            speedhack.hooks.push(hook);
+        }
+
+        if speedhack.hooks.is_empty() {
+            return Err(Error::NotFound);
+        }
+
+        Ok(speedhack)
+    }
+
+    /// how many times as fast time pass in the process
+    pub fn get_scale(&self) -> f64 {
+        self.scale
+    }
+
+    /// functions hooked
+    pub fn get_functions(&self) -> Vec<TimeFunction> {
+        self.hooks.iter().map(|e| e.function).collect()
+    }
+
+    /// make time pass `scale` times as fast from now on, like `2.0` for double speed or `0.0`
+    /// to stop it
+    pub fn set_scale(&mut self, scale: f64) -> Result<()> {
+        if !scale.is_finite() || scale < 0.0 {
+            return Err(Error::InvalidInput);
+        }
+
+        let counter = query_performance_counter()?;
+        for hook in &mut self.hooks {
+            let state = hook.state.rescale(counter, scale * hook.units_per_tick);
+            // NOTE: not atomic, a thread reading the state meanwhile may see one odd value
+            self.handle.write_memory(hook.address, &state.to_bytes())?;
+            hook.state = state;
+        }
+        self.scale = scale;
+
+        Ok(())
+    }
+
+    /// restore the start of every hooked function, same as dropping it
+    pub fn uninstall(self) {}
+}
+
+impl Drop for SpeedHack {
+    fn drop(&mut self) {
+        // NOTE: memory of the hooks is left allocated, a thread may still be running its code
+        for hook in self.hooks.iter().rev() {
+            let _ = self.handle.write_memory(hook.target, &hook.original);
+            let _ = self
+                .handle
+                .flush_instruction_cache(hook.target, hook.original.len());
+        }
+    }
+}
+
+/// write the state and code of the hook, then jump to it from the start of `target`
+fn install_hook(
+    handle: &Handle,
+    function: TimeFunction,
+    target: usize,
+    query: usize,
+    frequency: i64,
+    pointer_size: usize,
+) -> Result<TimeHook> {
+    let address = alloc_near(handle, target, HOOK_SIZE, pointer_size)?;
+    let code_address = address + CODE_OFFSET;
+
+    let units_per_tick = function.units_per_tick(frequency);
+    let state = TimeState {
+        base_counter: query_performance_counter()?,
+        base_value: function.now()?,
+        factor: units_per_tick,
+    };
+    let code = match pointer_size {
+        4 => build_stub_x86(address as u32, query as u32, function.is_counter()),
+        _ => build_stub_x64(address as u64, query as u64, function.is_counter()),
+    };
+    handle.write_memory(address, &state.to_bytes())?;
+    handle.write_memory(code_address, &code)?;
+    handle.flush_instruction_cache(code_address, code.len())?;
+
+    let jump = encode_jump(target, code_address, pointer_size);
+    let original = handle.read_memory(target, jump.len())?;
+    handle.write_memory(target, &jump)?;
+    handle.flush_instruction_cache(target, jump.len())?;
+
+    Ok(TimeHook {
+        function,
+        target,
+        original,
+        address,
+        units_per_tick,
+        state,
+    })
+}
+
+/// allocate executable memory `jmp rel32` at `target` reach, anywhere for 32 bits process
+fn alloc_near(handle: &Handle, target: usize, size: usize, pointer_size: usize) -> Result<usize> {
+    if pointer_size == 4 {
+        return handle.alloc_memory(size, PageProtectionFlags::ExecuteReadWrite);
+    }
+
+    let low = target
+        .saturating_sub(JUMP_REACH)
+        .max(ALLOCATION_GRANULARITY);
+    let high = target.saturating_add(JUMP_REACH);
+
+    let mut address = low;
+    while address < high {
+        let Some(mbi) = handle.query_memory(address) else {
+            break;
+        };
+        let end = mbi.get_base_address() + mbi.get_region_size();
+        let candidate = address.next_multiple_of(ALLOCATION_GRANULARITY);
+
+        if mbi.get_state().contains(VirtualAllocationType::Free)
+            && candidate + size <= end.min(high)
+        {
+            let allocated = unsafe {
+                VirtualAllocEx(
+                    **handle,
+                    Some(candidate as *const _),
+                    size,
+                    (VirtualAllocationType::Commit | VirtualAllocationType::Reserve).into(),
+                    PageProtectionFlags::ExecuteReadWrite.into(),
+                )
+            };
+            if !allocated.is_null() {
+                return Ok(allocated as usize);
+            }
+        }
+
+        if end <= address {
+            break;
+        }
+        address = end;
+    }
+
+    Err(Error::NotFound)
+}
+
+fn query_performance_counter() -> Result<i64> {
+    let mut counter = 0i64;
+    unsafe { QueryPerformanceCounter(&mut counter) }
+        .map_err(|e| Error::win32("QueryPerformanceCounter", e))?;
+
+    Ok(counter)
+}
+
+fn query_performance_frequency() -> Result<i64> {
+    let mut frequency = 0i64;
+    unsafe { QueryPerformanceFrequency(&mut frequency) }
+        .map_err(|e| Error::win32("QueryPerformanceFrequency", e))?;
+
+    Ok(frequency)
+}
+
+/// x87 instructions turning the counter at `stack` into scaled time in place, with address
+/// of the state in `eax` or `rax`. `stack` is ModRM with empty reg field and what follow it.
+fn scale_counter(stack: &[u8]) -> Vec<u8> {
+    let mut code = Vec::new();
+    // fild qword [stack]; fild qword [eax]; fsubp; fmul qword [eax + 16]; fild qword [eax + 8]
+    // faddp; fistp qword [stack]
+    code.push(0xDF);
+    code.push(stack[0] | (5 << 3));
+    code.extend(&stack[1..]);
+    code.extend([
+        0xDF, 0x28, 0xDE, 0xE9, 0xDC, 0x48, 0x10, 0xDF, 0x68, 0x08, 0xDE, 0xC1,
+    ]);
+    code.push(0xDF);
+    code.push(stack[0] | (7 << 3));
+    code.extend(&stack[1..]);
+
+    code
+}
+
+/// code of the hook for microsoft x64 calling convention
+fn build_stub_x64(state: u64, query: u64, is_counter: bool) -> Vec<u8> {
+    let mut stub = Vec::new();
+    // push rbx; sub rsp, 0x30
+    stub.extend([0x53, 0x48, 0x83, 0xEC, 0x30]);
+    if is_counter {
+        // mov rbx, rcx
+        stub.extend([0x48, 0x89, 0xCB]);
+    }
+
+    // lea rcx, [rsp + 0x28]; xor edx, edx; mov rax, query; call rax
+    stub.extend([0x48, 0x8D, 0x4C, 0x24, 0x28, 0x31, 0xD2, 0x48, 0xB8]);
+    stub.extend(query.to_le_bytes());
+    stub.extend([0xFF, 0xD0]);
+
+    // mov rax, state
+    stub.extend([0x48, 0xB8]);
+    stub.extend(state.to_le_bytes());
+    stub.extend(scale_counter(&[0x44, 0x24, 0x28]));
+
+    // mov rax, [rsp + 0x28]
+    stub.extend([0x48, 0x8B, 0x44, 0x24, 0x28]);
+    if is_counter {
+        // mov [rbx], rax; mov eax, 1
+        stub.extend([0x48, 0x89, 0x03, 0xB8, 0x01, 0x00, 0x00, 0x00]);
+    }
+
+    // add rsp, 0x30; pop rbx; ret
+    stub.extend([0x48, 0x83, 0xC4, 0x30, 0x5B, 0xC3]);
+
+    stub
+}
+
+/// code of the hook for stdcall
+fn build_stub_x86(state: u32, query: u32, is_counter: bool) -> Vec<u8> {
+    let mut stub = Vec::new();
+    // push ebx; sub esp, 8
+    stub.extend([0x53, 0x83, 0xEC, 0x08]);
+    if is_counter {
+        // mov ebx, [esp + 0x10]
+        stub.extend([0x8B, 0x5C, 0x24, 0x10]);
+    }
+
+    // mov eax, esp; push 0; push eax; mov eax, query; call eax
+    stub.extend([0x89, 0xE0, 0x6A, 0x00, 0x50, 0xB8]);
+    stub.extend(query.to_le_bytes());
+    stub.extend([0xFF, 0xD0]);
+
+    // mov eax, state
+    stub.push(0xB8);
+    stub.extend(state.to_le_bytes());
+    stub.extend(scale_counter(&[0x04, 0x24]));
+
+    // mov eax, [esp]; mov edx, [esp + 4]
+    stub.extend([0x8B, 0x04, 0x24, 0x8B, 0x54, 0x24, 0x04]);
+    if is_counter {
+        // mov [ebx], eax; mov [ebx + 4], edx; mov eax, 1
+        stub.extend([0x89, 0x03, 0x89, 0x53, 0x04, 0xB8, 0x01, 0x00, 0x00, 0x00]);
+    }
+
+    // add esp, 8; pop ebx; ret or ret 4
+    stub.extend([0x83, 0xC4, 0x08, 0x5B]);
+    match is_counter {
+        true => stub.extend([0xC2, 0x04, 0x00]),
+        false => stub.push(0xC3),
+    }
+
+    stub
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build_stub_x64, build_stub_x86, TimeState, CODE_OFFSET, HOOK_SIZE};
+    use crate::module::ModuleFilter;
+
+    #[test]
+    fn rescaling_time() {
+        let state = TimeState {
+            base_counter: 1000,
+            base_value: 50,
+            factor: 0.5,
+        };
+        assert_eq!(state.value_at(1000), 50);
+        assert_eq!(state.value_at(1100), 100);
+
+        let faster = state.rescale(1100, 2.0);
+        assert_eq!(faster.value_at(1100), 100);
+        assert_eq!(faster.value_at(1110), 120);
+        assert_eq!(&faster.to_bytes()[16..], &2.0f64.to_le_bytes());
+    }
+
+    #[test]
+    fn building_stubs() {
+        let stub = build_stub_x64(0x2000, 0x7FF0_0000_1000, true);
+        assert!(CODE_OFFSET + stub.len() <= HOOK_SIZE);
+        assert_eq!(
+            &stub[..8],
+            &[0x53, 0x48, 0x83, 0xEC, 0x30, 0x48, 0x89, 0xCB]
+        );
+        // fild qword [rsp + 0x28] right after loading the state
+        assert_eq!(&stub[37..41], &[0xDF, 0x6C, 0x24, 0x28]);
+        assert!(stub.ends_with(&[0x48, 0x83, 0xC4, 0x30, 0x5B, 0xC3]));
+
+        let stub = build_stub_x86(0x2000, 0x1000, false);
+        assert!(CODE_OFFSET + stub.len() <= HOOK_SIZE);
+        // fild qword [esp] and fistp qword [esp]
+        assert_eq!(&stub[21..24], &[0xDF, 0x2C, 0x24]);
+        assert_eq!(&stub[36..39], &[0xDF, 0x3C, 0x24]);
+        assert!(stub.ends_with(&[0x83, 0xC4, 0x08, 0x5B, 0xC3]));
+        assert!(build_stub_x86(0x2000, 0x1000, true).ends_with(&[0xC2, 0x04, 0x00]));
+    }
+
+    #[test]
+    fn building_stub_for_wow64() {
+        // 32 bit ntdll is the one a WOW64 process can call
+        assert_eq!(ModuleFilter::for_pointer_size(4), ModuleFilter::Bit32);
+        assert_eq!(ModuleFilter::for_pointer_size(8), ModuleFilter::Bit64);
+
+        let stub = build_stub_x86(0x0040_2000, 0x77A0_1230, true);
+        assert!(CODE_OFFSET + stub.len() <= HOOK_SIZE);
+        // mov ebx, [esp + 0x10] then mov eax, query; call eax
+        assert_eq!(&stub[4..8], &[0x8B, 0x5C, 0x24, 0x10]);
+        assert_eq!(&stub[13..18], &[0xB8, 0x30, 0x12, 0xA0, 0x77]);
+        assert_eq!(&stub[18..20], &[0xFF, 0xD0]);
+        // mov eax, state
+        assert_eq!(&stub[20..25], &[0xB8, 0x00, 0x20, 0x40, 0x00]);
+    }
+}
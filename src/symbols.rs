@@ -0,0 +1,234 @@
+use std::fmt::{Display, Formatter};
+use std::mem::size_of;
+
+use windows::core::HSTRING;
+use windows::Win32::Foundation::HANDLE;
+use windows::Win32::System::Diagnostics::Debug::{
+    SymFromAddrW, SymFromNameW, SymGetModuleInfoW64, SymLoadModuleExW, SymRefreshModuleList,
+    IMAGEHLP_MODULEW64, MAX_SYM_NAME, SYMBOL_INFOW, SYM_LOAD_FLAGS,
+};
+
+use crate::backtrace::{self, Frame};
+use crate::dbghelp::{self, DbgHelpSession};
+use crate::error::{Error, Result};
+use crate::handle::Handle;
+use crate::module::Module;
+use crate::thread::Thread;
+
+/// symbol an address resolved to
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Symbol {
+    module_name: Option<String>,
+    name: String,
+    address: usize,
+    displacement: usize,
+}
+
+impl Symbol {
+    /// name of the module the symbol belong to, like `kernel32`
+    pub fn get_module_name(&self) -> Option<&str> {
+        self.module_name.as_deref()
+    }
+
+    /// undecorated name of the symbol, like `Sleep`
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// absolute address of the symbol
+    pub fn get_address(&self) -> usize {
+        self.address
+    }
+
+    /// offset of the resolved address from the symbol
+    pub fn get_displacement(&self) -> usize {
+        self.displacement
+    }
+}
+
+/// format as `module!function+0x10`
+impl Display for Symbol {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if let Some(module_name) = &self.module_name {
+            write!(f, "{}!", module_name)?;
+        }
+        write!(f, "{}", self.name)?;
+        if self.displacement != 0 {
+            write!(f, "+{:#x}", self.displacement)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Symbol handler of DbgHelp for a process, cleaned up when the last resolver of the process
+/// dropped.
+///
+/// DbgHelp is single threaded, every call to it from the crate take turns behind one lock.
+pub struct SymbolResolver<'a> {
+    handle: &'a Handle,
+    _session: DbgHelpSession,
+}
+
+impl<'a> SymbolResolver<'a> {
+    /// initialize symbol handler for the process of `handle`, loading symbols of its modules.
+    ///
+    /// `search_path` is the `;` separated pdb search path, like
+    /// `srv*C:\symbols*https://msdl.microsoft.com/download/symbols`, default of DbgHelp when
+    /// `None`. symbol handler already initialized for the process is shared, along with its
+    /// search path.
+    pub fn try_new(handle: &'a Handle, search_path: Option<&str>) -> Result<Self> {
+        Ok(Self {
+            handle,
+            _session: DbgHelpSession::try_new(handle, search_path)?,
+        })
+    }
+
+    /// pick up modules loaded by the process since initialized
+    pub fn refresh(&self) -> Result<()> {
+        let _lock = dbghelp::lock();
+        unsafe { SymRefreshModuleList(self.raw()) }
+            .map_err(|e| Error::win32("SymRefreshModuleList", e))
+    }
+
+    /// load symbols of `module`, needed for module not seen by the symbol handler yet
+    pub fn load_module(&self, module: &Module) -> Result<()> {
+        let _lock = dbghelp::lock();
+        let address = unsafe {
+            SymLoadModuleExW(
+                self.raw(),
+                HANDLE::default(),
+                &HSTRING::from(module.get_path()),
+                &HSTRING::from(module.get_name()),
+                module.get_address() as u64,
+                module.get_size(),
+                None,
+                SYM_LOAD_FLAGS(0),
+            )
+        };
+        if address == 0 {
+            // NOTE: zero with success means the module is already loaded
+            let error = windows::core::Error::from_win32();
+            if error.code().is_err() {
+                return Err(Error::win32("SymLoadModuleExW", error));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// symbol at or before `address`
+    pub fn symbolize(&self, address: usize) -> Result<Symbol> {
+        let mut buffer = SymbolInfoBuffer::new();
+        let mut displacement = 0u64;
+        let _lock = dbghelp::lock();
+        unsafe {
+            SymFromAddrW(
+                self.raw(),
+                address as u64,
+                Some(&mut displacement),
+                buffer.as_mut_ptr(),
+            )
+        }
+        .map_err(|e| Error::win32("SymFromAddrW", e))?;
+
+        Ok(Symbol {
+            module_name: self.get_module_name(address),
+            name: buffer.get_name(),
+            address: buffer.get_address(),
+            displacement: displacement as usize,
+        })
+    }
+
+    /// address of the symbol with the given name, like `kernel32!Sleep`
+    pub fn resolve(&self, name: &str) -> Result<usize> {
+        let mut buffer = SymbolInfoBuffer::new();
+        let _lock = dbghelp::lock();
+        unsafe { SymFromNameW(self.raw(), &HSTRING::from(name), buffer.as_mut_ptr()) }
+            .map_err(|e| Error::win32("SymFromNameW", e))?;
+
+        Ok(buffer.get_address())
+    }
+
+    /// walk the call stack of the suspended `thread`, symbolizing every frame
+    pub fn backtrace(&self, thread: &Thread) -> Result<Vec<Frame>> {
+        backtrace::walk_stack(self.handle, thread, |address| {
+            self.symbolize(address).ok().map(|e| e.to_string())
+        })
+    }
+
+    /// name of the module containing `address`, DbgHelp lock should be held
+    fn get_module_name(&self, address: usize) -> Option<String> {
+        let mut module = IMAGEHLP_MODULEW64 {
+            SizeOfStruct: size_of::<IMAGEHLP_MODULEW64>() as u32,
+            ..Default::default()
+        };
+        unsafe { SymGetModuleInfoW64(self.raw(), address as u64, &mut module) }.ok()?;
+
+        Some(
+            String::from_utf16_lossy(&module.ModuleName)
+                .trim_end_matches('\u{0}')
+                .to_string(),
+        )
+    }
+
+    fn raw(&self) -> HANDLE {
+        **self.handle
+    }
+}
+
+/// `SYMBOL_INFOW` followed by room for the longest name
+struct SymbolInfoBuffer(Vec<u64>);
+
+impl SymbolInfoBuffer {
+    fn new() -> Self {
+        let size = size_of::<SYMBOL_INFOW>() + MAX_SYM_NAME as usize * 2;
+        let mut buffer = Self(vec![0u64; size.div_ceil(8)]);
+
+        let info = buffer.as_mut_ptr();
+        unsafe {
+            (*info).SizeOfStruct = size_of::<SYMBOL_INFOW>() as u32;
+            (*info).MaxNameLen = MAX_SYM_NAME;
+        }
+
+        buffer
+    }
+
+    fn as_mut_ptr(&mut self) -> *mut SYMBOL_INFOW {
+        self.0.as_mut_ptr() as *mut SYMBOL_INFOW
+    }
+
+    fn get_address(&self) -> usize {
+        let info = self.0.as_ptr() as *const SYMBOL_INFOW;
+        unsafe { (*info).Address as usize }
+    }
+
+    fn get_name(&self) -> String {
+        let info = self.0.as_ptr() as *const SYMBOL_INFOW;
+        unsafe {
+            let len = ((*info).NameLen).min(MAX_SYM_NAME) as usize;
+            let name = std::slice::from_raw_parts((*info).Name.as_ptr(), len);
+            String::from_utf16_lossy(name)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Symbol;
+
+    #[test]
+    fn formatting_symbol() {
+        let mut symbol = Symbol {
+            module_name: Some("kernel32".to_string()),
+            name: "Sleep".to_string(),
+            address: 0x1000,
+            displacement: 0x10,
+        };
+        assert_eq!(symbol.to_string(), "kernel32!Sleep+0x10");
+
+        symbol.module_name = None;
+        symbol.displacement = 0;
+        assert_eq!(symbol.to_string(), "Sleep");
+    }
+}
@@ -0,0 +1,573 @@
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+
+use crate::address::Address;
+use crate::error::{Error, Result};
+use crate::handle::Handle;
+
+/// escaped characters of xml with their replacement
+const XML_ESCAPES: [(&str, &str); 5] = [
+    ("&lt;", "<"),
+    ("&gt;", ">"),
+    ("&quot;", "\""),
+    ("&apos;", "'"),
+    ("&amp;", "&"),
+];
+
+/// type of value stored at an [TableEntry]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ValueType {
+    /// unsigned 8 bit integer
+    U8,
+    /// signed 8 bit integer
+    I8,
+    /// unsigned 16 bit integer
+    U16,
+    /// signed 16 bit integer
+    I16,
+    /// unsigned 32 bit integer
+    U32,
+    /// signed 32 bit integer
+    I32,
+    /// unsigned 64 bit integer
+    U64,
+    /// signed 64 bit integer
+    I64,
+    /// 32 bit float
+    F32,
+    /// 64 bit float
+    F64,
+    /// utf-8 string of the given length in bytes
+    String(usize),
+    /// utf-16 string of the given length in code units
+    WideString(usize),
+    /// bytes of the given length
+    Bytes(usize),
+}
+
+impl ValueType {
+    /// size of the value in bytes
+    pub fn get_size(&self) -> usize {
+        match self {
+            Self::U8 | Self::I8 => 1,
+            Self::U16 | Self::I16 => 2,
+            Self::U32 | Self::I32 | Self::F32 => 4,
+            Self::U64 | Self::I64 | Self::F64 => 8,
+            Self::String(len) | Self::Bytes(len) => *len,
+            Self::WideString(len) => len * 2,
+        }
+    }
+
+    /// whether the value can be stored as the type, string and bytes may be shorter than it
+    pub fn accepts(&self, value: &Value) -> bool {
+        match (self, value) {
+            (Self::String(len), Value::String(e)) => e.len() <= *len,
+            (Self::WideString(len), Value::String(e)) => e.encode_utf16().count() <= *len,
+            (Self::Bytes(len), Value::Bytes(e)) => e.len() <= *len,
+            _ => value.get_value_type() == *self,
+        }
+    }
+
+    /// bytes of `value` laid out in memory as the type, shorter string and bytes are padded
+    /// with zero to the size of the type
+    pub fn encode(&self, value: &Value) -> Result<Vec<u8>> {
+        if !self.accepts(value) {
+            return Err(Error::InvalidInput);
+        }
+
+        let mut bytes = match (self, value) {
+            (Self::WideString(_), Value::String(e)) => {
+                e.encode_utf16().flat_map(|e| e.to_le_bytes()).collect()
+            }
+            _ => value.to_bytes(),
+        };
+        bytes.resize(self.get_size(), 0);
+
+        Ok(bytes)
+    }
+
+    /// value of the type from bytes laid out in memory, `bytes` has the size of the type
+    pub fn from_bytes(&self, bytes: &[u8]) -> Result<Value> {
+        if bytes.len() != self.get_size() {
+            return Err(Error::InvalidInput);
+        }
+
+        macro_rules! number {
+            ($variant:ident, $t:ty) => {
+                Value::$variant(<$t>::from_le_bytes(bytes.try_into().unwrap()))
+            };
+        }
+
+        Ok(match self {
+            Self::U8 => number!(U8, u8),
+            Self::I8 => number!(I8, i8),
+            Self::U16 => number!(U16, u16),
+            Self::I16 => number!(I16, i16),
+            Self::U32 => number!(U32, u32),
+            Self::I32 => number!(I32, i32),
+            Self::U64 => number!(U64, u64),
+            Self::I64 => number!(I64, i64),
+            Self::F32 => number!(F32, f32),
+            Self::F64 => number!(F64, f64),
+            Self::String(_) => Value::String(
+                String::from_utf8_lossy(bytes)
+                    .trim_end_matches('\0')
+                    .to_string(),
+            ),
+            Self::WideString(_) => {
+                let units: Vec<u16> = bytes
+                    .chunks_exact(2)
+                    .map(|e| u16::from_le_bytes([e[0], e[1]]))
+                    .collect();
+                Value::String(
+                    String::from_utf16_lossy(&units)
+                        .trim_end_matches('\0')
+                        .to_string(),
+                )
+            }
+            Self::Bytes(_) => Value::Bytes(bytes.to_vec()),
+        })
+    }
+}
+
+/// value read from or written to an [TableEntry]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Value {
+    /// unsigned 8 bit integer
+    U8(u8),
+    /// signed 8 bit integer
+    I8(i8),
+    /// unsigned 16 bit integer
+    U16(u16),
+    /// signed 16 bit integer
+    I16(i16),
+    /// unsigned 32 bit integer
+    U32(u32),
+    /// signed 32 bit integer
+    I32(i32),
+    /// unsigned 64 bit integer
+    U64(u64),
+    /// signed 64 bit integer
+    I64(i64),
+    /// 32 bit float
+    F32(f32),
+    /// 64 bit float
+    F64(f64),
+    /// utf-8 string
+    String(String),
+    /// bytes
+    Bytes(Vec<u8>),
+}
+
+impl Value {
+    /// type of the value, sized by its length for string and bytes
+    pub fn get_value_type(&self) -> ValueType {
+        match self {
+            Self::U8(_) => ValueType::U8,
+            Self::I8(_) => ValueType::I8,
+            Self::U16(_) => ValueType::U16,
+            Self::I16(_) => ValueType::I16,
+            Self::U32(_) => ValueType::U32,
+            Self::I32(_) => ValueType::I32,
+            Self::U64(_) => ValueType::U64,
+            Self::I64(_) => ValueType::I64,
+            Self::F32(_) => ValueType::F32,
+            Self::F64(_) => ValueType::F64,
+            Self::String(e) => ValueType::String(e.len()),
+            Self::Bytes(e) => ValueType::Bytes(e.len()),
+        }
+    }
+
+    /// bytes of the value as laid out in memory
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            Self::U8(e) => e.to_le_bytes().to_vec(),
+            Self::I8(e) => e.to_le_bytes().to_vec(),
+            Self::U16(e) => e.to_le_bytes().to_vec(),
+            Self::I16(e) => e.to_le_bytes().to_vec(),
+            Self::U32(e) => e.to_le_bytes().to_vec(),
+            Self::I32(e) => e.to_le_bytes().to_vec(),
+            Self::U64(e) => e.to_le_bytes().to_vec(),
+            Self::I64(e) => e.to_le_bytes().to_vec(),
+            Self::F32(e) => e.to_le_bytes().to_vec(),
+            Self::F64(e) => e.to_le_bytes().to_vec(),
+            Self::String(e) => e.as_bytes().to_vec(),
+            Self::Bytes(e) => e.clone(),
+        }
+    }
+}
+
+/// format numbers as is, string as is and bytes as hex separated by space
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::U8(e) => write!(f, "{}", e),
+            Self::I8(e) => write!(f, "{}", e),
+            Self::U16(e) => write!(f, "{}", e),
+            Self::I16(e) => write!(f, "{}", e),
+            Self::U32(e) => write!(f, "{}", e),
+            Self::I32(e) => write!(f, "{}", e),
+            Self::U64(e) => write!(f, "{}", e),
+            Self::I64(e) => write!(f, "{}", e),
+            Self::F32(e) => write!(f, "{}", e),
+            Self::F64(e) => write!(f, "{}", e),
+            Self::String(e) => write!(f, "{}", e),
+            Self::Bytes(e) => {
+                let hex: Vec<String> = e.iter().map(|e| format!("{:02X}", e)).collect();
+                write!(f, "{}", hex.join(" "))
+            }
+        }
+    }
+}
+
+/// named value in memory of a process, at an address or at the end of a pointer chain
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TableEntry {
+    name: String,
+    base: Address,
+    offsets: Vec<usize>,
+    value_type: ValueType,
+    description: String,
+}
+
+impl TableEntry {
+    /// create entry of value at `base`, prefer [Address::ModuleOffset] for address that has to
+    /// survive restart of the process.
+    ///
+    /// with `offsets` it is a pointer chain, look at [Handle::resolve_pointer_chain].
+    pub fn new<A: Into<Address>>(
+        name: &str,
+        base: A,
+        offsets: &[usize],
+        value_type: ValueType,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            base: base.into(),
+            offsets: offsets.to_vec(),
+            value_type,
+            description: String::new(),
+        }
+    }
+
+    /// name of the entry
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// base address, absolute or relative to a module
+    pub fn get_base(&self) -> &Address {
+        &self.base
+    }
+
+    /// offsets of the pointer chain, empty when the value is at the base address
+    pub fn get_offsets(&self) -> &[usize] {
+        &self.offsets
+    }
+
+    /// type of the value
+    pub fn get_value_type(&self) -> ValueType {
+        self.value_type
+    }
+
+    /// description of the entry
+    pub fn get_description(&self) -> &str {
+        &self.description
+    }
+
+    /// set description of the entry
+    pub fn set_description(&mut self, description: &str) {
+        self.description = description.to_string();
+    }
+
+    /// address of the value in the process, following the pointer chain
+    pub fn resolve(&self, handle: &Handle) -> Result<usize> {
+        handle.resolve_pointer_chain(self.base.resolve(handle)?, &self.offsets)
+    }
+
+    /// current value in the process
+    pub fn read_value(&self, handle: &Handle) -> Result<Value> {
+        let bytes = handle.read_memory(self.resolve(handle)?, self.value_type.get_size())?;
+
+        self.value_type.from_bytes(&bytes)
+    }
+
+    /// write value to the process, value has to be accepted by the type of the entry.
+    ///
+    /// shorter string and bytes are padded with zero, so nothing of the previous value is left.
+    pub fn write_value(&self, handle: &Handle, value: &Value) -> Result<()> {
+        let bytes = self.value_type.encode(value)?;
+
+        handle.write_memory(self.resolve(handle)?, &bytes)
+    }
+}
+
+/// Named addresses of a process, kept between sessions like a cheat table
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AddressTable {
+    entries: Vec<TableEntry>,
+}
+
+impl AddressTable {
+    /// create empty table
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// entries of the table in insertion order
+    pub fn get_entries(&self) -> &[TableEntry] {
+        &self.entries
+    }
+
+    /// first entry with the name
+    pub fn find(&self, name: &str) -> Option<&TableEntry> {
+        self.entries.iter().find(|e| e.name == name)
+    }
+
+    /// add entry at the end of the table
+    pub fn push(&mut self, entry: TableEntry) {
+        self.entries.push(entry);
+    }
+
+    /// remove every entry with the name, returning whether any was removed
+    pub fn remove(&mut self, name: &str) -> bool {
+        let len = self.entries.len();
+        self.entries.retain(|e| e.name != name);
+
+        self.entries.len() != len
+    }
+
+    /// table as pretty printed json
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).map_err(|e| Error::Io(e.into()))
+    }
+
+    /// table from json made by [AddressTable::to_json]
+    #[cfg(feature = "serde")]
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json).map_err(|e| Error::Io(e.into()))
+    }
+
+    /// save table as json to the file
+    #[cfg(feature = "serde")]
+    pub fn save_json<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        std::fs::write(path, self.to_json()?)?;
+
+        Ok(())
+    }
+
+    /// load table from json file saved by [AddressTable::save_json]
+    #[cfg(feature = "serde")]
+    pub fn load_json<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+
+    /// table from `.CT` xml of Cheat Engine.
+    ///
+    /// every cheat entry with an address is imported, group headers and entries of type not
+    /// supported like auto assembler scripts are skipped.
+    pub fn from_cheat_table(xml: &str) -> Result<Self> {
+        if !xml.contains("<CheatTable") {
+            return Err(Error::InvalidInput);
+        }
+
+        let mut entries = Vec::new();
+        let mut rest = xml;
+        while let Some(start) = rest.find("<CheatEntry>") {
+            rest = &rest[start + "<CheatEntry>".len()..];
+            // NOTE: fields of an entry come before its nested entries
+            let end = ["<CheatEntry>", "</CheatEntry>"]
+                .iter()
+                .filter_map(|e| rest.find(e))
+                .min()
+                .unwrap_or(rest.len());
+
+            entries.extend(parse_cheat_entry(&rest[..end]));
+        }
+
+        Ok(Self { entries })
+    }
+
+    /// load table from `.CT` file of Cheat Engine, look at [AddressTable::from_cheat_table]
+    pub fn load_cheat_table<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_cheat_table(&std::fs::read_to_string(path)?)
+    }
+}
+
+/// entry from fields of `<CheatEntry>`, `None` without address or with unsupported type
+fn parse_cheat_entry(xml: &str) -> Option<TableEntry> {
+    let field = |tag: &str| xml_element(xml, tag).map(|e| xml_unescape(e.trim()));
+
+    let base = field("Address")?.parse().ok()?;
+    let is_signed = field("ShowAsSigned").as_deref() == Some("1");
+    let value_type = match (field("VariableType")?.as_str(), is_signed) {
+        ("Byte", false) => ValueType::U8,
+        ("Byte", true) => ValueType::I8,
+        ("2 Bytes", false) => ValueType::U16,
+        ("2 Bytes", true) => ValueType::I16,
+        ("4 Bytes", false) => ValueType::U32,
+        ("4 Bytes", true) => ValueType::I32,
+        ("8 Bytes", false) => ValueType::U64,
+        ("8 Bytes", true) => ValueType::I64,
+        ("Float", _) => ValueType::F32,
+        ("Double", _) => ValueType::F64,
+        ("String", _) => match field("Unicode").as_deref() == Some("1") {
+            true => ValueType::WideString(field("Length")?.parse().ok()?),
+            false => ValueType::String(field("Length")?.parse().ok()?),
+        },
+        ("Array of byte", _) => ValueType::Bytes(field("ByteLength")?.parse().ok()?),
+        _ => return None,
+    };
+
+    // NOTE: Cheat Engine list offsets from the last one dereferenced
+    let mut offsets = Vec::new();
+    let mut rest = xml_element(xml, "Offsets").unwrap_or_default();
+    while let Some(offset) = xml_element(rest, "Offset") {
+        offsets.push(parse_cheat_offset(offset.trim())?);
+        rest = &rest[rest.find("</Offset>")? + "</Offset>".len()..];
+    }
+    offsets.reverse();
+
+    let description = field("Description").unwrap_or_default();
+    let description = description.trim_matches('"');
+
+    Some(TableEntry {
+        name: description.to_string(),
+        base,
+        offsets,
+        value_type,
+        description: description.to_string(),
+    })
+}
+
+/// hex offset of Cheat Engine, negative one is wrapped around like the pointer it is added to
+fn parse_cheat_offset(text: &str) -> Option<usize> {
+    match text.strip_prefix('-') {
+        Some(text) => usize::from_str_radix(text, 16)
+            .ok()
+            .map(usize::wrapping_neg),
+        None => usize::from_str_radix(text, 16).ok(),
+    }
+}
+
+/// text between the first `<tag>` and `</tag>`
+fn xml_element<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+
+    let start = xml.find(&open)? + open.len();
+    let len = xml[start..].find(&close)?;
+
+    Some(&xml[start..start + len])
+}
+
+fn xml_unescape(text: &str) -> String {
+    XML_ESCAPES
+        .iter()
+        .fold(text.to_string(), |text, (escaped, c)| {
+            text.replace(escaped, c)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AddressTable, Value, ValueType};
+    use crate::address::Address;
+
+    #[test]
+    fn importing_cheat_table() {
+        let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+<CheatTable CheatEngineTableVersion="45">
+  <CheatEntries>
+    <CheatEntry>
+      <ID>0</ID>
+      <Description>"Player"</Description>
+      <GroupHeader>1</GroupHeader>
+      <CheatEntries>
+        <CheatEntry>
+          <ID>1</ID>
+          <Description>"Health &amp; Armor"</Description>
+          <ShowAsSigned>1</ShowAsSigned>
+          <VariableType>4 Bytes</VariableType>
+          <Address>"game.exe"+0012F0</Address>
+          <Offsets>
+            <Offset>-1C</Offset>
+            <Offset>8</Offset>
+          </Offsets>
+        </CheatEntry>
+      </CheatEntries>
+    </CheatEntry>
+    <CheatEntry>
+      <ID>2</ID>
+      <Description>"Name"</Description>
+      <VariableType>String</VariableType>
+      <Length>16</Length>
+      <Address>7FF61000</Address>
+    </CheatEntry>
+    <CheatEntry>
+      <ID>3</ID>
+      <Description>"Title"</Description>
+      <VariableType>String</VariableType>
+      <Length>8</Length>
+      <Unicode>1</Unicode>
+      <Address>7FF62000</Address>
+    </CheatEntry>
+  </CheatEntries>
+</CheatTable>"#;
+
+        let table = AddressTable::from_cheat_table(xml).unwrap();
+        assert_eq!(table.get_entries().len(), 3);
+
+        let health = table.find("Health & Armor").unwrap();
+        assert_eq!(health.get_value_type(), ValueType::I32);
+        assert_eq!(
+            health.get_base(),
+            &Address::module_offset("game.exe", 0x12F0)
+        );
+        assert_eq!(health.get_offsets(), &[0x8, 0x1Cusize.wrapping_neg()]);
+
+        let name = table.find("Name").unwrap();
+        assert_eq!(name.get_value_type(), ValueType::String(16));
+        assert!(name.get_offsets().is_empty());
+
+        let title = table.find("Title").unwrap();
+        assert_eq!(title.get_value_type(), ValueType::WideString(8));
+
+        assert!(AddressTable::from_cheat_table("<html></html>").is_err());
+    }
+
+    #[test]
+    fn encoding_values() {
+        let name = ValueType::String(6);
+        assert_eq!(
+            name.encode(&Value::String("abc".to_string())).unwrap(),
+            b"abc\0\0\0"
+        );
+        assert!(name.encode(&Value::String("abcdefg".to_string())).is_err());
+        assert!(name.encode(&Value::U32(1)).is_err());
+
+        let title = ValueType::WideString(3);
+        let bytes = title.encode(&Value::String("hé".to_string())).unwrap();
+        assert_eq!(bytes, [b'h', 0, 0xE9, 0, 0, 0]);
+        assert_eq!(
+            title.from_bytes(&bytes).unwrap(),
+            Value::String("hé".to_string())
+        );
+
+        assert_eq!(
+            ValueType::Bytes(4)
+                .encode(&Value::Bytes(vec![0x90]))
+                .unwrap(),
+            [0x90, 0, 0, 0]
+        );
+        assert_eq!(
+            ValueType::I16.encode(&Value::I16(-2)).unwrap(),
+            [0xFE, 0xFF]
+        );
+    }
+}
@@ -0,0 +1,417 @@
+use std::ops::{Deref, DerefMut, Range};
+use std::time::Duration;
+
+use windows::Win32::Foundation::{CloseHandle, BOOL, HANDLE, WAIT_OBJECT_0, WAIT_TIMEOUT};
+use windows::Win32::System::Diagnostics::Debug::{GetThreadContext, SetThreadContext, CONTEXT};
+use windows::Win32::System::Diagnostics::ToolHelp::THREADENTRY32;
+use windows::Win32::System::Threading::{
+    GetExitCodeThread, OpenThread, ResumeThread, SuspendThread, WaitForSingleObject, INFINITE,
+    THREAD_ACCESS_RIGHTS,
+};
+
+#[cfg(target_arch = "x86_64")]
+use windows::Win32::System::Diagnostics::Debug::CONTEXT_ALL_AMD64 as CONTEXT_ALL;
+#[cfg(target_arch = "aarch64")]
+use windows::Win32::System::Diagnostics::Debug::CONTEXT_ALL_ARM64 as CONTEXT_ALL;
+#[cfg(target_arch = "x86")]
+use windows::Win32::System::Diagnostics::Debug::CONTEXT_ALL_X86 as CONTEXT_ALL;
+
+use crate::error::{Error, Result};
+use crate::handle::{Handle, HandleSnapshotFlag};
+use crate::module::Module;
+use crate::peb::Teb;
+
+/// Look at [THREADENTRY32 structure (tlhelp32.h) - Win32 API](https://learn.microsoft.com/en-us/windows/win32/api/tlhelp32/ns-tlhelp32-threadentry32)
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "ThreadEntryRepr", into = "ThreadEntryRepr")
+)]
+pub struct ThreadEntry(THREADENTRY32);
+
+impl ThreadEntry {
+    /// get `th32ThreadID`
+    pub fn get_thread_id(&self) -> u32 {
+        self.0.th32ThreadID
+    }
+
+    /// get `th32OwnerProcessID`
+    pub fn get_owner_process_id(&self) -> u32 {
+        self.0.th32OwnerProcessID
+    }
+
+    /// get `tpBasePri`
+    pub fn get_base_priority(&self) -> i32 {
+        self.0.tpBasePri
+    }
+
+    /// open the thread
+    pub fn open(&self) -> Result<Thread> {
+        Thread::try_from(self.get_thread_id())
+    }
+}
+
+impl Deref for ThreadEntry {
+    type Target = THREADENTRY32;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<THREADENTRY32> for ThreadEntry {
+    fn from(value: THREADENTRY32) -> Self {
+        Self(value)
+    }
+}
+
+/// serialized form of [ThreadEntry]
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct ThreadEntryRepr {
+    thread_id: u32,
+    owner_process_id: u32,
+    base_priority: i32,
+}
+
+#[cfg(feature = "serde")]
+impl From<ThreadEntry> for ThreadEntryRepr {
+    fn from(value: ThreadEntry) -> Self {
+        Self {
+            thread_id: value.get_thread_id(),
+            owner_process_id: value.get_owner_process_id(),
+            base_priority: value.get_base_priority(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<ThreadEntryRepr> for ThreadEntry {
+    fn from(value: ThreadEntryRepr) -> Self {
+        Self(THREADENTRY32 {
+            dwSize: std::mem::size_of::<THREADENTRY32>() as u32,
+            th32ThreadID: value.thread_id,
+            th32OwnerProcessID: value.owner_process_id,
+            tpBasePri: value.base_priority,
+            ..Default::default()
+        })
+    }
+}
+
+/// Look at [CONTEXT structure (winnt.h) - Win32 API](https://learn.microsoft.com/en-us/windows/win32/api/winnt/ns-winnt-context)
+#[repr(C, align(16))]
+#[derive(Clone, Copy)]
+pub struct ThreadContext(CONTEXT);
+
+impl ThreadContext {
+    /// address of the next instruction the thread execute
+    pub fn get_instruction_pointer(&self) -> usize {
+        #[cfg(target_arch = "x86_64")]
+        let address = self.0.Rip;
+        #[cfg(target_arch = "x86")]
+        let address = self.0.Eip;
+        #[cfg(target_arch = "aarch64")]
+        let address = self.0.Pc;
+
+        address as usize
+    }
+
+    /// set address of the next instruction the thread execute
+    pub fn set_instruction_pointer(&mut self, address: usize) {
+        #[cfg(target_arch = "x86_64")]
+        {
+            self.0.Rip = address as _;
+        }
+        #[cfg(target_arch = "x86")]
+        {
+            self.0.Eip = address as _;
+        }
+        #[cfg(target_arch = "aarch64")]
+        {
+            self.0.Pc = address as _;
+        }
+    }
+
+    /// address of the top of the stack
+    pub fn get_stack_pointer(&self) -> usize {
+        #[cfg(target_arch = "x86_64")]
+        let address = self.0.Rsp;
+        #[cfg(target_arch = "x86")]
+        let address = self.0.Esp;
+        #[cfg(target_arch = "aarch64")]
+        let address = self.0.Sp;
+
+        address as usize
+    }
+
+    /// address of the current stack frame
+    pub fn get_frame_pointer(&self) -> usize {
+        #[cfg(target_arch = "x86_64")]
+        let address = self.0.Rbp;
+        #[cfg(target_arch = "x86")]
+        let address = self.0.Ebp;
+        #[cfg(target_arch = "aarch64")]
+        let address = unsafe { self.0.Anonymous.Anonymous.Fp };
+
+        address as usize
+    }
+
+    /// raise single step exception after the next instruction executed
+    pub fn set_single_step(&mut self, enabled: bool) {
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        let (flags, bit) = (&mut self.0.EFlags, 1 << 8);
+        #[cfg(target_arch = "aarch64")]
+        let (flags, bit) = (&mut self.0.Cpsr, 1 << 21);
+
+        match enabled {
+            true => *flags |= bit,
+            false => *flags &= !bit,
+        }
+    }
+}
+
+impl Deref for ThreadContext {
+    type Target = CONTEXT;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for ThreadContext {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl From<CONTEXT> for ThreadContext {
+    fn from(value: CONTEXT) -> Self {
+        Self(value)
+    }
+}
+
+/// stack of a thread, read from its thread environment block
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StackRange {
+    thread_id: u32,
+    base: usize,
+    limit: usize,
+}
+
+impl StackRange {
+    /// id of the thread owning the stack
+    pub fn get_thread_id(&self) -> u32 {
+        self.thread_id
+    }
+
+    /// highest address of the stack, where it start growing down from
+    pub fn get_base(&self) -> usize {
+        self.base
+    }
+
+    /// lowest committed address of the stack, it move down as the stack grow
+    pub fn get_limit(&self) -> usize {
+        self.limit
+    }
+
+    /// committed part of the stack
+    pub fn get_range(&self) -> Range<usize> {
+        self.limit..self.base
+    }
+
+    /// whether `address` is inside committed part of the stack
+    pub fn contains(&self, address: usize) -> bool {
+        self.get_range().contains(&address)
+    }
+}
+
+/// exit code of a thread or process that has not terminated
+pub(crate) const STILL_ACTIVE: u32 = 259;
+
+/// thread handle
+pub struct Thread {
+    raw: HANDLE,
+    thread_id: u32,
+}
+
+impl Thread {
+    pub(crate) fn from_raw(raw: HANDLE, thread_id: u32) -> Self {
+        Self { raw, thread_id }
+    }
+
+    /// thread id of the handle
+    pub fn get_thread_id(&self) -> u32 {
+        self.thread_id
+    }
+
+    /// suspend the thread, returning the previous suspend count
+    pub fn suspend(&self) -> Result<u32> {
+        let count = unsafe { SuspendThread(self.raw) };
+        if count == u32::MAX {
+            return Err(Error::last_win32("SuspendThread"));
+        }
+
+        Ok(count)
+    }
+
+    /// resume the thread, returning the previous suspend count
+    pub fn resume(&self) -> Result<u32> {
+        let count = unsafe { ResumeThread(self.raw) };
+        if count == u32::MAX {
+            return Err(Error::last_win32("ResumeThread"));
+        }
+
+        Ok(count)
+    }
+
+    /// get registers of the thread, the thread should be suspended
+    pub fn get_context(&self) -> Result<ThreadContext> {
+        let mut context = ThreadContext(CONTEXT {
+            ContextFlags: CONTEXT_ALL,
+            ..Default::default()
+        });
+
+        unsafe { GetThreadContext(self.raw, &mut context.0) }
+            .map_err(|e| Error::win32("GetThreadContext", e))?;
+
+        Ok(context)
+    }
+
+    /// set registers of the thread, the thread should be suspended
+    pub fn set_context(&self, context: &ThreadContext) -> Result<()> {
+        unsafe { SetThreadContext(self.raw, &context.0) }
+            .map_err(|e| Error::win32("SetThreadContext", e))
+    }
+
+    /// exit code of the thread, `None` when the thread is still running
+    pub fn get_exit_code(&self) -> Result<Option<u32>> {
+        let mut exit_code = 0u32;
+        unsafe { GetExitCodeThread(self.raw, &mut exit_code) }
+            .map_err(|e| Error::win32("GetExitCodeThread", e))?;
+
+        if exit_code == STILL_ACTIVE {
+            return Ok(None);
+        }
+
+        Ok(Some(exit_code))
+    }
+
+    /// stack of the thread which belong to the process of `handle`
+    pub fn stack_range(&self, handle: &Handle) -> Result<StackRange> {
+        let teb = Teb::read(handle, self)?;
+
+        Ok(StackRange {
+            thread_id: self.thread_id,
+            base: teb.get_stack_base(),
+            limit: teb.get_stack_limit(),
+        })
+    }
+
+    /// value of tls `slot` allocated by `TlsAlloc` in the thread which belong to the process of
+    /// `handle`
+    pub fn tls_value(&self, handle: &Handle, slot: u32) -> Result<usize> {
+        Teb::read(handle, self)?.read_tls_slot(handle, slot)
+    }
+
+    /// address of the static tls block of `module`, holding its `thread_local` variables, in
+    /// the thread which belong to the process of `handle`
+    pub fn tls_block(&self, handle: &Handle, module: &Module) -> Result<usize> {
+        let index = module.tls_index(handle)?.ok_or(Error::NotFound)?;
+
+        Teb::read(handle, self)?.read_static_tls_block(handle, index)
+    }
+
+    /// wait for the thread to terminate, returning its exit code.
+    ///
+    /// wait forever when `timeout` is `None`.
+    pub fn join(&self, timeout: Option<Duration>) -> Result<u32> {
+        let milliseconds =
+            timeout.map_or(INFINITE, |e| e.as_millis().min(INFINITE as u128 - 1) as u32);
+
+        match unsafe { WaitForSingleObject(self.raw, milliseconds) } {
+            WAIT_OBJECT_0 => (),
+            WAIT_TIMEOUT => return Err(Error::Timeout),
+            _ => return Err(Error::last_win32("WaitForSingleObject")),
+        }
+
+        let mut exit_code = 0u32;
+        unsafe { GetExitCodeThread(self.raw, &mut exit_code) }
+            .map_err(|e| Error::win32("GetExitCodeThread", e))?;
+
+        Ok(exit_code)
+    }
+}
+
+impl Handle {
+    /// stacks of every thread of the process, skipping threads which cannot be opened
+    pub fn stacks(&self) -> Result<Vec<StackRange>> {
+        Ok(self
+            .create_snapshot(HandleSnapshotFlag::SnapThread)?
+            .get_threads()
+            .filter_map(|entry| entry.open().ok()?.stack_range(self).ok())
+            .collect())
+    }
+}
+
+impl Deref for Thread {
+    type Target = HANDLE;
+
+    fn deref(&self) -> &Self::Target {
+        &self.raw
+    }
+}
+
+impl Drop for Thread {
+    fn drop(&mut self) {
+        if !self.raw.is_invalid() {
+            let _ = unsafe { CloseHandle(self.raw) };
+        }
+    }
+}
+
+impl TryFrom<u32> for Thread {
+    type Error = Error;
+
+    fn try_from(value: u32) -> Result<Thread, Self::Error> {
+        // THREAD_SUSPEND_RESUME | THREAD_GET_CONTEXT | THREAD_SET_CONTEXT | THREAD_QUERY_INFORMATION | SYNCHRONIZE
+        let h = unsafe {
+            OpenThread(
+                THREAD_ACCESS_RIGHTS(0x2 | 0x8 | 0x10 | 0x40 | 0x100000),
+                BOOL(0),
+                value,
+            )
+        }
+        .map_err(|e| Error::win32("OpenThread", e))?;
+
+        Ok(Self {
+            raw: h,
+            thread_id: value,
+        })
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use windows::Win32::System::Diagnostics::ToolHelp::THREADENTRY32;
+
+    use super::ThreadEntry;
+
+    #[test]
+    fn serializing_thread_entries() {
+        let entry = ThreadEntry::from(THREADENTRY32 {
+            dwSize: std::mem::size_of::<THREADENTRY32>() as u32,
+            th32ThreadID: 12,
+            th32OwnerProcessID: 4,
+            tpBasePri: 8,
+            ..Default::default()
+        });
+
+        let json = serde_json::to_string(&entry).unwrap();
+        assert_eq!(
+            json,
+            r#"{"thread_id":12,"owner_process_id":4,"base_priority":8}"#
+        );
+        assert!(serde_json::from_str::<ThreadEntry>(&json).unwrap() == entry);
+    }
+}
@@ -0,0 +1,273 @@
+use std::ffi::c_void;
+use std::fmt::{Display, Formatter};
+
+use windows::core::{PCWSTR, PWSTR};
+use windows::Win32::Foundation::{CloseHandle, HANDLE, PSID};
+use windows::Win32::Security::{
+    GetTokenInformation, LookupPrivilegeNameW, TokenElevation, TokenIntegrityLevel,
+    TokenPrivileges, TokenUser, SE_PRIVILEGE_ENABLED, TOKEN_ELEVATION, TOKEN_INFORMATION_CLASS,
+    TOKEN_MANDATORY_LABEL, TOKEN_PRIVILEGES, TOKEN_QUERY, TOKEN_USER,
+};
+use windows::Win32::System::Threading::OpenProcessToken;
+
+use crate::error::{Error, Result};
+use crate::handle::Handle;
+
+/// mandatory integrity level of a token
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum IntegrityLevel {
+    /// `SECURITY_MANDATORY_UNTRUSTED_RID`
+    Untrusted,
+    /// `SECURITY_MANDATORY_LOW_RID`, like sandboxed browser tabs
+    Low,
+    /// `SECURITY_MANDATORY_MEDIUM_RID`, standard user
+    Medium,
+    /// `SECURITY_MANDATORY_MEDIUM_PLUS_RID`
+    MediumPlus,
+    /// `SECURITY_MANDATORY_HIGH_RID`, elevated administrator
+    High,
+    /// `SECURITY_MANDATORY_SYSTEM_RID`
+    System,
+    /// `SECURITY_MANDATORY_PROTECTED_PROCESS_RID`
+    Protected,
+    /// level between the well known ones
+    Other(u32),
+}
+
+impl From<u32> for IntegrityLevel {
+    fn from(value: u32) -> Self {
+        match value {
+            0x0000 => Self::Untrusted,
+            0x1000 => Self::Low,
+            0x2000 => Self::Medium,
+            0x2100 => Self::MediumPlus,
+            0x3000 => Self::High,
+            0x4000 => Self::System,
+            0x5000 => Self::Protected,
+            value => Self::Other(value),
+        }
+    }
+}
+
+impl Display for IntegrityLevel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Untrusted => write!(f, "untrusted"),
+            Self::Low => write!(f, "low"),
+            Self::Medium => write!(f, "medium"),
+            Self::MediumPlus => write!(f, "medium plus"),
+            Self::High => write!(f, "high"),
+            Self::System => write!(f, "system"),
+            Self::Protected => write!(f, "protected"),
+            Self::Other(value) => write!(f, "{:#x}", value),
+        }
+    }
+}
+
+/// privilege held by a token
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenPrivilege {
+    name: String,
+    is_enabled: bool,
+}
+
+impl TokenPrivilege {
+    /// name of the privilege, like `SeDebugPrivilege`
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// whether the privilege is enabled, held privilege need to be enabled before it take effect
+    pub fn is_enabled(&self) -> bool {
+        self.is_enabled
+    }
+}
+
+/// security context of a process, read from its access token
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenInfo {
+    user_sid: String,
+    integrity_level: IntegrityLevel,
+    is_elevated: bool,
+    privileges: Vec<TokenPrivilege>,
+}
+
+impl TokenInfo {
+    /// sid of the user running the process, like `S-1-5-18`
+    pub fn get_user_sid(&self) -> &str {
+        &self.user_sid
+    }
+
+    /// mandatory integrity level of the process
+    pub fn get_integrity_level(&self) -> IntegrityLevel {
+        self.integrity_level
+    }
+
+    /// whether the process run elevated
+    pub fn is_elevated(&self) -> bool {
+        self.is_elevated
+    }
+
+    /// privileges held by the process
+    pub fn get_privileges(&self) -> &[TokenPrivilege] {
+        &self.privileges
+    }
+
+    /// whether the process hold the privilege with the given name and it is enabled
+    pub fn has_privilege(&self, name: &str) -> bool {
+        self.privileges
+            .iter()
+            .any(|e| e.is_enabled && e.name.eq_ignore_ascii_case(name))
+    }
+}
+
+impl Handle {
+    /// read user, integrity level, elevation and privileges of the process from its token.
+    ///
+    /// handle need `QueryLimitedInformation` access.
+    pub fn token_info(&self) -> Result<TokenInfo> {
+        let mut token = HANDLE::default();
+        unsafe { OpenProcessToken(**self, TOKEN_QUERY, &mut token) }
+            .map_err(|e| Error::win32("OpenProcessToken", e))?;
+
+        let result = read_token_info(token);
+        let _ = unsafe { CloseHandle(token) };
+
+        result
+    }
+}
+
+fn read_token_info(token: HANDLE) -> Result<TokenInfo> {
+    let user = query_token(token, TokenUser)?;
+    let user = unsafe { &*(user.as_ptr() as *const TOKEN_USER) };
+    let user_sid = format_sid(unsafe { sid_bytes(user.User.Sid) }).ok_or(Error::Unsupported)?;
+
+    let label = query_token(token, TokenIntegrityLevel)?;
+    let label = unsafe { &*(label.as_ptr() as *const TOKEN_MANDATORY_LABEL) };
+    let integrity_level = sid_last_subauthority(unsafe { sid_bytes(label.Label.Sid) })
+        .map(IntegrityLevel::from)
+        .ok_or(Error::Unsupported)?;
+
+    let elevation = query_token(token, TokenElevation)?;
+    let elevation = unsafe { &*(elevation.as_ptr() as *const TOKEN_ELEVATION) };
+
+    let privileges = query_token(token, TokenPrivileges)?;
+    let privileges = unsafe {
+        let privileges = &*(privileges.as_ptr() as *const TOKEN_PRIVILEGES);
+        std::slice::from_raw_parts(
+            privileges.Privileges.as_ptr(),
+            privileges.PrivilegeCount as usize,
+        )
+    };
+    let privileges = privileges
+        .iter()
+        .map(|e| {
+            let mut name = [0u16; 64];
+            let mut len = name.len() as u32;
+            unsafe {
+                LookupPrivilegeNameW(PCWSTR::null(), &e.Luid, PWSTR(name.as_mut_ptr()), &mut len)
+            }
+            .map_err(|e| Error::win32("LookupPrivilegeNameW", e))?;
+
+            Ok(TokenPrivilege {
+                name: String::from_utf16_lossy(&name[..len as usize]),
+                is_enabled: e.Attributes.contains(SE_PRIVILEGE_ENABLED),
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(TokenInfo {
+        user_sid,
+        integrity_level,
+        is_elevated: elevation.TokenIsElevated != 0,
+        privileges,
+    })
+}
+
+/// token information of `class`, in buffer aligned for the structure it hold
+fn query_token(token: HANDLE, class: TOKEN_INFORMATION_CLASS) -> Result<Vec<u64>> {
+    let mut len = 0u32;
+    // NOTE: fail with insufficient buffer, only the length is wanted
+    let _ = unsafe { GetTokenInformation(token, class, None, 0, &mut len) };
+    if len == 0 {
+        return Err(Error::last_win32("GetTokenInformation"));
+    }
+
+    let mut buffer = vec![0u64; (len as usize).div_ceil(8)];
+    unsafe {
+        GetTokenInformation(
+            token,
+            class,
+            Some(buffer.as_mut_ptr() as *mut c_void),
+            len,
+            &mut len,
+        )
+    }
+    .map_err(|e| Error::win32("GetTokenInformation", e))?;
+
+    Ok(buffer)
+}
+
+/// bytes of the sid `sid` point to
+///
+/// # Safety
+///
+/// `sid` must point to a valid sid
+unsafe fn sid_bytes<'a>(sid: PSID) -> &'a [u8] {
+    let sid = sid.0 as *const u8;
+    let subauthority_count = *sid.add(1) as usize;
+    std::slice::from_raw_parts(sid, 8 + subauthority_count * 4)
+}
+
+/// format sid in its string form, like `S-1-5-21-1004336348-1177238915-682003330-512`
+fn format_sid(sid: &[u8]) -> Option<String> {
+    let revision = *sid.first()?;
+    let subauthority_count = *sid.get(1)? as usize;
+    let authority = sid
+        .get(2..8)?
+        .iter()
+        .fold(0u64, |authority, e| authority << 8 | *e as u64);
+
+    let mut formatted = format!("S-{}-{}", revision, authority);
+    for index in 0..subauthority_count {
+        let subauthority = sid.get(8 + index * 4..12 + index * 4)?;
+        let subauthority = u32::from_le_bytes(subauthority.try_into().unwrap());
+        formatted.push_str(&format!("-{}", subauthority));
+    }
+
+    Some(formatted)
+}
+
+/// last subauthority of sid, the rid
+fn sid_last_subauthority(sid: &[u8]) -> Option<u32> {
+    let subauthority_count = *sid.get(1)? as usize;
+    let offset = 8 + subauthority_count.checked_sub(1)? * 4;
+    let subauthority = sid.get(offset..offset + 4)?;
+
+    Some(u32::from_le_bytes(subauthority.try_into().unwrap()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_sid, sid_last_subauthority, IntegrityLevel};
+
+    #[test]
+    fn formatting_sid() {
+        // S-1-5-21-1-512
+        let mut sid = vec![1, 3, 0, 0, 0, 0, 0, 5];
+        for subauthority in [21u32, 1, 512] {
+            sid.extend(subauthority.to_le_bytes());
+        }
+        assert_eq!(format_sid(&sid).as_deref(), Some("S-1-5-21-1-512"));
+        assert_eq!(sid_last_subauthority(&sid), Some(512));
+        assert_eq!(format_sid(&sid[..12]), None);
+
+        // mandatory label S-1-16-12288
+        let label = [1, 1, 0, 0, 0, 0, 0, 16, 0x00, 0x30, 0, 0];
+        assert_eq!(format_sid(&label).as_deref(), Some("S-1-16-12288"));
+        assert_eq!(
+            sid_last_subauthority(&label).map(IntegrityLevel::from),
+            Some(IntegrityLevel::High)
+        );
+    }
+}
@@ -0,0 +1,657 @@
+use crate::backend::MemoryBackend;
+use crate::error::{Error, Result};
+use crate::handle::Handle;
+use crate::memory::MemoryBasicInformationFilter;
+use crate::scanner::ScanOptions;
+
+/// size of chunks a baseline of [ValueScanner::first_scan_unknown] is compressed by
+const BASELINE_CHUNK_SIZE: usize = 0x10000;
+/// longest run of repeated byte encoded by a single PackBits control byte
+const MAX_RUN: usize = 128;
+/// longest literal bytes encoded by a single PackBits control byte
+const MAX_LITERAL: usize = 128;
+/// relative difference, in units of epsilon, float compared by [ScanValue::is_close] may have
+const FLOAT_ULPS: u8 = 4;
+
+/// value that can be searched by [ValueScanner]
+pub trait ScanValue: Clone + PartialEq + PartialOrd {
+    /// bytes of the value as laid out in memory
+    fn to_bytes(&self) -> Vec<u8>;
+
+    /// value from bytes laid out in memory, `bytes` has the length of [ScanValue::to_bytes]
+    fn from_bytes(bytes: &[u8]) -> Self;
+
+    /// step between candidate addresses
+    fn alignment(&self) -> usize {
+        1
+    }
+
+    /// value as number for approximate comparisons, `None` for value which is not a number
+    fn to_f64(&self) -> Option<f64> {
+        None
+    }
+
+    /// size of every value of the type, `None` for type with variable size like string
+    fn fixed_size() -> Option<usize> {
+        None
+    }
+
+    /// `self` plus `rhs` in the type of the value, wrapping for integer. `None` for value which
+    /// is not a number
+    fn add_value(&self, _rhs: &Self) -> Option<Self> {
+        None
+    }
+
+    /// `self` minus `rhs` in the type of the value, wrapping for integer. `None` for value
+    /// which is not a number
+    fn sub_value(&self, _rhs: &Self) -> Option<Self> {
+        None
+    }
+
+    /// whether both values are the same, float may differ by rounding of the last few bits
+    fn is_close(&self, other: &Self) -> bool {
+        self == other
+    }
+}
+
+macro_rules! impl_scan_value_common {
+    ($t:ty) => {
+        fn to_bytes(&self) -> Vec<u8> {
+            self.to_ne_bytes().to_vec()
+        }
+
+        fn from_bytes(bytes: &[u8]) -> Self {
+            Self::from_ne_bytes(bytes.try_into().unwrap())
+        }
+
+        fn alignment(&self) -> usize {
+            std::mem::size_of::<$t>()
+        }
+
+        fn to_f64(&self) -> Option<f64> {
+            Some(*self as f64)
+        }
+
+        fn fixed_size() -> Option<usize> {
+            Some(std::mem::size_of::<$t>())
+        }
+    };
+}
+
+macro_rules! impl_scan_value {
+    (int: $($t:ty),*) => {
+        $(impl ScanValue for $t {
+            impl_scan_value_common!($t);
+
+            fn add_value(&self, rhs: &Self) -> Option<Self> {
+                Some(self.wrapping_add(*rhs))
+            }
+
+            fn sub_value(&self, rhs: &Self) -> Option<Self> {
+                Some(self.wrapping_sub(*rhs))
+            }
+        })*
+    };
+    (float: $($t:ty),*) => {
+        $(impl ScanValue for $t {
+            impl_scan_value_common!($t);
+
+            fn add_value(&self, rhs: &Self) -> Option<Self> {
+                Some(self + rhs)
+            }
+
+            fn sub_value(&self, rhs: &Self) -> Option<Self> {
+                Some(self - rhs)
+            }
+
+            fn is_close(&self, other: &Self) -> bool {
+                let scale = self.abs().max(other.abs()).max(1.0);
+                (self - other).abs() <= <$t>::EPSILON * <$t>::from(FLOAT_ULPS) * scale
+            }
+        })*
+    };
+}
+
+impl_scan_value!(int: i8, i16, i32, i64, u8, u16, u32, u64);
+impl_scan_value!(float: f32, f64);
+
+impl ScanValue for String {
+    fn to_bytes(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Self {
+        String::from_utf8_lossy(bytes).to_string()
+    }
+}
+
+/// comparison used to refine results of [ValueScanner]
+#[derive(Clone, Debug, PartialEq)]
+pub enum ScanCondition<T> {
+    /// value equal to the given value
+    Exact(T),
+    /// value different from the previous scan
+    Changed,
+    /// value same as the previous scan
+    Unchanged,
+    /// value greater than the previous scan
+    Increased,
+    /// value less than the previous scan
+    Decreased,
+    /// value between both values, inclusive
+    Between(T, T),
+    /// number within epsilon, the second value, of the first value
+    Approximately(T, T),
+    /// number equal to the value once both are rounded to the number of decimal places
+    Rounded(T, u32),
+    /// number greater than the previous scan by exactly the value
+    IncreasedBy(T),
+    /// number less than the previous scan by exactly the value
+    DecreasedBy(T),
+}
+
+impl<T: ScanValue> ScanCondition<T> {
+    /// whether `new` value satisfy the condition against `old` value
+    pub fn matches(&self, old: &T, new: &T) -> bool {
+        match self {
+            Self::Exact(value) => new == value,
+            Self::Changed => new != old,
+            Self::Unchanged => new == old,
+            Self::Increased => new > old,
+            Self::Decreased => new < old,
+            Self::Between(low, high) => low <= new && new <= high,
+            Self::Approximately(value, epsilon) => {
+                match (new.to_f64(), value.to_f64(), epsilon.to_f64()) {
+                    (Some(new), Some(value), Some(epsilon)) => (new - value).abs() <= epsilon,
+                    _ => false,
+                }
+            }
+            Self::Rounded(value, decimals) => match (new.to_f64(), value.to_f64()) {
+                (Some(new), Some(value)) => {
+                    let scale = 10f64.powi(*decimals as i32);
+                    (new * scale).round() == (value * scale).round()
+                }
+                _ => false,
+            },
+            Self::IncreasedBy(value) => old.add_value(value).is_some_and(|e| new.is_close(&e)),
+            Self::DecreasedBy(value) => old.sub_value(value).is_some_and(|e| new.is_close(&e)),
+        }
+    }
+
+    /// value the condition compares against, `None` for condition comparing against the
+    /// previous scan
+    pub fn get_value(&self) -> Option<&T> {
+        match self {
+            Self::Exact(value)
+            | Self::Between(value, _)
+            | Self::Approximately(value, _)
+            | Self::Rounded(value, _) => Some(value),
+            Self::Changed
+            | Self::Unchanged
+            | Self::Increased
+            | Self::Decreased
+            | Self::IncreasedBy(_)
+            | Self::DecreasedBy(_) => None,
+        }
+    }
+}
+
+/// address and its value found by [ValueScanner]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScanResult<T> {
+    address: usize,
+    value: T,
+}
+
+impl<T> ScanResult<T> {
+    /// address of the value
+    pub fn get_address(&self) -> usize {
+        self.address
+    }
+
+    /// value at the address on the last scan
+    pub fn get_value(&self) -> &T {
+        &self.value
+    }
+}
+
+/// Iterative scanning of typed value in memory of a process, through any [MemoryBackend]
+pub struct ValueScanner<'a, T: ScanValue, B: MemoryBackend + ?Sized = Handle> {
+    handle: &'a B,
+    options: ScanOptions,
+    results: Vec<ScanResult<T>>,
+    baseline: Option<Vec<BaselineRegion>>,
+}
+
+/// region captured by [ValueScanner::first_scan_unknown], bytes packed by chunk
+struct BaselineRegion {
+    address: usize,
+    chunks: Vec<Vec<u8>>,
+}
+
+impl<'a, T: ScanValue, B: MemoryBackend + ?Sized> ValueScanner<'a, T, B> {
+    /// create new instance for scanning memory of the handle
+    pub fn new(handle: &'a B) -> Self {
+        Self {
+            handle,
+            options: ScanOptions::default(),
+            results: Vec::new(),
+            baseline: None,
+        }
+    }
+
+    /// constrain the first scan by the options, alignment replace the natural alignment of
+    /// the value
+    pub fn with_options(mut self, options: ScanOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// results of the last scan
+    pub fn get_results(&self) -> &[ScanResult<T>] {
+        &self.results
+    }
+
+    /// search every committed writable region for `value`, replacing previous results.
+    ///
+    /// return the number of results.
+    pub fn first_scan(&mut self, value: T) -> Result<usize> {
+        self.first_scan_matching(ScanCondition::Exact(value))
+    }
+
+    /// search every committed writable region for value satisfying the condition, like
+    /// [ScanCondition::Approximately] for float which rarely match an exact bit pattern,
+    /// replacing previous results.
+    ///
+    /// condition comparing against the previous scan is [Error::InvalidInput]. return the
+    /// number of results.
+    pub fn first_scan_matching(&mut self, condition: ScanCondition<T>) -> Result<usize> {
+        let value = condition.get_value().ok_or(Error::InvalidInput)?;
+        let size = value.to_bytes().len();
+        let alignment = self
+            .options
+            .get_alignment()
+            .unwrap_or_else(|| value.alignment());
+
+        self.results.clear();
+        self.baseline = None;
+        let stacks = self.options.stack_ranges(self.handle)?;
+
+        for mbi in self
+            .handle
+            .memory_regions()
+            .committed()
+            .writable()
+            .filter(|mbi| self.options.keeps(mbi, &stacks))
+        {
+            let data = match self
+                .handle
+                .read_memory(mbi.get_base_address(), mbi.get_region_size())
+            {
+                Ok(data) => data,
+                Err(_) => continue,
+            };
+
+            // NOTE: exact value is compared by its bytes, without decoding every window
+            self.results.extend(match &condition {
+                ScanCondition::Exact(value) => {
+                    scan_bytes(&data, mbi.get_base_address(), value, alignment)
+                }
+                _ => scan_matching(&data, mbi.get_base_address(), &condition, size, alignment),
+            });
+        }
+
+        Ok(self.results.len())
+    }
+
+    /// capture every committed writable region as baseline for value which is unknown at
+    /// first, replacing previous results.
+    ///
+    /// the next [ValueScanner::next_scan] compares memory against the baseline, like
+    /// [ScanCondition::Decreased] after taking damage. baseline is kept as compressed chunks,
+    /// memory mostly zero take a fraction of its size. type with variable size is
+    /// [Error::InvalidInput]. return the number of bytes captured.
+    pub fn first_scan_unknown(&mut self) -> Result<usize> {
+        T::fixed_size().ok_or(Error::InvalidInput)?;
+
+        self.results.clear();
+        self.baseline = None;
+        let stacks = self.options.stack_ranges(self.handle)?;
+
+        let mut captured = 0;
+        let mut baseline = Vec::new();
+        for mbi in self
+            .handle
+            .memory_regions()
+            .committed()
+            .writable()
+            .filter(|mbi| self.options.keeps(mbi, &stacks))
+        {
+            let data = match self
+                .handle
+                .read_memory(mbi.get_base_address(), mbi.get_region_size())
+            {
+                Ok(data) => data,
+                Err(_) => continue,
+            };
+
+            captured += data.len();
+            baseline.push(BaselineRegion {
+                address: mbi.get_base_address(),
+                chunks: data.chunks(BASELINE_CHUNK_SIZE).map(pack_bits).collect(),
+            });
+        }
+        self.baseline = Some(baseline);
+
+        Ok(captured)
+    }
+
+    /// keep results which current value satisfy the condition against the previous scan, or
+    /// against the baseline of [ValueScanner::first_scan_unknown] on the first refinement.
+    ///
+    /// return the number of results.
+    pub fn next_scan(&mut self, condition: ScanCondition<T>) -> Result<usize> {
+        let handle = self.handle;
+
+        if let Some(baseline) = self.baseline.take() {
+            let size = T::fixed_size().ok_or(Error::InvalidInput)?;
+            let alignment = self.options.get_alignment().unwrap_or(size);
+
+            for region in baseline {
+                for (index, chunk) in region.chunks.iter().enumerate() {
+                    // NOTE: extend by the start of next chunk for value crossing the boundary,
+                    // only that much of it is unpacked
+                    let mut old = unpack_bits(chunk, usize::MAX);
+                    let len = old.len();
+                    if let Some(next) = region.chunks.get(index + 1) {
+                        old.extend(unpack_bits(next, size - 1));
+                    }
+
+                    let address = region.address + index * BASELINE_CHUNK_SIZE;
+                    let new = match handle.read_memory(address, old.len()) {
+                        Ok(new) => new,
+                        Err(_) => continue,
+                    };
+
+                    self.results.extend(compare_chunk(
+                        &old, &new, address, len, &condition, size, alignment,
+                    ));
+                }
+            }
+
+            return Ok(self.results.len());
+        }
+
+        self.results.retain_mut(|result| {
+            let size = result.value.to_bytes().len();
+            let new = match handle.read_memory(result.address, size) {
+                Ok(data) => T::from_bytes(&data),
+                Err(_) => return false,
+            };
+
+            let is_match = condition.matches(&result.value, &new);
+            result.value = new;
+            is_match
+        });
+
+        Ok(self.results.len())
+    }
+}
+
+fn scan_bytes<T: ScanValue>(
+    data: &[u8],
+    base_address: usize,
+    value: &T,
+    alignment: usize,
+) -> Vec<ScanResult<T>> {
+    let bytes = value.to_bytes();
+    if bytes.is_empty() {
+        return Vec::new();
+    }
+
+    data.windows(bytes.len())
+        .enumerate()
+        .step_by(alignment)
+        .filter(|(_, window)| *window == bytes.as_slice())
+        .map(|(offset, _)| ScanResult {
+            address: base_address + offset,
+            value: value.clone(),
+        })
+        .collect()
+}
+
+fn scan_matching<T: ScanValue>(
+    data: &[u8],
+    base_address: usize,
+    condition: &ScanCondition<T>,
+    size: usize,
+    alignment: usize,
+) -> Vec<ScanResult<T>> {
+    if size == 0 {
+        return Vec::new();
+    }
+
+    data.windows(size)
+        .enumerate()
+        .step_by(alignment)
+        .filter_map(|(offset, window)| {
+            let value = T::from_bytes(window);
+            condition.matches(&value, &value).then_some(ScanResult {
+                address: base_address + offset,
+                value,
+            })
+        })
+        .collect()
+}
+
+/// values in `new` satisfying the condition against `old` at the same offset, starting before
+/// `len`
+fn compare_chunk<T: ScanValue>(
+    old: &[u8],
+    new: &[u8],
+    base_address: usize,
+    len: usize,
+    condition: &ScanCondition<T>,
+    size: usize,
+    alignment: usize,
+) -> Vec<ScanResult<T>> {
+    old.windows(size)
+        .zip(new.windows(size))
+        .take(len)
+        .enumerate()
+        .step_by(alignment)
+        .filter_map(|(offset, (old, new))| {
+            let value = T::from_bytes(new);
+            condition
+                .matches(&T::from_bytes(old), &value)
+                .then_some(ScanResult {
+                    address: base_address + offset,
+                    value,
+                })
+        })
+        .collect()
+}
+
+/// compress with PackBits, control byte below 128 followed by that many plus one literal
+/// bytes, otherwise followed by a byte repeated 257 minus it times
+fn pack_bits(data: &[u8]) -> Vec<u8> {
+    let mut packed = Vec::new();
+    let mut literal_start = 0;
+    let mut index = 0;
+
+    let flush = |packed: &mut Vec<u8>, literal: &[u8]| {
+        for literal in literal.chunks(MAX_LITERAL) {
+            packed.push((literal.len() - 1) as u8);
+            packed.extend_from_slice(literal);
+        }
+    };
+
+    while index < data.len() {
+        let run = data[index..]
+            .iter()
+            .take(MAX_RUN)
+            .take_while(|e| **e == data[index])
+            .count();
+
+        if run >= 3 {
+            flush(&mut packed, &data[literal_start..index]);
+            packed.push((257 - run) as u8);
+            packed.push(data[index]);
+            index += run;
+            literal_start = index;
+        } else {
+            index += run;
+        }
+    }
+    flush(&mut packed, &data[literal_start..]);
+
+    packed
+}
+
+/// decompress bytes compressed by [pack_bits], stopping after `max_len` bytes
+fn unpack_bits(packed: &[u8], max_len: usize) -> Vec<u8> {
+    let mut data = Vec::new();
+    let mut index = 0;
+
+    while let Some(control) = packed.get(index).filter(|_| data.len() < max_len) {
+        if *control < 128 {
+            let end = (index + 2 + *control as usize).min(packed.len());
+            data.extend_from_slice(&packed[index + 1..end]);
+            index = end;
+        } else if let Some(byte) = packed.get(index + 1) {
+            data.extend(std::iter::repeat_n(*byte, 257 - *control as usize));
+            index += 2;
+        } else {
+            break;
+        }
+    }
+    data.truncate(max_len);
+
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compare_chunk, pack_bits, scan_bytes, scan_matching, unpack_bits, ScanCondition};
+
+    #[test]
+    fn scanning_aligned_values() {
+        let mut data = vec![0u8; 16];
+        data[4..8].copy_from_slice(&100i32.to_ne_bytes());
+        data[9..13].copy_from_slice(&100i32.to_ne_bytes());
+
+        let results = scan_bytes(&data, 0x1000, &100i32, 4);
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].get_address(), 0x1004);
+    }
+
+    #[test]
+    fn scanning_strings() {
+        let data = b"..hello..hello".to_vec();
+
+        let results = scan_bytes(&data, 0x1000, &String::from("hello"), 1);
+
+        assert_eq!(
+            results.iter().map(|e| e.get_address()).collect::<Vec<_>>(),
+            vec![0x1002, 0x1009]
+        );
+    }
+
+    #[test]
+    fn scanning_approximate_floats() {
+        let mut data = vec![0u8; 16];
+        data[0..4].copy_from_slice(&99.7f32.to_ne_bytes());
+        data[8..12].copy_from_slice(&100.02f32.to_ne_bytes());
+
+        let condition = ScanCondition::Approximately(100.0f32, 0.05);
+        let results = scan_matching(&data, 0x1000, &condition, 4, 4);
+        assert_eq!(
+            results.iter().map(|e| e.get_address()).collect::<Vec<_>>(),
+            vec![0x1008]
+        );
+
+        let condition = ScanCondition::Between(99.0f32, 101.0);
+        assert_eq!(scan_matching(&data, 0x1000, &condition, 4, 4).len(), 2);
+    }
+
+    #[test]
+    fn packing_bits() {
+        let mut data = vec![0u8; 0x10000];
+        data[0x100..0x104].copy_from_slice(&[1, 2, 3, 4]);
+        data[0x200..0x300].fill(7);
+        data[0x300..0x400]
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, e)| *e = i as u8);
+
+        let packed = pack_bits(&data);
+        assert!(packed.len() < 0x800);
+        assert_eq!(unpack_bits(&packed, usize::MAX), data);
+        assert_eq!(unpack_bits(&packed, 0x102), &data[..0x102]);
+        assert_eq!(unpack_bits(&packed, 3), vec![0, 0, 0]);
+        assert_eq!(unpack_bits(&pack_bits(&[]), usize::MAX), Vec::<u8>::new());
+        assert_eq!(unpack_bits(&pack_bits(&[5, 5]), usize::MAX), vec![5, 5]);
+    }
+
+    #[test]
+    fn comparing_against_baseline() {
+        let old = [100i32, 50, 7, 7]
+            .iter()
+            .flat_map(|e| e.to_ne_bytes())
+            .collect::<Vec<_>>();
+        let new = [90i32, 50, 8, 7]
+            .iter()
+            .flat_map(|e| e.to_ne_bytes())
+            .collect::<Vec<_>>();
+
+        let results = compare_chunk(&old, &new, 0x1000, 16, &ScanCondition::<i32>::Changed, 4, 4);
+        assert_eq!(
+            results.iter().map(|e| e.get_address()).collect::<Vec<_>>(),
+            vec![0x1000, 0x1008]
+        );
+
+        let results = compare_chunk(
+            &old,
+            &new,
+            0x1000,
+            16,
+            &ScanCondition::DecreasedBy(10),
+            4,
+            4,
+        );
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].get_value(), &90);
+
+        // only values starting before the length
+        assert_eq!(
+            compare_chunk(&old, &new, 0x1000, 8, &ScanCondition::<i32>::Changed, 4, 4).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn matching_conditions() {
+        assert!(ScanCondition::Exact(5).matches(&1, &5));
+        assert!(ScanCondition::<i32>::Changed.matches(&1, &5));
+        assert!(!ScanCondition::<i32>::Unchanged.matches(&1, &5));
+        assert!(ScanCondition::<f32>::Increased.matches(&1.0, &1.5));
+        assert!(ScanCondition::<f32>::Decreased.matches(&1.0, &0.5));
+        assert!(ScanCondition::Rounded(2.51f64, 2).matches(&0.0, &2.5149));
+        assert!(!ScanCondition::Rounded(2.51f64, 2).matches(&0.0, &2.516));
+        assert!(!ScanCondition::Approximately(String::new(), String::new())
+            .matches(&String::new(), &String::new()));
+    }
+
+    #[test]
+    fn matching_differences() {
+        assert!(ScanCondition::IncreasedBy(10u8).matches(&250, &4));
+        assert!(ScanCondition::DecreasedBy(1u64).matches(&u64::MAX, &(u64::MAX - 1)));
+        assert!(!ScanCondition::DecreasedBy(1u64).matches(&u64::MAX, &(u64::MAX - 2)));
+        assert!(ScanCondition::IncreasedBy(0.1f32).matches(&100.0, &100.1));
+        assert!(ScanCondition::DecreasedBy(0.1f64).matches(&0.3, &0.2));
+        assert!(!ScanCondition::IncreasedBy(0.1f32).matches(&100.0, &100.2));
+        assert!(!ScanCondition::IncreasedBy(String::new()).matches(&String::new(), &String::new()));
+    }
+}
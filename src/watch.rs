@@ -0,0 +1,244 @@
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::error::Result;
+use crate::handle::{Handle, HandleSnapshotFlag};
+use crate::module::Module;
+use crate::worker::StoppableThread;
+
+/// change observed by [Watcher]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WatchEvent {
+    /// bytes starting from `address` differ from the previous poll
+    Changed {
+        /// address of the first changed byte
+        address: usize,
+        /// bytes on the previous poll
+        old: Vec<u8>,
+        /// bytes on the current poll
+        new: Vec<u8>,
+    },
+}
+
+struct WatchTarget {
+    address: usize,
+    last: Vec<u8>,
+}
+
+/// Polling of addresses or regions of a process from a background thread.
+///
+/// events are delivered over a channel, polling stop when the watcher dropped.
+pub struct Watcher {
+    receiver: Receiver<WatchEvent>,
+    _thread: StoppableThread,
+}
+
+impl Watcher {
+    /// start polling every `(address, size)` of `targets` on `interval`.
+    ///
+    /// every target must be readable when the watcher start.
+    pub fn spawn(
+        handle: Arc<Handle>,
+        targets: &[(usize, usize)],
+        interval: Duration,
+    ) -> Result<Self> {
+        let mut targets = targets
+            .iter()
+            .map(|(address, size)| {
+                Ok(WatchTarget {
+                    address: *address,
+                    last: handle.read_memory(*address, *size)?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let (sender, receiver) = mpsc::channel();
+
+        let thread = StoppableThread::spawn_polling(interval, move || {
+            for target in targets.iter_mut() {
+                let current = match handle.read_memory(target.address, target.last.len()) {
+                    Ok(current) => current,
+                    Err(_) => continue,
+                };
+
+                for event in diff_bytes(target.address, &target.last, &current) {
+                    if sender.send(event).is_err() {
+                        return false;
+                    }
+                }
+                target.last = current;
+            }
+
+            true
+        });
+
+        Ok(Self {
+            receiver,
+            _thread: thread,
+        })
+    }
+
+    /// channel the events delivered to
+    pub fn get_receiver(&self) -> &Receiver<WatchEvent> {
+        &self.receiver
+    }
+
+    /// stop polling and wait for the background thread to exit
+    pub fn stop(self) {}
+}
+
+/// change of loaded modules observed by [ModuleWatcher]
+#[derive(Clone, PartialEq, Eq)]
+pub enum ModuleEvent {
+    /// module appeared since the previous poll
+    ModuleLoaded(Module),
+    /// module disappeared since the previous poll
+    ModuleUnloaded(Module),
+}
+
+/// Polling of modules loaded by a process from a background thread.
+///
+/// events are delivered over a channel, polling stop when the watcher dropped.
+pub struct ModuleWatcher {
+    receiver: Receiver<ModuleEvent>,
+    _thread: StoppableThread,
+}
+
+impl ModuleWatcher {
+    /// start snapshotting modules of the process on `interval`.
+    ///
+    /// modules loaded before the watcher start are not reported.
+    pub fn spawn(handle: Arc<Handle>, interval: Duration) -> Result<Self> {
+        let snapshot_modules = |handle: &Handle| -> Result<Vec<Module>> {
+            let snapshot = handle.create_snapshot(
+                HandleSnapshotFlag::SnapModule | HandleSnapshotFlag::SnapModule32,
+            )?;
+            Ok(snapshot.get_modules().collect())
+        };
+        let mut last = snapshot_modules(&handle)?;
+
+        let (sender, receiver) = mpsc::channel();
+
+        let thread = StoppableThread::spawn_polling(interval, move || {
+            // NOTE: snapshot fail while the loader is busy, try again on the next poll
+            let current = match snapshot_modules(&handle) {
+                Ok(current) => current,
+                Err(_) => return true,
+            };
+
+            for event in diff_modules(&last, &current) {
+                if sender.send(event).is_err() {
+                    return false;
+                }
+            }
+            last = current;
+
+            true
+        });
+
+        Ok(Self {
+            receiver,
+            _thread: thread,
+        })
+    }
+
+    /// channel the events delivered to
+    pub fn get_receiver(&self) -> &Receiver<ModuleEvent> {
+        &self.receiver
+    }
+
+    /// stop polling and wait for the background thread to exit
+    pub fn stop(self) {}
+}
+
+/// unloaded modules followed by loaded modules, matched by address and path
+fn diff_modules(old: &[Module], new: &[Module]) -> Vec<ModuleEvent> {
+    let is_same =
+        |a: &Module, b: &Module| a.get_address() == b.get_address() && a.get_path() == b.get_path();
+
+    let unloaded = old
+        .iter()
+        .filter(|e| !new.iter().any(|n| is_same(e, n)))
+        .map(|e| ModuleEvent::ModuleUnloaded(*e));
+    let loaded = new
+        .iter()
+        .filter(|e| !old.iter().any(|o| is_same(e, o)))
+        .map(|e| ModuleEvent::ModuleLoaded(*e));
+
+    unloaded.chain(loaded).collect()
+}
+
+/// one event per run of consecutive changed bytes
+fn diff_bytes(address: usize, old: &[u8], new: &[u8]) -> Vec<WatchEvent> {
+    let mut events = Vec::new();
+    let mut offset = 0usize;
+
+    while offset < old.len() {
+        if old[offset] == new[offset] {
+            offset += 1;
+            continue;
+        }
+
+        let start = offset;
+        while offset < old.len() && old[offset] != new[offset] {
+            offset += 1;
+        }
+
+        events.push(WatchEvent::Changed {
+            address: address + start,
+            old: old[start..offset].to_vec(),
+            new: new[start..offset].to_vec(),
+        });
+    }
+
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use windows::Win32::System::Diagnostics::ToolHelp::MODULEENTRY32W;
+
+    use super::{diff_bytes, diff_modules, ModuleEvent, WatchEvent};
+    use crate::module::Module;
+
+    #[test]
+    fn diffing_changed_runs() {
+        let events = diff_bytes(0x1000, &[1, 2, 3, 4, 5], &[1, 9, 9, 4, 6]);
+
+        assert_eq!(
+            events,
+            vec![
+                WatchEvent::Changed {
+                    address: 0x1001,
+                    old: vec![2, 3],
+                    new: vec![9, 9],
+                },
+                WatchEvent::Changed {
+                    address: 0x1004,
+                    old: vec![5],
+                    new: vec![6],
+                },
+            ]
+        );
+        assert!(diff_bytes(0, &[1, 2], &[1, 2]).is_empty());
+    }
+
+    #[test]
+    fn diffing_module_lists() {
+        let module = |address: usize, size: u32| {
+            Module::from(MODULEENTRY32W {
+                modBaseAddr: address as *mut u8,
+                modBaseSize: size,
+                ..Default::default()
+            })
+        };
+        let old = [module(0x1000, 0x100), module(0x2000, 0x100)];
+        let new = [module(0x2000, 0x200), module(0x3000, 0x100)];
+
+        let events = diff_modules(&old, &new);
+        assert_eq!(events.len(), 2);
+        assert!(events[0] == ModuleEvent::ModuleUnloaded(old[0]));
+        assert!(events[1] == ModuleEvent::ModuleLoaded(new[1]));
+    }
+}
@@ -0,0 +1,77 @@
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// Background thread asked to stop and waited for when dropped.
+pub(crate) struct StoppableThread {
+    stop_sender: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl StoppableThread {
+    /// run `f` on a new thread, the receiver it is given disconnect once asked to stop
+    pub(crate) fn spawn<F>(f: F) -> Self
+    where
+        F: FnOnce(Receiver<()>) + Send + 'static,
+    {
+        let (stop_sender, stop_receiver) = mpsc::channel::<()>();
+        let thread = std::thread::spawn(move || f(stop_receiver));
+
+        Self {
+            stop_sender: Some(stop_sender),
+            thread: Some(thread),
+        }
+    }
+
+    /// call `tick` every `interval` on a new thread, until asked to stop or `tick` return
+    /// `false`
+    pub(crate) fn spawn_polling<F>(interval: Duration, mut tick: F) -> Self
+    where
+        F: FnMut() -> bool + Send + 'static,
+    {
+        Self::spawn(move |stop| {
+            while let Err(RecvTimeoutError::Timeout) = stop.recv_timeout(interval) {
+                if !tick() {
+                    return;
+                }
+            }
+        })
+    }
+}
+
+impl Drop for StoppableThread {
+    fn drop(&mut self) {
+        // NOTE: dropping the sender wakes the thread up with disconnected
+        self.stop_sender.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use super::StoppableThread;
+
+    #[test]
+    fn stopping_threads() {
+        let ticks = Arc::new(AtomicUsize::new(0));
+        let thread = StoppableThread::spawn_polling(Duration::from_millis(1), {
+            let ticks = ticks.clone();
+            move || ticks.fetch_add(1, Ordering::SeqCst) < 2
+        });
+        while ticks.load(Ordering::SeqCst) < 3 {
+            std::thread::yield_now();
+        }
+        drop(thread);
+        assert_eq!(ticks.load(Ordering::SeqCst), 3);
+
+        // dropping return only once the thread exited
+        let thread = StoppableThread::spawn_polling(Duration::from_secs(60), || true);
+        drop(thread);
+    }
+}
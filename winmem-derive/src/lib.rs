@@ -0,0 +1,119 @@
+//! derive macros for winmem, enable `derive` feature of winmem to use them.
+#![warn(missing_docs)]
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Expr, Fields};
+
+/// implement `RemoteStruct` by reading and writing fields annotated with `#[offset(..)]`.
+///
+/// fields without offset are skipped on write and filled with `Default::default()` on read,
+/// so gaps of reverse engineered struct need not be mirrored.
+///
+/// ```ignore
+/// #[derive(Default, RemoteStruct)]
+/// struct Player {
+///     #[offset(0x10)]
+///     health: i32,
+///     #[offset(0x40)]
+///     position: [f32; 3],
+/// }
+/// ```
+#[proc_macro_derive(RemoteStruct, attributes(offset))]
+pub fn derive_remote_struct(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand_remote_struct(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand_remote_struct(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "RemoteStruct only support struct with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "RemoteStruct only support struct",
+            ))
+        }
+    };
+
+    let mut reads = Vec::new();
+    let mut writes = Vec::new();
+    for field in fields {
+        let ident = &field.ident;
+        let offset = field
+            .attrs
+            .iter()
+            .find(|attr| attr.path().is_ident("offset"))
+            .map(|attr| attr.parse_args::<Expr>())
+            .transpose()?;
+
+        match offset {
+            Some(offset) => {
+                reads.push(quote! { #ident: backend.read(base + (#offset) as usize)? });
+                writes.push(quote! { backend.write(base + (#offset) as usize, self.#ident)?; });
+            }
+            None => reads.push(quote! { #ident: ::core::default::Default::default() }),
+        }
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::winmem::memory::RemoteStruct for #name #ty_generics #where_clause {
+            fn read_from<__B: ::winmem::backend::MemoryBackend>(
+                backend: &__B,
+                base: usize,
+            ) -> ::winmem::Result<Self> {
+                Ok(Self {
+                    #(#reads,)*
+                })
+            }
+
+            fn write_to<__B: ::winmem::backend::MemoryBackend>(
+                &self,
+                backend: &__B,
+                base: usize,
+            ) -> ::winmem::Result<()> {
+                #(#writes)*
+                Ok(())
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::expand_remote_struct;
+
+    #[test]
+    fn expanding_annotated_fields() {
+        let expanded = expand_remote_struct(syn::parse_quote! {
+            struct Player {
+                #[offset(0x10)]
+                health: i32,
+                name: String,
+            }
+        })
+        .unwrap()
+        .to_string();
+
+        assert!(expanded.contains("backend . read (base + (0x10) as usize) ?"));
+        assert!(expanded.contains("name : :: core :: default :: Default :: default ()"));
+        assert!(!expanded.contains("self . name"));
+
+        assert!(expand_remote_struct(syn::parse_quote! { struct Tuple(i32); }).is_err());
+    }
+}